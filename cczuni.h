@@ -8,29 +8,250 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * 学号、密码、验证码、密钥、路径、日期等短参数的最大长度（字节，不含结尾的 NUL），
+ * 更长的参数返回错误，不会被处理。
+ */
+#define CCZUNI_MAX_FIELD_LEN 4096
+
+/**
+ * JSON、会话、成绩 `token` 等参数的最大长度（字节，不含结尾的 NUL），更长的参数返回错误。
+ */
+#define CCZUNI_MAX_DOCUMENT_LEN ((16 * 1024) * 1024)
+
 typedef struct DefaultClient DefaultClient;
 
+typedef struct ScheduleWatch ScheduleWatch;
+
+/**
+ * `cczuni_schedule_watch_start` 的回调。
+ *
+ * `diff_json` 为 `{"success": true, "data": ...}`，`data` 同 `cczuni_diff_schedule` 的 `data`，
+ * 只在回调期间有效，**不要释放**；`user_data` 为启动时传入的指针。回调在库的后台线程中调用。
+ */
+typedef void (*CczuniScheduleCallback)(const char *diff_json, void *user_data);
+
 /**
  * 创建一个新的 cczuni 客户端实例。
  *
+ * 除 `cczuni_client_free` 外，同一客户端的函数可以在多个线程中同时调用；
+ * 同时进行的登录会依次执行，不会混用彼此的 Cookie。
+ *
  * # Arguments
  * * `user` - C 字符串，用户的学号。
  * * `password` - C 字符串，用户的密码。
  *
  * # Returns
- * 返回一个指向客户端实例的不透明指针。如果创建失败，返回空指针。
+ * 返回一个指向客户端实例的不透明指针。如果创建失败（如参数为空指针或不是有效的 UTF-8），返回空指针。
  * **调用者必须在使用完毕后调用 `cczuni_client_free` 来释放内存。**
+ *
+ * # Safety
+ * `user` 与 `password` 必须是有效的、以 NUL 结尾的 C 字符串。
  */
 struct DefaultClient *cczuni_client_new(const char *user, const char *password);
 
+/**
+ * 用 WebView 等方式登录后得到的 Cookie 创建客户端，无需再次登录即可访问各应用。
+ *
+ * # Arguments
+ * * `cookies_json` - C 字符串，Cookie 的 JSON 数组，每项为 `[url, name, value]`，
+ *   如 `[["https://zmvpn.cczu.edu.cn/", "wengine_vpn_ticketzmvpn_cczu_edu_cn", "…"]]`。
+ *   其中有 WebVPN 的地址时按 WebVPN 登录处理，否则按校内登录处理。
+ *
+ * # Returns
+ * 返回一个指向客户端实例的不透明指针。如果参数无效或地址无法解析，返回空指针。
+ * Cookie 在第一次请求前不会被检查，失效时请求会像会话过期一样失败。
+ * **调用者必须在使用完毕后调用 `cczuni_client_free` 来释放内存。**
+ *
+ * # Safety
+ * `cookies_json` 必须是有效的、以 NUL 结尾的 C 字符串。
+ */
+struct DefaultClient *cczuni_client_from_cookies(const char *cookies_json);
+
+/**
+ * 创建一个把 Cookie 保存在文件中的客户端：从文件中已有的 Cookie 开始，
+ * 之后每当响应设置 Cookie 时写回文件，下次运行时可以沿用登录状态。
+ *
+ * # Arguments
+ * * `user` - C 字符串，用户的学号。
+ * * `password` - C 字符串，用户的密码。
+ * * `cookie_path` - C 字符串，Cookie 文件的路径，文件不存在时会被创建。
+ *   文件中包含登录凭据，请放在只有当前用户可读的位置。
+ *
+ * # Returns
+ * 返回一个指向客户端实例的不透明指针。如果参数无效，或文件无法读取、内容不是保存的 Cookie，返回空指针。
+ * **调用者必须在使用完毕后调用 `cczuni_client_free` 来释放内存。**
+ *
+ * # Safety
+ * `user`、`password` 与 `cookie_path` 必须是有效的、以 NUL 结尾的 C 字符串。
+ */
+struct DefaultClient *cczuni_client_new_with_cookie_file(const char *user,
+                                                         const char *password,
+                                                         const char *cookie_path);
+
+/**
+ * 导出客户端的 Cookie（不含 SSO 的 `CASTGC` 票据），用密钥加密，
+ * 之后可以用 `cczuni_client_import_session_encrypted` 恢复登录状态。
+ *
+ * # Arguments
+ * * `client_ptr` - 客户端指针。
+ * * `key` - C 字符串，32 字节密钥的 Base64 编码。请把密钥保存在系统的钥匙串中，不要与导出内容放在一起。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为加密后的会话（Base64 字符串）。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+ * `key` 必须是有效的、以 NUL 结尾的 C 字符串。
+ */
+char *cczuni_export_session_encrypted(struct DefaultClient *client_ptr, const char *key);
+
+/**
+ * 用 `cczuni_export_session_encrypted` 导出的会话创建客户端。
+ *
+ * # Arguments
+ * * `user` - C 字符串，用户的学号。
+ * * `password` - C 字符串，用户的密码。
+ * * `session` - C 字符串，`cczuni_export_session_encrypted` 返回的 `data`。
+ * * `key` - C 字符串，导出时使用的密钥（Base64）。
+ *
+ * # Returns
+ * 返回一个指向客户端实例的不透明指针。如果参数无效、密钥错误或会话被篡改，返回空指针。
+ * **调用者必须在使用完毕后调用 `cczuni_client_free` 来释放内存。**
+ *
+ * # Safety
+ * `user`、`password`、`session` 与 `key` 必须是有效的、以 NUL 结尾的 C 字符串。
+ */
+struct DefaultClient *cczuni_client_import_session_encrypted(const char *user,
+                                                             const char *password,
+                                                             const char *session,
+                                                             const char *key);
+
 /**
  * 释放 cczuni 客户端实例占用的内存。
  *
  * # Arguments
  * * `client_ptr` - 通过 `cczuni_client_new` 创建的客户端指针。
+ *
+ * # Safety
+ * `client_ptr` 必须为空指针，或由 `cczuni_client_new` 返回且尚未释放，
+ * 且没有其他线程正在使用它。
  */
 void cczuni_client_free(struct DefaultClient *client_ptr);
 
+/**
+ * 设置客户端所在的校区，影响宿舍楼列表等与校区有关的功能，课表条目中也会带上校区。
+ *
+ * # Arguments
+ * * `client_ptr` - 客户端指针。
+ * * `campus` - `0` 为未设置（覆盖所有校区），`1` 为武进校区，`2` 为西太湖校区。
+ *
+ * # Returns
+ * `campus` 不是以上取值时返回 `false`，客户端不变。
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+ */
+bool cczuni_client_set_campus(struct DefaultClient *client_ptr, int32_t campus);
+
+/**
+ * 设置该客户端的函数返回的 JSON 的字段命名，便于 Kotlin/Dart 等默认使用 camelCase 的模型直接解析。
+ *
+ * # Arguments
+ * * `client_ptr` - 客户端指针。
+ * * `enabled` - 为 `true` 时字段名使用 camelCase（如 `courseCredits`），为 `false` 时恢复默认的
+ *   snake_case（如 `course_credits`）。嵌套的成绩、课表等记录同样转换；`raw` 中的原始记录与
+ *   取值保持不变。
+ *
+ * 启用后，传入该客户端函数的 JSON 参数（如 `cczuni_simulate_gpa` 的课程）也应使用 camelCase。
+ * 不接收客户端的函数不受影响：`cczuni_diff_schedule`、`cczuni_format_grade_notification` 等
+ * 两种命名的参数都接受，`cczuni_diff_schedule` 以传入课表的命名返回；`cczuni_get_services_status`
+ * 等始终返回 snake_case。
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+ */
+void cczuni_client_set_camel_case(struct DefaultClient *client_ptr, bool enabled);
+
+/**
+ * 设置该客户端课表中 `weekday` 的编号方式，与界面从周日还是周一开始排列一致。
+ *
+ * # Arguments
+ * * `client_ptr` - 客户端指针。
+ * * `sunday_first` - 为 `true` 时周日为 1、周一为 2……周六为 7；为 `false` 时恢复默认的
+ *   周一为 1……周日为 7。影响 `cczuni_get_schedule` 与 `cczuni_get_schedule_full`。
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+ */
+void cczuni_client_set_week_start(struct DefaultClient *client_ptr, bool sunday_first);
+
+/**
+ * 在后台定期检查统一身份认证的登录状态，失效时自动重新登录，避免用户请求时才等待登录。
+ *
+ * 每个客户端只会运行一个保活任务：已在运行时直接返回 `true`，间隔保持不变。
+ * 任务在调用 `cczuni_client_keep_alive_stop` 或 `cczuni_client_free` 时停止。
+ *
+ * # Arguments
+ * * `client_ptr` - 客户端指针。
+ * * `interval_secs` - 检查间隔，单位为秒，不能为 `0`。
+ *
+ * # Returns
+ * 保活任务在运行时返回 `true`，`interval_secs` 为 `0` 时返回 `false`。
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+ */
+bool cczuni_client_keep_alive_start(struct DefaultClient *client_ptr, uint64_t interval_secs);
+
+/**
+ * 停止 `cczuni_client_keep_alive_start` 启动的保活任务，没有任务时什么也不做。
+ *
+ * # Arguments
+ * * `client_ptr` - 客户端指针。
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+ */
+void cczuni_client_keep_alive_stop(struct DefaultClient *client_ptr);
+
+/**
+ * 在后台定期获取当前学期的课表，与上一次获取的课表比较，有变动（如换教室、调课）时调用 `callback`。
+ *
+ * 第一次获取的课表只用于比较，不会触发回调；获取失败时在下一次继续尝试。
+ * 获取比间隔还慢时不会重叠，错过的多次检查合并为一次。
+ *
+ * # Arguments
+ * * `client_ptr` - 客户端指针，任务使用它的一个副本登录教务企业微信。
+ * * `interval_secs` - 检查间隔，单位为秒，不能为 `0`。
+ * * `callback` - 课表变动时调用，见 `CczuniScheduleCallback`。
+ * * `user_data` - 原样传给 `callback` 的指针，可以为空。
+ *
+ * # Returns
+ * 返回任务指针，`interval_secs` 为 `0` 时返回空指针。
+ * **返回的指针必须使用 `cczuni_schedule_watch_stop` 停止并释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+ * `user_data` 在任务停止前必须一直有效，且可以在其他线程中使用。
+ */
+struct ScheduleWatch *cczuni_schedule_watch_start(struct DefaultClient *client_ptr,
+                                                  uint64_t interval_secs,
+                                                  CczuniScheduleCallback callback,
+                                                  void *user_data);
+
+/**
+ * 停止 `cczuni_schedule_watch_start` 启动的任务并释放它，正在进行的获取会被放弃，不再调用回调。
+ *
+ * # Arguments
+ * * `watch_ptr` - 任务指针。
+ *
+ * # Safety
+ * `watch_ptr` 必须为空指针，或由 `cczuni_schedule_watch_start` 返回且尚未释放。
+ */
+void cczuni_schedule_watch_stop(struct ScheduleWatch *watch_ptr);
+
 /**
  * 使用指定的客户端进行统一身份认证登录。
  *
@@ -40,9 +261,29 @@ void cczuni_client_free(struct DefaultClient *client_ptr);
  * # Returns
  * 返回一个 JSON 字符串，包含登录结果。
  * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
  */
 char *cczuni_login(struct DefaultClient *client_ptr);
 
+/**
+ * 提交短信验证码，继续被二次认证打断的登录。
+ *
+ * # Arguments
+ * * `client_ptr` - `cczuni_login` 返回二次认证错误的客户端指针。
+ * * `code` - C 字符串，收到的验证码。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，包含提交结果。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+ * `code` 必须是有效的、以 NUL 结尾的 C 字符串。
+ */
+char *cczuni_submit_second_factor(struct DefaultClient *client_ptr, const char *code);
+
 /**
  * 获取学生的成绩列表。
  *
@@ -52,9 +293,185 @@ char *cczuni_login(struct DefaultClient *client_ptr);
  * # Returns
  * 返回一个包含成绩信息的 JSON 字符串。
  * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
  */
 char *cczuni_get_grades(struct DefaultClient *client_ptr);
 
+/**
+ * 同 `cczuni_get_grades`，但整个操作（登录及获取成绩的所有请求）限时 `timeout_ms` 毫秒。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ * * `timeout_ms` - 整个操作的时限，毫秒。
+ *
+ * # Returns
+ * 与 `cczuni_get_grades` 相同；超过时限时中止尚未完成的请求，`error` 为超时信息。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+ */
+char *cczuni_get_grades_within(struct DefaultClient *client_ptr, uint64_t timeout_ms);
+
+/**
+ * 获取学生的成绩列表，同时附上教务系统返回的原始记录，供迁移时比对。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为 `{"parsed","raw"}`，`parsed` 与 `cczuni_get_grades`
+ * 的 `data` 相同，`raw` 为原始记录。确认一致后请改用 `cczuni_get_grades`。
+ * 宽松解析跳过的记录在 `warnings` 中说明，没有时省略。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+ */
+char *cczuni_get_grades_full(struct DefaultClient *client_ptr);
+
+/**
+ * 获取学生的总绩点与专业课绩点（学科基础与专业课程）。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为 `{"overall_gpa","major_gpa","credits"}`，
+ * 教务系统未给出课程的学分类别时 `major_gpa` 为 `null`。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+ */
+char *cczuni_get_gpa(struct DefaultClient *client_ptr);
+
+/**
+ * 根据成绩估算学业预警：未重修通过的不及格学分与总绩点。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为
+ * `{"level","source","failed_credits","failed_courses","reasons"}`，
+ * `level` 为 `none`/`yellow`/`red`；`source` 为 `computed`，表示结果由本库估算，并非教务系统给出；
+ * `reasons` 每项为 `{"kind": "failed_credits", "credits", "threshold"}` 或
+ * `{"kind": "low_gpa", "gpa", "threshold"}`。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+ */
+char *cczuni_get_academic_warning(struct DefaultClient *client_ptr);
+
+/**
+ * 按学分类别汇总已获得的学分（重修通过的课程只计一次，未通过的不计）。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ * * `requirements_json` - C 字符串，各类别的毕业要求学分，如 `{"major_required": 60, "practical": 20}`，
+ *   类别为 `general_required`/`general_elective`/`major_required`/`major_elective`/`practical`/`uncategorized`；
+ *   本库不获取培养方案，不需要时传 `{}`。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为 `{"categories","total_earned","has_uncategorized"}`，
+ * `categories` 每项为 `{"category","earned","required","courses"}`。
+ * 教务系统未给出学分类别的课程计入 `uncategorized`，此时 `has_uncategorized` 为 `true`。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+ * `requirements_json` 必须是有效的、以 NUL 结尾的 C 字符串。
+ */
+char *cczuni_get_credit_summary(struct DefaultClient *client_ptr, const char *requirements_json);
+
+/**
+ * 获取学生每个学期的绩点，以及到该学期为止的累计绩点，按学期先后排列。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为 `[{"term","term_gpa","cumulative_gpa","credits"}]`。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+ */
+char *cczuni_get_gpa_trend(struct DefaultClient *client_ptr);
+
+/**
+ * 加上假设的课程及其预期绩点，估算之后的总绩点。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ * * `hypotheticals_json` - C 字符串，JSON 数组 `[{"course_id", "credits", "grade_points"}]`，
+ *   `course_id` 可省略；填写已修课程的 `course_id` 表示重修，预期绩点将替换原成绩。
+ *   `credits` 须在 0 到 50 之间，`grade_points` 须在 0 到 5 之间，否则返回错误。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为 `{"projected_gpa", "projected_credits"}`。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+ * `hypotheticals_json` 必须是有效的、以 NUL 结尾的 C 字符串。
+ */
+char *cczuni_simulate_gpa(struct DefaultClient *client_ptr, const char *hypotheticals_json);
+
+/**
+ * 获取上次查询之后新出或有变动的成绩，用于轮询成绩通知。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ * * `token` - C 字符串，上次返回的 `token`；第一次查询传空字符串，此时所有成绩都是新的。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为 `{"token", "new_grades", "finalized"}`，`finalized` 为之前暂定或未录入、现已确认的成绩，
+ * 调用方需要保存 `token` 供下次使用。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+ * `token` 必须是有效的、以 NUL 结尾的 C 字符串。
+ */
+char *cczuni_get_new_grades(struct DefaultClient *client_ptr, const char *token);
+
+/**
+ * 把新成绩整理成一行通知文字，如 `3 门新成绩：高等数学A1 92、…`，最多列出 3 门课程。
+ *
+ * # Arguments
+ * * `grades_json` - C 字符串，成绩的 JSON 数组，如 `cczuni_get_new_grades` 返回的 `new_grades`。
+ * * `locale` - C 字符串，语言标签，`en` 开头为英文，其余为中文。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为通知文字。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `grades_json` 与 `locale` 必须是有效的、以 NUL 结尾的 C 字符串。
+ */
+char *cczuni_format_grade_notification(const char *grades_json, const char *locale);
+
+/**
+ * 把 `cczuni_get_new_grades` 返回的 `finalized` 整理成一行通知文字，如 `成绩已确认：线性代数 85`。
+ *
+ * # Arguments
+ * * `grades_json` - C 字符串，成绩的 JSON 数组。
+ * * `locale` - C 字符串，语言标签，`en` 开头为英文，其余为中文。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为通知文字。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `grades_json` 与 `locale` 必须是有效的、以 NUL 结尾的 C 字符串。
+ */
+char *cczuni_format_finalized_notification(const char *grades_json, const char *locale);
+
 /**
  * 获取学生的课表信息。
  *
@@ -62,26 +479,289 @@ char *cczuni_get_grades(struct DefaultClient *client_ptr);
  * * `client_ptr` - **已登录的**客户端指针。
  *
  * # Returns
- * 返回一个包含课表信息的 JSON 字符串。
+ * 返回一个包含课表信息的 JSON 字符串，`data` 为课程列表，每项为
+ * `{"course", "teacher", "location", "campus", "weekday", "start_period", "end_period", "start_time", "end_time", "weeks"}`，
+ * `weekday` 从 1（周一）到 7（周日）（见 `cczuni_client_set_week_start`），节次从 1 开始且包含 `end_period`，
+ * `weeks` 为上课的所有周次（已按单双周展开），`campus` 为客户端设置的校区，未设置时为 `null`。
+ * `start_time`/`end_time` 为作息表中的上下课时间（如 `08:00`），两个校区的作息表相同。
+ * 教务系统给出教师代码时另有 `teacher_id`，与 `teacher` 中的教师一一对应。
  * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
  */
 char *cczuni_get_schedule(struct DefaultClient *client_ptr);
 
+/**
+ * 同 `cczuni_get_schedule`，但整个操作（登录、获取学期及课表的所有请求）限时 `timeout_ms` 毫秒。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ * * `timeout_ms` - 整个操作的时限，毫秒。
+ *
+ * # Returns
+ * 与 `cczuni_get_schedule` 相同；超过时限时中止尚未完成的请求，`error` 为超时信息。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+ */
+char *cczuni_get_schedule_within(struct DefaultClient *client_ptr, uint64_t timeout_ms);
+
+/**
+ * 获取学生的课表信息，同时附上教务系统返回的原始记录，供迁移时比对。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为 `{"parsed","raw"}`，`parsed` 与 `cczuni_get_schedule`
+ * 的 `data` 相同，`raw` 为原始的课表行。确认一致后请改用 `cczuni_get_schedule`。
+ * 宽松解析跳过的记录与未知的列在 `warnings` 中说明，没有时省略。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+ */
+char *cczuni_get_schedule_full(struct DefaultClient *client_ptr);
+
+/**
+ * 获取学生的课表矩阵，即教务系统原始的排列方式。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为二维数组，行为节次，列为周一到周日，
+ * 每格为 `{"course", "teacher"}`。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+ */
+char *cczuni_get_schedule_matrix(struct DefaultClient *client_ptr);
+
+/**
+ * 导出当前学期的课表为 iCalendar（`.ics`）文件的内容，可以直接写入文件或交给系统日历导入。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ * * `first_week_date` - C 字符串，第一周周一的日期，如 `20250901`。
+ * * `out_len` - 用于写入返回内容的字节数。
+ * * `error_ptr` - 出错时写入错误的 JSON 字符串（需用 `cczuni_free_string` 释放），不需要时传空指针。
+ *
+ * # Returns
+ * 返回指向 UTF-8 编码的 iCalendar 内容的指针，共 `*out_len` 字节，**不以 NUL 结尾**。
+ * 出错时返回空指针，`*out_len` 为 0。
+ * **返回的内容归调用方所有，必须使用 `cczuni_free_bytes` 并传入同一 `*out_len` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+ * `first_week_date` 必须是有效的、以 NUL 结尾的 C 字符串，`out_len` 必须是有效的、可写的指针，
+ * `error_ptr` 必须为空指针或有效的、可写的指针。
+ */
+uint8_t *cczuni_get_schedule_ics(struct DefaultClient *client_ptr,
+                                 const char *first_week_date,
+                                 uintptr_t *out_len,
+                                 char **error_ptr);
+
+/**
+ * 导出当前学期的课表为 FullCalendar 的事件列表，供网页端直接使用。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ * * `first_week_date` - C 字符串，第一周周一的日期，如 `20250901`。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为事件列表，每项为 `{"title","start","end","rrule","duration","extendedProps"}`：
+ * 每周（或单双周）上课的课程为一个带 `rrule`（rrule 插件格式 `{"freq","interval","dtstart","count"}`）
+ * 与 `duration`（如 `01:25`）的事件，没有 `start`/`end`；只上一周的课程为带 `start`/`end` 的单个事件，
+ * 没有 `rrule`/`duration`。时间均为北京时间（`+08:00`），`extendedProps` 为该课程的
+ * `{"course","teacher","location","campus","weekday","start_period","end_period","start_time","end_time","weeks"}`，`weekday` 从周一开始。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+ * `first_week_date` 必须是有效的、以 NUL 结尾的 C 字符串。
+ */
+char *cczuni_get_schedule_fullcalendar(struct DefaultClient *client_ptr, const char *first_week_date);
+
+/**
+ * 比较两次获取的课表，找出新增、删除和变动的课程，用于提醒学生调课、换教室。
+ *
+ * 同一课程、同一教师的课出现在新的时间或教室时视为变动，而不是删除后新增。
+ *
+ * # Arguments
+ * * `old_json` - C 字符串，之前保存的 `cczuni_get_schedule` 的 `data`。
+ * * `new_json` - C 字符串，新获取的 `cczuni_get_schedule` 的 `data`。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为 `{"added", "removed", "changed"}`，`added`/`removed` 为课程列表，
+ * `changed` 每项为 `{"before", "after", "kinds"}`，`kinds` 为 `location`/`time`/`weeks` 中变动的部分。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `old_json` 与 `new_json` 必须是有效的、以 NUL 结尾的 C 字符串。
+ */
+char *cczuni_diff_schedule(const char *old_json, const char *new_json);
+
+/**
+ * 登录一次教务企业微信，同时获取首页所需的学生信息、绩点、当前周次与学业预警。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ * * `first_week_date` - C 字符串，第一周周一的日期，如 `20250901`。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为 `{"profile","gpa","current_week","academic_warning"}`，
+ * 每一项都是 `{"success","data","error"}`，某一项获取失败不影响其他项。
+ * `profile` 的 `data` 为教务系统给出的学生信息（含 `xh`、`xm`、`bj`、`pjxfjd` 等），
+ * `gpa` 同 `cczuni_get_gpa`，`academic_warning` 同 `cczuni_get_academic_warning`，
+ * `current_week` 为 `{"week","estimated"}`，按北京时间计算，与设备时区无关。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+ * `first_week_date` 必须是有效的、以 NUL 结尾的 C 字符串。
+ */
+char *cczuni_get_overview(struct DefaultClient *client_ptr, const char *first_week_date);
+
+/**
+ * 登录一次教务企业微信，获取本学期每门课程的上课时间与成绩，用于课程卡片。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为数组，每项为 `{"course","course_id","teachers","meetings","grade"}`，
+ * 按第一次上课的时间排列。`meetings` 每项同 `cczuni_diff_schedule` 的课表项，
+ * `grade` 为同名课程的成绩（忽略空格与全角字符，重修的课程优先取任课教师相同的那次），尚无成绩时为 `null`。
+ * 课表中没有的课程不会出现。暂不包含评教状态。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+ */
+char *cczuni_get_course_overview(struct DefaultClient *client_ptr);
+
+/**
+ * 登录一次教务企业微信，导出能获取的全部信息（学生信息、各学期成绩、绩点与本学期课表），用于毕业前备份。
+ *
+ * # Arguments
+ * * `client_ptr` - **已登录的**客户端指针。
+ * * `out_len` - 用于写入返回内容的字节数。
+ * * `error_ptr` - 出错时写入错误的 JSON 字符串（需用 `cczuni_free_string` 释放），不需要时传空指针。
+ *
+ * # Returns
+ * 返回指向 UTF-8 编码的 JSON 文档的指针，共 `*out_len` 字节，**不以 NUL 结尾**，可以直接写入文件。
+ * 文档为 `{"version","profile","grades","gpa","schedule"}`，`version` 为导出时的库版本，
+ * 其余每项为 `{"data": ...}`，获取失败时为 `{"error": "..."}`，某一项失败不影响其他项。
+ * `profile` 同 `cczuni_get_overview` 的 `profile`，`grades` 同 `cczuni_get_grades`，`gpa` 同 `cczuni_get_gpa`，
+ * `schedule` 同 `cczuni_get_schedule`。字段名始终为 snake_case，不受 `cczuni_client_set_camel_case` 影响。
+ * 暂不包含获奖与体测记录。
+ * 出错时返回空指针，`*out_len` 为 0。
+ * **返回的内容归调用方所有，必须使用 `cczuni_free_bytes` 并传入同一 `*out_len` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，`out_len` 必须是有效的、可写的指针，
+ * `error_ptr` 必须为空指针或有效的、可写的指针。
+ */
+uint8_t *cczuni_get_archive(struct DefaultClient *client_ptr,
+                            uintptr_t *out_len,
+                            char **error_ptr);
+
+/**
+ * 运行自检：依次检查统一身份认证是否可达、能否登录，以及登录后能否获取数据，用于排查问题。
+ *
+ * # Arguments
+ * * `client_ptr` - 客户端指针。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为 `{"steps", "failed_step"}`，
+ * `steps` 每项为 `{"name", "ok", "elapsed_ms", "kind", "error"}`，在第一个失败的步骤处停止，
+ * `kind` 为 `network`/`auth`/`second_factor`/`password_change`/`not_activated`/`rate_limited`/`parse`。报告中不会出现密码，自检不会修改任何数据。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ *
+ * # Safety
+ * `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+ */
+char *cczuni_selftest(struct DefaultClient *client_ptr);
+
 /**
  * 获取各个服务的在线状态。
  *
  * # Returns
- * 返回一个包含服务状态的 JSON 字符串。
+ * 返回一个包含服务状态的 JSON 字符串，每个服务为 `{"health": "up", "code": 200}`，
+ * `health` 为 `up`/`degraded`/`auth_redirect`/`down`/`timeout`/`maintenance`/`captive_portal`/`unknown`，
+ * `maintenance` 表示服务返回了维护公告页面，此时 `message` 为公告内容，
+ * `captive_portal` 表示当前网络需要先进行上网认证（被重定向到了认证页面），
+ * 服务有响应时还带有 `latency_ms`（本次响应时间，毫秒）和 `avg_latency_ms`（多次查询的滑动平均），
+ * 服务无响应时 `code` 为 `null`、没有 `latency_ms`，`avg_latency_ms` 为之前的平均值（如果有）。
+ * 每个服务最多等待 3 秒，超时为 `timeout`；可以用 `cczuni_cancel` 中止。
  * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
  */
 char *cczuni_get_services_status(void);
 
+/**
+ * 获取各个服务的在线状态，以列表形式返回，带有服务的名称与地址，无需另外维护服务名称表。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串，`data` 为数组，每项为
+ * `{"id", "display_name", "url", "health", "checked_at", "latency_ms"}`，
+ * `id` 为 `sso`/`wechat`/`webvpn`，`display_name` 为服务的中文名称，`health` 同 `cczuni_get_services_status`，
+ * `checked_at` 为查询时间（Unix 秒），服务无响应时 `latency_ms` 为 `null`。
+ * 每个服务最多等待 3 秒，可以用 `cczuni_cancel` 中止。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ */
+char *cczuni_get_services_status_list(void);
+
+/**
+ * 中止正在进行的 `cczuni_get_services_status` 或 `cczuni_get_services_status_list`，可以从其他线程调用。
+ *
+ * 被中止的调用立即返回错误 `Status sweep cancelled`，之后的调用不受影响。
+ */
+void cczuni_cancel(void);
+
+/**
+ * 获取 cczuni 库的版本号。
+ *
+ * # Returns
+ * 返回版本号字符串，如 `0.1.0`。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ */
+char *cczuni_version(void);
+
+/**
+ * 获取编译时启用的可选功能，用于在界面上只显示可用的功能。
+ *
+ * # Returns
+ * 返回一个 JSON 字符串数组，如 `["rustls-tls","webvpn-login","calendar"]`，
+ * 为 `Cargo.toml` 中启用的 feature（`full` 与 `default` 以其包含的 feature 列出）。
+ * **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+ */
+char *cczuni_features(void);
+
 /**
  * 释放由 cczuni 库函数返回的字符串所占用的内存。
  *
  * # Arguments
  * * `string_ptr` - 指向由其他 `cczuni_` 函数返回的 C 字符串的指针。
+ *
+ * # Safety
+ * `string_ptr` 必须为空指针，或由 `cczuni_` 函数返回且尚未释放。
  */
 void cczuni_free_string(char *string_ptr);
 
+/**
+ * 释放由 cczuni 库函数返回的字节（如 `cczuni_get_schedule_ics` 的内容）所占用的内存。
+ *
+ * # Arguments
+ * * `bytes_ptr` - 指向由其他 `cczuni_` 函数返回的字节的指针。
+ * * `len` - 该函数写入 `out_len` 的字节数。
+ *
+ * # Safety
+ * `bytes_ptr` 必须为空指针，或由 `cczuni_` 函数返回且尚未释放，`len` 必须与返回时的长度一致。
+ */
+void cczuni_free_bytes(uint8_t *bytes_ptr, uintptr_t len);
+
 #endif  /* CCZUNI_FFI_H */
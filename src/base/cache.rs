@@ -0,0 +1,112 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use reqwest::{header::HeaderMap, Method, Response, ResponseBuilderExt, StatusCode, Url, Version};
+
+use super::typing::{other_error, TorErr};
+
+/// A response with its whole body, that can be handed out again as a [`Response`].
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub url: Url,
+    pub status: StatusCode,
+    pub version: Version,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl CachedResponse {
+    pub fn to_response(&self) -> TorErr<Response> {
+        let mut builder = http::Response::builder()
+            .status(self.status)
+            .version(self.version)
+            .url(self.url.clone());
+        if let Some(map) = builder.headers_mut() {
+            *map = self.headers.clone();
+        }
+        Ok(builder.body(self.body.clone()).map_err(other_error)?.into())
+    }
+}
+
+/// Opt-in cache of the read requests of a client, see
+/// [`crate::impls::client::DefaultClient::with_cache`].
+///
+/// Entries are keyed by method, url and body, so two POST reads with different parameters
+/// don't share an entry. Only successful answers are kept.
+#[derive(Debug)]
+pub struct ResponseCache {
+    default_ttl: Duration,
+    /// (part of the url, ttl), the first match wins
+    ttls: Vec<(String, Duration)>,
+    entries: Mutex<HashMap<String, (Instant, CachedResponse)>>,
+}
+
+impl ResponseCache {
+    pub fn new(default_ttl: Duration) -> Self {
+        Self {
+            default_ttl,
+            ttls: vec![],
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Use `ttl` for the urls containing `endpoint`, `Duration::ZERO` to never cache them.
+    pub fn with_ttl(mut self, endpoint: impl Into<String>, ttl: Duration) -> Self {
+        self.ttls.push((endpoint.into(), ttl));
+        self
+    }
+
+    pub fn ttl(&self, url: &Url) -> Duration {
+        self.ttls
+            .iter()
+            .find(|(endpoint, _)| url.as_str().contains(endpoint.as_str()))
+            .map(|(_, ttl)| *ttl)
+            .unwrap_or(self.default_ttl)
+    }
+
+    pub fn key(method: &Method, url: &Url, body: Option<&[u8]>) -> String {
+        format!(
+            "{method} {url}\n{}",
+            String::from_utf8_lossy(body.unwrap_or_default())
+        )
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((expires, response)) if *expires > Instant::now() => Some(response.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: String, response: CachedResponse) {
+        let ttl = self.ttl(&response.url);
+        if ttl.is_zero() {
+            return;
+        }
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key, (Instant::now() + ttl, response));
+    }
+
+    /// Drop every entry of `url`, whatever the method and the body.
+    pub fn invalidate(&self, url: &Url) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|_, (_, response)| &response.url != url);
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
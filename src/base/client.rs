@@ -1,28 +1,195 @@
 use reqwest_cookie_store::CookieStoreMutex;
-use std::{collections::HashMap, sync::Arc};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 
+use super::{cache::ResponseCache, metrics::Metrics};
+
 /// You must decide what account to use to invoke different method!
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Account {
     pub user: String,
     pub password: String,
 }
 
-impl Default for Account {
-    fn default() -> Self {
+impl Account {
+    pub fn new(user: impl Into<String>, password: impl Into<String>) -> Self {
         Self {
-            user: Default::default(),
-            password: Default::default(),
+            user: user.into(),
+            password: password.into(),
         }
     }
 }
 
-impl Account {
-    pub fn new(user: impl Into<String>, password: impl Into<String>) -> Self {
+/// Bodies are refused past 16 MiB by default, far above any page of the portals.
+pub const DEFAULT_MAX_RESPONSE_SIZE: usize = 16 * 1024 * 1024;
+
+/// A wait before asking again an endpoint that answered empty, for
+/// [`ClientOptions::retry_on_empty`].
+pub const DEFAULT_RETRY_ON_EMPTY: Duration = Duration::from_millis(500);
+
+/// The portals answer in English to an English browser, and the parsers expect the Chinese pages.
+pub const DEFAULT_ACCEPT_LANGUAGE: &str = "zh-CN";
+
+/// Tunables of a [`Client`]
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    /// Bodies bigger than this are refused with
+    /// [`crate::base::typing::CczuError::ResponseTooLarge`] while reading them.
+    pub max_response_size: usize,
+    /// Campus of the account, for the dorm buildings of the card app, and told in the
+    /// schedule entries. The campuses share one period table, the calendar doesn't depend on
+    /// it. Nothing tells the campus of an account, it is never guessed.
+    ///
+    /// `None` when unknown, the dorm buildings then cover every campus.
+    pub campus: Option<Campus>,
+    /// `Accept-Language` of every request, [`DEFAULT_ACCEPT_LANGUAGE`] by default
+    pub accept_language: String,
+    /// Some endpoints sometimes answer an empty list for a moment. When one of those that
+    /// shouldn't be empty does, ask again once after this delay. `None`, the default, takes the
+    /// first answer, turn it on here or for an endpoint of [`Self::endpoints`].
+    pub retry_on_empty: Option<Duration>,
+    /// [`ParseMode::Lenient`] by default
+    pub parse_mode: ParseMode,
+    /// Proxy of every request, the system one (`HTTP_PROXY`...) when `None`
+    pub proxy: Option<reqwest::Proxy>,
+    /// Give up on a request after this long, `None` waits as long as the portal takes
+    pub timeout: Option<Duration>,
+    /// When an app answers [`crate::base::typing::CczuError::RateLimited`] with a
+    /// `Retry-After` up to this long, wait it out and ask again once. `None`, the default,
+    /// returns the error right away.
+    pub wait_rate_limit: Option<Duration>,
+    /// Post the "agree and continue" of a terms-of-use or notice page shown during the login
+    /// (on the first login of a term for some), instead of stopping on it. On by default,
+    /// see [`crate::impls::login::sso::SSOUniversalLogin::login_warnings`].
+    pub accept_agreements: bool,
+    /// Which login SSO serves, [`SSOVariant::Auto`] by default
+    pub sso_variant: SSOVariant,
+    /// Keep the redirects followed by the logins, to tell where a login went, see
+    /// [`crate::impls::login::sso::SSOUniversalLogin::login_redirects`]. Off by default.
+    pub record_login_redirects: bool,
+    /// Name the keys of the JSON the C API ([`crate::ffi`]) answers for this client in
+    /// camelCase, like `courseCredits`, and read its JSON arguments so. Off by default,
+    /// `course_credits`.
+    pub camel_case: bool,
+    /// Day the weeks of the schedules of the C API for this client start with, Monday by
+    /// default. The Rust functions take it as an argument, see
+    /// [`crate::extension::calendar::flatten_week_matrix_from`].
+    pub week_start: WeekStart,
+    /// Options of single endpoints over the ones above, by the name of the endpoint in its url,
+    /// like `cj_xh` for the grades of jwqywx or `kb_xq_xh` for its schedule
+    pub endpoints: HashMap<String, EndpointOptions>,
+}
+
+/// What an endpoint does differently from the rest of the client, see
+/// [`ClientOptions::endpoints`]. `None` keeps the option of the client.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EndpointOptions {
+    pub parse_mode: Option<ParseMode>,
+    /// `Some(None)` never asks again, whatever [`ClientOptions::retry_on_empty`] says
+    pub retry_on_empty: Option<Option<Duration>>,
+}
+
+impl ClientOptions {
+    /// [`Self::parse_mode`] of `endpoint`, with its override.
+    pub fn parse_mode_of(&self, endpoint: &str) -> ParseMode {
+        self.endpoints
+            .get(endpoint)
+            .and_then(|options| options.parse_mode)
+            .unwrap_or(self.parse_mode)
+    }
+
+    /// [`Self::retry_on_empty`] of `endpoint`, with its override.
+    pub fn retry_on_empty_of(&self, endpoint: &str) -> Option<Duration> {
+        self.endpoints
+            .get(endpoint)
+            .and_then(|options| options.retry_on_empty)
+            .unwrap_or(self.retry_on_empty)
+    }
+}
+
+/// What the parsers do with the records and columns they don't understand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Fail, as soon as the portal changes something (for tests)
+    Strict,
+    /// Skip them with a warning and return the rest, the app keeps working through small
+    /// portal changes
+    #[default]
+    Lenient,
+}
+
+/// The login handshakes of SSO, the classic CAS form or the JSON login of the newer portal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SSOVariant {
+    /// The one the login page is made for: the JSON login when it declares its API
+    #[default]
+    Auto,
+    /// Post the form of the login page, with its execution token
+    Classic,
+    /// Post the account as JSON to the API of the login page, `api/login` next to the page
+    /// when it declares none
+    Json,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
         Self {
-            user: user.into(),
-            password: password.into(),
+            max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            campus: None,
+            accept_language: DEFAULT_ACCEPT_LANGUAGE.to_owned(),
+            retry_on_empty: None,
+            parse_mode: ParseMode::default(),
+            proxy: None,
+            timeout: None,
+            wait_rate_limit: None,
+            accept_agreements: true,
+            sso_variant: SSOVariant::default(),
+            record_login_redirects: false,
+            camel_case: false,
+            week_start: WeekStart::default(),
+            endpoints: HashMap::new(),
+        }
+    }
+}
+
+/// Campuses of CCZU
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Campus {
+    /// 武进校区
+    Wujin,
+    /// 西太湖校区
+    Xitaihu,
+}
+
+impl Campus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Campus::Wujin => "武进校区",
+            Campus::Xitaihu => "西太湖校区",
+        }
+    }
+}
+
+/// The day a week starts with for the caller, numbering
+/// [`crate::extension::calendar::ScheduleEntry::weekday`] from it.
+///
+/// [`crate::extension::calendar::ParsedCourse::day`] and the week matrix stay Monday first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeekStart {
+    #[default]
+    Monday,
+    Sunday,
+}
+
+impl WeekStart {
+    /// `weekday`, 1 as Monday, numbered from this day.
+    pub fn weekday(self, weekday: usize) -> usize {
+        match self {
+            WeekStart::Monday => weekday,
+            WeekStart::Sunday => weekday % 7 + 1,
         }
     }
 }
@@ -38,7 +205,7 @@ pub enum Property {
 impl Property {
     pub fn get_bool(&self) -> Option<bool> {
         match self {
-            Property::Bool(value) => Some(value.clone()),
+            Property::Bool(value) => Some(*value),
             _ => None,
         }
     }
@@ -49,7 +216,7 @@ impl Property {
 
     pub fn get_i32(&self) -> Option<i32> {
         match self {
-            Property::I32(value) => Some(value.clone()),
+            Property::I32(value) => Some(*value),
             _ => None,
         }
     }
@@ -86,4 +253,23 @@ pub trait Client {
     fn reqwest_client(&self) -> reqwest::Client;
     fn cookies(&self) -> Arc<CookieStoreMutex>;
     fn properties(&self) -> Arc<RwLock<HashMap<&'static str, Property>>>;
+    fn options(&self) -> ClientOptions {
+        ClientOptions::default()
+    }
+    fn campus(&self) -> Option<Campus> {
+        self.options().campus
+    }
+    /// No cache unless the client is given one.
+    fn cache(&self) -> Option<Arc<ResponseCache>> {
+        None
+    }
+    /// Told about the requests of the client, none by default.
+    fn metrics(&self) -> Option<Arc<dyn Metrics>> {
+        None
+    }
+    /// Writing the bodies the client reads as fixtures, none by default.
+    #[cfg(any(test, feature = "record-fixtures"))]
+    fn fixture_recorder(&self) -> Option<Arc<crate::utils::record::FixtureRecorder>> {
+        None
+    }
 }
@@ -0,0 +1,76 @@
+use std::{fmt::Debug, sync::Mutex};
+
+#[cfg(feature = "cookie-file")]
+use std::{io::ErrorKind, path::PathBuf};
+
+use super::typing::{other_error, EmptyOrErr, TorErr};
+
+/// Where the cookies of a [`crate::impls::client::DefaultClient`] are kept between two clients,
+/// see [`crate::impls::client::DefaultClient::with_cookie_store`].
+///
+/// The cookies are handed over as the JSON of
+/// [`crate::impls::client::DefaultClient::export_session`], tickets included: a store
+/// is trusted with the login of the account, a keystore is the right place on mobile.
+pub trait CookieStore: Debug + Send + Sync {
+    /// The cookies saved last, `None` when nothing was saved yet.
+    fn load(&self) -> TorErr<Option<String>>;
+
+    fn save(&self, cookies: &str) -> EmptyOrErr;
+}
+
+/// Keeps the cookies for as long as it lives, to share a login between clients of one process.
+#[derive(Debug, Default)]
+pub struct MemoryCookieStore {
+    cookies: Mutex<Option<String>>,
+}
+
+impl CookieStore for MemoryCookieStore {
+    fn load(&self) -> TorErr<Option<String>> {
+        Ok(self
+            .cookies
+            .lock()
+            .map_err(|e| other_error(e.to_string()))?
+            .clone())
+    }
+
+    fn save(&self, cookies: &str) -> EmptyOrErr {
+        *self
+            .cookies
+            .lock()
+            .map_err(|e| other_error(e.to_string()))? = Some(cookies.to_owned());
+        Ok(())
+    }
+}
+
+/// Keeps the cookies in a file, for the desktop where the login should survive the process.
+#[cfg(feature = "cookie-file")]
+#[derive(Debug, Clone)]
+pub struct FileCookieStore {
+    path: PathBuf,
+}
+
+#[cfg(feature = "cookie-file")]
+impl FileCookieStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[cfg(feature = "cookie-file")]
+impl CookieStore for FileCookieStore {
+    fn load(&self) -> TorErr<Option<String>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(cookies) => Ok(Some(cookies)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Written next to the file then renamed, a crash never leaves half of the cookies.
+    fn save(&self, cookies: &str) -> EmptyOrErr {
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        std::fs::write(&temporary, cookies)?;
+        std::fs::rename(&temporary, &self.path)
+    }
+}
@@ -0,0 +1,72 @@
+use std::{fmt::Debug, time::Duration};
+
+use reqwest::{Response, StatusCode};
+
+/// Told about every request sent to the portals, to count them and time them per endpoint,
+/// see [`crate::impls::client::DefaultClient::with_metrics`].
+///
+/// Called on the task sending the request, so it should only record: a Prometheus exporter
+/// bumps its counters and histograms here and serves them elsewhere.
+///
+/// The answers of the [`crate::base::cache::ResponseCache`] are not requests and not reported.
+pub trait Metrics: Debug + Send + Sync {
+    /// `endpoint` is the host and path of the url, without the query, like
+    /// `jwqywx.cczu.edu.cn/api/cj_xh`. `duration` runs until the head of the response.
+    fn on_request(&self, endpoint: &str, duration: Duration, outcome: RequestOutcome) {
+        let _ = (endpoint, duration, outcome);
+    }
+}
+
+/// Records nothing, what a client without metrics does.
+#[derive(Debug, Default)]
+pub struct NoMetrics;
+
+impl Metrics for NoMetrics {}
+
+/// How a request ended, by the status of its response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequestOutcome {
+    /// `2xx`
+    Success,
+    /// `3xx`, the logins go through many of them
+    Redirect,
+    /// `4xx`
+    ClientError,
+    /// `5xx`
+    ServerError,
+    /// No response within the timeout of the client
+    Timeout,
+    /// No response at all, the connection failed
+    NetworkError,
+}
+
+impl RequestOutcome {
+    pub fn of_status(status: StatusCode) -> Self {
+        match status.as_u16() {
+            300..=399 => Self::Redirect,
+            400..=499 => Self::ClientError,
+            500..=599 => Self::ServerError,
+            _ => Self::Success,
+        }
+    }
+
+    pub fn of(result: &reqwest::Result<Response>) -> Self {
+        match result {
+            Ok(response) => Self::of_status(response.status()),
+            Err(error) if error.is_timeout() => Self::Timeout,
+            Err(error) => error.status().map_or(Self::NetworkError, Self::of_status),
+        }
+    }
+
+    /// Snake case name, for a label.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Redirect => "redirect",
+            Self::ClientError => "client_error",
+            Self::ServerError => "server_error",
+            Self::Timeout => "timeout",
+            Self::NetworkError => "network_error",
+        }
+    }
+}
@@ -1,3 +1,8 @@
 pub mod app;
+pub mod cache;
 pub mod client;
+pub mod cookies;
+pub mod metrics;
 pub mod typing;
+pub mod units;
+pub mod version;
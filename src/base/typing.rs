@@ -1,9 +1,159 @@
-use std::io::ErrorKind;
+use std::{fmt::Display, time::Duration};
 
 pub type TorErr<T> = tokio::io::Result<T>;
 pub type EmptyOrErr = TorErr<()>;
 
 #[inline]
 pub fn other_error(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> tokio::io::Error {
-    tokio::io::Error::new(ErrorKind::Other, error)
+    tokio::io::Error::other(error)
+}
+
+/// Errors the caller may want to react to, instead of only showing the message.
+///
+/// They are carried inside [`tokio::io::Error`] like any other error of this crate,
+/// use [`TypedErrorExt::typed`] to get them back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CczuError {
+    /// SSO wants a SMS code or a phone binding before finishing the login.
+    ///
+    /// Finish it with [`crate::impls::login::sso::SSOUniversalLogin::submit_second_factor`].
+    SecondFactorRequired { method: String, hint: String },
+    /// The body went past [`crate::base::client::ClientOptions::max_response_size`].
+    ResponseTooLarge { limit: usize },
+    /// The portal refused, the account doesn't have the role for this.
+    Forbidden,
+    /// The course has no breakdown of its grade, or isn't graded.
+    DetailNotAvailable,
+    /// SSO makes the account change its password before logging in, at `url` in a browser.
+    PasswordChangeRequired { url: String },
+    /// A new account not activated yet, SSO refuses it as if the password was wrong. The
+    /// activation is done at `activation_url` in a browser.
+    AccountNotActivated { activation_url: String },
+    /// The login answered without a token usable as `Authorization`.
+    TokenExtractionFailed,
+    /// An app answered with a login page instead of its data: log in again and retry.
+    SessionExpired,
+    /// Jwqywx refused its token as expired, its fetches log in again once by themselves before
+    /// giving up with this.
+    TokenExpired,
+    /// The portal answered `429` or its page saying the IP is blocked for a while. Wait
+    /// `retry_after` (its `Retry-After`, unknown without one) before asking again, asking
+    /// sooner only makes the block longer.
+    RateLimited { retry_after: Option<Duration> },
+    /// Jwqywx answered with a `status` other than its success, `reason` the `message` it gave
+    /// when it was one.
+    Refused { status: i32, reason: String },
+    /// The answer ended before its JSON did, cut off by the portal or on the way: ask again.
+    Truncated,
+    /// The whole operation took longer than `deadline`, see
+    /// [`crate::utils::deadline::with_deadline`].
+    DeadlineExceeded { deadline: Duration },
+}
+
+impl Display for CczuError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CczuError::SecondFactorRequired { method, hint } => {
+                write!(f, "Second factor `{method}` required")?;
+                if !hint.is_empty() {
+                    write!(f, " ({hint})")?;
+                }
+                Ok(())
+            }
+            CczuError::ResponseTooLarge { limit } => {
+                write!(f, "Response is larger than {limit} bytes")
+            }
+            CczuError::Forbidden => write!(f, "Permission denied"),
+            CczuError::DetailNotAvailable => write!(f, "No detail available"),
+            CczuError::PasswordChangeRequired { url } => {
+                write!(f, "Password change required at {url}")
+            }
+            CczuError::AccountNotActivated { activation_url } => {
+                write!(f, "Account not activated, activate it at {activation_url}")
+            }
+            CczuError::TokenExtractionFailed => write!(f, "No token in the login answer"),
+            CczuError::SessionExpired => write!(f, "Session expired, login again"),
+            CczuError::TokenExpired => write!(f, "Token expired, login again"),
+            CczuError::RateLimited { retry_after } => {
+                write!(f, "Rate limited by the portal")?;
+                if let Some(retry_after) = retry_after {
+                    write!(f, ", retry after {}s", retry_after.as_secs())?;
+                }
+                Ok(())
+            }
+            CczuError::Refused { status, reason } => {
+                write!(f, "Refused with status {status}")?;
+                if !reason.is_empty() {
+                    write!(f, ": {reason}")?;
+                }
+                Ok(())
+            }
+            CczuError::Truncated => write!(f, "Answer cut off before its end"),
+            CczuError::DeadlineExceeded { deadline } => {
+                write!(f, "Not done within {}ms", deadline.as_millis())
+            }
+        }
+    }
+}
+
+impl std::error::Error for CczuError {}
+
+#[inline]
+pub fn typed_error(error: CczuError) -> tokio::io::Error {
+    other_error(error)
+}
+
+/// An error of a traced operation, with the id its log lines carry.
+///
+/// [`TypedErrorExt`] looks through it, the kind of the wrapped error is kept.
+#[derive(Debug)]
+pub struct OperationError {
+    pub operation: u64,
+    pub source: tokio::io::Error,
+}
+
+impl Display for OperationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (operation {})", self.source, self.operation)
+    }
+}
+
+impl std::error::Error for OperationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+pub trait TypedErrorExt {
+    fn typed(&self) -> Option<&CczuError>;
+
+    /// Id of the operation that failed, to find its log lines.
+    fn operation(&self) -> Option<u64>;
+
+    /// The error itself, without the [`OperationError`] around it.
+    fn unwrap_operation(&self) -> &tokio::io::Error;
+}
+
+impl TypedErrorExt for tokio::io::Error {
+    fn typed(&self) -> Option<&CczuError> {
+        self.unwrap_operation()
+            .get_ref()
+            .and_then(|error| error.downcast_ref())
+    }
+
+    fn operation(&self) -> Option<u64> {
+        self.get_ref()
+            .and_then(|error| error.downcast_ref::<OperationError>())
+            .map(|error| error.operation)
+    }
+
+    fn unwrap_operation(&self) -> &tokio::io::Error {
+        match self
+            .get_ref()
+            .and_then(|error| error.downcast_ref::<OperationError>())
+        {
+            Some(error) => error.source.unwrap_operation(),
+            None => self,
+        }
+    }
 }
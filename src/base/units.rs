@@ -0,0 +1,89 @@
+//! The numbers of a grade record, one type each so a score can't be passed as a credit.
+//!
+//! They are plain numbers in JSON, a number out of range fails to deserialize.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+macro_rules! unit {
+    ($(#[$doc:meta])* $name:ident, $max:expr) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+        #[serde(try_from = "f32", into = "f32")]
+        pub struct $name(f32);
+
+        impl $name {
+            pub const ZERO: Self = Self(0.0);
+            pub const MAX: f32 = $max;
+
+            /// `None` when `value` is not in `0..=MAX`.
+            pub fn new(value: f32) -> Option<Self> {
+                (0.0..=Self::MAX).contains(&value).then_some(Self(value))
+            }
+
+            pub fn value(self) -> f32 {
+                self.0
+            }
+        }
+
+        impl TryFrom<f32> for $name {
+            type Error = String;
+
+            fn try_from(value: f32) -> Result<Self, Self::Error> {
+                Self::new(value).ok_or(format!(
+                    "{} {value} out of 0..={}",
+                    stringify!($name),
+                    Self::MAX
+                ))
+            }
+        }
+
+        impl From<$name> for f32 {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    };
+}
+
+unit!(
+    /// Credits of one course
+    Credit,
+    50.0
+);
+unit!(
+    /// Grade of a course out of 100
+    Score,
+    100.0
+);
+unit!(
+    /// Grade points of a course, or a GPA
+    GradePoint,
+    5.0
+);
+
+#[cfg(test)]
+mod test {
+    use super::{Credit, GradePoint, Score};
+
+    #[test]
+    fn checked() {
+        assert_eq!(Score::new(83.5).map(Score::value), Some(83.5));
+        assert_eq!(Score::new(101.0), None);
+        assert_eq!(GradePoint::new(-1.0), None);
+        assert_eq!(Credit::new(f32::NAN), None);
+
+        // Numbers in JSON, as before
+        let credit: Credit = serde_json::from_str("2.5").unwrap();
+        assert_eq!(serde_json::to_string(&credit).unwrap(), "2.5");
+        assert_eq!(credit.to_string(), "2.5");
+        assert!(serde_json::from_str::<GradePoint>("83").is_err());
+    }
+}
@@ -0,0 +1,57 @@
+//! What this build of the crate is, for a host enabling its features by what is compiled in.
+
+/// Version of the crate, like `0.1.0`.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The features of the manifest compiled in, `full` and `default` given by the ones they turn
+/// on. In the order of the manifest.
+pub const FEATURES: &[&str] = &[
+    #[cfg(feature = "vendored")]
+    "vendored",
+    #[cfg(feature = "default-tls")]
+    "default-tls",
+    #[cfg(feature = "rustls-tls")]
+    "rustls-tls",
+    #[cfg(feature = "webvpn-login")]
+    "webvpn-login",
+    #[cfg(feature = "calendar")]
+    "calendar",
+    #[cfg(feature = "internals")]
+    "internals",
+    #[cfg(feature = "cookie-file")]
+    "cookie-file",
+    #[cfg(feature = "session-crypto")]
+    "session-crypto",
+    #[cfg(feature = "streaming-parse")]
+    "streaming-parse",
+    #[cfg(feature = "testing")]
+    "testing",
+    #[cfg(feature = "record-fixtures")]
+    "record-fixtures",
+    #[cfg(feature = "cli")]
+    "cli",
+];
+
+/// Whether the feature `name` of the manifest is compiled in.
+pub fn has_feature(name: &str) -> bool {
+    FEATURES.contains(&name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{has_feature, FEATURES, VERSION};
+
+    #[test]
+    fn features_of_the_manifest() {
+        let manifest = include_str!("../../Cargo.toml");
+        assert!(manifest.contains(&format!("\nversion = \"{VERSION}\"")));
+        for feature in FEATURES {
+            assert!(
+                manifest.contains(&format!("\n{feature} = [")),
+                "{feature} is not a feature of the manifest"
+            );
+        }
+        assert_eq!(has_feature("calendar"), cfg!(feature = "calendar"));
+        assert!(!has_feature("full"));
+    }
+}
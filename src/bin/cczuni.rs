@@ -0,0 +1,273 @@
+//! Command line access to the API, to reproduce an issue without building an FFI host.
+//!
+//! Built with `--features cli`.
+
+use std::{env, process::ExitCode, time::Duration};
+
+use cczuni::{
+    base::{
+        app::AppVisitor,
+        client::{Account, ClientOptions},
+        typing::{other_error, TorErr},
+    },
+    extension::calendar::{flatten_week_matrix, TermCalendarParser},
+    impls::{
+        apps::wechat::jwqywx::JwqywxApplication, client::DefaultClient,
+        login::sso::SSOUniversalLogin,
+    },
+    utils::status::{services_status_with_latency, LatencyTracker},
+};
+use serde::Serialize;
+
+const USAGE: &str = "\
+Usage: cczuni [options] <command>
+
+Commands:
+    login                   Log in through SSO
+    grades                  Grades of every term
+    schedule [--term TERM]  Schedule of TERM, the current term by default
+    status                  Health of the portals
+
+Options:
+    -u, --user USER         Student id, or CCZUNI_USER
+    -p, --password PASS     Password, or CCZUNI_PASSWORD
+    --proxy URL             Proxy of every request
+    --timeout SECONDS       Give up on a request after SECONDS
+    --table                 Print a table instead of JSON
+    -h, --help              Print this help";
+
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    command: String,
+    user: Option<String>,
+    password: Option<String>,
+    term: Option<String>,
+    proxy: Option<String>,
+    timeout: Option<u64>,
+    table: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("`{name}` needs a value"));
+        match arg.as_str() {
+            "-u" | "--user" => parsed.user = Some(value(&arg)?),
+            "-p" | "--password" => parsed.password = Some(value(&arg)?),
+            "--term" => parsed.term = Some(value(&arg)?),
+            "--proxy" => parsed.proxy = Some(value(&arg)?),
+            "--timeout" => {
+                let seconds = value(&arg)?;
+                parsed.timeout = Some(
+                    seconds
+                        .parse()
+                        .map_err(|_| format!("Invalid timeout `{seconds}`"))?,
+                );
+            }
+            "--table" => parsed.table = true,
+            "-h" | "--help" => return Err(USAGE.into()),
+            command if parsed.command.is_empty() && !command.starts_with('-') => {
+                parsed.command = command.into()
+            }
+            unknown => return Err(format!("Unknown argument `{unknown}`\n\n{USAGE}")),
+        }
+    }
+    if parsed.command.is_empty() {
+        return Err(USAGE.into());
+    }
+    Ok(parsed)
+}
+
+impl Args {
+    fn client(&self) -> TorErr<DefaultClient> {
+        let user = self.user.clone().or(env::var("CCZUNI_USER").ok());
+        let password = self.password.clone().or(env::var("CCZUNI_PASSWORD").ok());
+        let (Some(user), Some(password)) = (user, password) else {
+            return Err(other_error(
+                "No account, give --user and --password or CCZUNI_USER and CCZUNI_PASSWORD",
+            ));
+        };
+
+        let proxy = self
+            .proxy
+            .as_deref()
+            .map(reqwest::Proxy::all)
+            .transpose()
+            .map_err(other_error)?;
+        Ok(DefaultClient::with_options(
+            Account::new(user, password),
+            ClientOptions {
+                proxy,
+                timeout: self.timeout.map(Duration::from_secs),
+                ..Default::default()
+            },
+        ))
+    }
+}
+
+/// Columns padded to their widest cell.
+fn print_table(header: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths: Vec<usize> = header.iter().map(|cell| cell.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        println!("{}", padded.join("  ").trim_end());
+    };
+    line(header.to_vec());
+    for row in &rows {
+        line(row.iter().map(String::as_str).collect());
+    }
+}
+
+fn print_json(data: &impl Serialize) -> TorErr<()> {
+    println!("{}", serde_json::to_string_pretty(data)?);
+    Ok(())
+}
+
+async fn jwqywx(args: &Args) -> TorErr<JwqywxApplication<DefaultClient>> {
+    let app = args.client()?.visit::<JwqywxApplication<_>>().await;
+    app.login().await?;
+    Ok(app)
+}
+
+async fn run(args: Args) -> TorErr<()> {
+    match args.command.as_str() {
+        "login" => {
+            let info = args.client()?.sso_universal_login().await?;
+            if args.table {
+                println!("Logged in{}", if info.is_some() { " (WebVPN)" } else { "" });
+                return Ok(());
+            }
+            print_json(&info)
+        }
+        "grades" => {
+            let grades = jwqywx(&args).await?.get_grades().await?.message;
+            if !args.table {
+                return print_json(&grades);
+            }
+            let rows = grades
+                .iter()
+                .map(|grade| {
+                    vec![
+                        grade.term.to_string(),
+                        grade.course_name.clone(),
+                        grade.course_credits.to_string(),
+                        grade.grade.to_string(),
+                        grade.grade_points.to_string(),
+                    ]
+                })
+                .collect();
+            print_table(&["term", "course", "credits", "grade", "points"], rows);
+            Ok(())
+        }
+        "schedule" => {
+            let app = jwqywx(&args).await?;
+            let term = match &args.term {
+                Some(term) => term.clone(),
+                None => app.current_term().await?.term,
+            };
+            let matrix = app.get_term_classinfo_week_matrix(term).await?;
+            let entries = flatten_week_matrix(matrix, None)?;
+            if !args.table {
+                return print_json(&entries);
+            }
+            let rows = entries
+                .iter()
+                .map(|entry| {
+                    vec![
+                        entry.weekday.to_string(),
+                        format!("{}-{}", entry.start_period, entry.end_period),
+                        entry.course.clone(),
+                        entry.location.clone(),
+                        entry.teacher.clone(),
+                    ]
+                })
+                .collect();
+            print_table(
+                &["weekday", "periods", "course", "location", "teacher"],
+                rows,
+            );
+            Ok(())
+        }
+        // The probes use their own client, --proxy and --timeout don't apply
+        "status" => {
+            let status = services_status_with_latency(&LatencyTracker::default()).await;
+            if !args.table {
+                return print_json(&status);
+            }
+            let mut rows: Vec<Vec<String>> = status
+                .iter()
+                .map(|(name, probe)| {
+                    vec![
+                        name.to_string(),
+                        probe.health.label().into(),
+                        probe
+                            .latency_ms
+                            .map(|ms| format!("{ms} ms"))
+                            .unwrap_or_default(),
+                    ]
+                })
+                .collect();
+            rows.sort();
+            print_table(&["service", "health", "latency"], rows);
+            Ok(())
+        }
+        unknown => Err(other_error(format!(
+            "Unknown command `{unknown}`\n\n{USAGE}"
+        ))),
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{message}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_args, Args};
+
+    fn parse(args: &str) -> Result<Args, String> {
+        parse_args(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn arguments() {
+        assert_eq!(
+            parse("-u 2300000101 schedule --term 25-26-1 --timeout 10 --table").unwrap(),
+            Args {
+                command: "schedule".into(),
+                user: Some("2300000101".into()),
+                term: Some("25-26-1".into()),
+                timeout: Some(10),
+                table: true,
+                ..Default::default()
+            }
+        );
+        assert!(parse("").is_err());
+        assert!(parse("grades --timeout soon").is_err());
+        assert!(parse("grades --user").is_err());
+        assert!(parse("grades status").is_err());
+    }
+}
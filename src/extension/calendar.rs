@@ -1,13 +1,22 @@
-use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use icalendar::{Alarm, Calendar, Component, Event, EventLike, Trigger};
 use serde::{Deserialize, Serialize}; // 确保 Serialize 在这里
 use std::{
-    collections::HashMap, fs::read_to_string, future::Future, io::ErrorKind, path::Path,
+    collections::{hash_map::Entry, HashMap},
+    fs::read_to_string,
+    future::Future,
+    io::ErrorKind,
+    path::Path,
     sync::LazyLock,
 };
 use uuid::Uuid;
 
-use crate::base::typing::{other_error, TorErr};
+pub use crate::base::client::WeekStart;
+use crate::base::{
+    client::Campus,
+    typing::{other_error, TorErr},
+};
+use crate::extension::instructor::Instructor;
 
 pub static EVENT_PROP: LazyLock<HashMap<&'static str, &'static str>> = LazyLock::new(|| {
     let mut map: HashMap<&str, &str> = HashMap::new();
@@ -40,6 +49,7 @@ impl Schedule {
         serde_json::from_str(&read_to_string(path).unwrap()).unwrap()
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(data: &str) -> Self {
         serde_json::from_str(data).unwrap()
     }
@@ -99,10 +109,13 @@ pub enum OddOrEven {
     Each = 2,
 }
 
-#[derive(Clone, Debug, Serialize)] // <-- 在这里添加 Serialize
+#[derive(Clone, Debug, Serialize, Deserialize)] // <-- 在这里添加 Serialize
 pub struct RawCourse {
     pub course: String,
     pub teacher: String,
+    /// The ids of [`Self::teacher`] laid out the same, empty when the portal gives none
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub teacher_id: String,
 }
 
 #[derive(Clone, Debug)]
@@ -115,6 +128,8 @@ pub struct ParsedCourse {
     pub classroom: String,
     pub daylist: Vec<String>,
     pub teacher: String,
+    /// The ids of [`Self::teacher`], empty without one
+    pub teacher_id: String,
 }
 
 impl ParsedCourse {
@@ -135,6 +150,7 @@ impl ParsedCourse {
             classtime,
             classroom,
             teacher,
+            teacher_id: String::new(),
             daylist: vec![],
         }
     }
@@ -200,6 +216,264 @@ impl ParsedCourse {
     }
 }
 
+/// One course at one time of the week, the flat form of the week matrix.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    pub course: String,
+    pub teacher: String,
+    /// The ids of [`Self::teacher`] in the same order, `None` (and left out) when the portal
+    /// gives none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub teacher_id: Option<String>,
+    pub location: String,
+    pub campus: Option<Campus>,
+    /// 1 is Monday ... 7 is Sunday, or 1 is Sunday with [`WeekStart::Sunday`]
+    pub weekday: usize,
+    /// First period, from 1
+    pub start_period: usize,
+    /// Last period, included
+    pub end_period: usize,
+    /// Like `08:00`, when [`Self::start_period`] starts in the period table, `None` for a
+    /// period past its last
+    pub start_time: Option<String>,
+    /// When [`Self::end_period`] ends, like [`Self::start_time`]
+    pub end_time: Option<String>,
+    /// Every week the course takes place, odd/even weeks already applied
+    pub weeks: Vec<u32>,
+}
+
+impl ScheduleEntry {
+    /// The teachers of [`Self::teacher`], with their ids when the portal gave them.
+    pub fn instructors(&self) -> Vec<Instructor> {
+        Instructor::list(&self.teacher, self.teacher_id.as_deref())
+    }
+
+    /// This entry with the [`Self::start_time`] and [`Self::end_time`] of its periods in
+    /// `schedule`, `None` for a period past its end.
+    pub fn with_times(self, schedule: &Schedule) -> Self {
+        let clock = |period: usize, time: fn(&ScheduleElement) -> &str| {
+            let element = schedule.classtime.get(period.checked_sub(1)?)?;
+            let time = NaiveTime::parse_from_str(time(element), "%H%M").ok()?;
+            Some(time.format("%H:%M").to_string())
+        };
+        Self {
+            start_time: clock(self.start_period, |element| &element.start_time),
+            end_time: clock(self.end_period, |element| &element.end_time),
+            ..self
+        }
+    }
+}
+
+/// Weeks of a term, vacation included: a last known start further back is of a past term.
+pub const MAX_TERM_WEEKS: i64 = 26;
+
+/// Week of the term on a day.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CurrentWeek {
+    /// `1` in the week of the start, `0` or less before it
+    pub week: i64,
+    /// Counted from the last known start, the academic calendar couldn't be reached
+    pub estimated: bool,
+}
+
+/// Week of `today` in the term starting on `term_start`, as given by the academic calendar.
+///
+/// When the calendar failed, `last_known` (the start it gave before, kept by the caller) is
+/// used instead and the week marked estimated. It is an error too when `last_known` is more
+/// than [`MAX_TERM_WEEKS`] ago, a new term has begun since.
+pub fn current_week(
+    term_start: TorErr<NaiveDate>,
+    last_known: Option<NaiveDate>,
+    today: NaiveDate,
+) -> TorErr<CurrentWeek> {
+    let week_of = |start: NaiveDate| {
+        let monday = start - Duration::days(start.weekday().num_days_from_monday().into());
+        (today - monday).num_days().div_euclid(7) + 1
+    };
+    match (term_start, last_known) {
+        (Ok(start), _) => Ok(CurrentWeek {
+            week: week_of(start),
+            estimated: false,
+        }),
+        (Err(error), None) => Err(error),
+        (Err(_), Some(start)) if week_of(start) > MAX_TERM_WEEKS => Err(other_error(format!(
+            "Academic calendar unavailable, the last known term start {start} is of a past term"
+        ))),
+        (Err(_), Some(start)) => Ok(CurrentWeek {
+            week: week_of(start),
+            estimated: true,
+        }),
+    }
+}
+
+impl ParsedCourse {
+    /// The entries of this course, one per run of consecutive periods, timed by the period
+    /// table. Both campuses follow the same one, `campus` is only told in the entries.
+    pub fn flatten(&self, campus: Option<Campus>) -> Vec<ScheduleEntry> {
+        let mut weeks: Vec<u32> = self
+            .week
+            .iter()
+            .flat_map(|range| {
+                let mut bounds = range
+                    .split('-')
+                    .filter_map(|v| v.trim().parse::<u32>().ok());
+                let start = bounds.next().unwrap_or_default();
+                start..=bounds.next().unwrap_or(start)
+            })
+            .filter(|week| {
+                *week > 0
+                    && match self.odd_or_even {
+                        OddOrEven::Odd => week % 2 == 1,
+                        OddOrEven::Even => week % 2 == 0,
+                        OddOrEven::Each => true,
+                    }
+            })
+            .collect();
+        weeks.sort();
+        weeks.dedup();
+
+        let mut periods = self.classtime.clone();
+        periods.sort();
+        let mut runs: Vec<(usize, usize)> = vec![];
+        for period in periods {
+            match runs.last_mut() {
+                Some((_, end)) if *end + 1 == period => *end = period,
+                _ => runs.push((period, period)),
+            }
+        }
+
+        let schedule = Schedule::default();
+        runs.into_iter()
+            .map(|(start_period, end_period)| {
+                ScheduleEntry {
+                    course: self.name.clone(),
+                    teacher: self.teacher.clone(),
+                    teacher_id: (!self.teacher_id.is_empty()).then(|| self.teacher_id.clone()),
+                    location: self.classroom.clone(),
+                    campus,
+                    weekday: self.day,
+                    start_period,
+                    end_period,
+                    start_time: None,
+                    end_time: None,
+                    weeks: weeks.clone(),
+                }
+                .with_times(&schedule)
+            })
+            .collect()
+    }
+}
+
+/// [`parse_week_matrix`], then every course flattened, ordered by weekday and period.
+pub fn flatten_week_matrix(
+    row_matrix: Vec<Vec<RawCourse>>,
+    campus: Option<Campus>,
+) -> TorErr<Vec<ScheduleEntry>> {
+    flatten_week_matrix_from(row_matrix, campus, WeekStart::Monday)
+}
+
+/// [`flatten_week_matrix`] with the weekdays numbered from `week_start`.
+pub fn flatten_week_matrix_from(
+    row_matrix: Vec<Vec<RawCourse>>,
+    campus: Option<Campus>,
+    week_start: WeekStart,
+) -> TorErr<Vec<ScheduleEntry>> {
+    let mut entries: Vec<ScheduleEntry> = parse_week_matrix(row_matrix)?
+        .iter()
+        .flat_map(|course| course.flatten(campus))
+        .map(|entry| ScheduleEntry {
+            weekday: week_start.weekday(entry.weekday),
+            ..entry
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        (a.weekday, a.start_period, &a.course).cmp(&(b.weekday, b.start_period, &b.course))
+    });
+    Ok(entries)
+}
+
+/// What moved in an entry of [`ScheduleChange`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleChangeKind {
+    Location,
+    /// Weekday or periods
+    Time,
+    Weeks,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleChange {
+    pub before: ScheduleEntry,
+    pub after: ScheduleEntry,
+    pub kinds: Vec<ScheduleChangeKind>,
+}
+
+/// Result of [`diff_schedule`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleDiff {
+    pub added: Vec<ScheduleEntry>,
+    pub removed: Vec<ScheduleEntry>,
+    pub changed: Vec<ScheduleChange>,
+}
+
+impl ScheduleDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// What changed from the `old` schedule to the `new` one.
+///
+/// An entry gone and an entry of the same course with the same teacher showing up is a change
+/// of this entry (moved to another room, another time...), not a course removed and another
+/// added. Those at the same time are paired first.
+pub fn diff_schedule(old: &[ScheduleEntry], new: &[ScheduleEntry]) -> ScheduleDiff {
+    let mut removed: Vec<&ScheduleEntry> =
+        old.iter().filter(|entry| !new.contains(entry)).collect();
+    let mut added: Vec<&ScheduleEntry> = new.iter().filter(|entry| !old.contains(entry)).collect();
+    let same_course =
+        |a: &ScheduleEntry, b: &ScheduleEntry| a.course == b.course && a.teacher == b.teacher;
+    let same_time = |a: &ScheduleEntry, b: &ScheduleEntry| {
+        (a.weekday, a.start_period, a.end_period) == (b.weekday, b.start_period, b.end_period)
+    };
+
+    let mut changed = vec![];
+    for same_time_first in [true, false] {
+        removed.retain(|before| {
+            let Some(index) = added.iter().position(|after| {
+                same_course(before, after) && (!same_time_first || same_time(before, after))
+            }) else {
+                return true;
+            };
+            let after = added.remove(index);
+            let kinds = [
+                (
+                    before.location != after.location,
+                    ScheduleChangeKind::Location,
+                ),
+                (!same_time(before, after), ScheduleChangeKind::Time),
+                (before.weeks != after.weeks, ScheduleChangeKind::Weeks),
+            ]
+            .into_iter()
+            .filter_map(|(changed, kind)| changed.then_some(kind))
+            .collect();
+            changed.push(ScheduleChange {
+                before: (*before).clone(),
+                after: after.clone(),
+                kinds,
+            });
+            false
+        });
+    }
+
+    ScheduleDiff {
+        added: added.into_iter().cloned().collect(),
+        removed: removed.into_iter().cloned().collect(),
+        changed,
+    }
+}
+
 pub trait ApplicationCalendarExt {
     fn generate_icalendar_from_classlist(
         &self,
@@ -220,7 +494,6 @@ pub trait CalendarParser {
     /// The Matrix's column is indexed 0~6
     ///
     /// Each Vec<String> is in order.
-
     fn get_classinfo_week_matrix(&self) -> impl Future<Output = TorErr<Vec<Vec<RawCourse>>>>;
 }
 
@@ -229,102 +502,336 @@ pub trait TermCalendarParser: CalendarParser {
         &self,
         term: String,
     ) -> impl Future<Output = TorErr<Vec<Vec<RawCourse>>>>;
+
+    /// Changes between two schedules fetched from this portal, see [`diff_schedule`].
+    fn diff(&self, old: &[ScheduleEntry], new: &[ScheduleEntry]) -> ScheduleDiff {
+        diff_schedule(old, new)
+    }
 }
 
-impl<P: CalendarParser> ApplicationCalendarExt for P {
-    fn generate_icalendar_from_classlist(
-        &self,
-        classlist: Vec<ParsedCourse>,
-        firstweekdate: String,
-        schedule: Schedule,
-        reminder: Option<i32>,
-    ) -> TorErr<Calendar> {
-        let mut calendar = Calendar::new();
-        calendar.timezone("Asia/Shanghai").name("课程表");
-        let mut classlist = classlist;
-        classlist.iter_mut().for_each(|e| {
-            e.with_startdate(&firstweekdate);
-        });
+/// What a subscription feed remembers between two generations, to be kept by the caller.
+///
+/// Each event keeps its UID across generations, and its `SEQUENCE` goes up when it changes
+/// (another room, another time...), so calendar apps update it instead of adding a new one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IcsFeed {
+    /// UID to (fingerprint of the content, sequence)
+    pub events: HashMap<String, (String, u32)>,
+}
 
-        for info in classlist.iter() {
-            let start_time =
-                schedule.classtime[info.classtime.first().ok_or_else(|| {
-                    tokio::io::Error::new(ErrorKind::InvalidData, "No First data")
-                })? - 1]
-                    .clone()
-                    .start_time;
-            let end_time =
-                schedule.classtime[info.classtime.last().ok_or_else(|| {
-                    tokio::io::Error::new(ErrorKind::InvalidData, "No Last data")
-                })? - 1]
-                    .clone()
-                    .end_time;
-            let create_time = Utc::now();
-            for day in info.daylist.iter() {
-                let uid = format!("{}@gmail.com", Uuid::new_v4());
-                let start = NaiveDateTime::parse_from_str(
-                    format!("{}{}", day, start_time).as_str(),
-                    "%Y%m%d%H%M",
-                )
-                .unwrap();
-                let end = NaiveDateTime::parse_from_str(
-                    format!("{}{}", day, end_time).as_str(),
-                    "%Y%m%d%H%M",
-                )
-                .unwrap();
-
-                let mut event = Event::new();
-
-                EVENT_PROP.clone().into_iter().for_each(|(k, v)| {
-                    event.add_property(k, v);
-                });
-
-                event
-                    .summary(&info.name) // Name
-                    .location(&info.classroom) // Location
-                    .description(&info.teacher) // Teacher
-                    .add_property("WEEK", info.week.join(","))
-                    .timestamp(create_time)
-                    .uid(&uid)
-                    .starts(start)
-                    .ends(end);
-                if let Some(reminder) = reminder {
-                    event.alarm(Alarm::display(
-                        "课前提醒",
-                        Trigger::before_start(Duration::minutes(reminder as i64)),
-                    ));
+impl IcsFeed {
+    /// Sequence of the event, bumped if its content changed since the last generation.
+    fn sequence(&mut self, uid: &str, content: &str) -> (u32, bool) {
+        let fingerprint = Uuid::new_v3(&Uuid::NAMESPACE_OID, content.as_bytes()).to_string();
+        match self.events.entry(uid.to_owned()) {
+            Entry::Occupied(mut entry) => {
+                let (known, sequence) = entry.get_mut();
+                if *known != fingerprint {
+                    *known = fingerprint;
+                    *sequence += 1;
+                    return (*sequence, true);
                 }
-
-                calendar.push(event);
+                (*sequence, false)
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((fingerprint, 0));
+                (0, true)
             }
         }
+    }
+}
 
-        let mut fweek = NaiveDateTime::new(
-            NaiveDate::parse_from_str(&firstweekdate, "%Y%m%d").unwrap(),
-            NaiveTime::default(),
-        );
+fn stable_uid(identity: String) -> String {
+    format!(
+        "{}@cczuni",
+        Uuid::new_v3(&Uuid::NAMESPACE_DNS, identity.as_bytes())
+    )
+}
+
+/// The calendar of `classlist`, updating `feed` with the events generated.
+///
+/// The UID of a class only depends on the course, its weekday, its first period, its odd/even
+/// pattern and its date, so moving it to another room keeps the UID.
+pub fn generate_icalendar_feed(
+    classlist: Vec<ParsedCourse>,
+    firstweekdate: String,
+    schedule: &Schedule,
+    reminder: Option<i32>,
+    feed: &mut IcsFeed,
+) -> TorErr<Calendar> {
+    generate_icalendar_feed_at(
+        classlist,
+        firstweekdate,
+        schedule,
+        reminder,
+        feed,
+        Utc::now(),
+    )
+}
+
+/// [`generate_icalendar_feed`] stamped `now`: the same input gives the same bytes, for a
+/// snapshot or a feed that only changes with the schedule.
+///
+/// `now` is the `DTSTAMP` of every component, the alarms get a UID derived from their event.
+pub fn generate_icalendar_feed_at(
+    classlist: Vec<ParsedCourse>,
+    firstweekdate: String,
+    schedule: &Schedule,
+    reminder: Option<i32>,
+    feed: &mut IcsFeed,
+    now: DateTime<Utc>,
+) -> TorErr<Calendar> {
+    let mut calendar = Calendar::new();
+    calendar.timezone("Asia/Shanghai").name("课程表");
+    // Before `with_startdate`, which panics on it
+    let firstdate = NaiveDate::parse_from_str(&firstweekdate, "%Y%m%d")
+        .map_err(|error| tokio::io::Error::new(ErrorKind::InvalidData, error))?;
+    let mut classlist = classlist;
+    classlist.iter_mut().for_each(|e| {
+        e.with_startdate(&firstweekdate);
+    });
+
+    let create_time = now;
+    for info in classlist.iter() {
+        let first = *info
+            .classtime
+            .iter()
+            .min()
+            .ok_or_else(|| tokio::io::Error::new(ErrorKind::InvalidData, "No First data"))?;
+        let last = *info
+            .classtime
+            .iter()
+            .max()
+            .ok_or_else(|| tokio::io::Error::new(ErrorKind::InvalidData, "No Last data"))?;
+        let period = |index: usize| {
+            index
+                .checked_sub(1)
+                .and_then(|index| schedule.classtime.get(index))
+                .ok_or_else(|| {
+                    tokio::io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("No period {index} in the schedule"),
+                    )
+                })
+        };
+        let start_time = &period(first)?.start_time;
+        let end_time = &period(last)?.end_time;
+        for day in info.daylist.iter() {
+            let uid = stable_uid(format!(
+                "{}-{}-{}-{}-{}",
+                info.name,
+                info.day,
+                first,
+                info.odd_or_even.clone() as isize,
+                day
+            ));
+            let start = NaiveDateTime::parse_from_str(
+                format!("{}{}", day, start_time).as_str(),
+                "%Y%m%d%H%M",
+            )
+            .map_err(|error| tokio::io::Error::new(ErrorKind::InvalidData, error))?;
+            let end = NaiveDateTime::parse_from_str(
+                format!("{}{}", day, end_time).as_str(),
+                "%Y%m%d%H%M",
+            )
+            .map_err(|error| tokio::io::Error::new(ErrorKind::InvalidData, error))?;
+            let (sequence, changed) = feed.sequence(
+                &uid,
+                &format!(
+                    "{start}\n{end}\n{}\n{}\n{}\n{}\n{reminder:?}",
+                    info.name,
+                    info.classroom,
+                    info.teacher,
+                    info.week.join(",")
+                ),
+            );
 
-        let create_time = Utc::now();
-        for wn in 1..=19 {
-            let summary = format!("学期第 {} 周", wn);
-            let uid = format!("{}@gmail.com", Uuid::new_v4());
             let mut event = Event::new();
-            event
-                .uid(&uid)
-                .summary(&summary)
-                .timestamp(create_time)
-                .starts(fweek.date())
-                .ends(fweek.date() + Duration::days(7));
 
             EVENT_PROP.clone().into_iter().for_each(|(k, v)| {
                 event.add_property(k, v);
             });
 
-            calendar.push(event.clone());
-            fweek += Duration::days(7);
+            event
+                .summary(&info.name) // Name
+                .location(&info.classroom) // Location
+                .description(&info.teacher) // Teacher
+                .add_property("WEEK", info.week.join(","))
+                .add_property("SEQUENCE", sequence.to_string())
+                .timestamp(create_time)
+                .uid(&uid)
+                .starts(start)
+                .ends(end);
+            if changed {
+                event.last_modified(create_time);
+            }
+            if let Some(reminder) = reminder {
+                let mut alarm = Alarm::display(
+                    "课前提醒",
+                    Trigger::before_start(Duration::minutes(reminder as i64)),
+                );
+                // Written by `icalendar` otherwise, with the current time and a random UID
+                alarm.timestamp(create_time).uid(&format!("alarm-{uid}"));
+                event.alarm(alarm);
+            }
+
+            calendar.push(event);
         }
+    }
+
+    let mut fweek = NaiveDateTime::new(firstdate, NaiveTime::default());
+
+    for wn in 1..=19 {
+        let summary = format!("学期第 {} 周", wn);
+        let uid = stable_uid(format!("{}-{}", firstweekdate, wn));
+        let mut event = Event::new();
+        event
+            .uid(&uid)
+            .summary(&summary)
+            .timestamp(create_time)
+            .starts(fweek.date())
+            .ends(fweek.date() + Duration::days(7));
+
+        EVENT_PROP.clone().into_iter().for_each(|(k, v)| {
+            event.add_property(k, v);
+        });
 
-        Ok(calendar)
+        calendar.push(event.clone());
+        fweek += Duration::days(7);
+    }
+
+    Ok(calendar)
+}
+
+/// How a class repeats in a [`FullCalendarEvent`], in the options of the rrule plugin.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FullCalendarRrule {
+    /// Always `weekly`
+    pub freq: &'static str,
+    /// `2` for the odd or even weeks
+    pub interval: u32,
+    /// The first class
+    pub dtstart: String,
+    pub count: u32,
+}
+
+/// An event of FullCalendar: from `start` to `end` for a class of a single week, repeated by
+/// `rrule` for `duration` otherwise. Times are Beijing time, with their `+08:00`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FullCalendarEvent {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rrule: Option<FullCalendarRrule>,
+    /// Like `01:25`, of each class repeated by `rrule`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<String>,
+    /// The class, Monday first, with only the weeks of this event
+    pub extended_props: ScheduleEntry,
+}
+
+/// `weeks` (sorted) cut into runs one or two weeks apart, as (first week, interval, count).
+fn week_runs(weeks: &[u32]) -> Vec<(u32, u32, u32)> {
+    let mut runs: Vec<(u32, u32, u32)> = vec![];
+    for &week in weeks {
+        match runs.last_mut() {
+            Some((first, interval, count)) if *count == 1 && matches!(week - *first, 1 | 2) => {
+                *interval = week - *first;
+                *count = 2;
+            }
+            Some((first, interval, count)) if week == *first + *interval * *count => *count += 1,
+            _ => runs.push((week, 1, 1)),
+        }
+    }
+    runs
+}
+
+/// The events of `classlist` for FullCalendar, the web counterpart of
+/// [`generate_icalendar_feed`]. `term_start` is the Monday of the first week.
+///
+/// The weekly classes (odd or even weeks included) are an event with an `rrule`, a class given
+/// in one week only is an event of its own. Weeks that don't follow each other make one event
+/// per run.
+pub fn to_fullcalendar_events(
+    classlist: &[ParsedCourse],
+    term_start: NaiveDate,
+    schedule: &Schedule,
+) -> TorErr<Vec<FullCalendarEvent>> {
+    let period = |index: usize| {
+        index
+            .checked_sub(1)
+            .and_then(|index| schedule.classtime.get(index))
+            .ok_or_else(|| other_error(format!("No period {index} in the schedule")))
+    };
+    let time = |time: &str| NaiveTime::parse_from_str(time, "%H%M").map_err(other_error);
+    let at =
+        |date: NaiveDate, time: NaiveTime| format!("{}T{}+08:00", date, time.format("%H:%M:%S"));
+
+    let mut events = vec![];
+    for entry in classlist
+        .iter()
+        .flat_map(|course| course.flatten(None))
+        .map(|entry| entry.with_times(schedule))
+    {
+        let start = time(&period(entry.start_period)?.start_time)?;
+        let end = time(&period(entry.end_period)?.end_time)?;
+        let minutes = (end - start).num_minutes();
+        for (first, interval, count) in week_runs(&entry.weeks) {
+            let date =
+                term_start + Duration::days(i64::from(first - 1) * 7 + entry.weekday as i64 - 1);
+            let extended_props = ScheduleEntry {
+                weeks: (0..count).map(|n| first + n * interval).collect(),
+                ..entry.clone()
+            };
+            let event = if count == 1 {
+                FullCalendarEvent {
+                    title: entry.course.clone(),
+                    start: Some(at(date, start)),
+                    end: Some(at(date, end)),
+                    rrule: None,
+                    duration: None,
+                    extended_props,
+                }
+            } else {
+                FullCalendarEvent {
+                    title: entry.course.clone(),
+                    start: None,
+                    end: None,
+                    rrule: Some(FullCalendarRrule {
+                        freq: "weekly",
+                        interval,
+                        dtstart: at(date, start),
+                        count,
+                    }),
+                    duration: Some(format!("{:02}:{:02}", minutes / 60, minutes % 60)),
+                    extended_props,
+                }
+            };
+            events.push(event);
+        }
+    }
+    Ok(events)
+}
+
+impl<P: CalendarParser> ApplicationCalendarExt for P {
+    fn generate_icalendar_from_classlist(
+        &self,
+        classlist: Vec<ParsedCourse>,
+        firstweekdate: String,
+        schedule: Schedule,
+        reminder: Option<i32>,
+    ) -> TorErr<Calendar> {
+        generate_icalendar_feed(
+            classlist,
+            firstweekdate,
+            &schedule,
+            reminder,
+            &mut IcsFeed::default(),
+        )
     }
 
     async fn generate_icalendar(
@@ -368,12 +875,17 @@ pub fn parse_week_matrix(row_matrix: Vec<Vec<RawCourse>>) -> TorErr<Vec<ParsedCo
                 .filter(|v| !v.trim().is_empty())
                 .map(|v| v.trim().to_string())
                 .collect();
-            let teachers: Vec<String> = raw_course
+            let teacher_ids: Vec<&str> = raw_course.teacher_id.split(",/").collect();
+            let (teachers, teacher_ids): (Vec<String>, Vec<String>) = raw_course
                 .teacher
                 .split(",/")
-                .filter(|v| !v.trim().is_empty())
-                .map(|v| v.trim().to_string())
-                .collect();
+                .enumerate()
+                .filter(|(_, v)| !v.trim().is_empty())
+                .map(|(index, v)| {
+                    let id = teacher_ids.get(index).copied().unwrap_or_default();
+                    (v.trim().to_string(), id.trim().to_string())
+                })
+                .unzip();
             for (index, course) in courses.iter().enumerate() {
                 if course == "&nbsp;" || course.is_empty() {
                     continue;
@@ -424,8 +936,8 @@ pub fn parse_week_matrix(row_matrix: Vec<Vec<RawCourse>>) -> TorErr<Vec<ParsedCo
                     week = chucks[3].clone();
                 }
 
-                if !course_info.contains_key(&id) {
-                    let info = ParsedCourse::new(
+                if let Entry::Vacant(entry) = course_info.entry(id.clone()) {
+                    let mut info = ParsedCourse::new(
                         name,
                         match oe.as_str() {
                             "单" => OddOrEven::Odd,
@@ -441,10 +953,11 @@ pub fn parse_week_matrix(row_matrix: Vec<Vec<RawCourse>>) -> TorErr<Vec<ParsedCo
                         place,
                         teachers
                             .get(index)
-                            .map(|e| e.clone())
+                            .cloned()
                             .unwrap_or("未知教师".to_owned()),
                     );
-                    course_info.insert(id, info);
+                    info.teacher_id = teacher_ids.get(index).cloned().unwrap_or_default();
+                    entry.insert(info);
                 } else {
                     course_info.get_mut(&id).unwrap().add_classtime(time + 1);
                 }
@@ -452,9 +965,414 @@ pub fn parse_week_matrix(row_matrix: Vec<Vec<RawCourse>>) -> TorErr<Vec<ParsedCo
         }
     }
 
-    Ok(course_info
-        .values()
-        .into_iter()
-        .map(|e| e.clone())
-        .collect())
-}
\ No newline at end of file
+    Ok(course_info.values().cloned().collect())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::ErrorKind;
+
+    use chrono::{DateTime, NaiveDate};
+    use icalendar::{Calendar, Component, EventLike};
+
+    use super::{
+        current_week, diff_schedule, flatten_week_matrix, flatten_week_matrix_from,
+        generate_icalendar_feed, generate_icalendar_feed_at, parse_week_matrix,
+        to_fullcalendar_events, CurrentWeek, IcsFeed, OddOrEven, ParsedCourse, RawCourse, Schedule,
+        ScheduleChangeKind, ScheduleDiff, ScheduleEntry, WeekStart,
+    };
+    use crate::base::{client::Campus, typing::other_error};
+
+    #[test]
+    fn schedule_diff() {
+        let before: Vec<ScheduleEntry> =
+            serde_json::from_str(include_str!("fixtures/schedule_before.json")).unwrap();
+        let after: Vec<ScheduleEntry> =
+            serde_json::from_str(include_str!("fixtures/schedule_after.json")).unwrap();
+
+        assert!(diff_schedule(&before, &before).is_empty());
+
+        // The Wednesday class moved, the Monday one of the same course didn't
+        let diff = diff_schedule(&before, &after);
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        assert_eq!(diff.changed.len(), 1);
+        let change = &diff.changed[0];
+        assert_eq!(change.kinds, [ScheduleChangeKind::Location]);
+        assert_eq!((change.before.weekday, change.after.weekday), (3, 3));
+        assert_eq!(change.before.location, "W101");
+        assert_eq!(change.after.location, "W203");
+
+        // Another time, a dropped course and a new one
+        let mut later = after.clone();
+        later[3].weekday = 5;
+        later.remove(2);
+        later.push(ScheduleEntry {
+            course: "线性代数".into(),
+            teacher: "赵老师".into(),
+            ..after[2].clone()
+        });
+        let diff = diff_schedule(&after, &later);
+        assert_eq!(diff.removed, [after[2].clone()]);
+        assert_eq!(diff.added[0].course, "线性代数");
+        assert_eq!(diff.changed[0].kinds, [ScheduleChangeKind::Time]);
+
+        let json = serde_json::to_string(&diff).unwrap();
+        assert_eq!(serde_json::from_str::<ScheduleDiff>(&json).unwrap(), diff);
+    }
+
+    #[test]
+    fn flatten() {
+        let matrix: Vec<Vec<RawCourse>> =
+            serde_json::from_str(include_str!("fixtures/matrix_monday_first.json")).unwrap();
+        let entries = flatten_week_matrix(matrix.clone(), Some(Campus::Wujin)).unwrap();
+
+        let entry = |course: &str,
+                     teacher: &str,
+                     location: &str,
+                     weekday,
+                     periods: (_, _),
+                     times: (&str, &str),
+                     weeks| {
+            ScheduleEntry {
+                course: course.into(),
+                teacher: teacher.into(),
+                teacher_id: None,
+                location: location.into(),
+                campus: Some(Campus::Wujin),
+                weekday,
+                start_period: periods.0,
+                end_period: periods.1,
+                start_time: Some(times.0.into()),
+                end_time: Some(times.1.into()),
+                weeks,
+            }
+        };
+        assert_eq!(
+            entries,
+            vec![
+                entry(
+                    "高等数学",
+                    "张三",
+                    "W2204",
+                    1,
+                    (1, 2),
+                    ("08:00", "09:25"),
+                    (1..=16).collect()
+                ),
+                entry(
+                    "大学英语2 A级",
+                    "李四",
+                    "W1101",
+                    3,
+                    (2, 3),
+                    ("08:45", "10:25"),
+                    (4..=18).step_by(2).collect()
+                ),
+                entry(
+                    "体育3",
+                    "王五",
+                    "体育馆",
+                    7,
+                    (3, 3),
+                    ("09:45", "10:25"),
+                    (1..=16).collect()
+                ),
+            ]
+        );
+
+        assert_eq!(
+            serde_json::to_value(&entries[2]).unwrap(),
+            serde_json::json!({
+                "course": "体育3",
+                "teacher": "王五",
+                "location": "体育馆",
+                "campus": "wujin",
+                "weekday": 7,
+                "start_period": 3,
+                "end_period": 3,
+                "start_time": "09:45",
+                "end_time": "10:25",
+                "weeks": (1..=16).collect::<Vec<u32>>(),
+            })
+        );
+
+        // The same times without a campus, which has no period table of its own
+        let unknown = flatten_week_matrix(matrix, None).unwrap();
+        assert_eq!(unknown[0].campus, None);
+        assert_eq!(
+            (
+                unknown[0].start_time.as_deref(),
+                unknown[0].end_time.as_deref()
+            ),
+            (Some("08:00"), Some("09:25"))
+        );
+    }
+
+    #[test]
+    fn estimated_week() {
+        let date = |day: &str| NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap();
+        let unavailable = || Err(other_error("Academic calendar unavailable"));
+        // A Thursday of the sixth week
+        let today = date("2025-10-09");
+
+        assert_eq!(
+            current_week(Ok(date("2025-09-01")), None, today).unwrap(),
+            CurrentWeek {
+                week: 6,
+                estimated: false
+            }
+        );
+        // Started on a Wednesday, its week is still the first
+        assert_eq!(
+            current_week(unavailable(), Some(date("2025-09-03")), today).unwrap(),
+            CurrentWeek {
+                week: 6,
+                estimated: true
+            }
+        );
+        assert_eq!(
+            current_week(unavailable(), Some(date("2025-10-13")), today)
+                .unwrap()
+                .week,
+            0
+        );
+
+        assert!(current_week(unavailable(), None, today).is_err());
+        let past = current_week(unavailable(), Some(date("2025-02-17")), today).unwrap_err();
+        assert!(past.to_string().contains("past term"), "{past}");
+    }
+
+    #[test]
+    fn sunday_first() {
+        let matrix: Vec<Vec<RawCourse>> =
+            serde_json::from_str(include_str!("fixtures/matrix_monday_first.json")).unwrap();
+        let weekdays = |week_start| {
+            flatten_week_matrix_from(matrix.clone(), None, week_start)
+                .unwrap()
+                .into_iter()
+                .map(|entry| (entry.course, entry.weekday))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            weekdays(WeekStart::Sunday),
+            [
+                ("体育3".to_string(), 1),
+                ("高等数学".to_string(), 2),
+                ("大学英语2 A级".to_string(), 4)
+            ]
+        );
+        assert_eq!(
+            weekdays(WeekStart::default()),
+            [
+                ("高等数学".to_string(), 1),
+                ("大学英语2 A级".to_string(), 3),
+                ("体育3".to_string(), 7)
+            ]
+        );
+    }
+
+    #[test]
+    fn byte_stable_ics() {
+        let course = ParsedCourse::new(
+            "面向对象程序设计（Java）课程设计, 实验; 上机与综合实践".into(),
+            OddOrEven::Each,
+            1,
+            vec!["1-2".into()],
+            vec![1, 2],
+            "W101, 实验楼".into(),
+            "张三\n李四".into(),
+        );
+        let now = DateTime::parse_from_rfc3339("2025-08-25T08:00:00Z")
+            .unwrap()
+            .to_utc();
+        let generate = || {
+            generate_icalendar_feed_at(
+                vec![course.clone()],
+                "20250901".into(),
+                &Schedule::default(),
+                Some(15),
+                &mut IcsFeed::default(),
+                now,
+            )
+            .unwrap()
+            .to_string()
+        };
+
+        let ics = generate();
+        assert_eq!(ics, include_str!("fixtures/schedule_snapshot.ics"));
+        assert_eq!(ics, generate());
+
+        // RFC 5545: CRLF only, content lines folded at 75 octets
+        assert!(!ics.replace("\r\n", "").contains('\n'));
+        assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+
+        let parsed: Calendar = ics.parse().unwrap();
+        let event = parsed.components[0].as_event().unwrap();
+        assert_eq!(event.get_summary(), Some(course.name.as_str()));
+        assert_eq!(event.get_location(), Some("W101, 实验楼"));
+        assert_eq!(parsed.components.len(), 2 + 19);
+    }
+
+    #[test]
+    fn ics_of_bad_input() {
+        let course = |classtime| {
+            ParsedCourse::new(
+                "高等数学".into(),
+                OddOrEven::Each,
+                1,
+                vec!["1-2".into()],
+                classtime,
+                "W101".into(),
+                "张三".into(),
+            )
+        };
+        let generate = |classtime, firstweekdate: &str| {
+            generate_icalendar_feed(
+                vec![course(classtime)],
+                firstweekdate.into(),
+                &Schedule::default(),
+                None,
+                &mut IcsFeed::default(),
+            )
+            .map(|_| ())
+            .map_err(|error| error.kind())
+        };
+
+        assert_eq!(generate(vec![1, 2], "20250901"), Ok(()));
+        // A period 0 or past the last of the schedule
+        assert_eq!(
+            generate(vec![0, 1], "20250901"),
+            Err(ErrorKind::InvalidData)
+        );
+        assert_eq!(
+            generate(vec![1, 99], "20250901"),
+            Err(ErrorKind::InvalidData)
+        );
+        assert_eq!(
+            generate(vec![1, 2], "2025-09-01"),
+            Err(ErrorKind::InvalidData)
+        );
+    }
+
+    /// (UID, SEQUENCE, LOCATION) of the events with a location
+    fn events(calendar: &str) -> Vec<(String, String, String)> {
+        let unfolded = calendar.replace("\r\n ", "");
+        let mut events: Vec<_> = unfolded
+            .split("BEGIN:VEVENT")
+            .skip(1)
+            .filter_map(|event| {
+                let value = |name: &str| {
+                    event
+                        .lines()
+                        .find_map(|line| line.strip_prefix(&format!("{name}:")))
+                        .map(str::to_owned)
+                };
+                Some((value("UID")?, value("SEQUENCE")?, value("LOCATION")?))
+            })
+            .collect();
+        events.sort();
+        events
+    }
+
+    #[test]
+    fn feed_keeps_uids() {
+        let matrix: Vec<Vec<RawCourse>> =
+            serde_json::from_str(include_str!("fixtures/matrix_monday_first.json")).unwrap();
+        let schedule = Schedule::default();
+        let mut feed = IcsFeed::default();
+
+        let courses = parse_week_matrix(matrix).unwrap();
+        let generate = |courses, feed: &mut IcsFeed| {
+            generate_icalendar_feed(courses, "20250901".into(), &schedule, None, feed)
+                .unwrap()
+                .to_string()
+        };
+
+        let first = events(&generate(courses.clone(), &mut feed));
+        assert_eq!(first.len(), 16 + 8 + 16);
+        assert!(first.iter().all(|(_, sequence, _)| sequence == "0"));
+        // Regenerated without change, nothing moves
+        assert_eq!(events(&generate(courses.clone(), &mut feed)), first);
+
+        let moved: Vec<_> = courses
+            .into_iter()
+            .map(|mut course| {
+                if course.name == "体育3" {
+                    course.classroom = "操场".into();
+                }
+                course
+            })
+            .collect();
+        let second = events(&generate(moved, &mut feed));
+
+        let uids = |events: &[(String, String, String)]| {
+            let mut uids: Vec<String> = events.iter().map(|(uid, _, _)| uid.clone()).collect();
+            uids.sort();
+            uids
+        };
+        assert_eq!(uids(&first), uids(&second));
+        for (_, sequence, location) in second {
+            let expected = if location == "操场" { "1" } else { "0" };
+            assert_eq!(sequence, expected, "{location}");
+        }
+    }
+
+    #[test]
+    fn fullcalendar_events() {
+        let course = |name: &str, oe, day, week: &str, classtime| {
+            ParsedCourse::new(
+                name.into(),
+                oe,
+                day,
+                vec![week.into()],
+                classtime,
+                "W101".into(),
+                "李老师".into(),
+            )
+        };
+        let classlist = vec![
+            course("高等数学A1", OddOrEven::Each, 1, "1-16", vec![1, 2]),
+            course("大学物理实验", OddOrEven::Odd, 3, "1-15", vec![3, 4]),
+            course("形势与政策", OddOrEven::Each, 5, "5-5", vec![3, 4]),
+        ];
+        let term_start = NaiveDate::from_ymd_opt(2025, 9, 1).unwrap();
+        let events = to_fullcalendar_events(&classlist, term_start, &Schedule::default()).unwrap();
+        assert_eq!(events.len(), 3);
+
+        assert_eq!(
+            serde_json::to_value(&events[0]).unwrap(),
+            serde_json::json!({
+                "title": "高等数学A1",
+                "rrule": {
+                    "freq": "weekly",
+                    "interval": 1,
+                    "dtstart": "2025-09-01T08:00:00+08:00",
+                    "count": 16,
+                },
+                "duration": "01:25",
+                "extendedProps": {
+                    "course": "高等数学A1",
+                    "teacher": "李老师",
+                    "location": "W101",
+                    "campus": null,
+                    "weekday": 1,
+                    "start_period": 1,
+                    "end_period": 2,
+                    "start_time": "08:00",
+                    "end_time": "09:25",
+                    "weeks": (1..=16).collect::<Vec<u32>>(),
+                },
+            })
+        );
+        let odd = events[1].rrule.as_ref().unwrap();
+        assert_eq!((odd.interval, odd.count), (2, 8));
+        assert_eq!(odd.dtstart, "2025-09-03T09:45:00+08:00");
+
+        let single = serde_json::to_value(&events[2]).unwrap();
+        assert_eq!(single["start"], "2025-10-03T09:45:00+08:00");
+        assert_eq!(single["end"], "2025-10-03T11:15:00+08:00");
+        assert!(single.get("rrule").is_none() && single.get("duration").is_none());
+        assert_eq!(single["extendedProps"]["weeks"], serde_json::json!([5]));
+    }
+}
@@ -0,0 +1,880 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    base::{
+        typing::{other_error, TorErr},
+        units::{Credit, GradePoint},
+    },
+    impls::apps::wechat::jwqywx_type::{CourseGrade, GradeStatus},
+};
+
+/// Computations over grades already fetched with
+/// [`crate::impls::apps::wechat::jwqywx::JwqywxApplication::get_grades`].
+///
+/// GPA here is the credit weighted mean of `grade_points`, courses without credits are left out.
+pub struct GradeAnalytics {
+    grades: Vec<CourseGrade>,
+}
+
+/// One point of [`GradeAnalytics::gpa_trend`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TermGpa {
+    /// The term index of [`CourseGrade::term`], `1` is the first term after enrollment
+    pub term: i32,
+    pub term_gpa: f32,
+    /// GPA of this term and all the terms before it
+    pub cumulative_gpa: f32,
+    /// Credits counted in this term
+    pub credits: f32,
+}
+
+/// A course not graded yet, for [`GradeAnalytics::simulate`].
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HypotheticalCourse {
+    /// The `course_id` of a course already taken, to simulate retaking it:
+    /// the expected grade then replaces the records of that course.
+    #[serde(default)]
+    pub course_id: Option<String>,
+    pub credits: Credit,
+    pub grade_points: GradePoint,
+}
+
+/// Credit categories counted as major courses by [`GradeAnalytics::major_gpa`]
+pub const MAJOR_CATEGORIES: [&str; 2] = ["学科基础", "专业"];
+
+/// Result of [`GradeAnalytics::summary`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GpaSummary {
+    pub overall_gpa: f32,
+    pub major_gpa: Option<f32>,
+    pub credits: f32,
+}
+
+/// Where the credits of a course count for graduation, see [`GradeAnalytics::credit_summary`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CreditCategory {
+    GeneralRequired,
+    GeneralElective,
+    MajorRequired,
+    MajorElective,
+    Practical,
+    /// The portal sent no credit category, or one not known here
+    Uncategorized,
+}
+
+impl CreditCategory {
+    /// From the credit category (`xflb`) and the 必修/选修 of the course.
+    pub fn of(grade: &CourseGrade) -> Self {
+        let Some(category) = grade.credit_category.as_deref() else {
+            return CreditCategory::Uncategorized;
+        };
+        let elective = grade.course_type_name.contains("选修") || category.contains("选修");
+        if ["实践", "实验", "实习"]
+            .iter()
+            .any(|practical| category.contains(practical))
+        {
+            CreditCategory::Practical
+        } else if category.starts_with("通识") {
+            if elective {
+                CreditCategory::GeneralElective
+            } else {
+                CreditCategory::GeneralRequired
+            }
+        } else if MAJOR_CATEGORIES
+            .iter()
+            .any(|major| category.starts_with(major))
+        {
+            if elective {
+                CreditCategory::MajorElective
+            } else {
+                CreditCategory::MajorRequired
+            }
+        } else {
+            CreditCategory::Uncategorized
+        }
+    }
+}
+
+/// Credits of one [`CreditCategory`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CategoryCredits {
+    pub category: CreditCategory,
+    pub earned: f32,
+    /// As given to [`GradeAnalytics::credit_summary`], the training plan isn't fetched
+    pub required: Option<f32>,
+    pub courses: usize,
+}
+
+/// Result of [`GradeAnalytics::credit_summary`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CreditSummary {
+    /// Every category with credits or a requirement, in the order of [`CreditCategory`]
+    pub categories: Vec<CategoryCredits>,
+    pub total_earned: f32,
+    /// Some credits are in [`CreditCategory::Uncategorized`], the other sums are short of them
+    pub has_uncategorized: bool,
+}
+
+/// Grades below this fail the course.
+pub const PASSING_SCORE: f32 = 60.0;
+
+/// When [`GradeAnalytics::academic_warning_with`] raises a warning.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WarningThresholds {
+    /// Credits failed and not retaken for [`WarningLevel::Yellow`]
+    pub yellow_failed_credits: f32,
+    /// Credits failed and not retaken for [`WarningLevel::Red`]
+    pub red_failed_credits: f32,
+    /// A lower GPA is at least [`WarningLevel::Yellow`]
+    pub min_gpa: f32,
+}
+
+impl Default for WarningThresholds {
+    fn default() -> Self {
+        Self {
+            yellow_failed_credits: 10.0,
+            red_failed_credits: 20.0,
+            min_gpa: 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningLevel {
+    None,
+    Yellow,
+    Red,
+}
+
+/// Who decided on an [`AcademicWarning`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningSource {
+    /// Estimated from the grades with [`WarningThresholds`], the portals don't report
+    /// their own 学业预警
+    Computed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WarningReason {
+    FailedCredits { credits: f32, threshold: f32 },
+    LowGpa { gpa: f32, threshold: f32 },
+}
+
+/// Result of [`GradeAnalytics::academic_warning`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AcademicWarning {
+    pub level: WarningLevel,
+    pub source: WarningSource,
+    /// Credits of the courses failed and not passed since
+    pub failed_credits: f32,
+    pub failed_courses: Vec<String>,
+    /// Empty at [`WarningLevel::None`]
+    pub reasons: Vec<WarningReason>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GpaProjection {
+    pub projected_gpa: f32,
+    pub projected_credits: f32,
+}
+
+impl GradeAnalytics {
+    pub fn new(grades: Vec<CourseGrade>) -> Self {
+        Self { grades }
+    }
+
+    pub fn grades(&self) -> &[CourseGrade] {
+        &self.grades
+    }
+
+    /// GPA if the `additional` courses end with their expected grades.
+    pub fn simulate(&self, additional: &[HypotheticalCourse]) -> GpaProjection {
+        let retaken: Vec<&str> = additional
+            .iter()
+            .filter_map(|course| course.course_id.as_deref())
+            .collect();
+
+        let (credits, weighted) = self
+            .grades
+            .iter()
+            .filter(|grade| !retaken.contains(&grade.course_id.as_str()))
+            .map(|grade| (grade.course_credits, grade.grade_points))
+            .chain(
+                additional
+                    .iter()
+                    .map(|course| (course.credits, course.grade_points)),
+            )
+            .filter(|(credits, _)| *credits > Credit::ZERO)
+            .fold((0.0, 0.0), |(total, weighted), (credits, points)| {
+                (
+                    total + credits.value(),
+                    weighted + credits.value() * points.value(),
+                )
+            });
+
+        GpaProjection {
+            projected_gpa: if credits > 0.0 {
+                weighted / credits
+            } else {
+                0.0
+            },
+            projected_credits: credits,
+        }
+    }
+
+    /// GPA of every course.
+    pub fn overall_gpa(&self) -> f32 {
+        self.simulate(&[]).projected_gpa
+    }
+
+    /// GPA of the major courses only, those of the [`MAJOR_CATEGORIES`].
+    ///
+    /// `None` when a course has no credit category, as it can't be told apart,
+    /// or when there is no major course.
+    pub fn major_gpa(&self) -> Option<f32> {
+        let (mut credits, mut weighted) = (0.0, 0.0);
+        for grade in self
+            .grades
+            .iter()
+            .filter(|grade| grade.course_credits > Credit::ZERO)
+        {
+            let category = grade.credit_category.as_deref()?;
+            if MAJOR_CATEGORIES
+                .iter()
+                .any(|major| category.starts_with(major))
+            {
+                credits += grade.course_credits.value();
+                weighted += grade.course_credits.value() * grade.grade_points.value();
+            }
+        }
+        (credits > 0.0).then(|| weighted / credits)
+    }
+
+    pub fn summary(&self) -> GpaSummary {
+        let overall = self.simulate(&[]);
+        GpaSummary {
+            overall_gpa: overall.projected_gpa,
+            major_gpa: self.major_gpa(),
+            credits: overall.projected_credits,
+        }
+    }
+
+    /// The best record of each course, by course id.
+    fn best_records(&self) -> Vec<&CourseGrade> {
+        let mut best: BTreeMap<&str, &CourseGrade> = BTreeMap::new();
+        for grade in &self.grades {
+            best.entry(&grade.course_id)
+                .and_modify(|kept| {
+                    if grade.grade > kept.grade {
+                        *kept = grade
+                    }
+                })
+                .or_insert(grade);
+        }
+        best.into_values().collect()
+    }
+
+    /// Credits earned in each [`CreditCategory`], a course counting once when passed, next to
+    /// the `requirements` of the program.
+    pub fn credit_summary(&self, requirements: &BTreeMap<CreditCategory, f32>) -> CreditSummary {
+        let mut categories: BTreeMap<CreditCategory, CategoryCredits> = requirements
+            .iter()
+            .map(|(&category, &required)| {
+                let credits = CategoryCredits {
+                    category,
+                    earned: 0.0,
+                    required: Some(required),
+                    courses: 0,
+                };
+                (category, credits)
+            })
+            .collect();
+        for grade in self
+            .best_records()
+            .into_iter()
+            .filter(|grade| grade.grade.value() >= PASSING_SCORE)
+        {
+            let category = CreditCategory::of(grade);
+            let credits = categories.entry(category).or_insert(CategoryCredits {
+                category,
+                earned: 0.0,
+                required: None,
+                courses: 0,
+            });
+            credits.earned += grade.course_credits.value();
+            credits.courses += 1;
+        }
+
+        CreditSummary {
+            total_earned: categories.values().map(|credits| credits.earned).sum(),
+            has_uncategorized: categories
+                .get(&CreditCategory::Uncategorized)
+                .is_some_and(|credits| credits.courses > 0),
+            categories: categories.into_values().collect(),
+        }
+    }
+
+    /// [`Self::academic_warning_with`] the default thresholds.
+    pub fn academic_warning(&self) -> AcademicWarning {
+        self.academic_warning_with(&WarningThresholds::default())
+    }
+
+    /// An early signal of a student at risk, from the courses failed and not passed since
+    /// and the GPA.
+    pub fn academic_warning_with(&self, thresholds: &WarningThresholds) -> AcademicWarning {
+        // A retake passed clears the failure
+        let failed: Vec<&CourseGrade> = self
+            .best_records()
+            .into_iter()
+            .filter(|grade| grade.grade.value() < PASSING_SCORE)
+            .collect();
+        let failed_credits = failed
+            .iter()
+            .map(|grade| grade.course_credits.value())
+            .sum();
+
+        let (mut level, mut reasons) = (WarningLevel::None, vec![]);
+        for (reached, threshold) in [
+            (WarningLevel::Red, thresholds.red_failed_credits),
+            (WarningLevel::Yellow, thresholds.yellow_failed_credits),
+        ] {
+            if failed_credits >= threshold {
+                level = reached;
+                reasons.push(WarningReason::FailedCredits {
+                    credits: failed_credits,
+                    threshold,
+                });
+                break;
+            }
+        }
+        let gpa = self.overall_gpa();
+        if !self.grades.is_empty() && gpa < thresholds.min_gpa {
+            level = level.max(WarningLevel::Yellow);
+            reasons.push(WarningReason::LowGpa {
+                gpa,
+                threshold: thresholds.min_gpa,
+            });
+        }
+
+        AcademicWarning {
+            level,
+            source: WarningSource::Computed,
+            failed_credits,
+            failed_courses: failed
+                .iter()
+                .map(|grade| grade.course_name.clone())
+                .collect(),
+            reasons,
+        }
+    }
+
+    /// Per term GPA, from the first term to the last.
+    pub fn gpa_trend(&self) -> Vec<TermGpa> {
+        // (credits, credits * points) of each term, ordered by term
+        let mut terms: BTreeMap<i32, (f32, f32)> = BTreeMap::new();
+        for grade in self
+            .grades
+            .iter()
+            .filter(|grade| grade.course_credits > Credit::ZERO)
+        {
+            let (credits, weighted) = terms.entry(grade.term).or_default();
+            *credits += grade.course_credits.value();
+            *weighted += grade.course_credits.value() * grade.grade_points.value();
+        }
+
+        let (mut total_credits, mut total_weighted) = (0.0, 0.0);
+        terms
+            .into_iter()
+            .map(|(term, (credits, weighted))| {
+                total_credits += credits;
+                total_weighted += weighted;
+                TermGpa {
+                    term,
+                    term_gpa: weighted / credits,
+                    cumulative_gpa: total_weighted / total_credits,
+                    credits,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Result of [`grades_since`].
+#[derive(Debug, Serialize)]
+pub struct GradesSince {
+    /// Give it back next time, only the grades released or changed after it are returned
+    pub token: String,
+    /// Grades not seen before or changed, but the finalized ones
+    pub new_grades: Vec<CourseGrade>,
+    /// Grades seen pending or provisional before, final now
+    pub finalized: Vec<CourseGrade>,
+}
+
+/// FNV-1a of `fields`, each ended by a NUL.
+fn fnv1a(fields: &[&str]) -> u64 {
+    fields.iter().fold(0xcbf29ce484222325, |hash, field| {
+        field.bytes().chain([0]).fold(hash, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    })
+}
+
+/// FNV-1a of what makes a grade record: the course, the term, the grades and the status
+/// unless final, so the final records of older tokens are still known.
+fn fingerprint(grade: &CourseGrade) -> u64 {
+    let mut fields = vec![
+        grade.course_id.clone(),
+        grade.term.to_string(),
+        grade.grade.to_string(),
+        grade.grade_points.to_string(),
+        grade.exam_grade.clone(),
+    ];
+    if grade.status != GradeStatus::Final {
+        fields.push(format!("{:?}", grade.status));
+    }
+    fnv1a(&fields.iter().map(String::as_str).collect::<Vec<_>>())
+}
+
+/// Kept in the token for a course not final yet, whatever its grades.
+fn unfinished_mark(grade: &CourseGrade) -> u64 {
+    fnv1a(&[&grade.course_id, &grade.term.to_string(), "unfinished"])
+}
+
+/// The grades not seen in `token`, an empty token means everything is new.
+///
+/// A grade becoming final after being seen pending or provisional is in
+/// [`GradesSince::finalized`] instead of [`GradesSince::new_grades`], even when its score
+/// changed on the way. The token is the set of fingerprints of the records, nothing about
+/// the grades can be read from it.
+pub fn grades_since(grades: Vec<CourseGrade>, token: &str) -> TorErr<GradesSince> {
+    let seen: BTreeSet<u64> = BASE64_URL_SAFE_NO_PAD
+        .decode(token)
+        .ok()
+        .filter(|bytes| bytes.len() % 8 == 0)
+        .ok_or(other_error("Invalid grades token"))?
+        .chunks_exact(8)
+        .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+
+    let mut current: BTreeSet<u64> = grades.iter().map(fingerprint).collect();
+    current.extend(
+        grades
+            .iter()
+            .filter(|grade| grade.status != GradeStatus::Final)
+            .map(unfinished_mark),
+    );
+    let (finalized, new_grades) = grades
+        .into_iter()
+        .filter(|grade| !seen.contains(&fingerprint(grade)))
+        .partition(|grade| {
+            grade.status == GradeStatus::Final && seen.contains(&unfinished_mark(grade))
+        });
+
+    Ok(GradesSince {
+        token: BASE64_URL_SAFE_NO_PAD.encode(
+            current
+                .into_iter()
+                .flat_map(u64::to_le_bytes)
+                .collect::<Vec<u8>>(),
+        ),
+        new_grades,
+        finalized,
+    })
+}
+
+/// Language of the texts made for the user, like [`format_grade_notification`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    Zh,
+    En,
+}
+
+impl Locale {
+    /// From a language tag like `en-US`, Chinese unless it is English.
+    pub fn from_tag(tag: &str) -> Self {
+        if tag.trim().to_ascii_lowercase().starts_with("en") {
+            Locale::En
+        } else {
+            Locale::Zh
+        }
+    }
+}
+
+/// Courses named in a notification, the others are counted.
+pub const NOTIFICATION_COURSES: usize = 3;
+
+/// Like `高等数学A1 92`, a grade not final yet is marked.
+fn notification_course(grade: &CourseGrade, locale: Locale) -> String {
+    let mark = match (locale, grade.status) {
+        (_, GradeStatus::Final) => "",
+        (Locale::Zh, GradeStatus::Provisional) => "（暂定）",
+        (Locale::Zh, GradeStatus::Pending) => "（未录入）",
+        (Locale::En, GradeStatus::Provisional) => " (provisional)",
+        (Locale::En, GradeStatus::Pending) => " (pending)",
+    };
+    format!("{} {}{mark}", grade.course_name, grade.grade)
+}
+
+/// The first [`NOTIFICATION_COURSES`] of `grades` after `one` or `many` (with the count),
+/// `none` without grades.
+fn notification(grades: &[CourseGrade], locale: Locale, [none, one, many]: [&str; 3]) -> String {
+    let named: Vec<String> = grades
+        .iter()
+        .take(NOTIFICATION_COURSES)
+        .map(|grade| notification_course(grade, locale))
+        .collect();
+    let more = grades.len().saturating_sub(NOTIFICATION_COURSES);
+    match (locale, grades.len()) {
+        (_, 0) => none.into(),
+        (_, 1) => format!("{one}{}", named[0]),
+        (Locale::Zh, count) => {
+            let mut text = format!("{count} {many}{}", named.join("、"));
+            if more > 0 {
+                text += &format!(" 等 {more} 门");
+            }
+            text
+        }
+        (Locale::En, count) => {
+            let mut text = format!("{count} {many}{}", named.join(", "));
+            if more > 0 {
+                text += &format!(" and {more} more");
+            }
+            text
+        }
+    }
+}
+
+/// A one line summary of `new` for a push notification, like `3 门新成绩：高等数学A1 92、…`.
+///
+/// The grades not final yet are marked, `线性代数 83（暂定）`.
+pub fn format_grade_notification(new: &[CourseGrade], locale: Locale) -> String {
+    let texts = match locale {
+        Locale::Zh => ["没有新成绩", "新成绩：", "门新成绩："],
+        Locale::En => ["No new grades", "1 new grade: ", "new grades: "],
+    };
+    notification(new, locale, texts)
+}
+
+/// [`format_grade_notification`] of [`GradesSince::finalized`], like
+/// `成绩已确认：线性代数 85`.
+pub fn format_finalized_notification(finalized: &[CourseGrade], locale: Locale) -> String {
+    let texts = match locale {
+        Locale::Zh => ["没有新确认的成绩", "成绩已确认：", "门成绩已确认："],
+        Locale::En => [
+            "No grades finalized",
+            "1 grade finalized: ",
+            "grades finalized: ",
+        ],
+    };
+    notification(finalized, locale, texts)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::{
+        format_finalized_notification, format_grade_notification, grades_since, CreditCategory,
+        GradeAnalytics, HypotheticalCourse, Locale, WarningLevel, WarningReason, WarningSource,
+        WarningThresholds,
+    };
+    use crate::{
+        base::units::{Credit, GradePoint, Score},
+        impls::apps::wechat::jwqywx_type::{CourseGrade, GradeStatus, Message},
+    };
+
+    fn records() -> Vec<CourseGrade> {
+        let message: Message<CourseGrade> =
+            serde_json::from_str(include_str!("fixtures/grades.json")).unwrap();
+        message.message
+    }
+
+    fn fixture() -> GradeAnalytics {
+        GradeAnalytics::new(records())
+    }
+
+    fn close(left: f32, right: f32) -> bool {
+        (left - right).abs() < 1e-4
+    }
+
+    #[test]
+    fn gpa_trend() {
+        let trend = fixture().gpa_trend();
+
+        assert_eq!(
+            trend.iter().map(|point| point.term).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            trend.iter().map(|point| point.credits).collect::<Vec<_>>(),
+            vec![8.0, 4.0, 6.0]
+        );
+        assert!(close(trend[0].term_gpa, 3.625));
+        assert!(close(trend[0].cumulative_gpa, 3.625));
+        assert!(close(trend[1].term_gpa, 3.625));
+        assert!(close(trend[1].cumulative_gpa, 3.625));
+        assert!(close(trend[2].term_gpa, 8.0 / 6.0));
+        assert!(close(trend[2].cumulative_gpa, 51.5 / 18.0));
+    }
+
+    #[test]
+    fn gpa_trend_skips_zero_credit() {
+        let mut analytics = fixture();
+        analytics.grades.iter_mut().for_each(|grade| {
+            if grade.term == 2 {
+                grade.course_credits = Credit::ZERO
+            }
+        });
+
+        let trend = analytics.gpa_trend();
+        assert_eq!(
+            trend.iter().map(|point| point.term).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert!(close(trend[1].cumulative_gpa, 37.0 / 14.0));
+        assert!(GradeAnalytics::new(vec![]).gpa_trend().is_empty());
+    }
+
+    #[test]
+    fn simulate() {
+        let analytics = fixture();
+
+        let current = analytics.simulate(&[]);
+        assert_eq!(current.projected_credits, 18.0);
+        assert!(close(current.projected_gpa, 51.5 / 18.0));
+
+        let next: Vec<HypotheticalCourse> = serde_json::from_str(
+            r#"[{"credits": 4.0, "grade_points": 4.0}, {"credits": 2.0, "grade_points": 3.0}]"#,
+        )
+        .unwrap();
+        let projection = analytics.simulate(&next);
+        assert_eq!(projection.projected_credits, 24.0);
+        assert!(close(projection.projected_gpa, 73.5 / 24.0));
+
+        // Retaking the failed 思想道德与法治 replaces its 0 instead of adding credits
+        let retake = [HypotheticalCourse {
+            course_id: Some("10600031".into()),
+            credits: Credit::new(2.0).unwrap(),
+            grade_points: GradePoint::new(3.0).unwrap(),
+        }];
+        let projection = analytics.simulate(&retake);
+        assert_eq!(projection.projected_credits, 18.0);
+        assert!(close(projection.projected_gpa, 57.5 / 18.0));
+
+        assert_eq!(GradeAnalytics::new(vec![]).simulate(&[]).projected_gpa, 0.0);
+    }
+
+    #[test]
+    fn major_gpa() {
+        let message: Message<CourseGrade> =
+            serde_json::from_str(include_str!("fixtures/grades_categories.json")).unwrap();
+        let analytics = GradeAnalytics::new(message.message);
+
+        // 线性代数 (学科基础) and 数据结构 (专业核心), the 通识教育 courses are left out
+        let summary = analytics.summary();
+        assert!(close(summary.major_gpa.unwrap(), 18.5 / 7.0));
+        assert!(close(summary.overall_gpa, 51.5 / 18.0));
+        assert_eq!(summary.credits, 18.0);
+
+        // Not the overall GPA when the categories are missing
+        assert_eq!(fixture().major_gpa(), None);
+        let mut partial = analytics;
+        partial.grades[0].credit_category = None;
+        assert_eq!(partial.major_gpa(), None);
+    }
+
+    #[test]
+    fn academic_warning() {
+        let message: Message<CourseGrade> =
+            serde_json::from_str(include_str!("fixtures/grades_failures.json")).unwrap();
+        let analytics = GradeAnalytics::new(message.message);
+
+        // 高等数学A1 was retaken and passed, 大学物理, 线性代数 and 数据结构 were not
+        let warning = analytics.academic_warning();
+        assert_eq!(warning.level, WarningLevel::Yellow);
+        assert_eq!(warning.source, WarningSource::Computed);
+        assert_eq!(warning.failed_credits, 11.0);
+        assert_eq!(warning.failed_courses.len(), 3);
+        assert!(!warning.failed_courses.contains(&"高等数学A1".to_owned()));
+        assert_eq!(
+            warning.reasons[0],
+            WarningReason::FailedCredits {
+                credits: 11.0,
+                threshold: 10.0
+            }
+        );
+        assert!(
+            matches!(warning.reasons[1], WarningReason::LowGpa { gpa, .. } if close(gpa, 0.72))
+        );
+
+        let strict = WarningThresholds {
+            red_failed_credits: 11.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            analytics.academic_warning_with(&strict).level,
+            WarningLevel::Red
+        );
+
+        // One failed course of 2 credits
+        let warning = fixture().academic_warning();
+        assert_eq!(warning.level, WarningLevel::None);
+        assert_eq!(warning.failed_credits, 2.0);
+        assert!(warning.reasons.is_empty());
+        assert_eq!(
+            GradeAnalytics::new(vec![]).academic_warning().level,
+            WarningLevel::None
+        );
+    }
+
+    #[test]
+    fn credit_summary() {
+        let message: Message<CourseGrade> =
+            serde_json::from_str(include_str!("fixtures/grades_credit_summary.json")).unwrap();
+        let analytics = GradeAnalytics::new(message.message);
+        let requirements = BTreeMap::from([
+            (CreditCategory::MajorRequired, 60.0),
+            (CreditCategory::Practical, 20.0),
+        ]);
+
+        let summary = analytics.credit_summary(&requirements);
+        let earned: Vec<(CreditCategory, f32, usize)> = summary
+            .categories
+            .iter()
+            .map(|credits| (credits.category, credits.earned, credits.courses))
+            .collect();
+        // 思想道德与法治 is failed, 形势与政策 has no category
+        assert_eq!(
+            earned,
+            [
+                (CreditCategory::GeneralRequired, 9.0, 3),
+                (CreditCategory::GeneralElective, 2.0, 1),
+                (CreditCategory::MajorRequired, 7.0, 2),
+                (CreditCategory::MajorElective, 2.0, 1),
+                (CreditCategory::Practical, 2.0, 1),
+                (CreditCategory::Uncategorized, 1.0, 1),
+            ]
+        );
+        assert_eq!(summary.categories[2].required, Some(60.0));
+        assert_eq!(summary.categories[0].required, None);
+        assert_eq!(summary.total_earned, 23.0);
+        assert!(summary.has_uncategorized);
+
+        // Without any category, everything is uncategorized
+        let summary = fixture().credit_summary(&BTreeMap::new());
+        assert_eq!(summary.categories.len(), 1);
+        assert_eq!(
+            summary.categories[0].category,
+            CreditCategory::Uncategorized
+        );
+        assert_eq!(summary.total_earned, 16.0);
+    }
+
+    #[test]
+    fn grade_notification() {
+        let grades = records();
+        assert_eq!(format_grade_notification(&[], Locale::Zh), "没有新成绩");
+        assert_eq!(
+            format_grade_notification(&grades[..1], Locale::En),
+            "1 new grade: 线性代数 85"
+        );
+        assert_eq!(
+            format_grade_notification(&grades[..2], Locale::Zh),
+            "2 门新成绩：线性代数 85、高等数学A1 92"
+        );
+        assert_eq!(
+            format_grade_notification(&grades, Locale::from_tag("en-US")),
+            "6 new grades: 线性代数 85, 高等数学A1 92, 大学英语1 80 and 3 more"
+        );
+        assert!(format_grade_notification(&grades, Locale::Zh).ends_with("大学英语1 80 等 3 门"));
+    }
+
+    #[test]
+    fn new_grades_since() {
+        let first = grades_since(records(), "").unwrap();
+        assert_eq!(first.new_grades.len(), 6);
+
+        let again = grades_since(records(), &first.token).unwrap();
+        assert!(again.new_grades.is_empty());
+        assert_eq!(again.token, first.token);
+
+        // 思想道德与法治 got its make-up exam grade, 数据结构 is not out yet
+        let mut released = records();
+        released.retain(|grade| grade.course_id != "20400031");
+        let before = grades_since(released, "").unwrap();
+        let mut updated = records();
+        let retake = updated
+            .iter_mut()
+            .find(|grade| grade.course_id == "10600031")
+            .unwrap();
+        retake.grade = Score::new(75.0).unwrap();
+        retake.grade_points = GradePoint::new(2.5).unwrap();
+        let since = grades_since(updated, &before.token).unwrap();
+        let mut names: Vec<&str> = since
+            .new_grades
+            .iter()
+            .map(|grade| grade.course_name.as_str())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["思想道德与法治", "数据结构"]);
+
+        assert!(grades_since(records(), "not a token!").is_err());
+    }
+
+    #[test]
+    fn provisional_then_final() {
+        let message: Message<CourseGrade> =
+            serde_json::from_str(include_str!("fixtures/grades_provisional.json")).unwrap();
+        let entering = message.message;
+        let statuses: Vec<GradeStatus> = entering.iter().map(|grade| grade.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                GradeStatus::Provisional,
+                GradeStatus::Final,
+                GradeStatus::Pending
+            ]
+        );
+        assert_eq!(
+            format_grade_notification(&entering, Locale::Zh),
+            "3 门新成绩：线性代数 83（暂定）、高等数学A1 92、数据结构 0（未录入）"
+        );
+
+        let before = grades_since(entering, "").unwrap();
+        assert!(before.finalized.is_empty());
+        // The same records, all final now: 线性代数 83 became 85
+        let since = grades_since(records(), &before.token).unwrap();
+        let names = |grades: &[CourseGrade]| {
+            let mut names: Vec<String> = grades
+                .iter()
+                .map(|grade| grade.course_name.clone())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(&since.finalized), ["数据结构", "线性代数"]);
+        assert_eq!(
+            names(&since.new_grades),
+            ["体育2", "大学英语1", "思想道德与法治"]
+        );
+        assert_eq!(
+            format_finalized_notification(&since.finalized[..1], Locale::En),
+            "1 grade finalized: 线性代数 85"
+        );
+
+        // Changed once final, it is new again
+        let mut corrected = records();
+        corrected[0].grade = Score::new(88.0).unwrap();
+        let again = grades_since(corrected, &since.token).unwrap();
+        assert!(again.finalized.is_empty());
+        assert_eq!(names(&again.new_grades), ["线性代数"]);
+    }
+}
@@ -0,0 +1,61 @@
+//! The teachers of the schedule and of the grades as one type, told apart by their id when
+//! the portal gives one.
+
+use serde::{Deserialize, Serialize};
+
+/// A teacher of a course, as the schedule or a grade names them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Instructor {
+    pub name: String,
+    /// The staff id of the portal, `None` where only the name is given
+    pub id: Option<String>,
+}
+
+/// The names of a schedule entry or a grade, some are given as `王五,赵六`.
+pub(crate) fn split_names(names: &str) -> impl Iterator<Item = &str> {
+    names
+        .split([',', '，', '、', '/', ' '])
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+impl Instructor {
+    pub fn named(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            id: None,
+        }
+    }
+
+    /// The teachers of `names`, each with the id in the same place of `ids` (`T01,T02` for
+    /// `王五,赵六`). The ids are dropped when there are not as many as the names.
+    pub fn list(names: &str, ids: Option<&str>) -> Vec<Self> {
+        let names: Vec<&str> = split_names(names).collect();
+        let ids: Vec<&str> = ids
+            .map(|ids| split_names(ids).collect())
+            .unwrap_or_default();
+        let ids_match = ids.len() == names.len();
+        names
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| Self {
+                name: name.to_owned(),
+                id: ids_match.then(|| ids[index].to_owned()),
+            })
+            .collect()
+    }
+
+    /// The same person: by the ids when both have one, by the names otherwise, however the
+    /// names are spaced.
+    pub fn is(&self, other: &Instructor) -> bool {
+        match (&self.id, &other.id) {
+            (Some(id), Some(other)) => id == other,
+            _ => {
+                let name = |instructor: &Instructor| -> String {
+                    instructor.name.split_whitespace().collect()
+                };
+                name(self) == name(other)
+            }
+        }
+    }
+}
@@ -1,2 +1,4 @@
 #[cfg(feature = "calendar")]
 pub mod calendar;
+pub mod grades;
+pub mod instructor;
@@ -1,14 +1,48 @@
 // src/ffi.rs
 
 use crate::base::app::AppVisitor;
+#[cfg(any(feature = "cookie-file", feature = "session-crypto"))]
+use crate::base::client::{Account, ClientOptions};
+use crate::base::client::{Campus, Client};
+#[cfg(feature = "cookie-file")]
+use crate::base::cookies::FileCookieStore;
+use crate::base::typing::other_error;
+use crate::base::version::{FEATURES, VERSION};
+use crate::extension::calendar::{
+    diff_schedule, flatten_week_matrix_from, generate_icalendar_feed, parse_week_matrix,
+    to_fullcalendar_events, CurrentWeek, IcsFeed, RawCourse, Schedule, ScheduleEntry,
+    TermCalendarParser, WeekStart,
+};
+use crate::extension::grades::{
+    format_finalized_notification, format_grade_notification, AcademicWarning, CreditCategory,
+    GpaSummary, GradeAnalytics, HypotheticalCourse, Locale,
+};
 use crate::impls::apps::wechat::jwqywx::JwqywxApplication;
+use crate::impls::apps::wechat::jwqywx_type::{CourseGrade, Detailed, StudentPoint};
 use crate::impls::client::DefaultClient;
+#[cfg(feature = "session-crypto")]
+use crate::impls::client::SessionPolicy;
 use crate::impls::login::sso::SSOUniversalLogin;
-use crate::utils::status::services_status_code;
-use libc::c_char;
+use crate::utils::deadline::with_deadline;
+use crate::utils::schedule_watch::ScheduleWatch;
+#[cfg(feature = "session-crypto")]
+use crate::utils::session_crypto::SESSION_KEY_LEN;
+use crate::utils::status::{
+    services_status_until, services_status_with_latency_until, CancelToken, LatencyTracker,
+    PROBE_TIMEOUT,
+};
+#[cfg(feature = "session-crypto")]
+use base64::{prelude::BASE64_STANDARD, Engine};
+use chrono::NaiveDate;
+use libc::{c_char, c_void};
 use once_cell::sync::Lazy;
-use serde::Serialize;
-use std::ffi::{CStr, CString};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
 use tokio::runtime::Runtime;
 
 // 1. 全局 Tokio 运行时
@@ -20,6 +54,12 @@ static RUNTIME: Lazy<Runtime> = Lazy::new(|| {
         .expect("Failed to create Tokio runtime")
 });
 
+// 记录各个服务的平均响应时间，在多次状态查询之间保留。
+static LATENCY: Lazy<LatencyTracker> = Lazy::new(LatencyTracker::default);
+
+// `cczuni_cancel` 中止的状态查询，中止后由下一次查询换新。
+static STATUS_CANCEL: Lazy<Mutex<CancelToken>> = Lazy::new(Default::default);
+
 // 2. FFI 结果封装
 // 定义一个通用的返回结构体，用于将成功或失败的结果序列化为 JSON。
 #[derive(Serialize)]
@@ -46,46 +86,403 @@ impl<T: Serialize> FfiResult<T> {
         }
     }
 
-    fn to_json_string(self) -> String {
-        serde_json::to_string(&self).unwrap_or_else(|e| {
-            serde_json::to_string(&FfiResult::<()>::error(&format!(
-                "JSON serialization failed: {}",
-                e
-            )))
-            .unwrap()
-        })
+    fn of<E: ToString>(result: Result<T, E>) -> Self {
+        match result {
+            Ok(data) => Self::success(data),
+            Err(e) => Self::error(&e.to_string()),
+        }
+    }
+
+    /// 字段名为 snake_case 的 JSON，用于不接收客户端的函数。
+    fn to_json_string(&self) -> String {
+        self.to_json_string_in(false)
+    }
+
+    /// 字段名按 `client` 的设置命名的 JSON，见 `cczuni_client_set_camel_case`。
+    fn to_client_json(&self, client: &DefaultClient) -> String {
+        self.to_json_string_in(client.options().camel_case)
+    }
+
+    fn to_json_string_in(&self, camel_case: bool) -> String {
+        let value = serde_json::to_value(self).map(|value| {
+            if camel_case {
+                rename_keys(value, snake_to_camel)
+            } else {
+                value
+            }
+        });
+        value
+            .and_then(|value| serde_json::to_string(&value))
+            .unwrap_or_else(|e| {
+                serde_json::to_string(&FfiResult::<()>::error(&format!(
+                    "JSON serialization failed: {}",
+                    e
+                )))
+                .unwrap()
+            })
+    }
+}
+
+/// `cczuni_get_overview` 的 `data`，每一项单独成功或失败。
+#[derive(Serialize)]
+struct FfiOverview<'a> {
+    profile: FfiResult<&'a StudentPoint>,
+    gpa: FfiResult<&'a GpaSummary>,
+    current_week: FfiResult<&'a CurrentWeek>,
+    academic_warning: FfiResult<&'a AcademicWarning>,
+}
+
+/// `course_credits` 转为 `courseCredits`。
+fn snake_to_camel(key: &str) -> String {
+    let mut camel = String::with_capacity(key.len());
+    let mut upper = false;
+    for c in key.chars() {
+        match c {
+            '_' if !camel.is_empty() => upper = true,
+            c if upper => {
+                camel.extend(c.to_uppercase());
+                upper = false;
+            }
+            c => camel.push(c),
+        }
+    }
+    camel
+}
+
+/// `courseCredits` 转为 `course_credits`。
+fn camel_to_snake(key: &str) -> String {
+    let mut snake = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            snake.push('_');
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+/// 递归地重命名所有对象的键。`raw` 为教务系统的原始记录，保持原样。
+fn rename_keys(value: Value, rename: fn(&str) -> String) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| match key.as_str() {
+                    "raw" => (key, value),
+                    _ => (rename(&key), rename_keys(value, rename)),
+                })
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(|value| rename_keys(value, rename))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// 解析调用方传入的 JSON，`client` 启用 camelCase 时先把字段名转回 snake_case。
+fn parse_json<T: DeserializeOwned>(json: &str, client: &DefaultClient) -> serde_json::Result<T> {
+    if !client.options().camel_case {
+        return serde_json::from_str(json);
+    }
+    serde_json::from_value(rename_keys(serde_json::from_str(json)?, camel_to_snake))
+}
+
+/// 不接收客户端的函数解析传入的 JSON，两种命名都接受，同时返回其中是否有 camelCase 的字段名。
+fn parse_json_either<T: DeserializeOwned>(json: &str) -> serde_json::Result<(T, bool)> {
+    let value: Value = serde_json::from_str(json)?;
+    let snake = rename_keys(value.clone(), camel_to_snake);
+    let camel_case = snake != value;
+    Ok((serde_json::from_value(snake)?, camel_case))
+}
+
+/// 学号、密码、验证码、密钥、路径、日期等短参数的最大长度（字节，不含结尾的 NUL），
+/// 更长的参数返回错误，不会被处理。
+pub const CCZUNI_MAX_FIELD_LEN: usize = 4096;
+
+/// JSON、会话、成绩 `token` 等参数的最大长度（字节，不含结尾的 NUL），更长的参数返回错误。
+pub const CCZUNI_MAX_DOCUMENT_LEN: usize = 16 * 1024 * 1024;
+
+/// 读取调用方传入的短参数，最长 [`CCZUNI_MAX_FIELD_LEN`] 字节，见 [`read_c_str_within`]。
+///
+/// # Safety
+/// 同 [`read_c_str_within`]。
+unsafe fn read_c_str<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, String> {
+    unsafe { read_c_str_within(ptr, name, CCZUNI_MAX_FIELD_LEN) }
+}
+
+/// 读取调用方传入的 JSON 等长参数，最长 [`CCZUNI_MAX_DOCUMENT_LEN`] 字节。
+///
+/// # Safety
+/// 同 [`read_c_str_within`]。
+unsafe fn read_c_document<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, String> {
+    unsafe { read_c_str_within(ptr, name, CCZUNI_MAX_DOCUMENT_LEN) }
+}
+
+/// 读取调用方传入的 C 字符串，空指针、超过 `max` 字节或非 UTF-8 时返回错误，而不是有损转换。
+///
+/// 最多只读 `max + 1` 个字节寻找结尾的 NUL，没有结尾的缓冲区也不会被一直读下去。
+///
+/// # Safety
+/// `ptr` 必须为空指针，或有效的、以 NUL 结尾的 C 字符串，或至少 `max + 1` 字节可读。
+unsafe fn read_c_str_within<'a>(
+    ptr: *const c_char,
+    name: &str,
+    max: usize,
+) -> Result<&'a str, String> {
+    if ptr.is_null() {
+        return Err(format!("`{}` is null", name));
+    }
+    let Some(len) = (0..=max).find(|&at| unsafe { *ptr.add(at) } == 0) else {
+        return Err(format!("`{}` is longer than {} bytes", name, max));
+    };
+    let bytes = unsafe { std::slice::from_raw_parts(ptr as *const u8, len) };
+    std::str::from_utf8(bytes).map_err(|e| format!("`{}` is not valid UTF-8: {}", name, e))
+}
+
+/// 把字节交给调用方：长度写入 `out_len`，返回的指针由 `cczuni_free_bytes` 释放。
+///
+/// # Safety
+/// `out_len` 必须是有效的、可写的指针。
+unsafe fn into_raw_bytes(bytes: Vec<u8>, out_len: *mut usize) -> *mut u8 {
+    let bytes = bytes.into_boxed_slice();
+    unsafe { *out_len = bytes.len() };
+    Box::into_raw(bytes) as *mut u8
+}
+
+/// 返回字节的函数出错时：长度写 0，`error_ptr` 不为空时写入错误的 JSON 字符串，返回空指针。
+///
+/// # Safety
+/// `out_len` 必须是有效的、可写的指针，`error_ptr` 必须为空指针或有效的、可写的指针。
+unsafe fn bytes_error(error: &str, out_len: *mut usize, error_ptr: *mut *mut c_char) -> *mut u8 {
+    unsafe { *out_len = 0 };
+    if !error_ptr.is_null() {
+        let json = FfiResult::<()>::error(error).to_json_string();
+        unsafe { *error_ptr = CString::new(json).unwrap().into_raw() };
     }
+    std::ptr::null_mut()
 }
 
 // 3. 客户端管理函数
 
+// 同一客户端指针可以在多个线程中同时使用，客户端的状态都在锁之后。
+const _: fn() = || {
+    fn shared_between_threads<T: Send + Sync>() {}
+    shared_between_threads::<DefaultClient>();
+};
+
 /// 创建一个新的 cczuni 客户端实例。
 ///
+/// 除 `cczuni_client_free` 外，同一客户端的函数可以在多个线程中同时调用；
+/// 同时进行的登录会依次执行，不会混用彼此的 Cookie。
+///
 /// # Arguments
 /// * `user` - C 字符串，用户的学号。
 /// * `password` - C 字符串，用户的密码。
 ///
 /// # Returns
-/// 返回一个指向客户端实例的不透明指针。如果创建失败，返回空指针。
+/// 返回一个指向客户端实例的不透明指针。如果创建失败（如参数为空指针或不是有效的 UTF-8），返回空指针。
 /// **调用者必须在使用完毕后调用 `cczuni_client_free` 来释放内存。**
+///
+/// # Safety
+/// `user` 与 `password` 必须是有效的、以 NUL 结尾的 C 字符串。
 #[no_mangle]
-pub extern "C" fn cczuni_client_new(
+pub unsafe extern "C" fn cczuni_client_new(
     user: *const c_char,
     password: *const c_char,
 ) -> *mut DefaultClient {
-    let user_str = unsafe { CStr::from_ptr(user).to_string_lossy().into_owned() };
-    let password_str = unsafe { CStr::from_ptr(password).to_string_lossy().into_owned() };
+    let Ok(user_str) = (unsafe { read_c_str(user, "user") }) else {
+        return std::ptr::null_mut();
+    };
+    let Ok(password_str) = (unsafe { read_c_str(password, "password") }) else {
+        return std::ptr::null_mut();
+    };
 
     let client = DefaultClient::account(user_str, password_str);
     Box::into_raw(Box::new(client))
 }
 
+/// 用 WebView 等方式登录后得到的 Cookie 创建客户端，无需再次登录即可访问各应用。
+///
+/// # Arguments
+/// * `cookies_json` - C 字符串，Cookie 的 JSON 数组，每项为 `[url, name, value]`，
+///   如 `[["https://zmvpn.cczu.edu.cn/", "wengine_vpn_ticketzmvpn_cczu_edu_cn", "…"]]`。
+///   其中有 WebVPN 的地址时按 WebVPN 登录处理，否则按校内登录处理。
+///
+/// # Returns
+/// 返回一个指向客户端实例的不透明指针。如果参数无效或地址无法解析，返回空指针。
+/// Cookie 在第一次请求前不会被检查，失效时请求会像会话过期一样失败。
+/// **调用者必须在使用完毕后调用 `cczuni_client_free` 来释放内存。**
+///
+/// # Safety
+/// `cookies_json` 必须是有效的、以 NUL 结尾的 C 字符串。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_client_from_cookies(
+    cookies_json: *const c_char,
+) -> *mut DefaultClient {
+    let cookies = unsafe { read_c_document(cookies_json, "cookies_json") }
+        .and_then(|json| serde_json::from_str(json).map_err(|e| e.to_string()));
+    let Ok(cookies) = cookies else {
+        return std::ptr::null_mut();
+    };
+
+    match DefaultClient::from_cookies(cookies) {
+        Ok(client) => Box::into_raw(Box::new(client)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// 创建一个把 Cookie 保存在文件中的客户端：从文件中已有的 Cookie 开始，
+/// 之后每当响应设置 Cookie 时写回文件，下次运行时可以沿用登录状态。
+///
+/// # Arguments
+/// * `user` - C 字符串，用户的学号。
+/// * `password` - C 字符串，用户的密码。
+/// * `cookie_path` - C 字符串，Cookie 文件的路径，文件不存在时会被创建。
+///   文件中包含登录凭据，请放在只有当前用户可读的位置。
+///
+/// # Returns
+/// 返回一个指向客户端实例的不透明指针。如果参数无效，或文件无法读取、内容不是保存的 Cookie，返回空指针。
+/// **调用者必须在使用完毕后调用 `cczuni_client_free` 来释放内存。**
+///
+/// # Safety
+/// `user`、`password` 与 `cookie_path` 必须是有效的、以 NUL 结尾的 C 字符串。
+#[cfg(feature = "cookie-file")]
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_client_new_with_cookie_file(
+    user: *const c_char,
+    password: *const c_char,
+    cookie_path: *const c_char,
+) -> *mut DefaultClient {
+    let Ok(user_str) = (unsafe { read_c_str(user, "user") }) else {
+        return std::ptr::null_mut();
+    };
+    let Ok(password_str) = (unsafe { read_c_str(password, "password") }) else {
+        return std::ptr::null_mut();
+    };
+    let Ok(path_str) = (unsafe { read_c_str(cookie_path, "cookie_path") }) else {
+        return std::ptr::null_mut();
+    };
+
+    match DefaultClient::with_cookie_store(
+        Account::new(user_str, password_str),
+        ClientOptions::default(),
+        FileCookieStore::new(path_str),
+    ) {
+        Ok(client) => Box::into_raw(Box::new(client)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// 导出客户端的 Cookie（不含 SSO 的 `CASTGC` 票据），用密钥加密，
+/// 之后可以用 `cczuni_client_import_session_encrypted` 恢复登录状态。
+///
+/// # Arguments
+/// * `client_ptr` - 客户端指针。
+/// * `key` - C 字符串，32 字节密钥的 Base64 编码。请把密钥保存在系统的钥匙串中，不要与导出内容放在一起。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为加密后的会话（Base64 字符串）。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+/// `key` 必须是有效的、以 NUL 结尾的 C 字符串。
+#[cfg(feature = "session-crypto")]
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_export_session_encrypted(
+    client_ptr: *mut DefaultClient,
+    key: *const c_char,
+) -> *mut c_char {
+    let client = unsafe { &*client_ptr };
+    let key = match unsafe { read_c_str(key, "key") }.and_then(session_key) {
+        Ok(key) => key,
+        Err(e) => {
+            return CString::new(FfiResult::<()>::error(&e).to_client_json(client))
+                .unwrap()
+                .into_raw()
+        }
+    };
+
+    let result_json = match client.export_session_encrypted(&SessionPolicy::default(), &key) {
+        Ok(blob) => FfiResult::success(blob).to_client_json(client),
+        Err(e) => FfiResult::<()>::error(&e.to_string()).to_client_json(client),
+    };
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 用 `cczuni_export_session_encrypted` 导出的会话创建客户端。
+///
+/// # Arguments
+/// * `user` - C 字符串，用户的学号。
+/// * `password` - C 字符串，用户的密码。
+/// * `session` - C 字符串，`cczuni_export_session_encrypted` 返回的 `data`。
+/// * `key` - C 字符串，导出时使用的密钥（Base64）。
+///
+/// # Returns
+/// 返回一个指向客户端实例的不透明指针。如果参数无效、密钥错误或会话被篡改，返回空指针。
+/// **调用者必须在使用完毕后调用 `cczuni_client_free` 来释放内存。**
+///
+/// # Safety
+/// `user`、`password`、`session` 与 `key` 必须是有效的、以 NUL 结尾的 C 字符串。
+#[cfg(feature = "session-crypto")]
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_client_import_session_encrypted(
+    user: *const c_char,
+    password: *const c_char,
+    session: *const c_char,
+    key: *const c_char,
+) -> *mut DefaultClient {
+    let Ok(user_str) = (unsafe { read_c_str(user, "user") }) else {
+        return std::ptr::null_mut();
+    };
+    let Ok(password_str) = (unsafe { read_c_str(password, "password") }) else {
+        return std::ptr::null_mut();
+    };
+    let Ok(session_str) = (unsafe { read_c_document(session, "session") }) else {
+        return std::ptr::null_mut();
+    };
+    let Ok(key) = (unsafe { read_c_str(key, "key") }).and_then(session_key) else {
+        return std::ptr::null_mut();
+    };
+
+    match DefaultClient::import_session_encrypted(
+        Account::new(user_str, password_str),
+        ClientOptions::default(),
+        session_str,
+        &key,
+    ) {
+        Ok(client) => Box::into_raw(Box::new(client)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// 解析 Base64 编码的会话密钥。
+#[cfg(feature = "session-crypto")]
+fn session_key(key: &str) -> Result<[u8; SESSION_KEY_LEN], String> {
+    BASE64_STANDARD
+        .decode(key.trim())
+        .ok()
+        .and_then(|key| key.try_into().ok())
+        .ok_or(format!("`key` must be {SESSION_KEY_LEN} bytes in Base64"))
+}
+
 /// 释放 cczuni 客户端实例占用的内存。
 ///
 /// # Arguments
 /// * `client_ptr` - 通过 `cczuni_client_new` 创建的客户端指针。
+///
+/// # Safety
+/// `client_ptr` 必须为空指针，或由 `cczuni_client_new` 返回且尚未释放，
+/// 且没有其他线程正在使用它。
 #[no_mangle]
-pub extern "C" fn cczuni_client_free(client_ptr: *mut DefaultClient) {
+pub unsafe extern "C" fn cczuni_client_free(client_ptr: *mut DefaultClient) {
     if !client_ptr.is_null() {
         unsafe {
             let _ = Box::from_raw(client_ptr);
@@ -93,6 +490,190 @@ pub extern "C" fn cczuni_client_free(client_ptr: *mut DefaultClient) {
     }
 }
 
+/// 设置客户端所在的校区，影响宿舍楼列表等与校区有关的功能，课表条目中也会带上校区。
+///
+/// # Arguments
+/// * `client_ptr` - 客户端指针。
+/// * `campus` - `0` 为未设置（覆盖所有校区），`1` 为武进校区，`2` 为西太湖校区。
+///
+/// # Returns
+/// `campus` 不是以上取值时返回 `false`，客户端不变。
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_client_set_campus(
+    client_ptr: *mut DefaultClient,
+    campus: i32,
+) -> bool {
+    let client = unsafe { &*client_ptr };
+    let campus = match campus {
+        0 => None,
+        1 => Some(Campus::Wujin),
+        2 => Some(Campus::Xitaihu),
+        _ => return false,
+    };
+    client.set_campus(campus);
+    true
+}
+
+/// 设置该客户端的函数返回的 JSON 的字段命名，便于 Kotlin/Dart 等默认使用 camelCase 的模型直接解析。
+///
+/// # Arguments
+/// * `client_ptr` - 客户端指针。
+/// * `enabled` - 为 `true` 时字段名使用 camelCase（如 `courseCredits`），为 `false` 时恢复默认的
+///   snake_case（如 `course_credits`）。嵌套的成绩、课表等记录同样转换；`raw` 中的原始记录与
+///   取值保持不变。
+///
+/// 启用后，传入该客户端函数的 JSON 参数（如 `cczuni_simulate_gpa` 的课程）也应使用 camelCase。
+/// 不接收客户端的函数不受影响：`cczuni_diff_schedule`、`cczuni_format_grade_notification` 等
+/// 两种命名的参数都接受，`cczuni_diff_schedule` 以传入课表的命名返回；`cczuni_get_services_status`
+/// 等始终返回 snake_case。
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_client_set_camel_case(
+    client_ptr: *mut DefaultClient,
+    enabled: bool,
+) {
+    let client = unsafe { &*client_ptr };
+    client.update_options(|options| options.camel_case = enabled);
+}
+
+/// 设置该客户端课表中 `weekday` 的编号方式，与界面从周日还是周一开始排列一致。
+///
+/// # Arguments
+/// * `client_ptr` - 客户端指针。
+/// * `sunday_first` - 为 `true` 时周日为 1、周一为 2……周六为 7；为 `false` 时恢复默认的
+///   周一为 1……周日为 7。影响 `cczuni_get_schedule` 与 `cczuni_get_schedule_full`。
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_client_set_week_start(
+    client_ptr: *mut DefaultClient,
+    sunday_first: bool,
+) {
+    let client = unsafe { &*client_ptr };
+    let week_start = match sunday_first {
+        true => WeekStart::Sunday,
+        false => WeekStart::Monday,
+    };
+    client.update_options(|options| options.week_start = week_start);
+}
+
+/// 在后台定期检查统一身份认证的登录状态，失效时自动重新登录，避免用户请求时才等待登录。
+///
+/// 每个客户端只会运行一个保活任务：已在运行时直接返回 `true`，间隔保持不变。
+/// 任务在调用 `cczuni_client_keep_alive_stop` 或 `cczuni_client_free` 时停止。
+///
+/// # Arguments
+/// * `client_ptr` - 客户端指针。
+/// * `interval_secs` - 检查间隔，单位为秒，不能为 `0`。
+///
+/// # Returns
+/// 保活任务在运行时返回 `true`，`interval_secs` 为 `0` 时返回 `false`。
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_client_keep_alive_start(
+    client_ptr: *mut DefaultClient,
+    interval_secs: u64,
+) -> bool {
+    let client = unsafe { &*client_ptr };
+    let _runtime = RUNTIME.enter();
+    client
+        .keep_alive(Duration::from_secs(interval_secs))
+        .is_ok()
+}
+
+/// 停止 `cczuni_client_keep_alive_start` 启动的保活任务，没有任务时什么也不做。
+///
+/// # Arguments
+/// * `client_ptr` - 客户端指针。
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_client_keep_alive_stop(client_ptr: *mut DefaultClient) {
+    let client = unsafe { &*client_ptr };
+    client.stop_keep_alive();
+}
+
+/// `cczuni_schedule_watch_start` 的回调。
+///
+/// `diff_json` 为 `{"success": true, "data": ...}`，`data` 同 `cczuni_diff_schedule` 的 `data`，
+/// 只在回调期间有效，**不要释放**；`user_data` 为启动时传入的指针。回调在库的后台线程中调用。
+pub type CczuniScheduleCallback = extern "C" fn(diff_json: *const c_char, user_data: *mut c_void);
+
+/// 由调用方保证 `user_data` 可以在其他线程中使用。
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+
+impl UserData {
+    /// Through a method, so that closures capture the whole `UserData` and not its field.
+    fn ptr(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+/// 在后台定期获取当前学期的课表，与上一次获取的课表比较，有变动（如换教室、调课）时调用 `callback`。
+///
+/// 第一次获取的课表只用于比较，不会触发回调；获取失败时在下一次继续尝试。
+/// 获取比间隔还慢时不会重叠，错过的多次检查合并为一次。
+///
+/// # Arguments
+/// * `client_ptr` - 客户端指针，任务使用它的一个副本登录教务企业微信。
+/// * `interval_secs` - 检查间隔，单位为秒，不能为 `0`。
+/// * `callback` - 课表变动时调用，见 `CczuniScheduleCallback`。
+/// * `user_data` - 原样传给 `callback` 的指针，可以为空。
+///
+/// # Returns
+/// 返回任务指针，`interval_secs` 为 `0` 时返回空指针。
+/// **返回的指针必须使用 `cczuni_schedule_watch_stop` 停止并释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+/// `user_data` 在任务停止前必须一直有效，且可以在其他线程中使用。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_schedule_watch_start(
+    client_ptr: *mut DefaultClient,
+    interval_secs: u64,
+    callback: CczuniScheduleCallback,
+    user_data: *mut c_void,
+) -> *mut ScheduleWatch {
+    let client = unsafe { &*client_ptr };
+    let _runtime = RUNTIME.enter();
+    let user_data = UserData(user_data);
+    // 回调时读取客户端当时的命名设置
+    let naming = client.clone();
+    let watch = client.watch_schedule(Duration::from_secs(interval_secs), move |diff| {
+        let json = CString::new(FfiResult::success(diff).to_client_json(&naming)).unwrap();
+        callback(json.as_ptr(), user_data.ptr());
+    });
+    match watch {
+        Ok(watch) => Box::into_raw(Box::new(watch)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// 停止 `cczuni_schedule_watch_start` 启动的任务并释放它，正在进行的获取会被放弃，不再调用回调。
+///
+/// # Arguments
+/// * `watch_ptr` - 任务指针。
+///
+/// # Safety
+/// `watch_ptr` 必须为空指针，或由 `cczuni_schedule_watch_start` 返回且尚未释放。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_schedule_watch_stop(watch_ptr: *mut ScheduleWatch) {
+    if !watch_ptr.is_null() {
+        drop(unsafe { Box::from_raw(watch_ptr) });
+    }
+}
+
 // 4. 核心功能函数
 
 /// 使用指定的客户端进行统一身份认证登录。
@@ -103,14 +684,57 @@ pub extern "C" fn cczuni_client_free(client_ptr: *mut DefaultClient) {
 /// # Returns
 /// 返回一个 JSON 字符串，包含登录结果。
 /// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
 #[no_mangle]
-pub extern "C" fn cczuni_login(client_ptr: *mut DefaultClient) -> *mut c_char {
+pub unsafe extern "C" fn cczuni_login(client_ptr: *mut DefaultClient) -> *mut c_char {
     let client = unsafe { &*client_ptr };
 
     let result_json = RUNTIME.block_on(async {
+        let _login = client.login.lock().await;
         match client.sso_universal_login().await {
-            Ok(login_info) => FfiResult::success(login_info).to_json_string(),
-            Err(e) => FfiResult::<()>::error(&e.to_string()).to_json_string(),
+            Ok(login_info) => FfiResult::success(login_info).to_client_json(client),
+            Err(e) => FfiResult::<()>::error(&e.to_string()).to_client_json(client),
+        }
+    });
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 提交短信验证码，继续被二次认证打断的登录。
+///
+/// # Arguments
+/// * `client_ptr` - `cczuni_login` 返回二次认证错误的客户端指针。
+/// * `code` - C 字符串，收到的验证码。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，包含提交结果。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+/// `code` 必须是有效的、以 NUL 结尾的 C 字符串。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_submit_second_factor(
+    client_ptr: *mut DefaultClient,
+    code: *const c_char,
+) -> *mut c_char {
+    let client = unsafe { &*client_ptr };
+    let code_str = match unsafe { read_c_str(code, "code") } {
+        Ok(code) => code.to_owned(),
+        Err(e) => {
+            return CString::new(FfiResult::<()>::error(&e).to_client_json(client))
+                .unwrap()
+                .into_raw()
+        }
+    };
+
+    let result_json = RUNTIME.block_on(async {
+        let _login = client.login.lock().await;
+        match client.submit_second_factor(code_str).await {
+            Ok(_) => FfiResult::success(()).to_client_json(client),
+            Err(e) => FfiResult::<()>::error(&e.to_string()).to_client_json(client),
         }
     });
 
@@ -125,103 +749,1305 @@ pub extern "C" fn cczuni_login(client_ptr: *mut DefaultClient) -> *mut c_char {
 /// # Returns
 /// 返回一个包含成绩信息的 JSON 字符串。
 /// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
 #[no_mangle]
-pub extern "C" fn cczuni_get_grades(client_ptr: *mut DefaultClient) -> *mut c_char {
+pub unsafe extern "C" fn cczuni_get_grades(client_ptr: *mut DefaultClient) -> *mut c_char {
     let client = unsafe { &*client_ptr };
 
     let result_json = RUNTIME.block_on(async {
-        // 我们使用 JwqywxApplication 作为示例，因为它返回结构化的数据
-        let app = client.visit::<JwqywxApplication<_>>().await;
-
-        // Jwqywx 需要先执行自己的登录
-        if let Err(e) = app.login().await {
-            return FfiResult::<()>::error(&format!("Failed to login to Jwqywx: {}", e))
-                .to_json_string();
+        match login_and_grades(client).await {
+            Ok(grades) => FfiResult::success(grades).to_client_json(client),
+            Err(e) => FfiResult::<()>::error(&e).to_client_json(client),
         }
+    });
 
-        match app.get_grades().await {
-            Ok(grades_msg) => FfiResult::success(grades_msg.message).to_json_string(),
-            Err(e) => FfiResult::<()>::error(&e.to_string()).to_json_string(),
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 登录教务企业微信，出错时返回错误信息。
+async fn login_jwqywx(client: &DefaultClient) -> Result<JwqywxApplication<DefaultClient>, String> {
+    // 我们使用 JwqywxApplication 作为示例，因为它返回结构化的数据
+    let app = client.visit::<JwqywxApplication<_>>().await;
+
+    // Jwqywx 需要先执行自己的登录
+    app.login()
+        .await
+        .map_err(|e| format!("Failed to login to Jwqywx: {}", e))?;
+    Ok(app)
+}
+
+/// 登录教务企业微信，获取成绩列表，出错时返回错误信息。
+async fn login_and_grades(client: &DefaultClient) -> Result<Vec<CourseGrade>, String> {
+    login_jwqywx(client)
+        .await?
+        .get_grades()
+        .await
+        .map(|grades| grades.message)
+        .map_err(|e| e.to_string())
+}
+
+/// `operation` 整体（登录及之后的所有请求）超过 `timeout_ms` 毫秒时中止，返回超时的错误信息。
+async fn within<T>(
+    timeout_ms: u64,
+    operation: impl Future<Output = Result<T, String>>,
+) -> Result<T, String> {
+    let operation = async { operation.await.map_err(other_error) };
+    with_deadline(Duration::from_millis(timeout_ms), operation)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 同 `cczuni_get_grades`，但整个操作（登录及获取成绩的所有请求）限时 `timeout_ms` 毫秒。
+///
+/// # Arguments
+/// * `client_ptr` - **已登录的**客户端指针。
+/// * `timeout_ms` - 整个操作的时限，毫秒。
+///
+/// # Returns
+/// 与 `cczuni_get_grades` 相同；超过时限时中止尚未完成的请求，`error` 为超时信息。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_get_grades_within(
+    client_ptr: *mut DefaultClient,
+    timeout_ms: u64,
+) -> *mut c_char {
+    let client = unsafe { &*client_ptr };
+
+    let result_json = RUNTIME.block_on(async {
+        match within(timeout_ms, login_and_grades(client)).await {
+            Ok(grades) => FfiResult::success(grades).to_client_json(client),
+            Err(e) => FfiResult::<()>::error(&e).to_client_json(client),
         }
     });
 
     CString::new(result_json).unwrap().into_raw()
 }
 
-/// 获取学生的课表信息。
+/// 获取学生的成绩列表，同时附上教务系统返回的原始记录，供迁移时比对。
 ///
 /// # Arguments
 /// * `client_ptr` - **已登录的**客户端指针。
 ///
 /// # Returns
-/// 返回一个包含课表信息的 JSON 字符串。
+/// 返回一个 JSON 字符串，`data` 为 `{"parsed","raw"}`，`parsed` 与 `cczuni_get_grades`
+/// 的 `data` 相同，`raw` 为原始记录。确认一致后请改用 `cczuni_get_grades`。
+/// 宽松解析跳过的记录在 `warnings` 中说明，没有时省略。
 /// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
 #[no_mangle]
-pub extern "C" fn cczuni_get_schedule(client_ptr: *mut DefaultClient) -> *mut c_char {
+pub unsafe extern "C" fn cczuni_get_grades_full(client_ptr: *mut DefaultClient) -> *mut c_char {
     let client = unsafe { &*client_ptr };
 
     let result_json = RUNTIME.block_on(async {
-        let app = client.visit::<JwqywxApplication<_>>().await;
-
-        if let Err(e) = app.login().await {
-            return FfiResult::<()>::error(&format!("Failed to login to Jwqywx: {}", e))
-                .to_json_string();
-        }
+        let app = match login_jwqywx(client).await {
+            Ok(app) => app,
+            Err(e) => return FfiResult::<()>::error(&e).to_client_json(client),
+        };
 
-        match app.terms().await {
-            Ok(terms) => {
-                if let Some(current_term) = terms.message.first() {
-                    // 导入 TermCalendarParser trait
-                    use crate::extension::calendar::TermCalendarParser;
-                    match app
-                        .get_term_classinfo_week_matrix(current_term.term.clone())
-                        .await
-                    {
-                        Ok(matrix) => FfiResult::success(matrix).to_json_string(),
-                        Err(e) => FfiResult::<()>::error(&e.to_string()).to_json_string(),
-                    }
-                } else {
-                    FfiResult::<()>::error("No terms found").to_json_string()
-                }
-            }
-            Err(e) => FfiResult::<()>::error(&e.to_string()).to_json_string(),
+        match app.get_grades_detailed().await {
+            Ok(grades) => FfiResult::success(grades).to_client_json(client),
+            Err(e) => FfiResult::<()>::error(&e.to_string()).to_client_json(client),
         }
     });
 
     CString::new(result_json).unwrap().into_raw()
 }
 
-/// 获取各个服务的在线状态。
+/// 获取学生的总绩点与专业课绩点（学科基础与专业课程）。
+///
+/// # Arguments
+/// * `client_ptr` - **已登录的**客户端指针。
 ///
 /// # Returns
-/// 返回一个包含服务状态的 JSON 字符串。
+/// 返回一个 JSON 字符串，`data` 为 `{"overall_gpa","major_gpa","credits"}`，
+/// 教务系统未给出课程的学分类别时 `major_gpa` 为 `null`。
 /// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
 #[no_mangle]
-pub extern "C" fn cczuni_get_services_status() -> *mut c_char {
+pub unsafe extern "C" fn cczuni_get_gpa(client_ptr: *mut DefaultClient) -> *mut c_char {
+    let client = unsafe { &*client_ptr };
+
     let result_json = RUNTIME.block_on(async {
-        let status_map = services_status_code().await;
-        // 将 StatusCode 转换为 u16 数字以便序列化
-        let serializable_map: std::collections::HashMap<_, _> = status_map
-            .into_iter()
-            .map(|(k, v)| (k, v.as_u16()))
-            .collect();
-        FfiResult::success(serializable_map).to_json_string()
+        match login_and_grades(client).await {
+            Ok(grades) => {
+                FfiResult::success(GradeAnalytics::new(grades).summary()).to_client_json(client)
+            }
+            Err(e) => FfiResult::<()>::error(&e).to_client_json(client),
+        }
     });
 
     CString::new(result_json).unwrap().into_raw()
 }
 
-// 5. 内存管理
-
-/// 释放由 cczuni 库函数返回的字符串所占用的内存。
+/// 根据成绩估算学业预警：未重修通过的不及格学分与总绩点。
 ///
 /// # Arguments
-/// * `string_ptr` - 指向由其他 `cczuni_` 函数返回的 C 字符串的指针。
-#[no_mangle]
-pub extern "C" fn cczuni_free_string(string_ptr: *mut c_char) {
-    if !string_ptr.is_null() {
+/// * `client_ptr` - **已登录的**客户端指针。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为
+/// `{"level","source","failed_credits","failed_courses","reasons"}`，
+/// `level` 为 `none`/`yellow`/`red`；`source` 为 `computed`，表示结果由本库估算，并非教务系统给出；
+/// `reasons` 每项为 `{"kind": "failed_credits", "credits", "threshold"}` 或
+/// `{"kind": "low_gpa", "gpa", "threshold"}`。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_get_academic_warning(
+    client_ptr: *mut DefaultClient,
+) -> *mut c_char {
+    let client = unsafe { &*client_ptr };
+
+    let result_json = RUNTIME.block_on(async {
+        match login_and_grades(client).await {
+            Ok(grades) => FfiResult::success(GradeAnalytics::new(grades).academic_warning())
+                .to_client_json(client),
+            Err(e) => FfiResult::<()>::error(&e).to_client_json(client),
+        }
+    });
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 按学分类别汇总已获得的学分（重修通过的课程只计一次，未通过的不计）。
+///
+/// # Arguments
+/// * `client_ptr` - **已登录的**客户端指针。
+/// * `requirements_json` - C 字符串，各类别的毕业要求学分，如 `{"major_required": 60, "practical": 20}`，
+///   类别为 `general_required`/`general_elective`/`major_required`/`major_elective`/`practical`/`uncategorized`；
+///   本库不获取培养方案，不需要时传 `{}`。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为 `{"categories","total_earned","has_uncategorized"}`，
+/// `categories` 每项为 `{"category","earned","required","courses"}`。
+/// 教务系统未给出学分类别的课程计入 `uncategorized`，此时 `has_uncategorized` 为 `true`。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+/// `requirements_json` 必须是有效的、以 NUL 结尾的 C 字符串。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_get_credit_summary(
+    client_ptr: *mut DefaultClient,
+    requirements_json: *const c_char,
+) -> *mut c_char {
+    let client = unsafe { &*client_ptr };
+    let requirements = match unsafe { read_c_document(requirements_json, "requirements_json") }
+        .and_then(|json| {
+            parse_json::<BTreeMap<CreditCategory, f32>>(json, client)
+                .map_err(|e| format!("Invalid `requirements_json`: {}", e))
+        }) {
+        Ok(requirements) => requirements,
+        Err(e) => {
+            return CString::new(FfiResult::<()>::error(&e).to_client_json(client))
+                .unwrap()
+                .into_raw()
+        }
+    };
+
+    let result_json = RUNTIME.block_on(async {
+        match login_and_grades(client).await {
+            Ok(grades) => {
+                FfiResult::success(GradeAnalytics::new(grades).credit_summary(&requirements))
+                    .to_client_json(client)
+            }
+            Err(e) => FfiResult::<()>::error(&e).to_client_json(client),
+        }
+    });
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 获取学生每个学期的绩点，以及到该学期为止的累计绩点，按学期先后排列。
+///
+/// # Arguments
+/// * `client_ptr` - **已登录的**客户端指针。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为 `[{"term","term_gpa","cumulative_gpa","credits"}]`。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_get_gpa_trend(client_ptr: *mut DefaultClient) -> *mut c_char {
+    let client = unsafe { &*client_ptr };
+
+    let result_json = RUNTIME.block_on(async {
+        match login_and_grades(client).await {
+            Ok(grades) => {
+                FfiResult::success(GradeAnalytics::new(grades).gpa_trend()).to_client_json(client)
+            }
+            Err(e) => FfiResult::<()>::error(&e).to_client_json(client),
+        }
+    });
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 加上假设的课程及其预期绩点，估算之后的总绩点。
+///
+/// # Arguments
+/// * `client_ptr` - **已登录的**客户端指针。
+/// * `hypotheticals_json` - C 字符串，JSON 数组 `[{"course_id", "credits", "grade_points"}]`，
+///   `course_id` 可省略；填写已修课程的 `course_id` 表示重修，预期绩点将替换原成绩。
+///   `credits` 须在 0 到 50 之间，`grade_points` 须在 0 到 5 之间，否则返回错误。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为 `{"projected_gpa", "projected_credits"}`。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+/// `hypotheticals_json` 必须是有效的、以 NUL 结尾的 C 字符串。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_simulate_gpa(
+    client_ptr: *mut DefaultClient,
+    hypotheticals_json: *const c_char,
+) -> *mut c_char {
+    let client = unsafe { &*client_ptr };
+    let hypotheticals_str =
+        match unsafe { read_c_document(hypotheticals_json, "hypotheticals_json") } {
+            Ok(hypotheticals) => hypotheticals,
+            Err(e) => {
+                return CString::new(FfiResult::<()>::error(&e).to_client_json(client))
+                    .unwrap()
+                    .into_raw()
+            }
+        };
+
+    let result_json = RUNTIME.block_on(async {
+        let hypotheticals: Vec<HypotheticalCourse> = match parse_json(hypotheticals_str, client) {
+            Ok(hypotheticals) => hypotheticals,
+            Err(e) => {
+                return FfiResult::<()>::error(&format!("Invalid hypotheticals: {}", e))
+                    .to_client_json(client)
+            }
+        };
+
+        match login_and_grades(client).await {
+            Ok(grades) => FfiResult::success(GradeAnalytics::new(grades).simulate(&hypotheticals))
+                .to_client_json(client),
+            Err(e) => FfiResult::<()>::error(&e).to_client_json(client),
+        }
+    });
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 获取上次查询之后新出或有变动的成绩，用于轮询成绩通知。
+///
+/// # Arguments
+/// * `client_ptr` - **已登录的**客户端指针。
+/// * `token` - C 字符串，上次返回的 `token`；第一次查询传空字符串，此时所有成绩都是新的。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为 `{"token", "new_grades", "finalized"}`，`finalized` 为之前暂定或未录入、现已确认的成绩，
+/// 调用方需要保存 `token` 供下次使用。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+/// `token` 必须是有效的、以 NUL 结尾的 C 字符串。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_get_new_grades(
+    client_ptr: *mut DefaultClient,
+    token: *const c_char,
+) -> *mut c_char {
+    let client = unsafe { &*client_ptr };
+    let token_str = match unsafe { read_c_document(token, "token") } {
+        Ok(token) => token,
+        Err(e) => {
+            return CString::new(FfiResult::<()>::error(&e).to_client_json(client))
+                .unwrap()
+                .into_raw()
+        }
+    };
+
+    let result_json = RUNTIME.block_on(async {
+        let app = match login_jwqywx(client).await {
+            Ok(app) => app,
+            Err(e) => return FfiResult::<()>::error(&e).to_client_json(client),
+        };
+
+        match app.grades_since(token_str).await {
+            Ok(since) => FfiResult::success(since).to_client_json(client),
+            Err(e) => FfiResult::<()>::error(&e.to_string()).to_client_json(client),
+        }
+    });
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 把新成绩整理成一行通知文字，如 `3 门新成绩：高等数学A1 92、…`，最多列出 3 门课程。
+///
+/// # Arguments
+/// * `grades_json` - C 字符串，成绩的 JSON 数组，如 `cczuni_get_new_grades` 返回的 `new_grades`。
+/// * `locale` - C 字符串，语言标签，`en` 开头为英文，其余为中文。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为通知文字。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `grades_json` 与 `locale` 必须是有效的、以 NUL 结尾的 C 字符串。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_format_grade_notification(
+    grades_json: *const c_char,
+    locale: *const c_char,
+) -> *mut c_char {
+    unsafe { format_notification(grades_json, locale, format_grade_notification) }
+}
+
+/// 把 `cczuni_get_new_grades` 返回的 `finalized` 整理成一行通知文字，如 `成绩已确认：线性代数 85`。
+///
+/// # Arguments
+/// * `grades_json` - C 字符串，成绩的 JSON 数组。
+/// * `locale` - C 字符串，语言标签，`en` 开头为英文，其余为中文。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为通知文字。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `grades_json` 与 `locale` 必须是有效的、以 NUL 结尾的 C 字符串。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_format_finalized_notification(
+    grades_json: *const c_char,
+    locale: *const c_char,
+) -> *mut c_char {
+    unsafe { format_notification(grades_json, locale, format_finalized_notification) }
+}
+
+/// 读取成绩与语言标签，返回 `format` 的通知文字。
+///
+/// # Safety
+/// 同 `cczuni_format_grade_notification`。
+unsafe fn format_notification(
+    grades_json: *const c_char,
+    locale: *const c_char,
+    format: fn(&[CourseGrade], Locale) -> String,
+) -> *mut c_char {
+    let arguments = unsafe { read_c_document(grades_json, "grades_json") }
+        .and_then(|json| {
+            parse_json_either::<Vec<CourseGrade>>(json)
+                .map(|(grades, _)| grades)
+                .map_err(|e| format!("Invalid `grades_json`: {}", e))
+        })
+        .and_then(|grades| Ok((grades, unsafe { read_c_str(locale, "locale") }?)));
+    let (grades, locale) = match arguments {
+        Ok(arguments) => arguments,
+        Err(e) => {
+            return CString::new(FfiResult::<()>::error(&e).to_json_string())
+                .unwrap()
+                .into_raw()
+        }
+    };
+
+    let result_json =
+        FfiResult::success(format(&grades, Locale::from_tag(locale))).to_json_string();
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 登录教务企业微信，获取当前学期，出错时返回错误信息。
+async fn current_term(
+    client: &DefaultClient,
+) -> Result<(JwqywxApplication<DefaultClient>, String), String> {
+    let app = login_jwqywx(client).await?;
+    let current_term = app.current_term().await.map_err(|e| e.to_string())?;
+    Ok((app, current_term.term))
+}
+
+/// 登录教务企业微信，获取当前学期的课表矩阵，出错时返回错误信息。
+async fn current_week_matrix(client: &DefaultClient) -> Result<Vec<Vec<RawCourse>>, String> {
+    let (app, term) = current_term(client).await?;
+    app.get_term_classinfo_week_matrix(term)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取学生的课表信息。
+///
+/// # Arguments
+/// * `client_ptr` - **已登录的**客户端指针。
+///
+/// # Returns
+/// 返回一个包含课表信息的 JSON 字符串，`data` 为课程列表，每项为
+/// `{"course", "teacher", "location", "campus", "weekday", "start_period", "end_period", "start_time", "end_time", "weeks"}`，
+/// `weekday` 从 1（周一）到 7（周日）（见 `cczuni_client_set_week_start`），节次从 1 开始且包含 `end_period`，
+/// `weeks` 为上课的所有周次（已按单双周展开），`campus` 为客户端设置的校区，未设置时为 `null`。
+/// `start_time`/`end_time` 为作息表中的上下课时间（如 `08:00`），两个校区的作息表相同。
+/// 教务系统给出教师代码时另有 `teacher_id`，与 `teacher` 中的教师一一对应。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_get_schedule(client_ptr: *mut DefaultClient) -> *mut c_char {
+    let client = unsafe { &*client_ptr };
+
+    let result_json = RUNTIME.block_on(async {
+        match current_schedule(client).await {
+            Ok(entries) => FfiResult::success(entries).to_client_json(client),
+            Err(e) => FfiResult::<()>::error(&e).to_client_json(client),
+        }
+    });
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 登录教务企业微信，获取当前学期展开后的课表，出错时返回错误信息。
+async fn current_schedule(client: &DefaultClient) -> Result<Vec<ScheduleEntry>, String> {
+    let matrix = current_week_matrix(client).await?;
+    flatten_week_matrix_from(matrix, client.campus(), client.options().week_start)
+        .map_err(|e| e.to_string())
+}
+
+/// 同 `cczuni_get_schedule`，但整个操作（登录、获取学期及课表的所有请求）限时 `timeout_ms` 毫秒。
+///
+/// # Arguments
+/// * `client_ptr` - **已登录的**客户端指针。
+/// * `timeout_ms` - 整个操作的时限，毫秒。
+///
+/// # Returns
+/// 与 `cczuni_get_schedule` 相同；超过时限时中止尚未完成的请求，`error` 为超时信息。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_get_schedule_within(
+    client_ptr: *mut DefaultClient,
+    timeout_ms: u64,
+) -> *mut c_char {
+    let client = unsafe { &*client_ptr };
+
+    let result_json = RUNTIME.block_on(async {
+        match within(timeout_ms, current_schedule(client)).await {
+            Ok(entries) => FfiResult::success(entries).to_client_json(client),
+            Err(e) => FfiResult::<()>::error(&e).to_client_json(client),
+        }
+    });
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 获取学生的课表信息，同时附上教务系统返回的原始记录，供迁移时比对。
+///
+/// # Arguments
+/// * `client_ptr` - **已登录的**客户端指针。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为 `{"parsed","raw"}`，`parsed` 与 `cczuni_get_schedule`
+/// 的 `data` 相同，`raw` 为原始的课表行。确认一致后请改用 `cczuni_get_schedule`。
+/// 宽松解析跳过的记录与未知的列在 `warnings` 中说明，没有时省略。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_get_schedule_full(client_ptr: *mut DefaultClient) -> *mut c_char {
+    let client = unsafe { &*client_ptr };
+
+    let result_json = RUNTIME.block_on(async {
+        let detailed = async {
+            let (app, term) = current_term(client).await?;
+            let matrix = app
+                .get_term_classinfo_week_matrix_detailed(term)
+                .await
+                .map_err(|e| e.to_string())?;
+            let entries = flatten_week_matrix_from(
+                matrix.parsed,
+                client.campus(),
+                client.options().week_start,
+            )
+            .map_err(|e| e.to_string())?;
+            Ok::<_, String>(Detailed {
+                parsed: entries,
+                raw: matrix.raw,
+                warnings: matrix.warnings,
+            })
+        };
+        match detailed.await {
+            Ok(schedule) => FfiResult::success(schedule).to_client_json(client),
+            Err(e) => FfiResult::<()>::error(&e).to_client_json(client),
+        }
+    });
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 获取学生的课表矩阵，即教务系统原始的排列方式。
+///
+/// # Arguments
+/// * `client_ptr` - **已登录的**客户端指针。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为二维数组，行为节次，列为周一到周日，
+/// 每格为 `{"course", "teacher"}`。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_get_schedule_matrix(client_ptr: *mut DefaultClient) -> *mut c_char {
+    let client = unsafe { &*client_ptr };
+
+    let result_json = RUNTIME.block_on(async {
+        match current_week_matrix(client).await {
+            Ok(matrix) => FfiResult::success(matrix).to_client_json(client),
+            Err(e) => FfiResult::<()>::error(&e).to_client_json(client),
+        }
+    });
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 导出当前学期的课表为 iCalendar（`.ics`）文件的内容，可以直接写入文件或交给系统日历导入。
+///
+/// # Arguments
+/// * `client_ptr` - **已登录的**客户端指针。
+/// * `first_week_date` - C 字符串，第一周周一的日期，如 `20250901`。
+/// * `out_len` - 用于写入返回内容的字节数。
+/// * `error_ptr` - 出错时写入错误的 JSON 字符串（需用 `cczuni_free_string` 释放），不需要时传空指针。
+///
+/// # Returns
+/// 返回指向 UTF-8 编码的 iCalendar 内容的指针，共 `*out_len` 字节，**不以 NUL 结尾**。
+/// 出错时返回空指针，`*out_len` 为 0。
+/// **返回的内容归调用方所有，必须使用 `cczuni_free_bytes` 并传入同一 `*out_len` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+/// `first_week_date` 必须是有效的、以 NUL 结尾的 C 字符串，`out_len` 必须是有效的、可写的指针，
+/// `error_ptr` 必须为空指针或有效的、可写的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_get_schedule_ics(
+    client_ptr: *mut DefaultClient,
+    first_week_date: *const c_char,
+    out_len: *mut usize,
+    error_ptr: *mut *mut c_char,
+) -> *mut u8 {
+    let first_week_date = match unsafe { read_c_str(first_week_date, "first_week_date") } {
+        Ok(date) => date,
+        Err(e) => return unsafe { bytes_error(&e, out_len, error_ptr) },
+    };
+    if let Err(e) = NaiveDate::parse_from_str(first_week_date, "%Y%m%d") {
+        let error = format!("`first_week_date` is not a date like 20250901: {}", e);
+        return unsafe { bytes_error(&error, out_len, error_ptr) };
+    }
+    let client = unsafe { &*client_ptr };
+
+    let calendar = RUNTIME.block_on(async {
+        let classlist =
+            parse_week_matrix(current_week_matrix(client).await?).map_err(|e| e.to_string())?;
+        generate_icalendar_feed(
+            classlist,
+            first_week_date.to_string(),
+            &Schedule::default(),
+            None,
+            &mut IcsFeed::default(),
+        )
+        .map_err(|e| e.to_string())
+    });
+
+    match calendar {
+        Ok(calendar) => unsafe { into_raw_bytes(calendar.to_string().into_bytes(), out_len) },
+        Err(e) => unsafe { bytes_error(&e, out_len, error_ptr) },
+    }
+}
+
+/// 导出当前学期的课表为 FullCalendar 的事件列表，供网页端直接使用。
+///
+/// # Arguments
+/// * `client_ptr` - **已登录的**客户端指针。
+/// * `first_week_date` - C 字符串，第一周周一的日期，如 `20250901`。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为事件列表，每项为 `{"title","start","end","rrule","duration","extendedProps"}`：
+/// 每周（或单双周）上课的课程为一个带 `rrule`（rrule 插件格式 `{"freq","interval","dtstart","count"}`）
+/// 与 `duration`（如 `01:25`）的事件，没有 `start`/`end`；只上一周的课程为带 `start`/`end` 的单个事件，
+/// 没有 `rrule`/`duration`。时间均为北京时间（`+08:00`），`extendedProps` 为该课程的
+/// `{"course","teacher","location","campus","weekday","start_period","end_period","start_time","end_time","weeks"}`，`weekday` 从周一开始。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+/// `first_week_date` 必须是有效的、以 NUL 结尾的 C 字符串。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_get_schedule_fullcalendar(
+    client_ptr: *mut DefaultClient,
+    first_week_date: *const c_char,
+) -> *mut c_char {
+    let term_start = unsafe { read_c_str(first_week_date, "first_week_date") }.and_then(|date| {
+        NaiveDate::parse_from_str(date, "%Y%m%d")
+            .map_err(|e| format!("`first_week_date` is not a date like 20250901: {}", e))
+    });
+    let client = unsafe { &*client_ptr };
+
+    let events = RUNTIME.block_on(async {
+        let term_start = term_start?;
+        let classlist =
+            parse_week_matrix(current_week_matrix(client).await?).map_err(|e| e.to_string())?;
+        to_fullcalendar_events(&classlist, term_start, &Schedule::default())
+            .map_err(|e| e.to_string())
+    });
+    let result_json = match events {
+        Ok(events) => FfiResult::success(events).to_client_json(client),
+        Err(e) => FfiResult::<()>::error(&e).to_client_json(client),
+    };
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 比较两次获取的课表，找出新增、删除和变动的课程，用于提醒学生调课、换教室。
+///
+/// 同一课程、同一教师的课出现在新的时间或教室时视为变动，而不是删除后新增。
+///
+/// # Arguments
+/// * `old_json` - C 字符串，之前保存的 `cczuni_get_schedule` 的 `data`。
+/// * `new_json` - C 字符串，新获取的 `cczuni_get_schedule` 的 `data`。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为 `{"added", "removed", "changed"}`，`added`/`removed` 为课程列表，
+/// `changed` 每项为 `{"before", "after", "kinds"}`，`kinds` 为 `location`/`time`/`weeks` 中变动的部分。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `old_json` 与 `new_json` 必须是有效的、以 NUL 结尾的 C 字符串。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_diff_schedule(
+    old_json: *const c_char,
+    new_json: *const c_char,
+) -> *mut c_char {
+    let mut schedules = vec![];
+    let mut camel_case = false;
+    for (ptr, name) in [(old_json, "old_json"), (new_json, "new_json")] {
+        let parsed = unsafe { read_c_document(ptr, name) }.and_then(|json| {
+            parse_json_either::<Vec<ScheduleEntry>>(json)
+                .map_err(|e| format!("Invalid `{}`: {}", name, e))
+        });
+        match parsed {
+            Ok((schedule, camel)) => {
+                schedules.push(schedule);
+                camel_case |= camel;
+            }
+            Err(e) => {
+                return CString::new(FfiResult::<()>::error(&e).to_json_string())
+                    .unwrap()
+                    .into_raw()
+            }
+        }
+    }
+
+    let result_json = FfiResult::success(diff_schedule(&schedules[0], &schedules[1]))
+        .to_json_string_in(camel_case);
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 登录一次教务企业微信，同时获取首页所需的学生信息、绩点、当前周次与学业预警。
+///
+/// # Arguments
+/// * `client_ptr` - **已登录的**客户端指针。
+/// * `first_week_date` - C 字符串，第一周周一的日期，如 `20250901`。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为 `{"profile","gpa","current_week","academic_warning"}`，
+/// 每一项都是 `{"success","data","error"}`，某一项获取失败不影响其他项。
+/// `profile` 的 `data` 为教务系统给出的学生信息（含 `xh`、`xm`、`bj`、`pjxfjd` 等），
+/// `gpa` 同 `cczuni_get_gpa`，`academic_warning` 同 `cczuni_get_academic_warning`，
+/// `current_week` 为 `{"week","estimated"}`，按北京时间计算，与设备时区无关。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，
+/// `first_week_date` 必须是有效的、以 NUL 结尾的 C 字符串。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_get_overview(
+    client_ptr: *mut DefaultClient,
+    first_week_date: *const c_char,
+) -> *mut c_char {
+    let term_start = unsafe { read_c_str(first_week_date, "first_week_date") }.and_then(|date| {
+        NaiveDate::parse_from_str(date, "%Y%m%d")
+            .map_err(|e| format!("`first_week_date` is not a date like 20250901: {}", e))
+    });
+    let client = unsafe { &*client_ptr };
+
+    let result_json = match term_start {
+        Ok(term_start) => {
+            let overview = RUNTIME.block_on(client.overview(term_start));
+            FfiResult::success(FfiOverview {
+                profile: FfiResult::of(overview.profile.as_ref()),
+                gpa: FfiResult::of(overview.gpa.as_ref()),
+                current_week: FfiResult::of(overview.current_week.as_ref()),
+                academic_warning: FfiResult::of(overview.academic_warning.as_ref()),
+            })
+            .to_client_json(client)
+        }
+        Err(e) => FfiResult::<()>::error(&e).to_client_json(client),
+    };
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 登录一次教务企业微信，获取本学期每门课程的上课时间与成绩，用于课程卡片。
+///
+/// # Arguments
+/// * `client_ptr` - **已登录的**客户端指针。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为数组，每项为 `{"course","course_id","teachers","meetings","grade"}`，
+/// 按第一次上课的时间排列。`meetings` 每项同 `cczuni_diff_schedule` 的课表项，
+/// `grade` 为同名课程的成绩（忽略空格与全角字符，重修的课程优先取任课教师相同的那次），尚无成绩时为 `null`。
+/// 课表中没有的课程不会出现。暂不包含评教状态。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_get_course_overview(client_ptr: *mut DefaultClient) -> *mut c_char {
+    let client = unsafe { &*client_ptr };
+
+    let result_json = match RUNTIME.block_on(client.course_overview()) {
+        Ok(courses) => FfiResult::success(courses).to_client_json(client),
+        Err(e) => FfiResult::<()>::error(&e.to_string()).to_client_json(client),
+    };
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 登录一次教务企业微信，导出能获取的全部信息（学生信息、各学期成绩、绩点与本学期课表），用于毕业前备份。
+///
+/// # Arguments
+/// * `client_ptr` - **已登录的**客户端指针。
+/// * `out_len` - 用于写入返回内容的字节数。
+/// * `error_ptr` - 出错时写入错误的 JSON 字符串（需用 `cczuni_free_string` 释放），不需要时传空指针。
+///
+/// # Returns
+/// 返回指向 UTF-8 编码的 JSON 文档的指针，共 `*out_len` 字节，**不以 NUL 结尾**，可以直接写入文件。
+/// 文档为 `{"version","profile","grades","gpa","schedule"}`，`version` 为导出时的库版本，
+/// 其余每项为 `{"data": ...}`，获取失败时为 `{"error": "..."}`，某一项失败不影响其他项。
+/// `profile` 同 `cczuni_get_overview` 的 `profile`，`grades` 同 `cczuni_get_grades`，`gpa` 同 `cczuni_get_gpa`，
+/// `schedule` 同 `cczuni_get_schedule`。字段名始终为 snake_case，不受 `cczuni_client_set_camel_case` 影响。
+/// 暂不包含获奖与体测记录。
+/// 出错时返回空指针，`*out_len` 为 0。
+/// **返回的内容归调用方所有，必须使用 `cczuni_free_bytes` 并传入同一 `*out_len` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针，`out_len` 必须是有效的、可写的指针，
+/// `error_ptr` 必须为空指针或有效的、可写的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_get_archive(
+    client_ptr: *mut DefaultClient,
+    out_len: *mut usize,
+    error_ptr: *mut *mut c_char,
+) -> *mut u8 {
+    let client = unsafe { &*client_ptr };
+
+    match RUNTIME.block_on(client.archive()).to_json() {
+        Ok(archive) => unsafe { into_raw_bytes(archive, out_len) },
+        Err(e) => unsafe { bytes_error(&e.to_string(), out_len, error_ptr) },
+    }
+}
+
+/// 运行自检：依次检查统一身份认证是否可达、能否登录，以及登录后能否获取数据，用于排查问题。
+///
+/// # Arguments
+/// * `client_ptr` - 客户端指针。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为 `{"steps", "failed_step"}`，
+/// `steps` 每项为 `{"name", "ok", "elapsed_ms", "kind", "error"}`，在第一个失败的步骤处停止，
+/// `kind` 为 `network`/`auth`/`second_factor`/`password_change`/`not_activated`/`rate_limited`/`parse`。报告中不会出现密码，自检不会修改任何数据。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+///
+/// # Safety
+/// `client_ptr` 必须是由 `cczuni_client_new` 返回且尚未释放的指针。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_selftest(client_ptr: *mut DefaultClient) -> *mut c_char {
+    let client = unsafe { &*client_ptr };
+
+    let result_json = RUNTIME
+        .block_on(async { FfiResult::success(client.selftest().await).to_client_json(client) });
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 获取各个服务的在线状态。
+///
+/// # Returns
+/// 返回一个包含服务状态的 JSON 字符串，每个服务为 `{"health": "up", "code": 200}`，
+/// `health` 为 `up`/`degraded`/`auth_redirect`/`down`/`timeout`/`maintenance`/`captive_portal`/`unknown`，
+/// `maintenance` 表示服务返回了维护公告页面，此时 `message` 为公告内容，
+/// `captive_portal` 表示当前网络需要先进行上网认证（被重定向到了认证页面），
+/// 服务有响应时还带有 `latency_ms`（本次响应时间，毫秒）和 `avg_latency_ms`（多次查询的滑动平均），
+/// 服务无响应时 `code` 为 `null`、没有 `latency_ms`，`avg_latency_ms` 为之前的平均值（如果有）。
+/// 每个服务最多等待 3 秒，超时为 `timeout`；可以用 `cczuni_cancel` 中止。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+#[no_mangle]
+pub extern "C" fn cczuni_get_services_status() -> *mut c_char {
+    let cancel = status_cancel();
+    let result_json = RUNTIME.block_on(async {
+        match services_status_with_latency_until(&LATENCY, PROBE_TIMEOUT, &cancel).await {
+            Ok(status) => FfiResult::success(status).to_json_string(),
+            Err(e) => FfiResult::<()>::error(&e.to_string()).to_json_string(),
+        }
+    });
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 获取各个服务的在线状态，以列表形式返回，带有服务的名称与地址，无需另外维护服务名称表。
+///
+/// # Returns
+/// 返回一个 JSON 字符串，`data` 为数组，每项为
+/// `{"id", "display_name", "url", "health", "checked_at", "latency_ms"}`，
+/// `id` 为 `sso`/`wechat`/`webvpn`，`display_name` 为服务的中文名称，`health` 同 `cczuni_get_services_status`，
+/// `checked_at` 为查询时间（Unix 秒），服务无响应时 `latency_ms` 为 `null`。
+/// 每个服务最多等待 3 秒，可以用 `cczuni_cancel` 中止。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+#[no_mangle]
+pub extern "C" fn cczuni_get_services_status_list() -> *mut c_char {
+    let cancel = status_cancel();
+    let result_json = RUNTIME.block_on(async {
+        match services_status_until(&LATENCY, PROBE_TIMEOUT, &cancel).await {
+            Ok(status) => FfiResult::success(status).to_json_string(),
+            Err(e) => FfiResult::<()>::error(&e.to_string()).to_json_string(),
+        }
+    });
+
+    CString::new(result_json).unwrap().into_raw()
+}
+
+/// 本次状态查询的中止令牌，上次已被中止时换一个新的。
+fn status_cancel() -> CancelToken {
+    let mut cancel = STATUS_CANCEL.lock().unwrap();
+    if cancel.is_cancelled() {
+        *cancel = CancelToken::default();
+    }
+    cancel.clone()
+}
+
+/// 中止正在进行的 `cczuni_get_services_status` 或 `cczuni_get_services_status_list`，可以从其他线程调用。
+///
+/// 被中止的调用立即返回错误 `Status sweep cancelled`，之后的调用不受影响。
+#[no_mangle]
+pub extern "C" fn cczuni_cancel() {
+    STATUS_CANCEL.lock().unwrap().cancel();
+}
+
+/// 获取 cczuni 库的版本号。
+///
+/// # Returns
+/// 返回版本号字符串，如 `0.1.0`。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+#[no_mangle]
+pub extern "C" fn cczuni_version() -> *mut c_char {
+    CString::new(VERSION).unwrap().into_raw()
+}
+
+/// 获取编译时启用的可选功能，用于在界面上只显示可用的功能。
+///
+/// # Returns
+/// 返回一个 JSON 字符串数组，如 `["rustls-tls","webvpn-login","calendar"]`，
+/// 为 `Cargo.toml` 中启用的 feature（`full` 与 `default` 以其包含的 feature 列出）。
+/// **返回的字符串必须使用 `cczuni_free_string` 进行释放。**
+#[no_mangle]
+pub extern "C" fn cczuni_features() -> *mut c_char {
+    CString::new(serde_json::to_string(FEATURES).unwrap())
+        .unwrap()
+        .into_raw()
+}
+
+// 5. 内存管理
+
+/// 释放由 cczuni 库函数返回的字符串所占用的内存。
+///
+/// # Arguments
+/// * `string_ptr` - 指向由其他 `cczuni_` 函数返回的 C 字符串的指针。
+///
+/// # Safety
+/// `string_ptr` 必须为空指针，或由 `cczuni_` 函数返回且尚未释放。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_free_string(string_ptr: *mut c_char) {
+    if !string_ptr.is_null() {
         unsafe {
             let _ = CString::from_raw(string_ptr);
         }
     }
 }
+
+/// 释放由 cczuni 库函数返回的字节（如 `cczuni_get_schedule_ics` 的内容）所占用的内存。
+///
+/// # Arguments
+/// * `bytes_ptr` - 指向由其他 `cczuni_` 函数返回的字节的指针。
+/// * `len` - 该函数写入 `out_len` 的字节数。
+///
+/// # Safety
+/// `bytes_ptr` 必须为空指针，或由 `cczuni_` 函数返回且尚未释放，`len` 必须与返回时的长度一致。
+#[no_mangle]
+pub unsafe extern "C" fn cczuni_free_bytes(bytes_ptr: *mut u8, len: usize) {
+    if !bytes_ptr.is_null() {
+        unsafe {
+            let _ = Box::from_raw(std::ptr::slice_from_raw_parts_mut(bytes_ptr, len));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::{CStr, CString};
+
+    use libc::{c_char, c_void};
+
+    use super::{
+        camel_to_snake, cczuni_client_free, cczuni_client_keep_alive_start,
+        cczuni_client_keep_alive_stop, cczuni_client_new, cczuni_client_set_camel_case,
+        cczuni_client_set_campus, cczuni_client_set_week_start, cczuni_diff_schedule,
+        cczuni_format_grade_notification, cczuni_free_bytes, cczuni_free_string, cczuni_get_grades,
+        cczuni_get_schedule_ics, cczuni_login, cczuni_schedule_watch_start,
+        cczuni_schedule_watch_stop, cczuni_simulate_gpa, into_raw_bytes, parse_json, rename_keys,
+        snake_to_camel, FfiResult, CCZUNI_MAX_FIELD_LEN, RUNTIME,
+    };
+    use crate::{
+        base::client::{Account, Campus, Client, ClientOptions},
+        extension::calendar::{ScheduleEntry, WeekStart},
+        impls::client::DefaultClient,
+        internals::fields::{ROOT_SSO_LOGIN, ROOT_YWTB, WECHAT_APP_API},
+        utils::testing::{TestResponse, TestServer},
+    };
+
+    const INVALID_UTF8: &[u8] = b"pass\xffword\0";
+
+    /// 读取返回的 JSON 并释放字符串
+    unsafe fn take_json(ptr: *mut c_char) -> serde_json::Value {
+        let value = serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { cczuni_free_string(ptr) };
+        value
+    }
+
+    #[test]
+    fn client_new_rejects_invalid_utf8() {
+        let user = CString::new("2300000101").unwrap();
+        let password = CStr::from_bytes_with_nul(INVALID_UTF8).unwrap();
+        unsafe {
+            assert!(cczuni_client_new(user.as_ptr(), password.as_ptr()).is_null());
+            assert!(cczuni_client_new(user.as_ptr(), std::ptr::null()).is_null());
+
+            let password = CString::new("密码").unwrap();
+            let client = cczuni_client_new(user.as_ptr(), password.as_ptr());
+            assert!(!client.is_null());
+            cczuni_client_free(client);
+        }
+    }
+
+    #[test]
+    fn string_arguments_reject_invalid_utf8() {
+        let user = CString::new("2300000101").unwrap();
+        let password = CString::new("password").unwrap();
+        let hypotheticals = CStr::from_bytes_with_nul(INVALID_UTF8).unwrap();
+        unsafe {
+            let client = cczuni_client_new(user.as_ptr(), password.as_ptr());
+            let result = take_json(cczuni_simulate_gpa(client, hypotheticals.as_ptr()));
+            assert_eq!(result["success"], false);
+            assert!(result["error"]
+                .as_str()
+                .unwrap()
+                .contains("not valid UTF-8"));
+            cczuni_client_free(client);
+        }
+    }
+
+    #[test]
+    fn set_campus() {
+        let user = CString::new("2300000101").unwrap();
+        let password = CString::new("password").unwrap();
+        unsafe {
+            let client = cczuni_client_new(user.as_ptr(), password.as_ptr());
+            assert!(cczuni_client_set_campus(client, 2));
+            assert_eq!((*client).campus(), Some(Campus::Xitaihu));
+            assert!(!cczuni_client_set_campus(client, 3));
+            assert_eq!((*client).campus(), Some(Campus::Xitaihu));
+            assert!(cczuni_client_set_campus(client, 0));
+            assert_eq!((*client).campus(), None);
+            cczuni_client_free(client);
+        }
+    }
+
+    #[test]
+    fn week_start_per_client() {
+        let user = CString::new("2300000101").unwrap();
+        let password = CString::new("password").unwrap();
+        unsafe {
+            let sunday = cczuni_client_new(user.as_ptr(), password.as_ptr());
+            let monday = cczuni_client_new(user.as_ptr(), password.as_ptr());
+            cczuni_client_set_week_start(sunday, true);
+            assert_eq!((*sunday).options().week_start, WeekStart::Sunday);
+            assert_eq!((*monday).options().week_start, WeekStart::Monday);
+            cczuni_client_set_week_start(sunday, false);
+            assert_eq!((*sunday).options().week_start, WeekStart::Monday);
+            cczuni_client_free(sunday);
+            cczuni_client_free(monday);
+        }
+    }
+
+    #[test]
+    fn bytes_keep_nul() {
+        let mut len = 0;
+        unsafe {
+            let bytes = into_raw_bytes(b"%PDF\0\x01\0end".to_vec(), &mut len);
+            assert_eq!(len, 10);
+            assert_eq!(std::slice::from_raw_parts(bytes, len), b"%PDF\0\x01\0end");
+            cczuni_free_bytes(bytes, len);
+            cczuni_free_bytes(std::ptr::null_mut(), 0);
+        }
+
+        let user = CString::new("2300000101").unwrap();
+        let password = CString::new("password").unwrap();
+        let date = CString::new("2025-09-01").unwrap();
+        unsafe {
+            let client = cczuni_client_new(user.as_ptr(), password.as_ptr());
+            let mut error: *mut c_char = std::ptr::null_mut();
+            len = 7;
+            let ics = cczuni_get_schedule_ics(client, date.as_ptr(), &mut len, &mut error);
+            assert!(ics.is_null());
+            assert_eq!(len, 0);
+            let json = CStr::from_ptr(error).to_str().unwrap();
+            assert!(json.contains("not a date like 20250901"), "{json}");
+            cczuni_free_string(error);
+
+            // Without `error_ptr` the error is only the null pointer
+            let ics =
+                cczuni_get_schedule_ics(client, std::ptr::null(), &mut len, std::ptr::null_mut());
+            assert!(ics.is_null());
+            cczuni_client_free(client);
+        }
+    }
+
+    #[test]
+    fn one_client_many_threads() {
+        let user = CString::new("2300000101").unwrap();
+        let password = CString::new("password").unwrap();
+        let client = unsafe { cczuni_client_new(user.as_ptr(), password.as_ptr()) };
+        // Raw pointers aren't `Send`, the host hands the same address to its threads
+        let address = client as usize;
+
+        let threads: Vec<_> = (0..8)
+            .map(|thread| {
+                std::thread::spawn(move || {
+                    let client = address as *mut DefaultClient;
+                    for round in 0..200 {
+                        unsafe {
+                            assert!(cczuni_client_set_campus(client, (thread + round) % 3));
+                            let _ = (*client).campus();
+                            if round % 50 == 0 {
+                                assert!(cczuni_client_keep_alive_start(client, 3600));
+                                cczuni_client_keep_alive_stop(client);
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        unsafe { cczuni_client_free(client) };
+    }
+
+    #[test]
+    fn concurrent_logins_and_grades() {
+        // The client reaches the real hosts through the server, as its HTTP proxy
+        let server = RUNTIME.block_on(TestServer::start());
+        server
+            .route("GET", ROOT_SSO_LOGIN, TestResponse::html("<html></html>"))
+            .route(
+                "GET",
+                &format!("{ROOT_SSO_LOGIN}?service="),
+                TestResponse::redirect(format!("{ROOT_YWTB}/pc/index.html")),
+            )
+            .route(
+                "GET",
+                &format!("{ROOT_YWTB}/pc/index.html"),
+                TestResponse::html("<html></html>"),
+            )
+            .route(
+                "POST",
+                &format!("{WECHAT_APP_API}/api/login"),
+                TestResponse::json(include_str!("impls/apps/wechat/fixtures/login_bearer.json")),
+            )
+            .route(
+                "POST",
+                &format!("{WECHAT_APP_API}/api/cj_xh"),
+                TestResponse::json(include_str!("impls/apps/wechat/fixtures/cj_xh_detail.json")),
+            );
+        let options = ClientOptions {
+            proxy: Some(reqwest::Proxy::http(server.root()).unwrap()),
+            ..Default::default()
+        };
+        let client = Box::into_raw(Box::new(DefaultClient::with_options(
+            Account::new("2300000101", "hunter2"),
+            options,
+        )));
+        let address = client as usize;
+
+        let threads: Vec<_> = (0..8)
+            .map(|thread| {
+                std::thread::spawn(move || {
+                    let client = address as *mut DefaultClient;
+                    for round in 0..10 {
+                        unsafe {
+                            if (thread + round) % 2 == 0 {
+                                let login = take_json(cczuni_login(client));
+                                assert_eq!(login["success"], true, "{login}");
+                            } else {
+                                let grades = take_json(cczuni_get_grades(client));
+                                assert_eq!(grades["success"], true, "{grades}");
+                                assert_eq!(grades["data"].as_array().unwrap().len(), 3);
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        unsafe { cczuni_client_free(client) };
+
+        assert_eq!(server.requests_to(ROOT_SSO_LOGIN).len(), 80);
+        assert_eq!(
+            server
+                .requests_to(&format!("{WECHAT_APP_API}/api/cj_xh"))
+                .len(),
+            40
+        );
+    }
+
+    extern "C" fn ignore_diff(_diff_json: *const c_char, _user_data: *mut c_void) {}
+
+    #[test]
+    fn over_length_inputs() {
+        let long = CString::new("2".repeat(CCZUNI_MAX_FIELD_LEN + 1)).unwrap();
+        let password = CString::new("hunter2").unwrap();
+        unsafe {
+            assert!(cczuni_client_new(long.as_ptr(), password.as_ptr()).is_null());
+
+            let grades = CString::new("[]").unwrap();
+            let result = take_json(cczuni_format_grade_notification(
+                grades.as_ptr(),
+                long.as_ptr(),
+            ));
+            assert_eq!(result["success"], false);
+            assert_eq!(
+                result["error"],
+                format!("`locale` is longer than {CCZUNI_MAX_FIELD_LEN} bytes")
+            );
+
+            let exact = CString::new("2".repeat(CCZUNI_MAX_FIELD_LEN)).unwrap();
+            let client = cczuni_client_new(exact.as_ptr(), password.as_ptr());
+            assert!(!client.is_null());
+            cczuni_client_free(client);
+        }
+    }
+
+    #[test]
+    fn schedule_watch_needs_interval() {
+        let client = Box::into_raw(Box::new(DefaultClient::default()));
+        unsafe {
+            let watch = cczuni_schedule_watch_start(client, 0, ignore_diff, std::ptr::null_mut());
+            assert!(watch.is_null());
+            cczuni_schedule_watch_stop(watch);
+            cczuni_client_free(client);
+        }
+    }
+
+    #[test]
+    fn camel_case_keys() {
+        let entry = ScheduleEntry {
+            course: "高等数学A1".into(),
+            teacher: "李四".into(),
+            teacher_id: None,
+            location: "W201".into(),
+            campus: None,
+            weekday: 1,
+            start_period: 1,
+            end_period: 2,
+            start_time: None,
+            end_time: None,
+            weeks: vec![1, 2],
+        };
+        let result = serde_json::to_value(FfiResult::success(vec![entry.clone()])).unwrap();
+        let camel = rename_keys(result, snake_to_camel);
+        assert_eq!(camel["data"][0]["startPeriod"], 1);
+        assert_eq!(camel["data"][0]["endPeriod"], 2);
+        assert!(camel["data"][0].get("start_period").is_none());
+
+        let back: Vec<ScheduleEntry> =
+            serde_json::from_value(rename_keys(camel["data"].clone(), camel_to_snake)).unwrap();
+        assert_eq!(back, [entry]);
+
+        // The records of the portal stay as they came
+        let raw = rename_keys(serde_json::json!({"raw": [{"k_c": 1}]}), snake_to_camel);
+        assert_eq!(raw["raw"][0]["k_c"], 1);
+    }
+
+    #[test]
+    fn camel_case_per_client() {
+        let user = CString::new("2300000101").unwrap();
+        let password = CString::new("password").unwrap();
+        let entry = ScheduleEntry {
+            course: "高等数学A1".into(),
+            teacher: "李四".into(),
+            teacher_id: None,
+            location: "W201".into(),
+            campus: None,
+            weekday: 1,
+            start_period: 1,
+            end_period: 2,
+            start_time: None,
+            end_time: None,
+            weeks: vec![1, 2],
+        };
+        unsafe {
+            let camel = cczuni_client_new(user.as_ptr(), password.as_ptr());
+            let snake = cczuni_client_new(user.as_ptr(), password.as_ptr());
+            cczuni_client_set_camel_case(camel, true);
+
+            let result = FfiResult::success(vec![entry.clone()]);
+            let answered: serde_json::Value =
+                serde_json::from_str(&result.to_client_json(&*camel)).unwrap();
+            assert_eq!(answered["data"][0]["startPeriod"], 1);
+            let answered: serde_json::Value =
+                serde_json::from_str(&result.to_client_json(&*snake)).unwrap();
+            assert_eq!(answered["data"][0]["start_period"], 1);
+
+            let json = serde_json::to_string(&answered["data"]).unwrap();
+            assert!(parse_json::<Vec<ScheduleEntry>>(&json, &*snake).is_ok());
+            cczuni_client_set_camel_case(camel, false);
+            assert!(parse_json::<Vec<ScheduleEntry>>(&json, &*camel).is_ok());
+            cczuni_client_free(camel);
+            cczuni_client_free(snake);
+        }
+
+        // Without a client, the diff answers in the naming of the schedules given
+        let camel = rename_keys(serde_json::to_value([&entry]).unwrap(), snake_to_camel);
+        let old = CString::new("[]").unwrap();
+        let new = CString::new(camel.to_string()).unwrap();
+        let diff = unsafe { take_json(cczuni_diff_schedule(old.as_ptr(), new.as_ptr())) };
+        assert_eq!(diff["data"]["added"][0]["startPeriod"], 1);
+        let new = CString::new(serde_json::to_string(&[&entry]).unwrap()).unwrap();
+        let diff = unsafe { take_json(cczuni_diff_schedule(old.as_ptr(), new.as_ptr())) };
+        assert_eq!(diff["data"]["added"][0]["start_period"], 1);
+    }
+}
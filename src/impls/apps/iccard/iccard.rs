@@ -9,10 +9,13 @@ use crate::{
         typing::{other_error, TorErr},
     },
     impls::apps::iccard::{
-        iccard_constants::PRESET_DORMBUILDINGS,
+        iccard_constants::{preset_areas, PRESET_DORMBUILDINGS},
         iccard_type::{DormArea, DormBuilding, DormBuildingsData, DormRoomElectricityBillData},
     },
-    internals::fields::DEFAULT_HEADERS,
+    internals::{
+        fields::DEFAULT_HEADERS,
+        response::{read_json, MeasuredSend},
+    },
 };
 
 pub struct ICCardApplication<C, S> {
@@ -68,10 +71,10 @@ impl<C: Client + Clone, S: Display> ICCardApplication<C, S> {
                     "roomid": room.into()
                 }).to_string(),
             }))
-            .send()
+            .send_measured(&self.client)
             .await
             .map_err(other_error)?;
-        Ok(response.json().await.map_err(other_error)?)
+        read_json(response, self.client.options().max_response_size).await
     }
 
     pub async fn list_buildings(
@@ -96,10 +99,10 @@ impl<C: Client + Clone, S: Display> ICCardApplication<C, S> {
                 "area": area.to_string(),
                 "account": self.client.account().user,
             }))
-            .send()
+            .send_measured(&self.client)
             .await
             .map_err(other_error)?;
-        Ok(response.json().await.map_err(other_error)?)
+        read_json(response, self.client.options().max_response_size).await
     }
 
     pub async fn list_all_preset_buildings(&self) -> TorErr<Vec<DormBuildingsData>> {
@@ -118,6 +121,17 @@ impl<C: Client + Clone, S: Display> ICCardApplication<C, S> {
     }
 }
 
+impl<C: Client + Clone, S: Display> ICCardApplication<C, S> {
+    /// Like [`Self::list_all_preset_buildings`], only the areas of the client's campus.
+    pub async fn list_campus_buildings(&self) -> TorErr<Vec<DormBuildingsData>> {
+        let mut results = Vec::new();
+        for area in preset_areas(self.client.campus()) {
+            results.push(self.list_buildings(area).await?);
+        }
+        Ok(results)
+    }
+}
+
 #[cfg(test)]
 mod ptest {
     use crate::{
@@ -134,4 +148,79 @@ mod ptest {
             println!("{:?}", app.list_all_preset_buildings().await.unwrap());
         });
     }
+
+    #[test]
+    fn campus_areas() {
+        use crate::{
+            base::client::{Campus, Client},
+            impls::apps::iccard::iccard_constants::preset_areas,
+        };
+
+        let ids = |client: DefaultClient| -> Vec<&str> {
+            preset_areas(client.campus())
+                .into_iter()
+                .map(|area| area.id)
+                .collect()
+        };
+        assert_eq!(ids(DefaultClient::iccard("1")).len(), 3);
+        assert_eq!(
+            ids(DefaultClient::iccard("1").with_campus(Campus::Wujin)),
+            ["0030000000002502"]
+        );
+        assert_eq!(
+            ids(DefaultClient::iccard("1").with_campus(Campus::Xitaihu)),
+            ["0030000000002501", "0030000000002503"]
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        impls::{
+            apps::iccard::{iccard::ICCardApplication, iccard_constants::PRESET_DORMBUILDINGS},
+            client::DefaultClient,
+        },
+        utils::testing::{TestResponse, TestServer},
+    };
+
+    const PATH: &str = "/wechat/callinterface/queryElecBuilding.html";
+
+    fn answer(buildings: &str) -> String {
+        format!(
+            r#"{{"area":{{"area":"西太湖校区","areaname":"西太湖校区"}},"errmsg":"","buildingtab":{buildings},"aid":"0030000000002501","account":"1","retcode":"0"}}"#
+        )
+    }
+
+    #[tokio::test]
+    async fn no_buildings() {
+        let server = TestServer::start().await;
+        let app = ICCardApplication {
+            client: DefaultClient::iccard("1"),
+            root: server.root().to_string(),
+        };
+        let area = || PRESET_DORMBUILDINGS[0].clone();
+
+        for buildings in ["[]", "null"] {
+            server.route("POST", PATH, TestResponse::json(answer(buildings)));
+            let data = app.list_buildings(area()).await.unwrap();
+            assert!(data.buildingtab.is_empty());
+        }
+        server.route(
+            "POST",
+            PATH,
+            TestResponse::json(answer(r#"[{"building":"1号楼","buildingid":"1"}]"#)),
+        );
+        assert_eq!(
+            app.list_buildings(area()).await.unwrap().buildingtab.len(),
+            1
+        );
+
+        server.route(
+            "POST",
+            PATH,
+            TestResponse::new(502, "text/html", "<html>Bad Gateway</html>"),
+        );
+        assert!(app.list_buildings(area()).await.is_err());
+    }
 }
@@ -1,4 +1,4 @@
-use crate::impls::apps::iccard::iccard_type::DormArea;
+use crate::{base::client::Campus, impls::apps::iccard::iccard_type::DormArea};
 
 // {"area": "西太湖校区", "areaname": "西太湖校区", "aid": "0030000000002501"},
 // {"area": "武进校区", "areaname": "武进校区", "aid": "0030000000002502"},
@@ -21,3 +21,11 @@ pub const PRESET_DORMBUILDINGS: [DormArea<&'static str>; 3] = [
         id: "0030000000002503",
     },
 ];
+
+/// The presets of `campus`, all of them when it is unknown.
+pub fn preset_areas(campus: Option<Campus>) -> Vec<DormArea<&'static str>> {
+    PRESET_DORMBUILDINGS
+        .into_iter()
+        .filter(|area| campus.is_none_or(|campus| area.name.starts_with(campus.name())))
+        .collect()
+}
@@ -1,16 +1,26 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 
+use crate::utils::amount::Money;
+
 #[derive(Debug, Clone)]
 pub struct DormArea<S: Into<String>> {
     pub name: S,
     pub id: S,
 }
 
+/// The portal sends `null` for a list it has nothing in.
+fn null_as_empty<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+    deserializer: D,
+) -> Result<Vec<T>, D::Error> {
+    Ok(Option::deserialize(deserializer)?.unwrap_or_default())
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct DormBuildingsData {
     pub area: DormBuildingsArea,
     pub errmsg: String,
+    #[serde(default, deserialize_with = "null_as_empty")]
     pub buildingtab: Vec<DormBuilding>,
     pub aid: String,
     pub account: String,
@@ -38,6 +48,7 @@ pub struct DormRoomElectricityBillData {
     pub room: DormRoom,
     pub pkgflag: String,
     pub price: String,
+    #[serde(default, deserialize_with = "null_as_empty")]
     pub pkgtab: Vec<Value>,
     pub floor: DormFloor,
     pub aid: String,
@@ -45,6 +56,13 @@ pub struct DormRoomElectricityBillData {
     pub retcode: String,
 }
 
+impl DormRoomElectricityBillData {
+    /// `bal`, the money left for the electricity of the room, `None` when it is not an amount.
+    pub fn balance(&self) -> Option<Money> {
+        Money::parse(&self.bal)
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct DormRoom {
     pub roomid: String,
@@ -1,3 +1,9 @@
+//! A method returning a list gives an empty one when the student has nothing there, no
+//! grades yet or no building in the area: the portal answered, with nothing. It is an error
+//! only when it could not be known, the request failed or the answer was not the page or the
+//! records asked for, a refusal or a login page among others. A caller never has to guess
+//! whether an empty list means "none" or "could not tell".
+
 /// ICCard just available in campus network
 pub mod iccard;
 /// SSO App should login in client via `sso_login`/`webvpn_login`
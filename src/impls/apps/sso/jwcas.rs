@@ -2,12 +2,15 @@ use std::fmt::Display;
 
 use reqwest::StatusCode;
 use scraper::{ElementRef, Html, Selector};
+use tracing::debug;
 
 use crate::base::app::Application;
 use crate::base::client::Client;
 use crate::base::typing::{other_error, EmptyOrErr, TorErr};
+use crate::impls::apps::wechat::jwqywx::JwqywxApplication;
 use crate::impls::services::sso_redirect::SSORedirect;
 use crate::internals::recursion::recursion_redirect_handle;
+use crate::internals::response::{read_app_bytes, MeasuredSend};
 
 use super::jwcas_type::GradeData;
 
@@ -15,6 +18,12 @@ use super::jwcas_type::GradeData;
 pub struct JwcasApplication<C> {
     pub client: C,
     pub root: String,
+    /// Get the grades from the JSON of [`Self::api`] first, the page of [`Self::root`] only
+    /// when it fails. Off by default.
+    pub prefer_api: bool,
+    /// Logged in on its first use by [`Self::get_api_gradeinfo_vec`], with the account of the
+    /// client.
+    pub api: JwqywxApplication<C>,
 }
 
 impl<C: Client + Clone> Application<C> for JwcasApplication<C> {
@@ -22,6 +31,8 @@ impl<C: Client + Clone> Application<C> for JwcasApplication<C> {
         Self {
             client: client.clone(),
             root: client.sso_redirect("http://219.230.159.132").await,
+            prefer_api: false,
+            api: JwqywxApplication::from_client(client).await,
         }
     }
 }
@@ -54,24 +65,70 @@ impl<C: Client + Clone + Send> JwcasApplication<C> {
     pub async fn get_html(&self, service: impl Display) -> TorErr<String> {
         let api = format!("{}{}", self.root, service);
 
-        if let Ok(response) = self.client.reqwest_client().get(api).send().await {
-            if response.status() == StatusCode::OK {
-                return Ok(response.text().await.unwrap());
+        if let Ok(response) = self
+            .client
+            .reqwest_client()
+            .get(api)
+            .send_measured(&self.client)
+            .await
+        {
+            let status = response.status();
+            let body = read_app_bytes(response, self.client.options().max_response_size).await?;
+            if status == StatusCode::OK {
+                return Ok(String::from_utf8_lossy(&body).into_owned());
             }
         }
 
         Err(other_error(format!("Get {service} failed")))
     }
 
+    /// [`Self::get_api_gradeinfo_vec`] with [`Self::prefer_api`], falling back to the page.
+    ///
+    /// Parsed by [`super::jwcas_stream::parse_gradelist`] with the `streaming-parse` feature,
+    /// by [`parse_gradelist`] otherwise.
     pub async fn get_gradeinfo_vec(&self) -> TorErr<Vec<GradeData>> {
+        if self.prefer_api {
+            match self.get_api_gradeinfo_vec().await {
+                Ok(grades) => return Ok(grades),
+                Err(error) => debug!("grades api failed, scraping the page: {error}"),
+            }
+        }
+
         let text = self.get_gradelist_html().await?;
-        let tb_up = Selector::parse(r#"table[id="GVkbk"]"#).unwrap();
-        let selector = Selector::parse(r#"tr[class="dg1-item"]"#).unwrap();
-        let dom = Html::parse_document(&text);
-        Ok(dom
-            .select(&tb_up)
-            .next()
-            .unwrap()
+        #[cfg(feature = "streaming-parse")]
+        let grades = super::jwcas_stream::parse_gradelist(&text);
+        #[cfg(not(feature = "streaming-parse"))]
+        let grades = parse_gradelist(&text);
+        match grades {
+            Some(grades) => Ok(grades),
+            // The grid is left out of the page when it has no rows
+            None if text.contains(GRADE_PAGE_FORM) => Ok(vec![]),
+            None => Err(other_error("Select Grade Failed")),
+        }
+    }
+
+    /// The grades of [`JwqywxApplication::get_grades`], in the rows of the page.
+    pub async fn get_api_gradeinfo_vec(&self) -> TorErr<Vec<GradeData>> {
+        if !self.api.logged_in().await {
+            self.api.login().await?;
+        }
+        let grades = self.api.get_grades().await?.message;
+        Ok(grades.into_iter().map(GradeData::from).collect())
+    }
+}
+
+/// In the grade page only, with or without grades
+const GRADE_PAGE_FORM: &str = r#"action="cx_cj_jxjhcj_xh.aspx""#;
+
+/// The rows of the grade table of the page of [`JwcasApplication::get_gradelist_html`],
+/// `None` without the table.
+pub fn parse_gradelist(html: &str) -> Option<Vec<GradeData>> {
+    let tb_up = Selector::parse(r#"table[id="GVkbk"]"#).unwrap();
+    let selector = Selector::parse(r#"tr[class="dg1-item"]"#).unwrap();
+    let dom = Html::parse_document(html);
+    Some(
+        dom.select(&tb_up)
+            .next()?
             .select(&selector)
             .map(|e| {
                 let childs: Vec<ElementRef> = e.child_elements().collect();
@@ -81,16 +138,15 @@ impl<C: Client + Clone + Send> JwcasApplication<C> {
                     grade: extract_string(childs.get(9)),
                 }
             })
-            .collect())
-    }
+            .collect(),
+    )
 }
 
 fn extract_string(element: Option<&ElementRef>) -> String {
-    if let Some(element) = element {
-        element.text().next().unwrap().to_string()
-    } else {
-        String::new()
-    }
+    element
+        .and_then(|element| element.text().next())
+        .unwrap_or_default()
+        .to_string()
 }
 
 #[cfg(feature = "calendar")]
@@ -179,7 +235,11 @@ pub mod calendar {
                                 .cloned()
                                 .unwrap_or(String::new());
 
-                            RawCourse { course, teacher }
+                            RawCourse {
+                                course,
+                                teacher,
+                                teacher_id: String::new(),
+                            }
                         })
                         .collect()
                 })
@@ -187,3 +247,170 @@ pub mod calendar {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::parse_gradelist;
+    use crate::impls::{
+        client::DefaultClient,
+        login::{sso_status::SSOLoginStatus, sso_type::SSOLoginConnectType},
+    };
+    use crate::utils::testing::{TestResponse, TestServer};
+
+    const PAGE: &str = include_str!("fixtures/cx_cj_jxjhcj_xh.html");
+
+    async fn server() -> TestServer {
+        let server = TestServer::start().await;
+        server
+            .route(
+                "POST",
+                "/api/login",
+                TestResponse::json(include_str!("../wechat/fixtures/login.json")),
+            )
+            .route(
+                "POST",
+                "/api/cj_xh",
+                TestResponse::json(include_str!("fixtures/cj_xh.json")),
+            )
+            .route(
+                "GET",
+                "/web_cjgl/cx_cj_jxjhcj_xh.aspx",
+                TestResponse::html(PAGE),
+            );
+        server
+    }
+
+    #[tokio::test]
+    async fn api_then_page() {
+        let server = server().await;
+        let mut app = server
+            .jwcas(DefaultClient::account("2300000101", "hunter2"))
+            .await;
+        let scraped = app.get_gradeinfo_vec().await.unwrap();
+        assert_eq!(Some(scraped.clone()), parse_gradelist(PAGE));
+        assert!(server.requests_to("/api/cj_xh").is_empty());
+
+        app.prefer_api = true;
+        assert_eq!(app.get_gradeinfo_vec().await.unwrap(), scraped);
+        assert_eq!(server.requests_to("/api/login").len(), 1);
+        assert_eq!(
+            server.requests_to("/web_cjgl/cx_cj_jxjhcj_xh.aspx").len(),
+            1
+        );
+
+        // Logged in already
+        app.get_gradeinfo_vec().await.unwrap();
+        assert_eq!(server.requests_to("/api/login").len(), 1);
+        assert_eq!(server.requests_to("/api/cj_xh").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn page_when_api_fails() {
+        let server = server().await;
+        server.route(
+            "POST",
+            "/api/cj_xh",
+            TestResponse::new(502, "text/html", "<html>Bad Gateway</html>"),
+        );
+        let mut app = server
+            .jwcas(DefaultClient::account("2300000101", "hunter2"))
+            .await;
+        app.prefer_api = true;
+
+        assert_eq!(app.get_gradeinfo_vec().await.ok(), parse_gradelist(PAGE));
+        assert_eq!(server.requests_to("/api/cj_xh").len(), 1);
+        assert_eq!(
+            server.requests_to("/web_cjgl/cx_cj_jxjhcj_xh.aspx").len(),
+            1
+        );
+
+        // Not even a login
+        server.route(
+            "POST",
+            "/api/login",
+            TestResponse::new(404, "text/html", ""),
+        );
+        let mut app = server.jwcas(DefaultClient::user("2300000101")).await;
+        app.prefer_api = true;
+        assert_eq!(app.get_gradeinfo_vec().await.ok(), parse_gradelist(PAGE));
+    }
+
+    #[tokio::test]
+    async fn page_when_api_refuses() {
+        let server = server().await;
+        server.route(
+            "POST",
+            "/api/cj_xh",
+            TestResponse::json(include_str!("../wechat/fixtures/refused_empty.json")),
+        );
+        let mut app = server
+            .jwcas(DefaultClient::account("2300000101", "hunter2"))
+            .await;
+        app.prefer_api = true;
+
+        // Not taken for no grades at all
+        let grades = app.get_gradeinfo_vec().await.unwrap();
+        assert_eq!(Some(grades), parse_gradelist(PAGE));
+        assert_eq!(server.requests_to("/api/cj_xh").len(), 1);
+        assert_eq!(
+            server.requests_to("/web_cjgl/cx_cj_jxjhcj_xh.aspx").len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn no_grades_yet() {
+        let server = server().await;
+        server.route(
+            "GET",
+            "/web_cjgl/cx_cj_jxjhcj_xh.aspx",
+            TestResponse::html(include_str!("fixtures/cx_cj_jxjhcj_xh_empty.html")),
+        );
+        let app = server.jwcas(DefaultClient::user("2500000101")).await;
+        assert_eq!(app.get_gradeinfo_vec().await.unwrap(), vec![]);
+
+        // Some other page
+        server.route(
+            "GET",
+            "/web_cjgl/cx_cj_jxjhcj_xh.aspx",
+            TestResponse::html("<html><body>系统维护中</body></html>"),
+        );
+        assert!(app.get_gradeinfo_vec().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn from_cookies() {
+        let server = server().await;
+        let client = DefaultClient::from_cookies(vec![(
+            server.url("/"),
+            "ASP.NET_SessionId".into(),
+            "webview".into(),
+        )])
+        .unwrap();
+        assert_eq!(
+            client.sso_login_connect_type().await,
+            Some(SSOLoginConnectType::COMMON)
+        );
+
+        let app = server.jwcas(client).await;
+        assert_eq!(app.get_gradeinfo_vec().await.ok(), parse_gradelist(PAGE));
+        let sent = server.requests_to("/web_cjgl/cx_cj_jxjhcj_xh.aspx");
+        assert_eq!(sent[0].header("cookie"), Some("ASP.NET_SessionId=webview"));
+        assert!(server.requests_to("/api/login").is_empty());
+
+        let vpn = DefaultClient::from_cookies(vec![(
+            "https://zmvpn.cczu.edu.cn/".into(),
+            "wengine_vpn_ticketzmvpn_cczu_edu_cn".into(),
+            "0123".into(),
+        )])
+        .unwrap();
+        assert_eq!(
+            vpn.sso_login_connect_type().await,
+            Some(SSOLoginConnectType::WEBVPN)
+        );
+        assert!(
+            DefaultClient::from_cookies(vec![("not a url".into(), "a".into(), "b".into())])
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! Parse of the jwcas grade page straight from the tokens, without the `scraper` DOM.
+//!
+//! The DOM keeps every node of the page alive until the parse ends, the ASP.NET view state
+//! included, which weighs on old phones. Here only the three cells kept of each row are
+//! held, at the cost of understanding a single layout: the table `GVkbk` and its
+//! `dg1-item` rows, the same as [`super::jwcas::parse_gradelist`]. Without the tree builder
+//! the markup is taken as written, broken nesting it would repair may come out differently.
+
+use std::cell::RefCell;
+
+use html5ever::{
+    tendril::StrTendril,
+    tokenizer::{
+        BufferQueue, Tag, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerOpts,
+    },
+};
+
+use super::jwcas_type::GradeData;
+
+#[derive(Default)]
+struct GradeSink {
+    state: RefCell<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Tables open inside `GVkbk`, `0` while outside of it
+    depth: usize,
+    found: bool,
+    /// Columns of the row, `None` outside of a `dg1-item` row
+    row: Option<Row>,
+    grades: Vec<GradeData>,
+}
+
+#[derive(Default)]
+struct Row {
+    column: Option<usize>,
+    cells: [String; 3],
+    /// Still before or in the first text of the current cell
+    reading: bool,
+    /// The current cell has had its first text
+    read: bool,
+}
+
+/// Column of [`GradeData::name`], [`GradeData::point`] and [`GradeData::grade`]
+const COLUMNS: [usize; 3] = [5, 8, 9];
+
+fn attribute<'a>(tag: &'a Tag, name: &str) -> Option<&'a str> {
+    tag.attrs
+        .iter()
+        .find(|attribute| &*attribute.name.local == name)
+        .map(|attribute| &*attribute.value)
+}
+
+impl State {
+    fn tag(&mut self, tag: Tag) {
+        if let Some(row) = self.row.as_mut().filter(|row| row.read) {
+            row.reading = false;
+        }
+        match (tag.kind, &*tag.name) {
+            (TagKind::StartTag, "table") if self.depth > 0 => self.depth += 1,
+            (TagKind::StartTag, "table")
+                if !self.found && attribute(&tag, "id") == Some("GVkbk") =>
+            {
+                self.found = true;
+                self.depth = 1;
+            }
+            (TagKind::EndTag, "table") if self.depth > 0 => self.depth -= 1,
+            (TagKind::StartTag, "tr") if self.depth == 1 => {
+                self.end_row();
+                if attribute(&tag, "class") == Some("dg1-item") {
+                    self.row = Some(Row::default());
+                }
+            }
+            (TagKind::EndTag, "tr") if self.depth == 1 => self.end_row(),
+            (TagKind::StartTag, "td" | "th") if self.depth == 1 => {
+                if let Some(row) = self.row.as_mut() {
+                    row.column = Some(row.column.map_or(0, |column| column + 1));
+                    row.reading = true;
+                    row.read = false;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let Some(row) = self.row.as_mut().filter(|row| row.reading) else {
+            return;
+        };
+        row.read = true;
+        if let Some(index) = row
+            .column
+            .and_then(|column| COLUMNS.iter().position(|kept| *kept == column))
+        {
+            row.cells[index].push_str(text);
+        }
+    }
+
+    fn end_row(&mut self) {
+        if let Some(row) = self.row.take() {
+            let [name, point, grade] = row.cells;
+            self.grades.push(GradeData { name, point, grade });
+        }
+    }
+}
+
+impl TokenSink for GradeSink {
+    type Handle = ();
+
+    fn process_token(&self, token: Token, _line_number: u64) -> TokenSinkResult<()> {
+        let mut state = self.state.borrow_mut();
+        match token {
+            Token::TagToken(tag) => state.tag(tag),
+            Token::CharacterTokens(text) => state.text(&text),
+            Token::EOFToken => state.end_row(),
+            _ => {}
+        }
+        TokenSinkResult::Continue
+    }
+}
+
+/// The rows of the grade table, `None` without the table.
+pub fn parse_gradelist(html: &str) -> Option<Vec<GradeData>> {
+    let tokenizer = Tokenizer::new(GradeSink::default(), TokenizerOpts::default());
+    let input = BufferQueue::default();
+    input.push_back(StrTendril::from_slice(html));
+    let _ = tokenizer.feed(&input);
+    tokenizer.end();
+
+    let state = tokenizer.sink.state.into_inner();
+    state.found.then_some(state.grades)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+    };
+
+    use super::parse_gradelist;
+    use crate::impls::apps::sso::jwcas;
+
+    /// Counts the bytes allocated by each thread, for the peak of one parse.
+    struct Counting;
+
+    thread_local! {
+        static LIVE: Cell<isize> = const { Cell::new(0) };
+        static PEAK: Cell<isize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let live = LIVE.get() + layout.size() as isize;
+            LIVE.set(live);
+            PEAK.set(PEAK.get().max(live));
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            LIVE.set(LIVE.get() - layout.size() as isize);
+            unsafe { System.dealloc(ptr, layout) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: Counting = Counting;
+
+    /// Bytes allocated at the most while `parse` runs, on top of what was there before.
+    fn peak<T>(parse: impl FnOnce() -> T) -> (T, isize) {
+        let before = LIVE.get();
+        PEAK.set(before);
+        let result = parse();
+        (result, PEAK.get() - before)
+    }
+
+    const PAGE: &str = include_str!("fixtures/cx_cj_jxjhcj_xh.html");
+
+    #[test]
+    fn same_as_dom() {
+        let grades = parse_gradelist(PAGE).unwrap();
+        assert_eq!(grades.len(), 6);
+        assert_eq!(grades[3].name, "体育2");
+        assert_eq!(grades[3].grade, "优秀");
+        assert_eq!(grades[4].point, "4.0");
+        assert_eq!(grades[0].grade, "92");
+        assert_eq!(Some(grades), jwcas::parse_gradelist(PAGE));
+
+        assert_eq!(parse_gradelist("<html><body>登录超时</body></html>"), None);
+    }
+
+    #[test]
+    fn less_memory() {
+        // Six hundred rows, more than any student has
+        let rows =
+            &PAGE[PAGE.find("\t<tr class=\"dg1-item\">").unwrap()..PAGE.find("</table>").unwrap()];
+        let page = PAGE.replacen(rows, &rows.repeat(100), 1);
+
+        let (dom, dom_peak) = peak(|| jwcas::parse_gradelist(&page));
+        let (streamed, streamed_peak) = peak(|| parse_gradelist(&page));
+        assert_eq!(dom, streamed);
+        assert_eq!(streamed.unwrap().len(), 600);
+        assert!(
+            streamed_peak * 4 < dom_peak,
+            "streamed {streamed_peak} bytes, DOM {dom_peak} bytes"
+        );
+    }
+}
@@ -1,6 +1,19 @@
-#[derive(Debug, Clone)]
+use crate::impls::apps::wechat::jwqywx_type::CourseGrade;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct GradeData {
     pub name: String,
     pub point: String,
     pub grade: String,
 }
+
+/// The row of the grade page for a record of the API, credits with one decimal like the page.
+impl From<CourseGrade> for GradeData {
+    fn from(grade: CourseGrade) -> Self {
+        Self {
+            name: grade.course_name,
+            point: format!("{:.1}", grade.course_credits.value()),
+            grade: grade.exam_grade,
+        }
+    }
+}
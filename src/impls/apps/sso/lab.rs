@@ -7,10 +7,13 @@ use crate::{
         typing::{other_error, EmptyOrErr, TorErr},
     },
     impls::login::sso::SSOUniversalLogin,
-    internals::fields::DEFAULT_HEADERS,
+    internals::{
+        fields::DEFAULT_HEADERS,
+        response::{read_json, MeasuredSend},
+    },
 };
 
-static LABAPP_ROOT: &'static str = "https://sysaqgl.cczu.edu.cn";
+static LABAPP_ROOT: &str = "https://sysaqgl.cczu.edu.cn";
 
 pub struct LabApplication<C> {
     client: C,
@@ -37,18 +40,16 @@ impl<C: Client + Clone + Send + Sync> LabApplication<C> {
         let mut params = HashMap::new();
         params.insert("cmd", "xuexi_online");
 
-        Ok(self
+        let response = self
             .client
             .reqwest_client()
             .post(api)
             .headers(DEFAULT_HEADERS.clone())
             .form(&params)
-            .send()
-            .await
-            .map_err(other_error)?
-            .json()
+            .send_measured(&self.client)
             .await
-            .map_err(other_error)?)
+            .map_err(other_error)?;
+        read_json(response, self.client.options().max_response_size).await
     }
 }
 
@@ -1,3 +1,5 @@
 pub mod jwcas;
+#[cfg(feature = "streaming-parse")]
+pub mod jwcas_stream;
 pub mod jwcas_type;
 pub mod lab;
@@ -1,23 +1,57 @@
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, ORIGIN, REFERER};
-use serde_json::json;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, AUTHORIZATION, ORIGIN, REFERER},
+    StatusCode, Url,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::{future::Future, sync::Arc};
+use tokio::{sync::RwLock, time::sleep};
 
 use crate::{
     base::{
         app::Application,
         client::Client,
-        typing::{other_error, TorErr},
+        typing::{other_error, typed_error, CczuError, TorErr, TypedErrorExt},
+    },
+    extension::grades::{grades_since, GradesSince},
+    internals::{
+        fields::{DEFAULT_HEADERS, WECHAT_APP_API},
+        response::{read_app_bytes, read_text, send_cached, MeasuredSend},
     },
-    internals::fields::{DEFAULT_HEADERS, WECHAT_APP_API},
 };
 
-use super::jwqywx_type::{CourseGrade, LoginUserData, Message, StudentPoint, Term};
+use super::jwqywx_type::{
+    CourseGrade, Detailed, GradeComponents, GradeDetail, LoginUserData, Message, StudentPoint, Term,
+};
+
+/// Endpoints that always have something for a student, an empty answer from them is a hiccup.
+///
+/// Lists that can really be empty (a new student's awards...) must not be here.
+pub const EXPECTED_NON_EMPTY: [&str; 2] = ["cj_xh", "xqall"];
+
+/// The `Authorization` of the login token, sent bare or already with its `Bearer `.
+fn bearer_token(token: Option<&str>) -> TorErr<HeaderValue> {
+    let token = token.map(str::trim).unwrap_or_default();
+    let token = match token.strip_prefix("Bearer") {
+        Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => rest.trim_start(),
+        _ => token,
+    };
+    if token.is_empty() {
+        return Err(typed_error(CczuError::TokenExtractionFailed));
+    }
+    HeaderValue::from_str(&format!("Bearer {token}"))
+        .map_err(|_| typed_error(CczuError::TokenExtractionFailed))
+}
 
 pub struct JwqywxApplication<C> {
     client: C,
+    /// [`WECHAT_APP_API`] by default
+    pub root: String,
     headers: Arc<RwLock<HeaderMap>>,
     authorizationid: Arc<RwLock<Option<String>>>,
+    /// The term the login answered with, the current one for the portal
+    current_term: Arc<RwLock<Option<String>>>,
+    warnings: Arc<RwLock<Vec<String>>>,
 }
 
 impl<C: Client> Application<C> for JwqywxApplication<C> {
@@ -33,8 +67,11 @@ impl<C: Client> Application<C> for JwqywxApplication<C> {
         );
         Self {
             client,
+            root: WECHAT_APP_API.to_owned(),
             headers: Arc::new(RwLock::new(header)),
             authorizationid: Arc::new(RwLock::new(None)),
+            current_term: Arc::new(RwLock::new(None)),
+            warnings: Arc::new(RwLock::new(vec![])),
         }
     }
 }
@@ -45,7 +82,7 @@ impl<C: Client> JwqywxApplication<C> {
         let result = self
             .client
             .reqwest_client()
-            .post(format!("{}/api/login", WECHAT_APP_API))
+            .post(format!("{}/api/login", self.root))
             .headers(DEFAULT_HEADERS.clone())
             .header("Referer", "http://jwqywx.cczu.edu.cn/")
             .header("Origin", "http://jwqywx.cczu.edu.cn")
@@ -53,35 +90,54 @@ impl<C: Client> JwqywxApplication<C> {
                 "userid":account.user,
                 "userpwd":account.password,
             }))
-            .send()
+            .send_measured(&self.client)
             .await;
         if let Ok(response) = result {
-            if let Ok(text) = response.text().await {
-                let message = serde_json::from_str::<Message<LoginUserData>>(&text)?;
-                self.write_token(format!(
-                    "Bearer {}",
-                    message.token.clone().ok_or(other_error("error"))?
-                ))
-                .await;
-                self.write_authorizationid(
-                    message
-                        .message
-                        .get(0)
-                        .ok_or(other_error("Jwqywx Login Failed, No User Data!"))?
-                        .id
-                        .clone(),
-                )
+            let text = read_text(response, self.client.options().max_response_size).await?;
+            let message = serde_json::from_str::<Message<LoginUserData>>(&text)?;
+            self.write_token(bearer_token(message.token.as_deref())?)
                 .await;
+            let user = message
+                .message
+                .first()
+                .ok_or(other_error("Jwqywx Login Failed, No User Data!"))?;
+            self.write_authorizationid(user.id.clone()).await;
+            *self.current_term.write().await = Some(user.term.clone());
 
-                return Ok(message);
-            }
+            return Ok(message);
         }
         Err(other_error("Jwqywx Login Failed"))
     }
 
-    async fn write_token(&self, token: String) {
+    /// [`Self::login`] again for a new token, logged in or not: what the fetches do once by
+    /// themselves when the portal refuses the token as expired.
+    pub async fn force_login(&self) -> TorErr<()> {
+        self.login().await.map(|_| ())
+    }
+
+    /// `fetch`, then once again after [`Self::force_login`] when it fails with
+    /// [`CczuError::TokenExpired`]. Never logged in, or not logging in again, the error stays.
+    async fn relogin_once<T, F, Fut>(&self, fetch: F) -> TorErr<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = TorErr<T>>,
+    {
+        match fetch().await {
+            Err(error)
+                if error.typed() == Some(&CczuError::TokenExpired) && self.logged_in().await =>
+            {
+                if self.force_login().await.is_err() {
+                    return Err(error);
+                }
+                fetch().await
+            }
+            result => result,
+        }
+    }
+
+    async fn write_token(&self, token: HeaderValue) {
         let mut header = DEFAULT_HEADERS.clone();
-        header.insert(AUTHORIZATION, HeaderValue::from_str(&token).unwrap());
+        header.insert(AUTHORIZATION, token);
         header.insert(
             REFERER,
             HeaderValue::from_static("http://jwqywx.cczu.edu.cn/"),
@@ -98,71 +154,224 @@ impl<C: Client> JwqywxApplication<C> {
         *authorizationid = Some(id);
     }
 
+    /// Whether [`Self::login`] went through, the token may have expired since.
+    pub async fn logged_in(&self) -> bool {
+        self.authorizationid.read().await.is_some()
+    }
+
     async fn get_authorizationid(&self) -> TorErr<String> {
         let authorizationid = self.authorizationid.read().await;
         authorizationid.clone().ok_or(other_error("Not logged in"))
     }
 
-    pub async fn get_grades(&self) -> TorErr<Message<CourseGrade>> {
-        let result = self
-            .client
-            .reqwest_client()
-            .post(format!("{}/api/cj_xh", WECHAT_APP_API))
-            .headers(self.headers.read().await.clone())
-            .json(&json!({
-                "xh":self.get_authorizationid().await?,
-            }))
-            .send()
-            .await;
-        if let Ok(response) = result {
-            return Ok(response.json().await.map_err(other_error)?);
+    /// `POST` `body` to `/api/{endpoint}` with the token, or `GET` it without a body.
+    ///
+    /// Logs in again and asks once more when the token expired, see [`Self::relogin_once`].
+    async fn fetch_message_once<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: Option<&Value>,
+    ) -> TorErr<Message<T>> {
+        self.relogin_once(|| self.fetch_message_with_token(endpoint, body))
+            .await
+    }
+
+    async fn fetch_message_with_token<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: Option<&Value>,
+    ) -> TorErr<Message<T>> {
+        let url = format!("{}/api/{}", self.root, endpoint);
+        let request = match body {
+            Some(body) => self
+                .client
+                .reqwest_client()
+                .post(url)
+                .headers(self.headers.read().await.clone())
+                .json(body),
+            None => self.client.reqwest_client().get(url),
+        };
+        if let Ok(response) = send_cached(&self.client, request).await {
+            let options = self.client.options();
+            let unauthorized = response.status() == StatusCode::UNAUTHORIZED;
+            let body = read_app_bytes(response, options.max_response_size).await?;
+            let message: Message<T> = match Message::parse(&body, options.parse_mode_of(endpoint)) {
+                // The token refused without a reason understood, or with none at all
+                Err(error)
+                    if unauthorized
+                        && matches!(error.typed(), None | Some(CczuError::Truncated)) =>
+                {
+                    return Err(typed_error(CczuError::TokenExpired))
+                }
+                message => message?,
+            };
+            *self.warnings.write().await = message.warnings.clone();
+            return Ok(message);
         }
         Err(other_error("Request Failed"))
     }
 
-    pub async fn get_credits_and_rank(&self) -> TorErr<Message<StudentPoint>> {
-        let result = self
-            .client
-            .reqwest_client()
-            .post(format!("{}/api/cj_xh_xfjd", WECHAT_APP_API))
-            .headers(self.headers.read().await.clone())
-            .json(&json!({
-                "xh":self.get_authorizationid().await?,
-            }))
-            .send()
-            .await;
-        if let Ok(response) = result {
-            return Ok(response.json().await.map_err(other_error)?);
+    /// What the last request left out in [`crate::base::client::ParseMode::Lenient`].
+    ///
+    /// One slot for the app and its clones: when requests run at the same time, the last
+    /// one to end wins. The answers carry their own, in [`Message::warnings`] and
+    /// [`Detailed::warnings`], only the week matrix of
+    /// [`crate::extension::calendar::TermCalendarParser`] needs this.
+    pub async fn warnings(&self) -> Vec<String> {
+        self.warnings.read().await.clone()
+    }
+
+    /// [`Self::fetch_message_once`], asked again once when one of the [`EXPECTED_NON_EMPTY`]
+    /// endpoints answers empty, see [`crate::base::client::ClientOptions::retry_on_empty_of`].
+    ///
+    /// Asked again once too after the wait of a [`CczuError::RateLimited`], if it is within
+    /// [`crate::base::client::ClientOptions::wait_rate_limit`], that answer being checked
+    /// for emptiness the same way.
+    async fn fetch_message<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: Option<&Value>,
+    ) -> TorErr<Message<T>> {
+        let message = match self.fetch_message_once(endpoint, body).await {
+            Ok(message) => message,
+            Err(error) => {
+                let wait = match (error.typed(), self.client.options().wait_rate_limit) {
+                    (
+                        Some(CczuError::RateLimited {
+                            retry_after: Some(wait),
+                        }),
+                        Some(max),
+                    ) if *wait <= max => *wait,
+                    _ => return Err(error),
+                };
+                sleep(wait).await;
+                self.fetch_message_once(endpoint, body).await?
+            }
+        };
+        let Some(delay) = self.client.options().retry_on_empty_of(endpoint) else {
+            return Ok(message);
+        };
+        if !message.message.is_empty() || !EXPECTED_NON_EMPTY.contains(&endpoint) {
+            return Ok(message);
         }
-        Err(other_error("Request Failed"))
+
+        sleep(delay).await;
+        if let (Some(cache), Ok(url)) = (
+            self.client.cache(),
+            Url::parse(&format!("{}/api/{}", self.root, endpoint)),
+        ) {
+            cache.invalidate(&url);
+        }
+        self.fetch_message_once(endpoint, body).await
+    }
+
+    async fn own_records(&self) -> TorErr<Value> {
+        Ok(json!({
+            "xh":self.get_authorizationid().await?,
+        }))
+    }
+
+    pub async fn get_grades(&self) -> TorErr<Message<CourseGrade>> {
+        self.fetch_message("cj_xh", Some(&self.own_records().await?))
+            .await
+    }
+
+    /// [`Self::get_grades`] with the records of the portal, see [`Detailed`].
+    pub async fn get_grades_detailed(&self) -> TorErr<Detailed<Vec<CourseGrade>>> {
+        let records: Message<Value> = self
+            .fetch_message("cj_xh", Some(&self.own_records().await?))
+            .await?;
+        let raw = records.message.clone();
+        let parse_mode = self.client.options().parse_mode_of("cj_xh");
+        let parsed = records.into_parsed(parse_mode)?;
+        *self.warnings.write().await = parsed.warnings.clone();
+        Ok(Detailed {
+            parsed: parsed.message,
+            raw,
+            warnings: parsed.warnings,
+        })
     }
 
+    /// The usual/midterm/final parts of the grade of a course, from the same records as
+    /// [`Self::get_grades`].
+    pub async fn grade_detail(&self, course_id: &str, term: i32) -> TorErr<GradeDetail> {
+        let records: Message<GradeComponents> = self
+            .fetch_message("cj_xh", Some(&self.own_records().await?))
+            .await?;
+        records
+            .message
+            .iter()
+            .find(|record| record.course_id == course_id && record.term == term)
+            .and_then(GradeComponents::detail)
+            .ok_or(typed_error(CczuError::DetailNotAvailable))
+    }
+
+    /// Grades released or changed since `token`, see [`grades_since`].
+    pub async fn grades_since(&self, token: &str) -> TorErr<GradesSince> {
+        grades_since(self.get_grades().await?.message, token)
+    }
+
+    pub async fn get_credits_and_rank(&self) -> TorErr<Message<StudentPoint>> {
+        self.fetch_message("cj_xh_xfjd", Some(&self.own_records().await?))
+            .await
+    }
+
+    /// Every term, newest first, the one of [`Self::current_term`] marked once logged in.
+    ///
+    /// The newest is not always the current one: the next term is listed before it starts.
     pub async fn terms(&self) -> TorErr<Message<Term>> {
-        let result = self
-            .client
-            .reqwest_client()
-            .get(format!("{}/api/xqall", WECHAT_APP_API))
-            .send()
-            .await;
-        if let Ok(response) = result {
-            return Ok(response.json().await.map_err(other_error)?);
+        let mut terms: Message<Term> = self.fetch_message("xqall", None).await?;
+        if let Some(current) = self.current_term.read().await.as_deref() {
+            for term in &mut terms.message {
+                term.is_current = term.term == current;
+            }
         }
-        Err(other_error("Request Failed"))
+        Ok(terms)
+    }
+
+    /// [`Self::terms`] without the ones before the enrollment of the student, always empty.
+    ///
+    /// For fetches going over every term. All the terms are kept when the portal gives no
+    /// enrollment year.
+    pub async fn terms_with_data(&self) -> TorErr<Message<Term>> {
+        let mut terms = self.terms().await?;
+        let enrolled = self
+            .get_credits_and_rank()
+            .await?
+            .message
+            .into_iter()
+            .find_map(|point| point.enrollment_year?.trim().parse::<u32>().ok());
+        if let Some(enrolled) = enrolled {
+            terms
+                .message
+                .retain(|term| term.start_year().is_none_or(|year| year >= enrolled));
+        }
+        Ok(terms)
+    }
+
+    /// The current term, as given by the login.
+    pub async fn current_term(&self) -> TorErr<Term> {
+        let term = self.current_term.read().await.clone();
+        Ok(Term {
+            term: term.ok_or(other_error("Not logged in"))?,
+            is_current: true,
+        })
     }
 }
 
 #[cfg(feature = "calendar")]
 pub mod calendar {
-    use serde_json::json;
+    use reqwest::StatusCode;
+    use serde_json::{json, Value};
 
     use crate::{
         base::{
-            client::Client,
-            typing::{other_error, TorErr},
+            client::{Client, ParseMode},
+            typing::{other_error, typed_error, CczuError, TorErr, TypedErrorExt},
         },
         extension::calendar::{CalendarParser, RawCourse, TermCalendarParser},
-        impls::apps::wechat::jwqywx_type::{calendar::SerdeRowCourses, Message},
-        internals::fields::WECHAT_APP_API,
+        impls::apps::wechat::jwqywx_type::{calendar::SerdeRowCourses, Detailed, Message},
+        internals::response::{read_app_bytes, send_cached},
     };
 
     use super::JwqywxApplication;
@@ -172,39 +381,623 @@ pub mod calendar {
             &self,
             term: String,
         ) -> TorErr<Vec<Vec<RawCourse>>> {
-            let result = self
-                .client
-                .reqwest_client()
-                .post(format!("{}/api/kb_xq_xh", WECHAT_APP_API))
-                .headers(self.headers.read().await.clone())
-                .json(&json!({
-                    "xh":self.client.account().user,
-                    "xq":term,
-                    "yhid":self.get_authorizationid().await?,
-                }))
-                .send()
-                .await;
+            self.week_matrix(self.client.account().user, term).await
+        }
+    }
+
+    impl<C: Client> JwqywxApplication<C> {
+        /// Schedule of another student or of a class, for the accounts allowed to look at it
+        /// (advisors, class monitors).
+        ///
+        /// The portal decides, an account without the permission gets [`CczuError::Forbidden`].
+        pub async fn get_term_classinfo_week_matrix_for(
+            &self,
+            student_or_class_id: impl Into<String>,
+            term: String,
+        ) -> TorErr<Vec<Vec<RawCourse>>> {
+            self.week_matrix(student_or_class_id.into(), term).await
+        }
+
+        /// [`TermCalendarParser::get_term_classinfo_week_matrix`] with the rows of the portal,
+        /// see [`Detailed`].
+        pub async fn get_term_classinfo_week_matrix_detailed(
+            &self,
+            term: String,
+        ) -> TorErr<Detailed<Vec<Vec<RawCourse>>>> {
+            let rows = self.week_rows(self.client.account().user, term).await?;
+            let raw = rows.message.clone();
+            let (parsed, warnings) = self.rows_matrix(rows).await?;
+            Ok(Detailed {
+                parsed,
+                raw,
+                warnings,
+            })
+        }
+
+        async fn week_matrix(&self, id: String, term: String) -> TorErr<Vec<Vec<RawCourse>>> {
+            let rows = self.week_rows(id, term).await?;
+            Ok(self.rows_matrix(rows).await?.0)
+        }
+
+        /// Logs in again and asks once more when the token expired, see [`Self::relogin_once`].
+        async fn week_rows(&self, id: String, term: String) -> TorErr<Message<Value>> {
+            self.relogin_once(|| self.week_rows_with_token(&id, &term))
+                .await
+        }
+
+        async fn week_rows_with_token(&self, id: &str, term: &str) -> TorErr<Message<Value>> {
+            let result = send_cached(
+                &self.client,
+                self.client
+                    .reqwest_client()
+                    .post(format!("{}/api/kb_xq_xh", self.root))
+                    .headers(self.headers.read().await.clone())
+                    .json(&json!({
+                        "xh":id,
+                        "xq":term,
+                        "yhid":self.get_authorizationid().await?,
+                    })),
+            )
+            .await;
             if let Ok(response) = result {
-                let data: Message<SerdeRowCourses> = response.json().await.unwrap();
-                return Ok(data.message.into_iter().map(|e| e.into()).collect());
+                let refused = matches!(
+                    response.status(),
+                    StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+                );
+                let body =
+                    read_app_bytes(response, self.client.options().max_response_size).await?;
+                return match Message::from_slice(&body) {
+                    Err(error) if error.typed() == Some(&CczuError::TokenExpired) => Err(error),
+                    _ if refused => Err(typed_error(CczuError::Forbidden)),
+                    message => message,
+                };
             }
             Err(other_error("Get Class Info failed"))
         }
+
+        /// The matrix of `rows`, with what was left out of it.
+        async fn rows_matrix(
+            &self,
+            rows: Message<Value>,
+        ) -> TorErr<(Vec<Vec<RawCourse>>, Vec<String>)> {
+            let parse_mode = self.client.options().parse_mode_of("kb_xq_xh");
+            let mut data: Message<SerdeRowCourses> = rows.into_parsed(parse_mode)?;
+            let mut warnings = std::mem::take(&mut data.warnings);
+            for (index, row) in data.message.iter().enumerate() {
+                let columns = row.unknown_columns();
+                if columns.is_empty() {
+                    continue;
+                }
+                let warning = format!("Row {index}: unknown columns {}", columns.join(", "));
+                if parse_mode == ParseMode::Strict {
+                    return Err(other_error(warning));
+                }
+                warnings.push(warning);
+            }
+            *self.warnings.write().await = warnings.clone();
+            Ok((
+                data.message.into_iter().map(|e| e.into()).collect(),
+                warnings,
+            ))
+        }
     }
 
     impl<C: Client> CalendarParser for JwqywxApplication<C> {
         async fn get_classinfo_week_matrix(&self) -> TorErr<Vec<Vec<RawCourse>>> {
-            self.get_term_classinfo_week_matrix(
-                self.terms()
+            self.get_term_classinfo_week_matrix(self.current_term().await?.term)
+                .await
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use std::time::Duration;
+
+        use reqwest::header::AUTHORIZATION;
+        use tokio::time::Instant;
+
+        use crate::{
+            base::{
+                client::{Account, ClientOptions, EndpointOptions, ParseMode},
+                typing::{CczuError, TypedErrorExt},
+            },
+            extension::calendar::{CalendarParser, TermCalendarParser},
+            impls::{
+                apps::wechat::jwqywx::{bearer_token, JwqywxApplication},
+                client::DefaultClient,
+            },
+            utils::testing::{Fault, FaultInjection, TestResponse, TestServer},
+        };
+
+        /// A server answering `method` on the `endpoint` of jwqywx with `bodies` one after
+        /// the other, the last one again once past the end.
+        async fn serve_sequence(method: &str, endpoint: &str, bodies: &[&str]) -> TestServer {
+            let server = TestServer::start().await;
+            let responses = bodies
+                .iter()
+                .map(|body| TestResponse::json(*body))
+                .collect();
+            server.sequence(method, &format!("/api/{endpoint}"), responses);
+            server
+        }
+
+        /// A server answering every request of `method` on `endpoint` with `body`.
+        async fn serve(method: &str, endpoint: &str, body: &str) -> TestServer {
+            serve_sequence(method, endpoint, &[body]).await
+        }
+
+        async fn app(server: &TestServer) -> JwqywxApplication<DefaultClient> {
+            app_with(DefaultClient::user("2300000101"), server).await
+        }
+
+        async fn app_with(
+            client: DefaultClient,
+            server: &TestServer,
+        ) -> JwqywxApplication<DefaultClient> {
+            let app = server.jwqywx(client).await;
+            app.write_authorizationid("1".into()).await;
+            app
+        }
+
+        #[tokio::test]
+        async fn other_schedule_forbidden() {
+            let server = TestServer::start().await;
+            server.route(
+                "POST",
+                "/api/kb_xq_xh",
+                TestResponse::new(
+                    403,
+                    "application/json",
+                    include_str!("fixtures/kb_forbidden.json"),
+                ),
+            );
+            let error = app(&server)
+                .await
+                .get_term_classinfo_week_matrix_for("2300000102", "25-26-1".into())
+                .await
+                .unwrap_err();
+            assert_eq!(error.typed(), Some(&CczuError::Forbidden));
+        }
+
+        #[tokio::test]
+        async fn other_schedule() {
+            let server = serve("POST", "kb_xq_xh", include_str!("fixtures/kb_rows.json")).await;
+            let app = app(&server).await;
+
+            let own = app
+                .get_term_classinfo_week_matrix("25-26-1".into())
+                .await
+                .unwrap();
+            let other = app
+                .get_term_classinfo_week_matrix_for("软件231", "25-26-1".into())
+                .await
+                .unwrap();
+            assert_eq!(own[0][0].course, other[0][0].course);
+            assert_eq!(other[0][0].teacher, "李老师");
+        }
+
+        #[tokio::test]
+        async fn unknown_column() {
+            let server = serve(
+                "POST",
+                "kb_xq_xh",
+                include_str!("fixtures/kb_extra_column.json"),
+            )
+            .await;
+
+            let app = app(&server).await;
+            let matrix = app
+                .get_term_classinfo_week_matrix("25-26-1".into())
+                .await
+                .unwrap();
+            assert_eq!(matrix.len(), 1);
+            assert_eq!(matrix[0].len(), 7);
+            assert_eq!(matrix[0][0].teacher, "李老师");
+            let warnings = app.warnings().await;
+            assert_eq!(warnings.len(), 2);
+            assert!(warnings[0].starts_with("Record 1 skipped"));
+            assert_eq!(warnings[1], "Row 0: unknown columns kc8");
+            // Its own, whatever the app did since
+            let detailed = app
+                .get_term_classinfo_week_matrix_detailed("25-26-1".into())
+                .await
+                .unwrap();
+            assert_eq!(detailed.warnings, warnings);
+
+            let strict = DefaultClient::with_options(
+                Account::new("2300000101", ""),
+                ClientOptions {
+                    parse_mode: ParseMode::Strict,
+                    ..Default::default()
+                },
+            );
+            let app = app_with(strict, &server).await;
+            assert!(app
+                .get_term_classinfo_week_matrix("25-26-1".into())
+                .await
+                .is_err());
+        }
+
+        #[tokio::test]
+        async fn strict_endpoint() {
+            let server = serve(
+                "POST",
+                "kb_xq_xh",
+                include_str!("fixtures/kb_extra_column.json"),
+            )
+            .await;
+            let strict_on = |endpoint: &str| {
+                let mut options = ClientOptions::default();
+                options.endpoints.insert(
+                    endpoint.into(),
+                    EndpointOptions {
+                        parse_mode: Some(ParseMode::Strict),
+                        ..Default::default()
+                    },
+                );
+                DefaultClient::with_options(Account::new("2300000101", ""), options)
+            };
+
+            let app = app_with(strict_on("kb_xq_xh"), &server).await;
+            assert!(app
+                .get_term_classinfo_week_matrix("25-26-1".into())
+                .await
+                .is_err());
+            // Lenient everywhere else
+            let app = app_with(strict_on("cj_xh"), &server).await;
+            assert_eq!(
+                app.get_term_classinfo_week_matrix("25-26-1".into())
                     .await
                     .unwrap()
-                    .message
-                    .first()
-                    .unwrap()
-                    .term
-                    .clone(),
+                    .len(),
+                1
+            );
+        }
+
+        #[tokio::test]
+        async fn detailed() {
+            let server = serve("POST", "cj_xh", include_str!("fixtures/cj_xh_detail.json")).await;
+            server.route(
+                "POST",
+                "/api/kb_xq_xh",
+                TestResponse::json(include_str!("fixtures/kb_extra_column.json")),
+            );
+            let app = app(&server).await;
+            let grades = app.get_grades_detailed().await.unwrap();
+            assert_eq!(grades.parsed.len(), grades.raw.len());
+            assert_eq!(grades.raw[0]["kcmc"], grades.parsed[0].course_name.as_str());
+
+            // The row left out of the parsed matrix is still in the raw records
+            let schedule = app
+                .get_term_classinfo_week_matrix_detailed("25-26-1".into())
+                .await
+                .unwrap();
+            assert_eq!(schedule.parsed.len(), 1);
+            assert_eq!(schedule.raw.len(), 2);
+            assert_eq!(schedule.raw[1], "第1-2节");
+        }
+
+        #[tokio::test]
+        async fn login_token() {
+            for fixture in [
+                include_str!("fixtures/login.json"),
+                include_str!("fixtures/login_bearer.json"),
+            ] {
+                let server = serve("POST", "login", fixture).await;
+                let app = app(&server).await;
+                app.login().await.unwrap();
+                assert_eq!(
+                    app.headers.read().await[AUTHORIZATION],
+                    "Bearer eyJhbGciOiJIUzI1NiJ9.e30.c2lnbmF0dXJl"
+                );
+                assert_eq!(app.get_authorizationid().await.unwrap(), "1001");
+            }
+
+            for token in [None, Some(""), Some("Bearer "), Some("new\nline")] {
+                assert_eq!(
+                    bearer_token(token).unwrap_err().typed(),
+                    Some(&CczuError::TokenExtractionFailed)
+                );
+            }
+        }
+
+        #[tokio::test]
+        async fn week_matrix_of_the_current_term() {
+            let server = TestServer::start().await;
+            server
+                .route(
+                    "POST",
+                    "/api/login",
+                    TestResponse::json(include_str!("fixtures/login.json")),
+                )
+                .route(
+                    "POST",
+                    "/api/xqall",
+                    TestResponse::json(include_str!("fixtures/xqall.json")),
+                )
+                .route(
+                    "POST",
+                    "/api/kb_xq_xh",
+                    TestResponse::json(include_str!("fixtures/kb_rows.json")),
+                );
+            let app = server
+                .jwqywx(DefaultClient::account("2300000101", "hunter2"))
+                .await;
+            assert!(app.get_classinfo_week_matrix().await.is_err());
+            app.login().await.unwrap();
+
+            let matrix = app.get_classinfo_week_matrix().await.unwrap();
+            assert_eq!(matrix[0][0].teacher, "李老师");
+            // 26-27-1 is listed first but hasn't started
+            let asked = &server.requests_to("/api/kb_xq_xh")[0].body;
+            assert!(asked.contains(r#""xq":"25-26-1""#), "{asked}");
+        }
+
+        #[tokio::test]
+        async fn current_term() {
+            let server = serve("POST", "login", include_str!("fixtures/login.json")).await;
+            server.route(
+                "GET",
+                "/api/xqall",
+                TestResponse::json(include_str!("fixtures/xqall.json")),
+            );
+            let app = app(&server).await;
+            assert!(app.current_term().await.is_err());
+            app.login().await.unwrap();
+
+            let terms = app.terms().await.unwrap().message;
+            assert_eq!(terms[0].term, "26-27-1");
+            let current: Vec<&str> = terms
+                .iter()
+                .filter(|term| term.is_current)
+                .map(|term| term.term.as_str())
+                .collect();
+            assert_eq!(current, ["25-26-1"]);
+            assert_eq!(app.current_term().await.unwrap().term, "25-26-1");
+        }
+
+        #[tokio::test]
+        async fn terms_with_data() {
+            let server = serve("POST", "login", include_str!("fixtures/login.json")).await;
+            server
+                .route(
+                    "GET",
+                    "/api/xqall",
+                    TestResponse::json(include_str!("fixtures/xqall.json")),
+                )
+                .route(
+                    "POST",
+                    "/api/cj_xh_xfjd",
+                    TestResponse::json(include_str!("fixtures/cj_xh_xfjd.json")),
+                );
+            let app = app(&server).await;
+            app.login().await.unwrap();
+
+            let terms: Vec<String> = app
+                .terms_with_data()
+                .await
+                .unwrap()
+                .message
+                .into_iter()
+                .map(|term| term.term)
+                .collect();
+            // Enrolled in 2025, the terms of 24-25 came before
+            assert_eq!(terms, ["26-27-1", "25-26-2", "25-26-1"]);
+        }
+
+        #[tokio::test]
+        async fn login_wall() {
+            let server = TestServer::start().await;
+            server.route(
+                "POST",
+                "/api/cj_xh",
+                TestResponse::html(include_str!("fixtures/cj_xh_login_wall.html")),
+            );
+            let error = app(&server).await.get_grades().await.unwrap_err();
+            assert_eq!(error.typed(), Some(&CczuError::SessionExpired));
+        }
+
+        #[tokio::test]
+        async fn token_expired_relogin() {
+            let server = serve_sequence(
+                "POST",
+                "cj_xh",
+                &[
+                    include_str!("fixtures/token_expired.json"),
+                    include_str!("fixtures/cj_xh_detail.json"),
+                ],
             )
-            .await
+            .await;
+            server.route(
+                "POST",
+                "/api/login",
+                TestResponse::json(include_str!("fixtures/login.json")),
+            );
+            let app = app(&server).await;
+            assert_eq!(app.get_grades().await.unwrap().message.len(), 3);
+            // The grades, the login, the grades again
+            let paths: Vec<_> = server.requests().into_iter().map(|r| r.path).collect();
+            assert_eq!(paths, ["/api/cj_xh", "/api/login", "/api/cj_xh"]);
+            assert_eq!(*app.authorizationid.read().await, Some("1001".into()));
+
+            // Still refused with a new token: once only
+            let unauthorized = |body| TestResponse::new(401, "application/json", body);
+            let server = TestServer::start().await;
+            server
+                .route(
+                    "POST",
+                    "/api/kb_xq_xh",
+                    unauthorized(include_str!("fixtures/token_expired.json")),
+                )
+                .route(
+                    "POST",
+                    "/api/login",
+                    unauthorized(include_str!("fixtures/login.json")),
+                );
+            let error = app_with(DefaultClient::user("2300000101"), &server)
+                .await
+                .get_term_classinfo_week_matrix("25-26-1".into())
+                .await
+                .unwrap_err();
+            assert_eq!(error.typed(), Some(&CczuError::TokenExpired));
+            assert_eq!(server.requests().len(), 3);
+        }
+
+        const EMPTY: &str = r#"{"status":1,"message":[],"token":null}"#;
+        const TERMS: &str = r#"{"status":1,"message":[{"xq":"25-26-1"}],"token":null}"#;
+
+        fn retrying(retry_on_empty: Option<Duration>) -> DefaultClient {
+            DefaultClient::with_options(
+                Account::new("2300000101", ""),
+                ClientOptions {
+                    retry_on_empty,
+                    ..Default::default()
+                },
+            )
+        }
+
+        #[tokio::test]
+        async fn empty_or_refused() {
+            let server = serve("POST", "cj_xh", r#"{"status":1,"message":null}"#).await;
+            server.route(
+                "GET",
+                "/api/xqall",
+                TestResponse::json(r#"{"status":1,"message":null}"#),
+            );
+            let app = app(&server).await;
+            assert!(app.get_grades().await.unwrap().message.is_empty());
+            assert!(app.terms().await.unwrap().message.is_empty());
+
+            let server = serve("POST", "cj_xh", r#"{"status":500,"message":"系统繁忙"}"#).await;
+            let error = app_with(DefaultClient::user("2300000101"), &server)
+                .await
+                .get_grades()
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains("系统繁忙"));
+        }
+
+        #[tokio::test]
+        async fn retry_once_on_empty() {
+            let server = serve_sequence("GET", "xqall", &[EMPTY, TERMS]).await;
+            let app = app_with(retrying(Some(Duration::from_millis(10))), &server).await;
+            assert_eq!(app.terms().await.unwrap().message[0].term, "25-26-1");
+            assert_eq!(server.requests().len(), 2);
+
+            // Still empty after the retry: take it
+            let server = serve("GET", "xqall", EMPTY).await;
+            let app = app_with(retrying(Some(Duration::from_millis(10))), &server).await;
+            assert!(app.terms().await.unwrap().message.is_empty());
+            assert_eq!(server.requests().len(), 2);
+
+            // Off, or an endpoint that may be empty
+            let server = serve_sequence("GET", "xqall", &[EMPTY, TERMS]).await;
+            let app = app_with(retrying(None), &server).await;
+            assert!(app.terms().await.unwrap().message.is_empty());
+            assert_eq!(server.requests().len(), 1);
+
+            // Off by default, on for the one endpoint
+            let server = serve_sequence("GET", "xqall", &[EMPTY, TERMS]).await;
+            let app = app_with(DefaultClient::user("2300000101"), &server).await;
+            assert!(app.terms().await.unwrap().message.is_empty());
+            assert_eq!(server.requests().len(), 1);
+
+            let server = serve_sequence("GET", "xqall", &[EMPTY, TERMS]).await;
+            let mut options = ClientOptions::default();
+            options.endpoints.insert(
+                "xqall".into(),
+                EndpointOptions {
+                    retry_on_empty: Some(Some(Duration::from_millis(10))),
+                    ..Default::default()
+                },
+            );
+            let client = DefaultClient::with_options(Account::new("2300000101", ""), options);
+            let app = app_with(client, &server).await;
+            assert_eq!(app.terms().await.unwrap().message[0].term, "25-26-1");
+            assert_eq!(server.requests().len(), 2);
+
+            let server = serve_sequence("POST", "cj_xh_xfjd", &[EMPTY, TERMS]).await;
+            let app = app_with(retrying(Some(Duration::from_millis(10))), &server).await;
+            assert!(app.get_credits_and_rank().await.unwrap().message.is_empty());
+            assert_eq!(server.requests().len(), 1);
+        }
+
+        fn waiting(wait_rate_limit: Option<Duration>) -> DefaultClient {
+            DefaultClient::with_options(
+                Account::new("2300000101", ""),
+                ClientOptions {
+                    wait_rate_limit,
+                    ..Default::default()
+                },
+            )
+        }
+
+        #[tokio::test(start_paused = true)]
+        async fn rate_limited() {
+            let server = TestServer::start().await;
+            server.route(
+                "POST",
+                "/api/cj_xh",
+                TestResponse::json(include_str!("fixtures/cj_xh_detail.json")),
+            );
+            server.inject(FaultInjection::new().nth(1, Fault::RateLimited(1)));
+            let app = app_with(waiting(Some(Duration::from_secs(2))), &server).await;
+            let start = Instant::now();
+            assert_eq!(app.get_grades().await.unwrap().message.len(), 3);
+            assert!(start.elapsed() >= Duration::from_secs(1));
+            assert_eq!(server.requests_to("/api/cj_xh").len(), 2);
+
+            // Longer than the client waits, or not waiting at all
+            server.inject(FaultInjection::new().nth(3, Fault::RateLimited(60)));
+            let limited = |seconds| CczuError::RateLimited {
+                retry_after: Some(Duration::from_secs(seconds)),
+            };
+            let error = app.get_grades().await.unwrap_err();
+            assert_eq!(error.typed(), Some(&limited(60)));
+            server.inject(FaultInjection::new().nth(4, Fault::RateLimited(1)));
+            let app = app_with(waiting(None), &server).await;
+            let error = app.get_grades().await.unwrap_err();
+            assert_eq!(error.typed(), Some(&limited(1)));
+            assert_eq!(server.requests_to("/api/cj_xh").len(), 4);
+        }
+
+        #[tokio::test(start_paused = true)]
+        async fn empty_after_rate_limited() {
+            let server = TestServer::start().await;
+            server.route(
+                "POST",
+                "/api/cj_xh",
+                TestResponse::json(include_str!("fixtures/success_empty.json")),
+            );
+            server.inject(FaultInjection::new().nth(1, Fault::RateLimited(1)));
+            let options = ClientOptions {
+                wait_rate_limit: Some(Duration::from_secs(2)),
+                retry_on_empty: Some(Duration::from_millis(10)),
+                ..Default::default()
+            };
+            let client = DefaultClient::with_options(Account::new("2300000101", ""), options);
+            let app = app_with(client, &server).await;
+            assert!(app.get_grades().await.unwrap().message.is_empty());
+            // The answer after the wait is asked again once, like any empty one
+            assert_eq!(server.requests_to("/api/cj_xh").len(), 3);
+        }
+
+        #[tokio::test]
+        async fn block_page() {
+            let server = TestServer::start().await;
+            server.route(
+                "POST",
+                "/api/cj_xh",
+                TestResponse::html(include_str!("fixtures/rate_limited.html")),
+            );
+            let app = app_with(waiting(Some(Duration::from_secs(2))), &server).await;
+            let error = app.get_grades().await.unwrap_err();
+            assert_eq!(
+                error.typed(),
+                Some(&CczuError::RateLimited { retry_after: None })
+            );
+            // Nothing to wait for, not asked again
+            assert_eq!(server.requests().len(), 1);
         }
     }
 }
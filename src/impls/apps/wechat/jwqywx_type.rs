@@ -1,10 +1,150 @@
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::{
+    base::{
+        client::ParseMode,
+        typing::{other_error, typed_error, CczuError, TorErr},
+        units::{Credit, GradePoint, Score},
+    },
+    extension::instructor::Instructor,
+};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Message<T> {
     pub status: i32,
     pub message: Vec<T>,
     pub token: Option<String>,
+    /// What [`ParseMode::Lenient`] left out of this answer, a line each.
+    #[serde(skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// The parsed data next to the records of the portal it comes from, for the apps comparing
+/// the two before they stop reading the records themselves.
+#[derive(Debug, Serialize)]
+pub struct Detailed<T> {
+    pub parsed: T,
+    pub raw: Vec<Value>,
+    /// See [`Message::warnings`]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+impl<T: DeserializeOwned> Message<T> {
+    /// Parse the records one by one, in [`ParseMode::Lenient`] those not understood are
+    /// left out with a line in [`Message::warnings`].
+    pub fn parse(bytes: &[u8], mode: ParseMode) -> TorErr<Self> {
+        Message::from_slice(bytes)?.into_parsed(mode)
+    }
+}
+
+/// In the reason of an answer refusing an expired token, `{"status":401,"message":"token已过期，请重新登录"}`
+const TOKEN_EXPIRED: [&str; 5] = [
+    "token已过期",
+    "token失效",
+    "token无效",
+    "登录已过期",
+    "登录超时",
+];
+
+/// Whether `reason` refuses the token, and logging in again would do.
+fn token_expired(reason: &str) -> bool {
+    let reason = reason.to_lowercase();
+    TOKEN_EXPIRED.iter().any(|marker| reason.contains(marker))
+}
+
+/// The `status` of an answer to trust, `{"status":1,"message":[...]}`
+const SUCCESS: i32 = 1;
+
+/// The typed error of an answer with another `status` than [`SUCCESS`].
+fn refusal(status: i32, reason: String) -> CczuError {
+    match status {
+        _ if token_expired(&reason) => CczuError::TokenExpired,
+        401 => CczuError::TokenExpired,
+        403 => CczuError::Forbidden,
+        429 => CczuError::RateLimited { retry_after: None },
+        status => CczuError::Refused { status, reason },
+    }
+}
+
+/// A [`Message`] before its `message` is known to be records.
+#[derive(Deserialize)]
+struct Answer {
+    status: i32,
+    #[serde(default)]
+    message: Value,
+    token: Option<String>,
+}
+
+impl Message<Value> {
+    /// The records of an answer, empty when the portal sends `null` or no `message` at all.
+    ///
+    /// An answer with another `status` than `1` is a refusal whatever its `message`, even
+    /// `{"status":500,"message":[]}`: [`CczuError::TokenExpired`] for `401` or a reason
+    /// refusing the token, [`CczuError::Forbidden`] for `403`, [`CczuError::RateLimited`] for
+    /// `429` and [`CczuError::Refused`] otherwise. A `message` that is not a list is the
+    /// reason of a refusal too, an error, never an empty list. An answer cut off before its end
+    /// is [`CczuError::Truncated`], not records nor a refusal.
+    pub fn from_slice(bytes: &[u8]) -> TorErr<Self> {
+        let answer: Answer = serde_json::from_slice(bytes).map_err(|e| match e.is_eof() {
+            true => typed_error(CczuError::Truncated),
+            false => e.into(),
+        })?;
+        if answer.status != SUCCESS {
+            let reason = match answer.message {
+                Value::String(reason) => reason,
+                _ => String::new(),
+            };
+            return Err(typed_error(refusal(answer.status, reason)));
+        }
+        let message = match answer.message {
+            Value::Array(records) => records,
+            Value::Null => vec![],
+            Value::String(reason) if token_expired(&reason) => {
+                return Err(typed_error(CczuError::TokenExpired))
+            }
+            Value::String(reason) => {
+                return Err(other_error(format!(
+                    "Jwqywx answered {}: {reason}",
+                    answer.status
+                )))
+            }
+            other => {
+                return Err(other_error(format!(
+                    "Jwqywx answered {} without records: {other}",
+                    answer.status
+                )))
+            }
+        };
+        Ok(Message {
+            status: answer.status,
+            message,
+            token: answer.token,
+            warnings: vec![],
+        })
+    }
+
+    /// The records as `T`, see [`Message::parse`].
+    pub fn into_parsed<T: DeserializeOwned>(self, mode: ParseMode) -> TorErr<Message<T>> {
+        let mut warnings = self.warnings;
+        let mut message = Vec::with_capacity(self.message.len());
+        for (index, record) in self.message.into_iter().enumerate() {
+            match serde_json::from_value(record) {
+                Ok(record) => message.push(record),
+                Err(e) if mode == ParseMode::Lenient => {
+                    warnings.push(format!("Record {index} skipped: {e}"))
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(Message {
+            status: self.status,
+            message,
+            token: self.token,
+            warnings,
+        })
+    }
 }
 
 #[derive(Deserialize, Debug, Serialize)]
@@ -32,7 +172,7 @@ pub struct LoginUserData {
     pub id: String,
 }
 
-#[derive(Deserialize, Debug, Serialize)]
+#[derive(Deserialize, Debug, Clone, Serialize)]
 pub struct CourseGrade {
     #[serde(rename = "bh")]
     pub class_id: String,
@@ -55,9 +195,12 @@ pub struct CourseGrade {
     #[serde(rename = "xs")]
     pub course_hours: i32,
     #[serde(rename = "xf")]
-    pub course_credits: f32,
+    pub course_credits: Credit,
     #[serde(rename = "jsmc")]
     pub teacher_name: String,
+    /// 教师代码, in the order of [`Self::teacher_name`], only sent for some courses
+    #[serde(rename = "jsdm", default, skip_serializing_if = "Option::is_none")]
+    pub teacher_id: Option<String>,
     #[serde(rename = "ksxzm")]
     pub is_exam_type: i32,
     #[serde(rename = "ksxz")]
@@ -70,18 +213,157 @@ pub struct CourseGrade {
     //  pub end_exam_grade: f32,
     #[serde(rename = "kscj")]
     pub exam_grade: String,
+    /// 学分类别 (通识教育, 学科基础, 专业核心...), only sent for some students
+    #[serde(rename = "xflb", default)]
+    pub credit_category: Option<String>,
     #[serde(rename = "idn")]
     pub ident: i32,
     #[serde(rename = "cj")]
-    pub grade: f32,
+    pub grade: Score,
     #[serde(rename = "xfjd")]
-    pub grade_points: f32,
+    pub grade_points: GradePoint,
+    /// 成绩状态, final when the portal doesn't send it
+    #[serde(
+        rename = "cjzt",
+        default,
+        deserialize_with = "GradeStatus::from_column"
+    )]
+    pub status: GradeStatus,
+}
+
+impl CourseGrade {
+    /// The teachers of [`Self::teacher_name`], with their ids when the portal sent them.
+    pub fn instructors(&self) -> Vec<Instructor> {
+        Instructor::list(&self.teacher_name, self.teacher_id.as_deref())
+    }
+}
+
+/// Where a grade is in its entry: the teachers enter it (录入中) before it is checked and
+/// published, and it may still change until then.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GradeStatus {
+    /// Not entered yet (未录入), the score is not the grade
+    Pending,
+    /// Entered but not checked yet (录入中, 待审核...)
+    Provisional,
+    #[default]
+    Final,
+}
+
+impl GradeStatus {
+    const PENDING: [&'static str; 3] = ["未录入", "待录入", "Pending"];
+    const PROVISIONAL: [&'static str; 6] = [
+        "录入中",
+        "暂存",
+        "待审核",
+        "未审核",
+        "未发布",
+        "Provisional",
+    ];
+
+    /// The text of the status column, anything else (已发布, 已审核, an empty text...) is final.
+    pub fn from_label(label: &str) -> Self {
+        let label = label.trim();
+        if Self::PENDING.contains(&label) {
+            GradeStatus::Pending
+        } else if Self::PROVISIONAL.contains(&label) {
+            GradeStatus::Provisional
+        } else {
+            GradeStatus::Final
+        }
+    }
+
+    fn from_column<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let label: Option<String> = Option::deserialize(deserializer)?;
+        Ok(label.map_or(GradeStatus::Final, |label| Self::from_label(&label)))
+    }
+}
+
+/// The part of a `cj_xh` record with the components of the grade.
+///
+/// The components are numbers, numeric strings, empty strings or null depending on the course.
+#[derive(Deserialize, Debug)]
+pub struct GradeComponents {
+    #[serde(rename = "kcdm")]
+    pub course_id: String,
+    #[serde(rename = "xq")]
+    pub term: i32,
+    #[serde(rename = "cj")]
+    pub grade: Score,
+    #[serde(rename = "pscj", default)]
+    pub usual: Value,
+    #[serde(rename = "qzcj", default)]
+    pub midterm: Value,
+    #[serde(rename = "qmcj", default)]
+    pub final_exam: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GradeDetail {
+    pub usual: Option<f32>,
+    pub midterm: Option<f32>,
+    #[serde(rename = "final")]
+    pub final_exam: Option<f32>,
+    /// Inferred from the grade when it only has the usual and the final parts
+    pub weights: Option<GradeWeights>,
+}
+
+/// Shares of the grade, summing to 1.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GradeWeights {
+    pub usual: f32,
+    pub midterm: f32,
+    #[serde(rename = "final")]
+    pub final_exam: f32,
+}
+
+fn component(value: &Value) -> Option<f32> {
+    match value {
+        Value::Number(number) => number.as_f64().map(|number| number as f32),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+impl GradeComponents {
+    /// `None` when the portal shows no component for this course.
+    pub fn detail(&self) -> Option<GradeDetail> {
+        let usual = component(&self.usual);
+        let midterm = component(&self.midterm);
+        let final_exam = component(&self.final_exam);
+        if usual.is_none() && midterm.is_none() && final_exam.is_none() {
+            return None;
+        }
+
+        // grade = w * usual + (1 - w) * final, rounded to 5% as the grades are rounded
+        let weights = match (usual, midterm, final_exam) {
+            (Some(usual), None, Some(final_exam)) if usual != final_exam => {
+                let weight = ((final_exam - self.grade.value()) / (final_exam - usual) * 20.0)
+                    .round()
+                    / 20.0;
+                (0.0..=1.0).contains(&weight).then_some(GradeWeights {
+                    usual: weight,
+                    midterm: 0.0,
+                    final_exam: 1.0 - weight,
+                })
+            }
+            _ => None,
+        };
+
+        Some(GradeDetail {
+            usual,
+            midterm,
+            final_exam,
+            weights,
+        })
+    }
 }
 
 #[derive(Deserialize, Debug, Serialize)]
 pub struct StudentPoint {
-    // #[serde(rename = "nj")]
-    // pub grade: String,
+    /// Year of enrollment, like `2023`, not given to every account
+    #[serde(rename = "nj", default)]
+    pub enrollment_year: Option<String>,
     #[serde(rename = "bh")]
     pub class_id: String,
     #[serde(rename = "bj")]
@@ -99,7 +381,7 @@ pub struct StudentPoint {
     #[serde(rename = "xsid")]
     pub student_xid: String,
     #[serde(rename = "pjxfjd")]
-    pub grade_points: f32,
+    pub grade_points: GradePoint,
     // #[serde(rename = "pm")]
     // pub rank: String,
     // #[serde(rename = "zypm")]
@@ -120,6 +402,95 @@ pub struct StudentPoint {
 pub struct Term {
     #[serde(rename = "xq")]
     pub term: String,
+    /// Set by [`super::jwqywx::JwqywxApplication::terms`], the portal doesn't list it
+    #[serde(default)]
+    pub is_current: bool,
+}
+
+impl Term {
+    /// Year the school year of the term starts, `2024` for `24-25-1`.
+    pub fn start_year(&self) -> Option<u32> {
+        let year: u32 = self.term.split('-').next()?.parse().ok()?;
+        Some(2000 + year)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value;
+
+    use super::{GradeComponents, GradeWeights, Message};
+    use crate::base::typing::{CczuError, TypedErrorExt};
+
+    #[test]
+    fn status_checked_before_records() {
+        let empty = Message::from_slice(include_bytes!("fixtures/success_empty.json")).unwrap();
+        assert!(empty.message.is_empty());
+
+        // As empty, but refused
+        let error = Message::<Value>::from_slice(include_bytes!("fixtures/refused_empty.json"))
+            .unwrap_err();
+        assert_eq!(
+            error.typed(),
+            Some(&CczuError::Refused {
+                status: 500,
+                reason: String::new()
+            })
+        );
+        let error = Message::from_slice(br#"{"status":0,"message":null}"#).unwrap_err();
+        assert!(matches!(
+            error.typed(),
+            Some(CczuError::Refused { status: 0, .. })
+        ));
+        let error = Message::from_slice(include_bytes!("fixtures/kb_forbidden.json")).unwrap_err();
+        assert_eq!(error.typed(), Some(&CczuError::Forbidden));
+
+        // Cut off in its records, or before any
+        let records = include_bytes!("fixtures/cj_xh_detail.json");
+        for cut in [&records[..records.len() / 2], &b""[..]] {
+            let error = Message::<Value>::from_slice(cut).unwrap_err();
+            assert_eq!(error.typed(), Some(&CczuError::Truncated));
+        }
+        let error = Message::<Value>::from_slice(b"<html>").unwrap_err();
+        assert_eq!(error.typed(), None);
+    }
+
+    #[test]
+    fn grade_detail() {
+        let message: Message<GradeComponents> =
+            serde_json::from_str(include_str!("fixtures/cj_xh_detail.json")).unwrap();
+        let details: Vec<_> = message
+            .message
+            .iter()
+            .map(|record| record.detail())
+            .collect();
+
+        let linear_algebra = details[0].clone().unwrap();
+        assert_eq!(
+            (
+                linear_algebra.usual,
+                linear_algebra.midterm,
+                linear_algebra.final_exam
+            ),
+            (Some(90.0), None, Some(80.0))
+        );
+        assert_eq!(
+            linear_algebra.weights,
+            Some(GradeWeights {
+                usual: 0.3,
+                midterm: 0.0,
+                final_exam: 0.7
+            })
+        );
+
+        // Three parts, the weights can't be told from the grade alone
+        let data_structures = details[1].clone().unwrap();
+        assert_eq!(data_structures.midterm, Some(70.0));
+        assert_eq!(data_structures.final_exam, Some(74.0));
+        assert_eq!(data_structures.weights, None);
+
+        assert_eq!(details[2], None);
+    }
 }
 
 #[cfg(feature = "calendar")]
@@ -145,10 +516,28 @@ pub mod calendar {
         pub fields: HashMap<String, Value>,
     }
 
-    impl Into<Vec<RawCourse>> for SerdeRowCourses {
-        fn into(self) -> Vec<RawCourse> {
+    impl SerdeRowCourses {
+        /// Course columns past `kc7`, there is no day for them.
+        pub fn unknown_columns(&self) -> Vec<&str> {
+            let mut columns: Vec<&str> = self
+                .fields
+                .keys()
+                .filter(|key| {
+                    key.strip_prefix("kc")
+                        .and_then(|day| day.parse::<usize>().ok())
+                        .is_some_and(|day| !(1..=7).contains(&day))
+                })
+                .map(String::as_str)
+                .collect();
+            columns.sort();
+            columns
+        }
+    }
+
+    impl From<SerdeRowCourses> for Vec<RawCourse> {
+        fn from(val: SerdeRowCourses) -> Self {
             let courses = (1..=7).map(|index| {
-                let value = self.fields.get(&format!("kc{index}"));
+                let value = val.fields.get(&format!("kc{index}"));
                 if let Some(Value::String(course)) = value {
                     return course.clone();
                 }
@@ -157,45 +546,54 @@ pub mod calendar {
             });
 
             let mut teachers = HashMap::new();
+            let mut teacher_ids = HashMap::new();
 
             for index in 1..=20 {
-                let name = self.fields.get(&format!("kcmc{index}"));
+                let name = val.fields.get(&format!("kcmc{index}"));
                 if let Some(Value::String(name)) = name {
-                    if let Some(Value::String(teacher)) = self.fields.get(&format!("skjs{index}")) {
+                    if let Some(Value::String(teacher)) = val.fields.get(&format!("skjs{index}")) {
                         teachers.insert(name.clone(), teacher.clone());
                     }
+                    if let Some(Value::String(id)) = val.fields.get(&format!("jsdm{index}")) {
+                        teacher_ids.insert(name.clone(), id.clone());
+                    }
                 }
             }
             courses
                 .map(|course| {
-                    let teacher = course
+                    let found = |found: &HashMap<String, String>, single: &str| {
+                        found
+                            .get(single.split(" ").next().unwrap_or(""))
+                            .cloned()
+                            .unwrap_or(String::new())
+                    };
+                    // The ids go with the teachers kept, in the same places
+                    let singles: Vec<(String, String)> = course
                         .split("/")
-                        .map(|single| {
-                            teachers
-                                .get(
-                                    single
-                                        .split(" ")
-                                        .collect::<Vec<&str>>()
-                                        .first()
-                                        .cloned()
-                                        .unwrap_or(""),
-                                )
-                                .cloned()
-                                .unwrap_or(String::new())
-                        })
-                        .reduce(|a, b| {
-                            if b.is_empty() {
-                                return a;
-                            }
-                            return format!("{},/{}", a, b);
-                        })
-                        .unwrap_or(String::new());
-
-                    RawCourse { course, teacher }
+                        .map(|single| (found(&teachers, single), found(&teacher_ids, single)))
+                        .enumerate()
+                        .filter(|(index, (teacher, _))| *index == 0 || !teacher.is_empty())
+                        .map(|(_, single)| single)
+                        .collect();
+                    let join = |part: fn(&(String, String)) -> &str| {
+                        singles.iter().map(part).collect::<Vec<&str>>().join(",/")
+                    };
+                    let teacher = join(|(teacher, _)| teacher);
+                    let teacher_id = if singles.iter().all(|(_, id)| id.is_empty()) {
+                        String::new()
+                    } else {
+                        join(|(_, id)| id)
+                    };
+
+                    RawCourse {
+                        course,
+                        teacher,
+                        teacher_id,
+                    }
                 })
                 .collect()
         }
     }
 }
 #[cfg(feature = "calendar")]
-pub use calendar::SerdeRowCourses;
\ No newline at end of file
+pub use calendar::SerdeRowCourses;
@@ -1,17 +1,219 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex, PoisonError},
+};
 
-use reqwest::redirect::Policy;
-use reqwest_cookie_store::CookieStoreMutex;
+use cookie_store::{Cookie, RawCookie};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, ACCEPT_LANGUAGE},
+    redirect::Policy,
+    Url,
+};
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use tokio::sync::RwLock;
 
-use crate::base::client::{Account, Client, Property};
+use crate::{
+    base::{
+        cache::ResponseCache,
+        client::{Account, Campus, Client, ClientOptions, Property},
+        cookies,
+        metrics::Metrics,
+        typing::{other_error, EmptyOrErr, TorErr},
+    },
+    impls::login::sso_type::SSOLoginConnectType,
+    internals::fields::ROOT_VPN_URL,
+    utils::keep_alive::KeepAliveTask,
+};
 
+/// Cookies left out of an exported session unless the policy says otherwise.
+///
+/// `CASTGC` is the SSO ticket granting cookie: whoever holds it can log in as the account
+/// to any service. The short lived cookies of the services (`JSESSIONID`, WebVPN's session...)
+/// are kept, a rebuilt client without `CASTGC` logs in again with its account when needed.
+pub const SENSITIVE_COOKIES: [&str; 1] = ["CASTGC"];
+
+/// Which cookies [`DefaultClient::export_session`] writes out.
+#[derive(Debug, Clone)]
+pub struct SessionPolicy {
+    /// Cookie names never exported, [`SENSITIVE_COOKIES`] by default
+    pub exclude: Vec<String>,
+    /// Only export the cookies with an expiry, dropping the browser session ones
+    pub persistent_only: bool,
+}
+
+impl Default for SessionPolicy {
+    fn default() -> Self {
+        Self {
+            exclude: SENSITIVE_COOKIES.map(String::from).to_vec(),
+            persistent_only: false,
+        }
+    }
+}
+
+impl SessionPolicy {
+    /// Export every cookie, tickets included.
+    pub fn everything() -> Self {
+        Self {
+            exclude: vec![],
+            persistent_only: false,
+        }
+    }
+}
+
+/// The [`Client`] of the crate, cheap to clone: the clones share the cookies, the properties
+/// and the options.
+///
+/// The futures of its async methods, of the login and service traits and of the apps visited
+/// with it are `Send`, they can be given to `tokio::spawn` on a multi-thread runtime. Generic
+/// code over `C: Client` doesn't get this: the traits don't promise it, only `DefaultClient`
+/// is checked.
 #[derive(Debug, Clone)]
 pub struct DefaultClient {
     account: Account,
     client: reqwest::Client,
     cookies: Arc<CookieStoreMutex>,
     properties: Arc<RwLock<HashMap<&'static str, Property>>>,
+    /// Shared by the clones, like the properties: the apps already visited see a new campus
+    options: Arc<Mutex<ClientOptions>>,
+    cache: Option<Arc<ResponseCache>>,
+    metrics: Option<Arc<dyn Metrics>>,
+    #[cfg(any(test, feature = "record-fixtures"))]
+    pub(crate) recorder: Option<Arc<crate::utils::record::FixtureRecorder>>,
+    store: Option<Arc<dyn cookies::CookieStore>>,
+    /// Shared by the clones, so there is one keep alive task per client
+    pub(crate) keep_alive: Arc<Mutex<Option<KeepAliveTask>>>,
+    /// Held by the logins of the FFI and of the keep alive task, two SSO handshakes of one
+    /// client at once would mix their cookies
+    pub(crate) login: Arc<tokio::sync::Mutex<()>>,
+}
+
+/// Cookie provider of the clients: keeps the jar clean over a long session, and with a
+/// [`cookies::CookieStore`] saves the cookies every time a response sets some.
+///
+/// Logging in again and again leaves the same cookie under two domains, set once with a
+/// `Domain` and once without: both are sent, and some portals answer an oversized `Cookie`
+/// with a 431. The cookie just set replaces the others of its name and path sent to its url.
+struct ClientCookies {
+    cookies: Arc<CookieStoreMutex>,
+    store: Option<Arc<dyn cookies::CookieStore>>,
+}
+
+impl reqwest::cookie::CookieStore for ClientCookies {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let headers: Vec<&HeaderValue> = cookie_headers.collect();
+        if let Ok(mut jar) = self.cookies.lock() {
+            drop_replaced(&mut jar, &headers, url);
+        }
+        self.cookies.set_cookies(&mut headers.into_iter(), url);
+        let Some(store) = &self.store else {
+            return;
+        };
+        // Not the failure of the request, `DefaultClient::save_cookies` reports it
+        if let Ok(cookies) = export_cookies(&self.cookies, &SessionPolicy::everything()) {
+            let _ = store.save(&cookies);
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        if let Ok(mut jar) = self.cookies.lock() {
+            drop_expired(&mut jar);
+        }
+        self.cookies.cookies(url)
+    }
+}
+
+/// Remove the cookies the `Set-Cookie` `headers` of `url` replace, see [`ClientCookies`].
+fn drop_replaced(jar: &mut CookieStore, headers: &[&HeaderValue], url: &Url) {
+    for header in headers {
+        let Some(cookie) = header
+            .to_str()
+            .ok()
+            .and_then(|header| Cookie::parse(header, url).ok())
+        else {
+            continue;
+        };
+        let (domain, path) = (String::from(&cookie.domain), String::from(&cookie.path));
+        let replaced: Vec<String> = jar
+            .iter_any()
+            .filter(|old| old.name() == cookie.name() && String::from(&old.path) == path)
+            .filter(|old| old.domain.matches(url) && String::from(&old.domain) != domain)
+            .map(|old| String::from(&old.domain))
+            .collect();
+        for old in replaced {
+            jar.remove(&old, &path, cookie.name());
+        }
+    }
+}
+
+fn drop_expired(jar: &mut CookieStore) {
+    let expired: Vec<(String, String, String)> = jar
+        .iter_any()
+        .filter(|cookie| cookie.is_expired())
+        .map(|cookie| {
+            (
+                String::from(&cookie.domain),
+                String::from(&cookie.path),
+                cookie.name().to_owned(),
+            )
+        })
+        .collect();
+    for (domain, path, name) in expired {
+        jar.remove(&domain, &path, &name);
+    }
+}
+
+/// Version of the exported sessions written by this build.
+///
+/// The sessions exported before it are a bare list of cookies, they are imported as version 0.
+pub const SESSION_VERSION: u32 = 1;
+
+/// An exported session, its cookies sorted so the same cookies always give the same text.
+#[derive(Serialize, Deserialize)]
+struct ExportedSession {
+    version: u32,
+    cookies: Vec<Cookie<'static>>,
+}
+
+fn export_cookies(cookies: &CookieStoreMutex, policy: &SessionPolicy) -> TorErr<String> {
+    let store = cookies.lock().map_err(|e| other_error(e.to_string()))?;
+    let mut kept: Vec<Cookie<'static>> = store
+        .iter_unexpired()
+        .filter(|cookie| !policy.exclude.iter().any(|name| name == cookie.name()))
+        .filter(|cookie| !policy.persistent_only || cookie.is_persistent())
+        .cloned()
+        .collect();
+    kept.sort_by(|a, b| (&a.domain, &a.path, a.name()).cmp(&(&b.domain, &b.path, b.name())));
+
+    Ok(serde_json::to_string_pretty(&ExportedSession {
+        version: SESSION_VERSION,
+        cookies: kept,
+    })?)
+}
+
+/// The cookies of a session of [`export_cookies`], of any version up to [`SESSION_VERSION`].
+fn import_cookies(session: &str) -> TorErr<CookieStore> {
+    let cookies = match serde_json::from_str::<Value>(session)? {
+        Value::Array(_) => serde_json::from_str(session)?,
+        Value::Object(fields) => {
+            let version = fields.get("version").and_then(Value::as_u64);
+            match version {
+                Some(version) if version <= SESSION_VERSION as u64 => {
+                    serde_json::from_str::<ExportedSession>(session)?.cookies
+                }
+                Some(version) => {
+                    return Err(other_error(format!(
+                        "Session of version {version}, this build imports up to version {SESSION_VERSION}"
+                    )))
+                }
+                None => return Err(other_error("Session without a version")),
+            }
+        }
+        _ => return Err(other_error("Not an exported session")),
+    };
+    Ok(CookieStore::from_cookies(cookies.into_iter().map(Ok::<_, Infallible>), true).unwrap())
 }
 
 impl Default for DefaultClient {
@@ -22,16 +224,107 @@ impl Default for DefaultClient {
 
 impl DefaultClient {
     pub fn new(account: Account) -> Self {
-        let cookies = Arc::new(CookieStoreMutex::default());
+        Self::with_options(account, ClientOptions::default())
+    }
+
+    pub fn with_options(account: Account, options: ClientOptions) -> Self {
+        Self::with_cookies(account, options, CookieStore::default(), None)
+    }
+
+    /// A client keeping its cookies in `store`, starting with the cookies saved there.
+    pub fn with_cookie_store(
+        account: Account,
+        options: ClientOptions,
+        store: impl cookies::CookieStore + 'static,
+    ) -> TorErr<Self> {
+        let cookies = match store.load()? {
+            Some(saved) => import_cookies(&saved)?,
+            None => CookieStore::default(),
+        };
+        Ok(Self::with_cookies(
+            account,
+            options,
+            cookies,
+            Some(Arc::new(store)),
+        ))
+    }
+
+    fn with_cookies(
+        account: Account,
+        options: ClientOptions,
+        cookies: CookieStore,
+        store: Option<Arc<dyn cookies::CookieStore>>,
+    ) -> Self {
+        let cookies = Arc::new(CookieStoreMutex::new(cookies));
+        let mut headers = HeaderMap::new();
+        if let Ok(language) = HeaderValue::from_str(&options.accept_language) {
+            headers.insert(ACCEPT_LANGUAGE, language);
+        }
+        let mut builder = reqwest::Client::builder()
+            .default_headers(headers)
+            .redirect(Policy::none());
+        if let Some(proxy) = options.proxy.clone() {
+            builder = builder.proxy(proxy);
+        }
+        if let Some(timeout) = options.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let builder = builder.cookie_provider(Arc::new(ClientCookies {
+            cookies: cookies.clone(),
+            store: store.clone(),
+        }));
         Self {
             account,
-            client: reqwest::Client::builder()
-                .cookie_provider(cookies.clone())
-                .redirect(Policy::none())
-                .build()
-                .unwrap(),
+            client: builder.build().unwrap(),
             cookies,
             properties: Arc::new(RwLock::new(HashMap::new())),
+            options: Arc::new(Mutex::new(options)),
+            cache: None,
+            metrics: None,
+            #[cfg(any(test, feature = "record-fixtures"))]
+            recorder: None,
+            store,
+            keep_alive: Arc::default(),
+            login: Arc::default(),
+        }
+    }
+
+    /// A client sending its requests through `client`, built by the app with its own TLS,
+    /// proxy or middleware.
+    ///
+    /// The logins keep their tickets in `cookies`, so `client` must be built with
+    /// `cookie_provider(cookies.clone())`, or they succeed and the next request is
+    /// logged out. The jar is used as is, without the cleaning of the other constructors.
+    /// It must not follow redirects either (`redirect(Policy::none())`): the logins follow
+    /// them one by one to pick up the tickets. The `proxy`, `timeout` and `accept_language`
+    /// of `options` are up to `client`.
+    pub fn with_http_client(
+        account: Account,
+        options: ClientOptions,
+        client: reqwest::Client,
+        cookies: Arc<CookieStoreMutex>,
+    ) -> Self {
+        Self {
+            account,
+            client,
+            cookies,
+            properties: Arc::new(RwLock::new(HashMap::new())),
+            options: Arc::new(Mutex::new(options)),
+            cache: None,
+            metrics: None,
+            #[cfg(any(test, feature = "record-fixtures"))]
+            recorder: None,
+            store: None,
+            keep_alive: Arc::default(),
+            login: Arc::default(),
+        }
+    }
+
+    /// A clone that doesn't share the keep alive task.
+    pub(crate) fn detached(&self) -> Self {
+        Self {
+            keep_alive: Arc::default(),
+            ..self.clone()
         }
     }
 
@@ -46,6 +339,110 @@ impl DefaultClient {
     pub fn iccard(card: impl Into<String>) -> Self {
         Self::new(Account::new(card, ""))
     }
+
+    pub fn with_campus(self, campus: Campus) -> Self {
+        self.set_campus(Some(campus));
+        self
+    }
+
+    /// Campus of this client and its clones, can be changed while requests are running.
+    pub fn set_campus(&self, campus: Option<Campus>) {
+        self.options
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .campus = campus;
+    }
+
+    /// Change the other options of this client and its clones the same way. The reqwest
+    /// client keeps the `proxy`, `timeout` and `accept_language` it was built with.
+    pub fn update_options(&self, update: impl FnOnce(&mut ClientOptions)) {
+        update(&mut self.options.lock().unwrap_or_else(PoisonError::into_inner));
+    }
+
+    /// Keep the answers of the read requests in `cache`, shared by the clones of this client.
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Report every request of this client and its clones to `metrics`.
+    pub fn with_metrics(mut self, metrics: impl Metrics + 'static) -> Self {
+        self.metrics = Some(Arc::new(metrics));
+        self
+    }
+
+    /// Cookies of this client as JSON, to be rebuilt with [`Self::import_session`].
+    pub fn export_session(&self, policy: &SessionPolicy) -> TorErr<String> {
+        export_cookies(&self.cookies, policy)
+    }
+
+    /// Save the cookies to the store of [`Self::with_cookie_store`] now.
+    ///
+    /// They are saved after every response setting cookies already, this is for the cookies
+    /// added by hand and to learn about a failing store.
+    pub fn save_cookies(&self) -> EmptyOrErr {
+        match &self.store {
+            Some(store) => store.save(&export_cookies(
+                &self.cookies,
+                &SessionPolicy::everything(),
+            )?),
+            None => Ok(()),
+        }
+    }
+
+    /// A client with the account and the cookies of an exported session.
+    ///
+    /// Sessions of an older version are read as they were written, a newer one is an error.
+    pub fn import_session(account: Account, session: &str) -> TorErr<Self> {
+        Self::import_session_with_options(account, ClientOptions::default(), session)
+    }
+
+    pub fn import_session_with_options(
+        account: Account,
+        options: ClientOptions,
+        session: &str,
+    ) -> TorErr<Self> {
+        Ok(Self::with_cookies(
+            account,
+            options,
+            import_cookies(session)?,
+            None,
+        ))
+    }
+
+    /// A client logged in elsewhere, by a WebView..., with its `(url, name, value)` cookies.
+    ///
+    /// Each cookie is sent back to the host of its url, under any path. The client is taken as
+    /// logged in through WebVPN when one of the urls is WebVPN's, on campus otherwise, so the
+    /// apps can be visited without a login. Nothing is checked before their first request: a
+    /// session gone by then fails it like any expired session.
+    pub fn from_cookies(cookies: Vec<(String, String, String)>) -> TorErr<Self> {
+        Self::from_cookies_with_options(Account::default(), ClientOptions::default(), cookies)
+    }
+
+    pub fn from_cookies_with_options(
+        account: Account,
+        options: ClientOptions,
+        cookies: Vec<(String, String, String)>,
+    ) -> TorErr<Self> {
+        let mut jar = CookieStore::default();
+        let mut connect = SSOLoginConnectType::COMMON;
+        for (url, name, value) in cookies {
+            let url = Url::parse(&url).map_err(|e| other_error(format!("{url}: {e}")))?;
+            if url.host_str() == ROOT_VPN_URL.host_str() {
+                connect = SSOLoginConnectType::WEBVPN;
+            }
+            let cookie = RawCookie::build((name, value)).path("/").build();
+            jar.insert_raw(&cookie, &url).map_err(other_error)?;
+        }
+
+        let client = Self::with_cookies(account, options, jar, None);
+        let properties = HashMap::from([(SSOLoginConnectType::key(), connect.into())]);
+        Ok(Self {
+            properties: Arc::new(RwLock::new(properties)),
+            ..client
+        })
+    }
 }
 
 impl Client for DefaultClient {
@@ -64,4 +461,273 @@ impl Client for DefaultClient {
     fn properties(&self) -> Arc<RwLock<HashMap<&'static str, Property>>> {
         self.properties.clone()
     }
+
+    fn options(&self) -> ClientOptions {
+        self.options
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn cache(&self) -> Option<Arc<ResponseCache>> {
+        self.cache.clone()
+    }
+
+    fn metrics(&self) -> Option<Arc<dyn Metrics>> {
+        self.metrics.clone()
+    }
+
+    #[cfg(any(test, feature = "record-fixtures"))]
+    fn fixture_recorder(&self) -> Option<Arc<crate::utils::record::FixtureRecorder>> {
+        self.recorder.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use reqwest::{
+        header::{HeaderMap, HeaderValue},
+        redirect::Policy,
+        Url,
+    };
+    use reqwest_cookie_store::CookieStoreMutex;
+
+    use super::{ClientCookies, DefaultClient, SessionPolicy, SESSION_VERSION};
+    use crate::{
+        base::client::{Account, Client, ClientOptions},
+        utils::testing::{RecordedRequest, TestResponse, TestServer},
+    };
+
+    fn logged_in() -> DefaultClient {
+        let client = DefaultClient::account("2300000101", "password");
+        let url = Url::parse("http://sso.cczu.edu.cn/sso/login").unwrap();
+        let cookies = client.cookies();
+        let mut store = cookies.lock().unwrap();
+        for cookie in [
+            "CASTGC=TGT-1-abc; Path=/sso",
+            "JSESSIONID=F00D; Path=/",
+            "remember=1; Path=/; Max-Age=86400",
+        ] {
+            store.parse(cookie, &url).unwrap();
+        }
+        drop(store);
+        client
+    }
+
+    fn names(client: &DefaultClient) -> Vec<String> {
+        let mut names: Vec<String> = client
+            .cookies()
+            .lock()
+            .unwrap()
+            .iter_unexpired()
+            .map(|cookie| cookie.name().to_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn duplicate_cookies() {
+        use reqwest::cookie::CookieStore;
+
+        let url = Url::parse("http://sso.cczu.edu.cn/sso/login").unwrap();
+        let cookies = Arc::new(CookieStoreMutex::default());
+        let mut store = cookies.lock().unwrap();
+        store
+            .parse("JSESSIONID=FIRST; Domain=cczu.edu.cn; Path=/", &url)
+            .unwrap();
+        store.parse("JSESSIONID=SECOND; Path=/", &url).unwrap();
+        store.parse("JSESSIONID=OTHER; Path=/sso", &url).unwrap();
+        drop(store);
+
+        let provider = ClientCookies {
+            cookies: cookies.clone(),
+            store: None,
+        };
+        let fresh = HeaderValue::from_static("JSESSIONID=LATEST; Domain=cczu.edu.cn; Path=/");
+        provider.set_cookies(&mut [&fresh].into_iter(), &url);
+
+        let header = provider.cookies(&url).unwrap();
+        let mut sent: Vec<&str> = header.to_str().unwrap().split("; ").collect();
+        sent.sort();
+        assert_eq!(sent, ["JSESSIONID=LATEST", "JSESSIONID=OTHER"]);
+        // Another path is another cookie
+        assert_eq!(cookies.lock().unwrap().iter_any().count(), 2);
+    }
+
+    #[test]
+    fn export_excludes_tickets() {
+        let client = logged_in();
+
+        let session = client.export_session(&SessionPolicy::default()).unwrap();
+        assert!(!session.contains("CASTGC"));
+        assert!(!session.contains("TGT-1-abc"));
+
+        let rebuilt =
+            DefaultClient::import_session(Account::new("2300000101", "password"), &session)
+                .unwrap();
+        assert_eq!(names(&rebuilt), ["JSESSIONID", "remember"]);
+        assert_eq!(rebuilt.account().user, "2300000101");
+
+        let session = client
+            .export_session(&SessionPolicy {
+                persistent_only: true,
+                ..Default::default()
+            })
+            .unwrap();
+        let rebuilt = DefaultClient::import_session(Account::default(), &session).unwrap();
+        assert_eq!(names(&rebuilt), ["remember"]);
+
+        let session = client.export_session(&SessionPolicy::everything()).unwrap();
+        let rebuilt = DefaultClient::import_session(Account::default(), &session).unwrap();
+        assert_eq!(names(&rebuilt), ["CASTGC", "JSESSIONID", "remember"]);
+    }
+
+    #[test]
+    fn session_versions() {
+        let client = logged_in();
+        let session = client.export_session(&SessionPolicy::everything()).unwrap();
+        let exported: serde_json::Value = serde_json::from_str(&session).unwrap();
+        assert_eq!(exported["version"], SESSION_VERSION);
+
+        // The same cookies added in another order
+        let url = Url::parse("http://sso.cczu.edu.cn/sso/login").unwrap();
+        let again = DefaultClient::default();
+        for cookie in [
+            "remember=1; Path=/; Max-Age=86400",
+            "JSESSIONID=F00D; Path=/",
+            "CASTGC=TGT-1-abc; Path=/sso",
+        ] {
+            again.cookies().lock().unwrap().parse(cookie, &url).unwrap();
+        }
+        assert_eq!(
+            again.export_session(&SessionPolicy::everything()).unwrap(),
+            session
+        );
+
+        // Exported before the version, a bare list of cookies
+        let mut legacy = vec![];
+        cookie_store::serde::json::save_incl_expired_and_nonpersistent(
+            &client.cookies().lock().unwrap(),
+            &mut legacy,
+        )
+        .unwrap();
+        let rebuilt =
+            DefaultClient::import_session(Account::default(), &String::from_utf8(legacy).unwrap())
+                .unwrap();
+        assert_eq!(names(&rebuilt), ["CASTGC", "JSESSIONID", "remember"]);
+
+        let newer = session.replacen(
+            &format!("\"version\": {SESSION_VERSION}"),
+            &format!("\"version\": {}", SESSION_VERSION + 1),
+            1,
+        );
+        let error = DefaultClient::import_session(Account::default(), &newer).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Session of version 2, this build imports up to version 1"
+        );
+    }
+
+    /// The request `client` sends to `server`.
+    async fn sent_to(client: &DefaultClient, server: &TestServer) -> RecordedRequest {
+        client
+            .reqwest_client()
+            .get(server.url("/"))
+            .send()
+            .await
+            .unwrap();
+        server.requests().pop().unwrap()
+    }
+
+    async fn sent(client: &DefaultClient) -> RecordedRequest {
+        sent_to(client, &TestServer::start().await).await
+    }
+
+    #[tokio::test]
+    async fn accept_language() {
+        let request = sent(&DefaultClient::default()).await;
+        assert_eq!(request.header("Accept-Language"), Some("zh-CN"));
+
+        let client = DefaultClient::with_options(
+            Account::default(),
+            ClientOptions {
+                accept_language: "en-US".into(),
+                ..Default::default()
+            },
+        );
+        let request = sent(&client).await;
+        assert_eq!(request.header("Accept-Language"), Some("en-US"));
+    }
+
+    #[tokio::test]
+    async fn http_client() {
+        let cookies = Arc::new(CookieStoreMutex::default());
+        let mut headers = HeaderMap::new();
+        headers.insert("x-app", HeaderValue::from_static("demo"));
+        let http = reqwest::Client::builder()
+            .default_headers(headers)
+            .redirect(Policy::none())
+            .cookie_provider(cookies.clone())
+            .build()
+            .unwrap();
+        let client = DefaultClient::with_http_client(
+            Account::default(),
+            ClientOptions::default(),
+            http,
+            cookies,
+        );
+
+        let url = Url::parse("http://127.0.0.1/").unwrap();
+        client
+            .cookies()
+            .lock()
+            .unwrap()
+            .parse("JSESSIONID=F00D; Path=/", &url)
+            .unwrap();
+        let request = sent(&client).await;
+        assert_eq!(request.header("x-app"), Some("demo"));
+        assert_eq!(request.header("Cookie"), Some("JSESSIONID=F00D"));
+    }
+
+    #[cfg(feature = "cookie-file")]
+    #[tokio::test]
+    async fn file_cookie_store() {
+        use crate::base::cookies::FileCookieStore;
+
+        let server = TestServer::start().await;
+        server.route(
+            "GET",
+            "/login",
+            TestResponse::new(200, "text/plain", "")
+                .header("Set-Cookie", "JSESSIONID=F00D; Path=/"),
+        );
+
+        let path = std::env::temp_dir().join(format!("cczuni-cookies-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let open = || {
+            DefaultClient::with_cookie_store(
+                Account::default(),
+                ClientOptions::default(),
+                FileCookieStore::new(&path),
+            )
+            .unwrap()
+        };
+
+        let client = open();
+        assert_eq!(sent_to(&client, &server).await.header("Cookie"), None);
+        client
+            .reqwest_client()
+            .get(server.url("/login"))
+            .send()
+            .await
+            .unwrap();
+        drop(client);
+
+        let request = sent_to(&open(), &server).await;
+        assert_eq!(request.header("Cookie"), Some("JSESSIONID=F00D"));
+        std::fs::remove_file(&path).unwrap();
+    }
 }
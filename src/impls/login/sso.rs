@@ -1,21 +1,60 @@
-use std::{collections::HashMap, future::Future};
+use std::{
+    collections::HashMap,
+    future::Future,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use crate::{
     base::{
-        client::Client,
-        typing::{other_error, TorErr},
+        client::{Client, Property, SSOVariant},
+        typing::{other_error, typed_error, CczuError, EmptyOrErr, TorErr},
     },
     internals::{
         cookies_io::CookiesIOExt,
         fields::{DEFAULT_HEADERS, ROOT_SSO_LOGIN, ROOT_VPN_URL},
-        recursion::recursion_redirect_handle,
+        recursion::{record_redirect, record_redirects, recursion_redirect_handle},
+        response::{buffer_response, read_json, read_text, MeasuredSend},
+        trace::traced,
     },
+    utils::text::element_text,
 };
 use base64::{prelude::BASE64_STANDARD, Engine};
-use reqwest::{cookie::Cookie, header::LOCATION, Response, StatusCode};
-use scraper::{Html, Selector};
+use reqwest::{cookie::Cookie, header::LOCATION, Response, StatusCode, Url};
+use scraper::{ElementRef, Html, Selector};
+use tracing::debug;
+
+#[cfg(any(test, feature = "testing"))]
+use super::sso_type::LoginFormDebug;
+use super::sso_type::{
+    Agreement, ElinkLoginInfo, JsonLoginAnswer, LoginState, RedirectHop, RoleSelection,
+    SSOLoginConnectType, SSORole, SSOUniversalLoginInfo, SecondFactorContext,
+};
+
+/// Inputs receiving the code on the second factor page
+const SECOND_FACTOR_INPUTS: [&str; 3] = ["dynamicCode", "smsCode", "phoneCode"];
+
+/// Inputs of the page forcing a password change
+const PASSWORD_CHANGE_INPUTS: [&str; 4] =
+    ["newPassword", "confirmPassword", "newPwd", "confirmPwd"];
+
+/// Told by SSO instead of the bad password message to an account not activated
+const NOT_ACTIVATED_MARKERS: [&str; 3] = ["未激活", "没有激活", "激活后再登录"];
+
+/// Radio inputs of the role selection page
+const ROLE_INPUTS: [&str; 3] = ["roleId", "role", "identity"];
+
+/// Hidden inputs or `<meta>` names of the CSRF token
+#[cfg(any(test, feature = "testing"))]
+const CSRF_NAMES: [&str; 4] = ["_csrf", "csrf_token", "csrfToken", "csrf-token"];
+
+/// `<meta>` of the login page of the newer SSO giving the url of its JSON login
+const JSON_LOGIN_META: &str = "sso-login-api";
+
+/// The JSON login next to the page, for [`SSOVariant::Json`] on a page declaring none
+const DEFAULT_JSON_LOGIN_API: &str = "api/login";
 
-use super::sso_type::{ElinkLoginInfo, SSOLoginConnectType, SSOUniversalLoginInfo};
+/// Checkbox or button of a terms-of-use or notice page to agree to
+const AGREEMENT_INPUTS: [&str; 4] = ["agree", "isAgree", "agreement", "accept"];
 
 pub trait SSOUniversalLogin {
     /// This method implements [`ROOT_SSO`] url login.
@@ -27,54 +66,246 @@ pub trait SSOUniversalLogin {
         &self,
         service: impl Into<String>,
     ) -> impl Future<Output = TorErr<Response>>;
+
+    /// Resume the login stopped by [`CczuError::SecondFactorRequired`] with the received code.
+    ///
+    /// In WebVPN Mode, call [`Self::sso_universal_login`] again after it to get the ElinkLoginInfo.
+    fn submit_second_factor(
+        &self,
+        code: impl Into<String>,
+    ) -> impl Future<Output = TorErr<Response>>;
+
+    /// Roles offered by the last role selection page, empty for an account with a single role.
+    ///
+    /// The student role is picked on that page unless [`Self::select_role`] chose another.
+    fn available_roles(&self) -> impl Future<Output = Vec<SSORole>>;
+
+    /// Log in as `role`, an [`SSORole::id`], on the next role selection page.
+    ///
+    /// A session already open keeps its role, the choice applies to the next login asking.
+    fn select_role(&self, role: impl Into<String>) -> impl Future<Output = EmptyOrErr>;
+
+    /// What the last login did on behalf of the user instead of stopping, a line each, like
+    /// the [`Agreement::ACCEPTED`] of a terms-of-use page, see
+    /// [`crate::base::client::ClientOptions::accept_agreements`].
+    fn login_warnings(&self) -> impl Future<Output = Vec<String>>;
+
+    /// The redirects followed by the last login, failed or not, with
+    /// [`crate::base::client::ClientOptions::record_login_redirects`]: empty otherwise.
+    fn login_redirects(&self) -> impl Future<Output = Vec<RedirectHop>>;
+
+    /// Fetch the login form the way [`Self::sso_universal_login`] does and give back what it
+    /// reads from it, without posting anything: to tell a token missing from the page from a
+    /// refused password.
+    #[cfg(any(test, feature = "testing"))]
+    fn debug_login_form(&self) -> impl Future<Output = TorErr<LoginFormDebug>>;
 }
 
 impl<C: Client + Clone + Send> SSOUniversalLogin for C {
     async fn sso_universal_login(&self) -> TorErr<Option<ElinkLoginInfo>> {
-        let login = universal_sso_login(self.clone()).await?;
-        self.properties().write().await.insert(
-            SSOLoginConnectType::key(),
-            login.login_connect_type.clone().into(),
-        );
-
-        match login.login_connect_type {
-            SSOLoginConnectType::WEBVPN => {
-                let response = login.response;
-
-                if let Some(cookie) = &response
-                    .cookies()
-                    .filter(|cookie| cookie.name() == "clientInfo")
-                    .collect::<Vec<Cookie>>()
-                    .first()
-                {
-                    let json =
-                        String::from_utf8(BASE64_STANDARD.decode(cookie.value()).unwrap()).unwrap();
-                    let data: ElinkLoginInfo = serde_json::from_str(&json)?;
-
-                    Ok(Some(data))
-                } else {
-                    Err(other_error("Get `EnlinkLoginInfo` failed"))
+        traced("sso_universal_login", async {
+            self.properties()
+                .write()
+                .await
+                .remove(Agreement::warnings_key());
+            let login = with_redirects(self, universal_sso_login(self.clone())).await?;
+            self.properties().write().await.insert(
+                SSOLoginConnectType::key(),
+                login.login_connect_type.clone().into(),
+            );
+
+            match login.login_connect_type {
+                SSOLoginConnectType::WEBVPN => {
+                    let response = login.response;
+
+                    if let Some(cookie) = &response
+                        .cookies()
+                        .filter(|cookie| cookie.name() == "clientInfo")
+                        .collect::<Vec<Cookie>>()
+                        .first()
+                    {
+                        let json =
+                            String::from_utf8(BASE64_STANDARD.decode(cookie.value()).unwrap())
+                                .unwrap();
+                        let mut data: ElinkLoginInfo = serde_json::from_str(&json)?;
+                        data.redirects = self.login_redirects().await;
+
+                        Ok(Some(data))
+                    } else {
+                        Err(other_error("Get `EnlinkLoginInfo` failed"))
+                    }
                 }
+                SSOLoginConnectType::COMMON => Ok(None),
             }
-            SSOLoginConnectType::COMMON => Ok(None),
-        }
+        })
+        .await
     }
 
     async fn sso_service_login(&self, service: impl Into<String>) -> TorErr<Response> {
-        service_sso_login(self.clone(), service).await
+        traced("sso_service_login", async {
+            self.properties()
+                .write()
+                .await
+                .remove(Agreement::warnings_key());
+            with_redirects(self, service_sso_login(self.clone(), service)).await
+        })
+        .await
     }
+
+    async fn submit_second_factor(&self, code: impl Into<String>) -> TorErr<Response> {
+        traced("submit_second_factor", async {
+            let context = self
+                .properties()
+                .write()
+                .await
+                .remove(SecondFactorContext::key())
+                .and_then(|property| property.get_string())
+                .ok_or(other_error("No pending second factor, login first"))?;
+            let context: SecondFactorContext = serde_json::from_str(&context)?;
+
+            let mut form = context.fields;
+            form.insert(context.code_field, code.into());
+            let response = self
+                .reqwest_client()
+                .post(context.action)
+                .form(&form)
+                .headers(DEFAULT_HEADERS.clone())
+                .send_measured(self)
+                .await
+                .map_err(other_error)?;
+
+            finish_login(self.clone(), response).await
+        })
+        .await
+    }
+
+    async fn available_roles(&self) -> Vec<SSORole> {
+        role_selection(self)
+            .await
+            .map(|selection| selection.roles)
+            .unwrap_or_default()
+    }
+
+    async fn select_role(&self, role: impl Into<String>) -> EmptyOrErr {
+        let role = role.into();
+        if let Some(selection) = role_selection(self).await {
+            if !selection.roles.iter().any(|known| known.id == role) {
+                return Err(other_error(format!("Unknown role `{role}`")));
+            }
+        }
+        self.properties()
+            .write()
+            .await
+            .insert(RoleSelection::chosen_key(), Property::String(role));
+        Ok(())
+    }
+
+    async fn login_warnings(&self) -> Vec<String> {
+        self.properties()
+            .read()
+            .await
+            .get(Agreement::warnings_key())
+            .and_then(|property| property.get_string())
+            .and_then(|warnings| serde_json::from_str(&warnings).ok())
+            .unwrap_or_default()
+    }
+
+    async fn login_redirects(&self) -> Vec<RedirectHop> {
+        self.properties()
+            .read()
+            .await
+            .get(RedirectHop::key())
+            .and_then(|property| property.get_string())
+            .and_then(|redirects| serde_json::from_str(&redirects).ok())
+            .unwrap_or_default()
+    }
+
+    #[cfg(any(test, feature = "testing"))]
+    async fn debug_login_form(&self) -> TorErr<LoginFormDebug> {
+        let response = self
+            .reqwest_client()
+            .get(ROOT_SSO_LOGIN)
+            .send_measured(self)
+            .await
+            .map_err(other_error)?;
+        let webvpn = response.status() == StatusCode::FOUND;
+        let response = if webvpn {
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .ok_or(other_error("Get Location Failed"))?
+                .to_str()
+                .map_err(other_error)?;
+            recursion_redirect_handle(self.clone(), location)
+                .await
+                .map_err(other_error)?
+        } else {
+            response
+        };
+
+        let url = response.url().clone();
+        let html = read_text(response, self.options().max_response_size).await?;
+        Ok(parse_login_form(&html, &url, webvpn))
+    }
+}
+
+/// `login`, its redirects kept for [`SSOUniversalLogin::login_redirects`] when the client
+/// records them.
+async fn with_redirects<T>(
+    client: &impl Client,
+    login: impl Future<Output = TorErr<T>>,
+) -> TorErr<T> {
+    client.properties().write().await.remove(RedirectHop::key());
+    if !client.options().record_login_redirects {
+        return login.await;
+    }
+    let (result, redirects) = record_redirects(login).await;
+    client.properties().write().await.insert(
+        RedirectHop::key(),
+        Property::String(serde_json::to_string(&redirects)?),
+    );
+    result
+}
+
+async fn role_selection(client: &impl Client) -> Option<RoleSelection> {
+    client
+        .properties()
+        .read()
+        .await
+        .get(RoleSelection::key())
+        .and_then(|property| property.get_string())
+        .and_then(|selection| serde_json::from_str(&selection).ok())
 }
 
 async fn universal_sso_login(client: impl Client + Clone + Send) -> TorErr<SSOUniversalLoginInfo> {
+    if let Some(state) = checkpoint(&client).await {
+        debug!(
+            webvpn = state.webvpn,
+            "Resuming the login from its fetched form"
+        );
+        if let Some(response) = resume_login(&client, &state).await? {
+            return if state.webvpn {
+                webvpn_login_redirect(client, response).await
+            } else {
+                Ok(SSOUniversalLoginInfo {
+                    response: finish_login(client, response).await?,
+                    login_connect_type: SSOLoginConnectType::COMMON,
+                })
+            };
+        }
+    }
+
     let response = client
         .reqwest_client()
         .get(ROOT_SSO_LOGIN)
-        .send()
+        .send_measured(&client)
         .await
         .map_err(other_error)?;
     let status = response.status();
+    debug!(%status, "SSO login page");
     // use webvpn
     if status == StatusCode::FOUND {
+        record_redirect(ROOT_SSO_LOGIN, status);
         // redirect to webvpn root
         // recursion to get the login page
         let response = recursion_redirect_handle(
@@ -90,44 +321,19 @@ async fn universal_sso_login(client: impl Client + Clone + Send) -> TorErr<SSOUn
         .map_err(other_error)?;
 
         let url = response.url().clone();
-        let dom = response.text().await.unwrap();
-        let mut form = parse_hidden_values(dom.as_str());
-
-        let account = client.account();
-        form.insert("username".into(), account.user);
-        form.insert("password".into(), BASE64_STANDARD.encode(account.password));
-
-        let response = client
-            .reqwest_client()
-            .post(url)
-            .form(&form)
-            .send()
-            .await
-            .map_err(other_error)?;
-
-        let redirect_location_header = response.headers().get("location");
-        if let None = redirect_location_header {
-            return Err(other_error("Redirect to None"));
+        let dom = read_text(response, client.options().max_response_size).await?;
+        if let Some(api) = json_login_api(&dom, &url, client.options().sso_variant) {
+            let redirect = submit_json_login(&client, api, &url).await?;
+            return webvpn_follow(client, &redirect).await;
         }
-        let redirect_location = redirect_location_header.unwrap().to_str().unwrap();
-
-        let response = client
-            .reqwest_client()
-            .get(redirect_location)
-            .headers(DEFAULT_HEADERS.clone())
-            .send()
-            .await
-            .map_err(other_error)?;
-
-        client
-            .cookies()
-            .lock()
-            .unwrap()
-            .add_reqwest_cookies(response.cookies(), &ROOT_VPN_URL);
-        Ok(SSOUniversalLoginInfo {
-            response,
-            login_connect_type: SSOLoginConnectType::WEBVPN,
-        })
+        let state = LoginState {
+            webvpn: true,
+            action: url.to_string(),
+            fields: parse_hidden_values(dom.as_str()),
+            fetched_at: unix_now(),
+        };
+        let response = submit_login_form(&client, &state).await?;
+        webvpn_login_redirect(client, response).await
     }
     // connect `cczu` and don't need to redirect
     else if status == StatusCode::OK {
@@ -140,21 +346,73 @@ async fn universal_sso_login(client: impl Client + Clone + Send) -> TorErr<SSOUn
     }
 }
 
+/// After the form is posted in WebVPN Mode: get the VPN cookies from the redirect.
+async fn webvpn_login_redirect(
+    client: impl Client + Clone + Send,
+    response: Response,
+) -> TorErr<SSOUniversalLoginInfo> {
+    let redirect_location_header = response.headers().get("location");
+    if redirect_location_header.is_none() {
+        let url = response.url().clone();
+        let dom = read_text(response, client.options().max_response_size).await?;
+        check_interstitial(&client, &url, &dom).await?;
+        return Err(other_error("Redirect to None"));
+    }
+    let redirect_location = redirect_location_header.unwrap().to_str().unwrap();
+    webvpn_follow(client, redirect_location).await
+}
+
+/// Get the VPN cookies from the page SSO sends the browser to once logged in.
+async fn webvpn_follow(
+    client: impl Client + Clone + Send,
+    redirect_location: &str,
+) -> TorErr<SSOUniversalLoginInfo> {
+    let response = client
+        .reqwest_client()
+        .get(redirect_location)
+        .headers(DEFAULT_HEADERS.clone())
+        .send_measured(&client)
+        .await
+        .map_err(other_error)?;
+    record_redirect(redirect_location, response.status());
+
+    client
+        .cookies()
+        .lock()
+        .unwrap()
+        .add_reqwest_cookies(response.cookies(), &ROOT_VPN_URL);
+    Ok(SSOUniversalLoginInfo {
+        response,
+        login_connect_type: SSOLoginConnectType::WEBVPN,
+    })
+}
+
 async fn service_sso_login(
     client: impl Client + Clone + Send,
     service: impl Into<String>,
 ) -> TorErr<Response> {
     let api = format!("{}?service={}", ROOT_SSO_LOGIN, service.into());
+    if let Some(state) = checkpoint(&client)
+        .await
+        .filter(|state| !state.webvpn && state.action == api)
+    {
+        if let Some(response) = resume_login(&client, &state).await? {
+            return finish_login(client, response).await;
+        }
+    }
+
     let response = client
         .reqwest_client()
         .get(api.clone())
-        .send()
+        .send_measured(&client)
         .await
         .map_err(other_error)?;
 
     // Has Logined before
     if response.status() == StatusCode::FOUND {
-        return Ok(recursion_redirect_handle(
+        debug!("SSO session still valid");
+        record_redirect(&api, response.status());
+        return recursion_redirect_handle(
             client,
             response
                 .headers()
@@ -163,27 +421,212 @@ async fn service_sso_login(
                 .to_str()
                 .map_err(other_error)?,
         )
-        .await?);
+        .await;
+    }
+
+    let url = response.url().clone();
+    let dom = read_text(response, client.options().max_response_size).await?;
+    if let Some(api) = json_login_api(&dom, &url, client.options().sso_variant) {
+        let redirect = submit_json_login(&client, api, &url).await?;
+        let response = recursion_redirect_handle(client.clone(), &redirect).await?;
+        return finish_login(client, response).await;
     }
+    let state = LoginState {
+        webvpn: false,
+        action: api,
+        fields: parse_hidden_values(dom.as_str()),
+        fetched_at: unix_now(),
+    };
+    let response = submit_login_form(&client, &state).await?;
+
+    finish_login(client, response).await
+}
 
-    let dom = response.text().await.unwrap();
-    let mut login_param = parse_hidden_values(dom.as_str());
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// The login form fetched by an interrupted login, if it is still fresh.
+async fn checkpoint(client: &impl Client) -> Option<LoginState> {
+    let state: LoginState = client
+        .properties()
+        .read()
+        .await
+        .get(LoginState::key())
+        .and_then(|property| property.get_string())
+        .and_then(|state| serde_json::from_str(&state).ok())?;
+    if unix_now().saturating_sub(state.fetched_at) > LoginState::MAX_AGE {
+        client.properties().write().await.remove(LoginState::key());
+        return None;
+    }
+    Some(state)
+}
+
+/// Post the login form, keeping it as the checkpoint until an answer comes back.
+async fn submit_login_form(client: &impl Client, state: &LoginState) -> TorErr<Response> {
+    client.properties().write().await.insert(
+        LoginState::key(),
+        Property::String(serde_json::to_string(state)?),
+    );
+
+    let mut form = state.fields.clone();
     let account = client.account();
-    login_param.insert("username".into(), account.user);
-    login_param.insert("password".into(), BASE64_STANDARD.encode(account.password));
+    form.insert("username".into(), account.user);
+    form.insert("password".into(), BASE64_STANDARD.encode(account.password));
+
+    let request = client.reqwest_client().post(&state.action).form(&form);
+    let request = if state.webvpn {
+        request
+    } else {
+        request.headers(DEFAULT_HEADERS.clone())
+    };
+    let response = request.send_measured(client).await.map_err(other_error)?;
+    debug!(webvpn = state.webvpn, status = %response.status(), "Login form posted");
 
+    // The token is spent once SSO answered
+    client.properties().write().await.remove(LoginState::key());
+    Ok(response)
+}
+
+/// The JSON login API of the login page `html` fetched from `url`, `None` to post its form
+/// instead, see [`SSOVariant`].
+pub fn json_login_api(html: &str, url: &Url, variant: SSOVariant) -> Option<Url> {
+    let declared = || {
+        let dom = Html::parse_document(html);
+        let meta_selector = Selector::parse("meta[name][content]").unwrap();
+        dom.select(&meta_selector)
+            .find(|meta| meta.attr("name") == Some(JSON_LOGIN_META))
+            .and_then(|meta| url.join(meta.attr("content")?).ok())
+    };
+    match variant {
+        SSOVariant::Auto => declared(),
+        SSOVariant::Classic => None,
+        SSOVariant::Json => declared().or(url.join(DEFAULT_JSON_LOGIN_API).ok()),
+    }
+}
+
+/// Post the account to the JSON login `api` of the login page at `page`, for the service of
+/// the page. Gives back where SSO sends the browser next.
+async fn submit_json_login(client: &impl Client, api: Url, page: &Url) -> TorErr<String> {
+    let service = page
+        .query_pairs()
+        .find(|(name, _)| name == "service")
+        .map(|(_, service)| service.into_owned())
+        .unwrap_or_default();
+    let account = client.account();
     let response = client
         .reqwest_client()
         .post(api)
-        .form(&login_param)
         .headers(DEFAULT_HEADERS.clone())
-        .send()
+        .json(&serde_json::json!({
+            "username": account.user,
+            "password": BASE64_STANDARD.encode(account.password),
+            "service": service,
+        }))
+        .send_measured(client)
         .await
         .map_err(other_error)?;
+    debug!(status = %response.status(), "JSON login posted");
 
-    if response.status() == StatusCode::FOUND {
-        Ok(recursion_redirect_handle(
-            client,
+    let answer: JsonLoginAnswer = read_json(response, client.options().max_response_size).await?;
+    match answer.data {
+        Some(data) if answer.code == 0 => Ok(data.redirect),
+        // No link in the answer, the login page shows it in a browser
+        _ if NOT_ACTIVATED_MARKERS
+            .iter()
+            .any(|marker| answer.message.contains(marker)) =>
+        {
+            Err(typed_error(CczuError::AccountNotActivated {
+                activation_url: page.to_string(),
+            }))
+        }
+        _ => Err(other_error(format!("Login Failed: {}", answer.message))),
+    }
+}
+
+/// Post the form of `state` again, `None` when SSO doesn't take it anymore.
+async fn resume_login(client: &impl Client, state: &LoginState) -> TorErr<Option<Response>> {
+    let response = submit_login_form(client, state).await?;
+    if response.headers().contains_key(LOCATION) {
+        return Ok(Some(response));
+    }
+
+    let url = response.url().clone();
+    let (dom, response) = buffer_response(response, client.options().max_response_size).await?;
+    if parse_second_factor(&dom, &url).is_some()
+        || parse_password_change(&dom, &url).is_some()
+        || parse_account_activation(&dom, &url).is_some()
+        || parse_role_selection(&dom, &url).is_some()
+        || parse_agreement(&dom, &url).is_some()
+    {
+        return Ok(Some(response));
+    }
+    // The login form again, the flow expired
+    debug!("Fetched form expired, starting over");
+    Ok(None)
+}
+
+/// Follow the redirect of a submitted login form, or stop on a page SSO shows before the service.
+///
+/// The role selection page is answered with the chosen role, the student one by default, and
+/// an agreement page is agreed to, see [`accept_agreement`].
+async fn finish_login(client: impl Client + Clone + Send, response: Response) -> TorErr<Response> {
+    let page = follow_login(&client, response).await?;
+    let (url, dom, response) = accept_agreement(&client, page).await?;
+    let Some(selection) = parse_role_selection(&dom, &url) else {
+        check_interstitial(&client, &url, &dom).await?;
+        return Ok(response);
+    };
+
+    let chosen = client
+        .properties()
+        .read()
+        .await
+        .get(RoleSelection::chosen_key())
+        .and_then(|property| property.get_string());
+    let role = chosen
+        .and_then(|chosen| selection.roles.iter().find(|role| role.id == chosen))
+        .or(selection.default_role())
+        .ok_or(other_error("No role to select"))?
+        .clone();
+    debug!(role = %role.name, "Role selected");
+    client.properties().write().await.insert(
+        RoleSelection::key(),
+        Property::String(serde_json::to_string(&selection)?),
+    );
+
+    let mut form = selection.fields;
+    form.insert(selection.role_field, role.id);
+    let response = client
+        .reqwest_client()
+        .post(selection.action)
+        .form(&form)
+        .headers(DEFAULT_HEADERS.clone())
+        .send_measured(&client)
+        .await
+        .map_err(other_error)?;
+
+    let page = follow_login(&client, response).await?;
+    let (url, dom, response) = accept_agreement(&client, page).await?;
+    if parse_role_selection(&dom, &url).is_some() {
+        return Err(other_error("Role selection refused"));
+    }
+    check_interstitial(&client, &url, &dom).await?;
+    Ok(response)
+}
+
+/// Follow the redirect of a posted form, buffering the page it lands on.
+async fn follow_login(
+    client: &(impl Client + Clone + Send),
+    response: Response,
+) -> TorErr<(Url, String, Response)> {
+    let response = if response.status() == StatusCode::FOUND {
+        record_redirect(response.url().as_str(), response.status());
+        recursion_redirect_handle(
+            client.clone(),
             response
                 .headers()
                 .get(LOCATION)
@@ -191,10 +634,79 @@ async fn service_sso_login(
                 .to_str()
                 .map_err(other_error)?,
         )
-        .await?)
+        .await?
     } else {
-        Ok(response)
+        response
+    };
+
+    let url = response.url().clone();
+    let (dom, response) = buffer_response(response, client.options().max_response_size).await?;
+    Ok((url, dom, response))
+}
+
+/// Post the agreement of `page` if it is an agreement page and
+/// [`crate::base::client::ClientOptions::accept_agreements`] is on, giving back the page after
+/// it, with a line in [`SSOUniversalLogin::login_warnings`]. Any other page is given back as is.
+async fn accept_agreement(
+    client: &(impl Client + Clone + Send),
+    page: (Url, String, Response),
+) -> TorErr<(Url, String, Response)> {
+    if !client.options().accept_agreements {
+        return Ok(page);
     }
+    let Some(agreement) = parse_agreement(&page.1, &page.0) else {
+        return Ok(page);
+    };
+    debug!(url = %page.0, "Accepting the agreement");
+    let response = client
+        .reqwest_client()
+        .post(agreement.action)
+        .form(&agreement.fields)
+        .headers(DEFAULT_HEADERS.clone())
+        .send_measured(client)
+        .await
+        .map_err(other_error)?;
+
+    let next = follow_login(client, response).await?;
+    if parse_agreement(&next.1, &next.0).is_some() {
+        return Err(other_error("Agreement refused"));
+    }
+    let mut warnings = client.login_warnings().await;
+    warnings.push(format!("{} at {}", Agreement::ACCEPTED, page.0));
+    client.properties().write().await.insert(
+        Agreement::warnings_key(),
+        Property::String(serde_json::to_string(&warnings)?),
+    );
+    Ok(next)
+}
+
+/// Stop on the pages between the password and the service, they are not a failed login.
+///
+/// The form of the second factor page is kept in the properties, so the login can be resumed.
+async fn check_interstitial(client: &impl Client, url: &Url, html: &str) -> EmptyOrErr {
+    if let Some(activation_url) = parse_account_activation(html, url) {
+        debug!("Account not activated");
+        return Err(typed_error(CczuError::AccountNotActivated {
+            activation_url,
+        }));
+    }
+    if let Some(url) = parse_password_change(html, url) {
+        debug!("Password change required");
+        return Err(typed_error(CczuError::PasswordChangeRequired { url }));
+    }
+    if let Some(context) = parse_second_factor(html, url) {
+        debug!(method = %context.method, "Second factor required");
+        let error = CczuError::SecondFactorRequired {
+            method: context.method.clone(),
+            hint: context.hint.clone(),
+        };
+        client.properties().write().await.insert(
+            SecondFactorContext::key(),
+            Property::String(serde_json::to_string(&context)?),
+        );
+        return Err(typed_error(error));
+    }
+    Ok(())
 }
 
 pub fn parse_hidden_values(html: &str) -> HashMap<String, String> {
@@ -212,3 +724,661 @@ pub fn parse_hidden_values(html: &str) -> HashMap<String, String> {
 
     hidden_values
 }
+
+/// What the login reads from the login form of `html` fetched from `url`, see
+/// [`SSOUniversalLogin::debug_login_form`].
+#[cfg(any(test, feature = "testing"))]
+pub fn parse_login_form(html: &str, url: &Url, webvpn: bool) -> LoginFormDebug {
+    let fields = parse_hidden_values(html);
+    let dom = Html::parse_document(html);
+    let meta_selector = Selector::parse("meta[name][content]").unwrap();
+    let csrf = CSRF_NAMES
+        .iter()
+        .find_map(|name| fields.get(*name).cloned())
+        .or_else(|| {
+            dom.select(&meta_selector)
+                .find(|meta| {
+                    meta.attr("name")
+                        .is_some_and(|name| CSRF_NAMES.contains(&name))
+                })
+                .and_then(|meta| meta.attr("content"))
+                .map(str::to_string)
+        });
+
+    LoginFormDebug {
+        webvpn,
+        action: url.to_string(),
+        execution: fields.get("execution").cloned(),
+        lt: fields.get("lt").cloned(),
+        csrf,
+        fields,
+    }
+}
+
+/// Find the SMS verification / phone binding page shown between the password and the service.
+pub fn parse_second_factor(html: &str, url: &Url) -> Option<SecondFactorContext> {
+    let dom = Html::parse_document(html);
+    let form_selector = Selector::parse("form").unwrap();
+    let input_selector = Selector::parse("input").unwrap();
+
+    let (form, code_field) = dom.select(&form_selector).find_map(|form| {
+        form.select(&input_selector)
+            .filter_map(|input| input.attr("name"))
+            .find(|name| SECOND_FACTOR_INPUTS.contains(name))
+            .map(|name| (form, name.to_string()))
+    })?;
+
+    let text: String = dom.root_element().text().collect();
+    let method = if text.contains("绑定手机") {
+        "bind_phone"
+    } else {
+        "sms"
+    };
+
+    let action = form
+        .attr("action")
+        .and_then(|action| url.join(action).ok())
+        .unwrap_or(url.clone());
+
+    Some(SecondFactorContext {
+        method: method.into(),
+        hint: masked_phone(&text).unwrap_or_default(),
+        action: action.to_string(),
+        code_field,
+        fields: form_hidden_values(form),
+    })
+}
+
+/// Find the page making the account change its password, forced after some time or on the
+/// first login. Its url is given back: the change is done by the user in a browser.
+pub fn parse_password_change(html: &str, url: &Url) -> Option<String> {
+    let dom = Html::parse_document(html);
+    let input_selector = Selector::parse("form input").unwrap();
+    dom.select(&input_selector)
+        .filter_map(|input| input.attr("name"))
+        .any(|name| PASSWORD_CHANGE_INPUTS.contains(&name))
+        .then(|| url.to_string())
+}
+
+/// Find the login page telling the account isn't activated yet, instead of a bad password.
+/// The url of its activation link is given back, the one of the page without a link.
+pub fn parse_account_activation(html: &str, url: &Url) -> Option<String> {
+    let dom = Html::parse_document(html);
+    let text: String = dom.root_element().text().collect();
+    if !NOT_ACTIVATED_MARKERS
+        .iter()
+        .any(|marker| text.contains(marker))
+    {
+        return None;
+    }
+    let link_selector = Selector::parse("a[href]").unwrap();
+    let activation = dom
+        .select(&link_selector)
+        .find(|link| {
+            link.text().collect::<String>().contains("激活")
+                || link.attr("href").is_some_and(|href| href.contains("activ"))
+        })
+        .and_then(|link| url.join(link.attr("href")?).ok())
+        .unwrap_or(url.clone());
+    Some(activation.to_string())
+}
+
+/// Find the page asking an account with several roles which one to log in as.
+pub fn parse_role_selection(html: &str, url: &Url) -> Option<RoleSelection> {
+    let dom = Html::parse_document(html);
+    let form_selector = Selector::parse("form").unwrap();
+    let radio_selector = Selector::parse(r#"input[type="radio"]"#).unwrap();
+
+    dom.select(&form_selector).find_map(|form| {
+        let radios: Vec<ElementRef> = form
+            .select(&radio_selector)
+            .filter(|input| {
+                input
+                    .attr("name")
+                    .is_some_and(|name| ROLE_INPUTS.contains(&name))
+            })
+            .collect();
+        let role_field = radios.first()?.attr("name")?.to_string();
+        let roles = radios
+            .iter()
+            .filter_map(|input| {
+                let id = input.attr("value")?.to_string();
+                // The label wrapping the input, or the value without one
+                let name = input
+                    .parent()
+                    .and_then(ElementRef::wrap)
+                    .filter(|parent| parent.value().name() == "label")
+                    .map(element_text)
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or(id.clone());
+                Some(SSORole { id, name })
+            })
+            .collect();
+        let action = form
+            .attr("action")
+            .and_then(|action| url.join(action).ok())
+            .unwrap_or(url.clone());
+
+        Some(RoleSelection {
+            action: action.to_string(),
+            role_field,
+            fields: form_hidden_values(form),
+            roles,
+        })
+    })
+}
+
+/// Find the terms-of-use or notice page to agree to before going on, by the agree checkbox or
+/// button of its form. A login form is never one, even with a privacy checkbox.
+pub fn parse_agreement(html: &str, url: &Url) -> Option<Agreement> {
+    let dom = Html::parse_document(html);
+    let form_selector = Selector::parse("form").unwrap();
+    let input_selector = Selector::parse("input, button").unwrap();
+
+    dom.select(&form_selector).find_map(|form| {
+        let inputs: Vec<ElementRef> = form.select(&input_selector).collect();
+        if inputs
+            .iter()
+            .any(|input| input.attr("type") == Some("password"))
+        {
+            return None;
+        }
+        let (name, value) = inputs.iter().find_map(|input| {
+            let name = input
+                .attr("name")
+                .filter(|name| AGREEMENT_INPUTS.contains(name))?;
+            Some((name, input.attr("value").unwrap_or("on")))
+        })?;
+
+        let mut fields = form_hidden_values(form);
+        fields.insert(name.into(), value.into());
+        let action = form
+            .attr("action")
+            .and_then(|action| url.join(action).ok())
+            .unwrap_or(url.clone());
+        Some(Agreement {
+            action: action.to_string(),
+            fields,
+        })
+    })
+}
+
+fn form_hidden_values(form: ElementRef) -> HashMap<String, String> {
+    let input_hidden_selector = Selector::parse(r#"input[type="hidden"]"#).unwrap();
+    form.select(&input_hidden_selector)
+        .filter_map(|tag| Some((tag.attr("name")?.into(), tag.attr("value")?.into())))
+        .collect()
+}
+
+/// Something like `138****5678`
+fn masked_phone(text: &str) -> Option<String> {
+    let chars: Vec<char> = text.chars().collect();
+    for start in 0..chars.len() {
+        let digits = chars[start..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count();
+        if digits != 3 || (start > 0 && chars[start - 1].is_ascii_digit()) {
+            continue;
+        }
+        let stars = chars[start + 3..].iter().take_while(|c| **c == '*').count();
+        let tail = chars[start + 3 + stars..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count();
+        if stars >= 2 && tail >= 2 {
+            return Some(chars[start..start + 3 + stars + tail].iter().collect());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use reqwest::Url;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::{
+        check_interstitial, json_login_api, parse_account_activation, parse_agreement,
+        parse_login_form, parse_password_change, parse_role_selection, parse_second_factor,
+        submit_json_login, unix_now, SSOUniversalLogin,
+    };
+    use crate::{
+        base::{
+            client::{Account, Client, ClientOptions, Property, SSOVariant},
+            typing::{CczuError, TypedErrorExt},
+        },
+        impls::{
+            client::DefaultClient,
+            login::sso_type::{Agreement, LoginState, RedirectHop, SSORole},
+        },
+    };
+
+    #[test]
+    fn second_factor_page() {
+        let url = Url::parse("http://sso.cczu.edu.cn/sso/login").unwrap();
+        let context =
+            parse_second_factor(include_str!("fixtures/sso_second_factor.html"), &url).unwrap();
+
+        assert_eq!(context.method, "sms");
+        assert_eq!(context.hint, "138****5678");
+        assert_eq!(context.code_field, "dynamicCode");
+        assert_eq!(
+            context.action,
+            "http://sso.cczu.edu.cn/sso/login?service=http%3A%2F%2Fywtb.cczu.edu.cn%2Fpc%2Findex.html"
+        );
+        assert_eq!(context.fields.get("execution").unwrap(), "e1s2");
+        assert_eq!(context.fields.get("_eventId").unwrap(), "reAuthSubmit");
+    }
+
+    #[test]
+    fn login_form_fields() {
+        let url = Url::parse("http://sso.cczu.edu.cn/sso/login").unwrap();
+        let form = parse_login_form(include_str!("fixtures/sso_login.html"), &url, false);
+
+        assert_eq!(form.action, "http://sso.cczu.edu.cn/sso/login");
+        assert_eq!(form.execution.as_deref(), Some("e1s1"));
+        assert_eq!(
+            form.lt.as_deref(),
+            Some("LT-2117-bd2vQeGb9lS1grNcmXkspcRK0fYcJC-cas")
+        );
+        assert_eq!(
+            form.csrf.as_deref(),
+            Some("3f1c9a52-7d4e-4b8a-9e21-c0a8f6d2b417")
+        );
+        assert_eq!(form.fields.len(), 3);
+        assert_eq!(form.fields.get("_eventId").unwrap(), "submit");
+        assert!(!form.fields.contains_key("password"));
+    }
+
+    #[test]
+    fn login_page_is_not_second_factor() {
+        let url = Url::parse("http://sso.cczu.edu.cn/sso/login").unwrap();
+        assert!(parse_second_factor(include_str!("fixtures/sso_login.html"), &url).is_none());
+    }
+
+    #[tokio::test]
+    async fn password_change_page() {
+        let url = Url::parse("http://sso.cczu.edu.cn/sso/pwd/expired").unwrap();
+        let page = include_str!("fixtures/sso_password_change.html");
+        assert_eq!(
+            parse_password_change(page, &url).as_deref(),
+            Some("http://sso.cczu.edu.cn/sso/pwd/expired")
+        );
+        for page in [
+            include_str!("fixtures/sso_login.html"),
+            include_str!("fixtures/sso_second_factor.html"),
+        ] {
+            assert!(parse_password_change(page, &url).is_none());
+        }
+
+        let error = check_interstitial(&DefaultClient::default(), &url, page)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.typed(),
+            Some(&CczuError::PasswordChangeRequired {
+                url: url.to_string()
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn not_activated_page() {
+        let url = Url::parse("http://sso.cczu.edu.cn/sso/login").unwrap();
+        let page = include_str!("fixtures/sso_not_activated.html");
+        assert_eq!(
+            parse_account_activation(page, &url).as_deref(),
+            Some("http://sso.cczu.edu.cn/sso/activation/index")
+        );
+        // The same page with the same activation link, but a wrong password
+        for page in [
+            include_str!("fixtures/sso_bad_password.html"),
+            include_str!("fixtures/sso_login.html"),
+        ] {
+            assert!(parse_account_activation(page, &url).is_none());
+            assert!(check_interstitial(&DefaultClient::default(), &url, page)
+                .await
+                .is_ok());
+        }
+
+        let error = check_interstitial(&DefaultClient::default(), &url, page)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.typed(),
+            Some(&CczuError::AccountNotActivated {
+                activation_url: "http://sso.cczu.edu.cn/sso/activation/index".into()
+            })
+        );
+    }
+
+    /// SSO that takes the posted form and redirects to `/service`, keeping every request.
+    async fn sso() -> (String, Arc<Mutex<Vec<String>>>) {
+        serve(|root, request| {
+            if request.starts_with("POST") {
+                redirect(&format!("{root}/service"))
+            } else {
+                page("welcome")
+            }
+        })
+        .await
+    }
+
+    fn redirect(location: &str) -> String {
+        format!("HTTP/1.1 302 Found\r\nLocation: {location}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+    }
+
+    fn page(body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    /// A server answering each request with `answer(root, request)`, keeping every request.
+    async fn serve(
+        answer: impl Fn(&str, &str) -> String + Send + 'static,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let root = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+        let (base, seen) = (root.clone(), requests.clone());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![];
+                let mut buf = [0u8; 4096];
+                // Read the head, then the body announced by Content-Length
+                while let Ok(read @ 1..) = stream.read(&mut buf).await {
+                    request.extend_from_slice(&buf[..read]);
+                    let text = String::from_utf8_lossy(&request).to_lowercase();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text
+                            .lines()
+                            .find_map(|line| line.strip_prefix("content-length: "))
+                            .map_or(0, |length| length.trim().parse().unwrap());
+                        if request.len() >= end + 4 + length {
+                            break;
+                        }
+                    }
+                }
+                let request = String::from_utf8_lossy(&request).into_owned();
+                let answer = answer(&base, &request);
+                seen.lock().unwrap().push(request);
+                let _ = stream.write_all(answer.as_bytes()).await;
+            }
+        });
+        (root, requests)
+    }
+
+    #[tokio::test]
+    async fn resume_from_fetched_form() {
+        let (root, requests) = sso().await;
+        let client = DefaultClient::account("2300000101", "hunter2");
+        let state = LoginState {
+            webvpn: false,
+            action: format!("{root}/sso/login"),
+            fields: HashMap::from([("execution".to_string(), "e1s1".to_string())]),
+            fetched_at: unix_now(),
+        };
+        client.properties().write().await.insert(
+            LoginState::key(),
+            Property::String(serde_json::to_string(&state).unwrap()),
+        );
+
+        assert!(client.sso_universal_login().await.unwrap().is_none());
+
+        // The form is posted straight away, no new form is fetched
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].starts_with("POST /sso/login "));
+        assert!(requests[0].contains("execution=e1s1"));
+        assert!(requests[0].contains("username=2300000101"));
+        assert!(requests[1].starts_with("GET /service "));
+        assert!(!client
+            .properties()
+            .read()
+            .await
+            .contains_key(LoginState::key()));
+    }
+
+    #[test]
+    fn role_selection_page() {
+        let url = Url::parse("http://sso.cczu.edu.cn/sso/login").unwrap();
+        let selection =
+            parse_role_selection(include_str!("fixtures/sso_role_selection.html"), &url).unwrap();
+
+        assert_eq!(selection.role_field, "roleId");
+        assert_eq!(selection.fields.get("execution").unwrap(), "e1s3");
+        assert_eq!(
+            selection.default_role(),
+            Some(&SSORole {
+                id: "S2300000101".into(),
+                name: "学生".into()
+            })
+        );
+        assert_eq!(selection.roles[0].name, "助教");
+        assert!(parse_role_selection(include_str!("fixtures/sso_login.html"), &url).is_none());
+    }
+
+    /// SSO asking for the role after the password, then redirecting to `/service`.
+    async fn sso_with_roles() -> (String, Arc<Mutex<Vec<String>>>) {
+        serve(|root, request| {
+            if request.contains("roleId=") {
+                redirect(&format!("{root}/service"))
+            } else if request.starts_with("POST") {
+                redirect(&format!("{root}/roles"))
+            } else if request.starts_with("GET /roles ") {
+                page(include_str!("fixtures/sso_role_selection.html"))
+            } else {
+                page("welcome")
+            }
+        })
+        .await
+    }
+
+    async fn login_with_roles(client: &DefaultClient, root: &str) {
+        let state = LoginState {
+            webvpn: false,
+            action: format!("{root}/sso/login"),
+            fields: HashMap::from([("execution".to_string(), "e1s1".to_string())]),
+            fetched_at: unix_now(),
+        };
+        client.properties().write().await.insert(
+            LoginState::key(),
+            Property::String(serde_json::to_string(&state).unwrap()),
+        );
+        assert!(client.sso_universal_login().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn student_role_selected() {
+        let (root, requests) = sso_with_roles().await;
+        let client = DefaultClient::account("2300000101", "hunter2");
+        assert!(client.available_roles().await.is_empty());
+        login_with_roles(&client, &root).await;
+
+        let posted = requests.lock().unwrap()[2].clone();
+        assert!(posted.starts_with("POST /sso/login?service="));
+        assert!(posted.contains("roleId=S2300000101"));
+        assert!(posted.contains("execution=e1s3"));
+        assert!(requests.lock().unwrap()[3].starts_with("GET /service "));
+        assert_eq!(client.available_roles().await.len(), 2);
+
+        // The TA role once chosen, not one the page doesn't offer
+        assert!(client.select_role("T0000000").await.is_err());
+        client.select_role("T2011005").await.unwrap();
+        requests.lock().unwrap().clear();
+        login_with_roles(&client, &root).await;
+        assert!(requests.lock().unwrap()[2].contains("roleId=T2011005"));
+    }
+
+    #[test]
+    fn agreement_page() {
+        let url = Url::parse("http://sso.cczu.edu.cn/sso/login").unwrap();
+        let agreement = parse_agreement(include_str!("fixtures/sso_agreement.html"), &url).unwrap();
+
+        assert_eq!(
+            agreement.action,
+            "http://sso.cczu.edu.cn/sso/login?service=http%3A%2F%2Fywtb.cczu.edu.cn%2Fpc%2Findex.html"
+        );
+        assert_eq!(agreement.fields.get("isAgree").unwrap(), "1");
+        assert_eq!(agreement.fields.get("execution").unwrap(), "e1s4");
+        for page in [
+            include_str!("fixtures/sso_login.html"),
+            include_str!("fixtures/sso_role_selection.html"),
+        ] {
+            assert!(parse_agreement(page, &url).is_none());
+        }
+    }
+
+    /// SSO showing its terms of use after the password, then redirecting to `/service`.
+    async fn sso_with_agreement() -> (String, Arc<Mutex<Vec<String>>>) {
+        serve(|root, request| {
+            if request.contains("isAgree=") {
+                redirect(&format!("{root}/service"))
+            } else if request.starts_with("POST") {
+                redirect(&format!("{root}/notice"))
+            } else if request.starts_with("GET /notice ") {
+                page(include_str!("fixtures/sso_agreement.html"))
+            } else {
+                page("welcome")
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn agreement_accepted() {
+        let (root, requests) = sso_with_agreement().await;
+        let client = DefaultClient::account("2300000101", "hunter2");
+        login_with_roles(&client, &root).await;
+
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[2].starts_with("POST /sso/login?service="));
+        assert!(requests[2].contains("isAgree=1"));
+        assert!(requests[2].contains("execution=e1s4"));
+        assert!(requests[3].starts_with("GET /service "));
+        assert_eq!(
+            client.login_warnings().await,
+            [format!("{} at {root}/notice", Agreement::ACCEPTED)]
+        );
+
+        // Left on the page when turned off
+        let (root, requests) = sso_with_agreement().await;
+        let client = DefaultClient::with_options(
+            Account::new("2300000101", "hunter2"),
+            ClientOptions {
+                accept_agreements: false,
+                ..Default::default()
+            },
+        );
+        login_with_roles(&client, &root).await;
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert!(client.login_warnings().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn redirects_recorded() {
+        let (root, _) = serve(|root, request| {
+            if request.starts_with("POST") {
+                redirect(&format!("{root}/cas?ticket=ST-9-abc&from=sso"))
+            } else if request.starts_with("GET /cas") {
+                redirect(&format!("{root}/service"))
+            } else {
+                page("welcome")
+            }
+        })
+        .await;
+        let client = DefaultClient::with_options(
+            Account::new("2300000101", "hunter2"),
+            ClientOptions {
+                record_login_redirects: true,
+                ..Default::default()
+            },
+        );
+        login_with_roles(&client, &root).await;
+
+        let hop = |url: String, status| RedirectHop { url, status };
+        assert_eq!(
+            client.login_redirects().await,
+            [
+                hop(format!("{root}/sso/login"), 302),
+                hop(format!("{root}/cas?ticket=redacted&from=sso"), 302),
+                hop(format!("{root}/service"), 200),
+            ]
+        );
+
+        // Off by default
+        let client = DefaultClient::account("2300000101", "hunter2");
+        login_with_roles(&client, &root).await;
+        assert!(client.login_redirects().await.is_empty());
+    }
+
+    #[test]
+    fn sso_variant_detected() {
+        let url = Url::parse("http://sso.cczu.edu.cn/sso/login?service=").unwrap();
+        let classic = include_str!("fixtures/sso_login.html");
+        let json = include_str!("fixtures/sso_login_json.html");
+
+        assert_eq!(json_login_api(classic, &url, SSOVariant::Auto), None);
+        assert_eq!(
+            json_login_api(json, &url, SSOVariant::Auto)
+                .unwrap()
+                .as_str(),
+            "http://sso.cczu.edu.cn/sso/api/login"
+        );
+        // Overridden
+        assert_eq!(json_login_api(json, &url, SSOVariant::Classic), None);
+        assert_eq!(
+            json_login_api(classic, &url, SSOVariant::Json)
+                .unwrap()
+                .as_str(),
+            "http://sso.cczu.edu.cn/sso/api/login"
+        );
+    }
+
+    #[tokio::test]
+    async fn json_login() {
+        let (root, requests) = serve(|root, request| {
+            let answer = if request.contains(r#""username":"2300000101""#) {
+                format!(r#"{{"code":0,"data":{{"redirect":"{root}/service?ticket=ST-1"}}}}"#)
+            } else {
+                r#"{"code":401,"message":"用户名或密码错误"}"#.to_string()
+            };
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{answer}",
+                answer.len()
+            )
+        })
+        .await;
+        let page = Url::parse(&format!(
+            "{root}/sso/login?service=http%3A%2F%2Fywtb.cczu.edu.cn%2Fpc%2Findex.html"
+        ))
+        .unwrap();
+        let api = Url::parse(&format!("{root}/sso/api/login")).unwrap();
+
+        let client = DefaultClient::account("2300000101", "hunter2");
+        let redirect = submit_json_login(&client, api.clone(), &page)
+            .await
+            .unwrap();
+        assert_eq!(redirect, format!("{root}/service?ticket=ST-1"));
+        let posted = requests.lock().unwrap()[0].clone();
+        assert!(posted.starts_with("POST /sso/api/login "));
+        assert!(posted.contains(r#""password":"aHVudGVyMg==""#));
+        assert!(posted.contains(r#""service":"http://ywtb.cczu.edu.cn/pc/index.html""#));
+
+        let client = DefaultClient::account("2300000102", "hunter2");
+        let error = submit_json_login(&client, api, &page).await.unwrap_err();
+        assert!(error.to_string().contains("用户名或密码错误"));
+    }
+}
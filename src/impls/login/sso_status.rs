@@ -7,7 +7,7 @@ use crate::{
         client::Client,
         typing::{other_error, TorErr},
     },
-    internals::fields::ROOT_SSO_LOGIN,
+    internals::{fields::ROOT_SSO_LOGIN, response::MeasuredSend},
 };
 
 use super::sso_type::SSOLoginConnectType;
@@ -27,7 +27,7 @@ impl<C: Client> SSOLoginStatus for C {
                 "{}?service=http://ywtb.cczu.edu.cn/pc/index.html",
                 ROOT_SSO_LOGIN
             ))
-            .send()
+            .send_measured(self)
             .await
         {
             if response.status() == StatusCode::OK {
@@ -51,16 +51,11 @@ impl<C: Client> SSOLoginStatus for C {
     }
 
     async fn sso_login_connect_type(&self) -> Option<SSOLoginConnectType> {
-        if let Some(property) = self
-            .properties()
+        self.properties()
             .read()
             .await
             .get(SSOLoginConnectType::key())
-        {
-            Some(property.clone().into())
-        } else {
-            None
-        }
+            .map(|property| property.clone().into())
     }
 
     async fn sso_login_type(&self) -> TorErr<SSOLoginConnectType> {
@@ -71,7 +66,7 @@ impl<C: Client> SSOLoginStatus for C {
         let response = self
             .reqwest_client()
             .get(ROOT_SSO_LOGIN)
-            .send()
+            .send_measured(self)
             .await
             .map_err(other_error)?;
         let statuscode = response.status();
@@ -13,9 +13,28 @@ pub struct ElinkLoginInfo {
     pub userid: String,
     #[serde(rename = "loginKey")]
     pub loginkey: String,
+    /// The redirects of the login with
+    /// [`crate::base::client::ClientOptions::record_login_redirects`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redirects: Vec<RedirectHop>,
 }
 
-#[derive(Debug, Clone)]
+/// A page reached by a redirect during the login, with the status it answered.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RedirectHop {
+    /// With the tickets of the query redacted
+    pub url: String,
+    pub status: u16,
+}
+
+impl RedirectHop {
+    #[inline(always)]
+    pub fn key() -> &'static str {
+        "sso-login-redirects"
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SSOLoginConnectType {
     WEBVPN,
     COMMON,
@@ -28,18 +47,18 @@ impl SSOLoginConnectType {
     }
 }
 
-impl Into<Property> for SSOLoginConnectType {
-    fn into(self) -> Property {
-        Property::Str(match self {
+impl From<SSOLoginConnectType> for Property {
+    fn from(val: SSOLoginConnectType) -> Self {
+        Property::Str(match val {
             SSOLoginConnectType::COMMON => "common",
             SSOLoginConnectType::WEBVPN => "webvpn",
         })
     }
 }
 
-impl Into<SSOLoginConnectType> for Property {
-    fn into(self) -> SSOLoginConnectType {
-        match self {
+impl From<Property> for SSOLoginConnectType {
+    fn from(val: Property) -> Self {
+        match val {
             Property::Str(data) => match data {
                 "common" => SSOLoginConnectType::COMMON,
                 "webvpn" => SSOLoginConnectType::WEBVPN,
@@ -50,9 +69,9 @@ impl Into<SSOLoginConnectType> for Property {
     }
 }
 
-impl Into<SSOLoginConnectType> for HashMap<&'static str, Property> {
-    fn into(self) -> SSOLoginConnectType {
-        self.get(SSOLoginConnectType::key())
+impl From<HashMap<&'static str, Property>> for SSOLoginConnectType {
+    fn from(val: HashMap<&'static str, Property>) -> Self {
+        val.get(SSOLoginConnectType::key())
             .expect("Can't get LoginConnectType! Need Login?")
             .clone()
             .into()
@@ -62,4 +81,152 @@ impl Into<SSOLoginConnectType> for HashMap<&'static str, Property> {
 pub struct SSOUniversalLoginInfo {
     pub response: Response,
     pub login_connect_type: SSOLoginConnectType,
-}
\ No newline at end of file
+}
+
+/// What is left of a login stopped by the second factor page.
+///
+/// The cookies stay in the client, so keeping the form is enough to resume the same handshake.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SecondFactorContext {
+    pub method: String,
+    pub hint: String,
+    /// Absolute url the code is posted to
+    pub action: String,
+    /// Name of the input receiving the code
+    pub code_field: String,
+    pub fields: HashMap<String, String>,
+}
+
+impl SecondFactorContext {
+    #[inline(always)]
+    pub fn key() -> &'static str {
+        "sso-second-factor"
+    }
+}
+
+/// Checkpoint of a login between fetching the form and posting it.
+///
+/// Until the form is posted its execution token is still good, so a login interrupted there
+/// (dropped future, failed request) posts it again instead of starting over.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct LoginState {
+    pub webvpn: bool,
+    /// Absolute url the form is posted to
+    pub action: String,
+    /// Hidden fields of the form, with the execution token
+    pub fields: HashMap<String, String>,
+    /// Unix seconds the form was fetched at
+    pub fetched_at: u64,
+}
+
+impl LoginState {
+    /// Older forms are fetched again, SSO may have dropped their flow.
+    pub const MAX_AGE: u64 = 300;
+
+    #[inline(always)]
+    pub fn key() -> &'static str {
+        "sso-login-state"
+    }
+}
+
+/// The login form as the login reads it, see
+/// [`crate::impls::login::sso::SSOUniversalLogin::debug_login_form`].
+///
+/// The account is never in it, it is added to these fields when posting.
+#[cfg(any(test, feature = "testing"))]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoginFormDebug {
+    pub webvpn: bool,
+    /// Absolute url the form is posted to, the one of the page
+    pub action: String,
+    pub execution: Option<String>,
+    pub lt: Option<String>,
+    /// From a hidden input or a `<meta>` of the page
+    pub csrf: Option<String>,
+    /// Every hidden input, posted as they are
+    pub fields: HashMap<String, String>,
+}
+
+/// What the JSON login of the newer SSO answers.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JsonLoginAnswer {
+    /// `0` once logged in
+    pub code: i32,
+    #[serde(default)]
+    pub message: String,
+    pub data: Option<JsonLoginData>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JsonLoginData {
+    /// Where the browser goes next, the service with its ticket
+    pub redirect: String,
+}
+
+/// A terms-of-use or notice page to agree to before the service, answered by posting its form.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Agreement {
+    /// Absolute url the agreement is posted to
+    pub action: String,
+    /// Hidden fields of the form, with the agreement input checked
+    pub fields: HashMap<String, String>,
+}
+
+impl Agreement {
+    /// Start of the line of [`crate::impls::login::sso::SSOUniversalLogin::login_warnings`]
+    /// left by an agreement accepted on behalf of the user, followed by the url of its page.
+    pub const ACCEPTED: &'static str = "Agreement accepted";
+
+    /// Key of the lines of [`crate::impls::login::sso::SSOUniversalLogin::login_warnings`]
+    #[inline(always)]
+    pub fn warnings_key() -> &'static str {
+        "sso-login-warnings"
+    }
+}
+
+/// One role of an account with several, like a student also working as a TA.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SSORole {
+    /// Value posted for the role
+    pub id: String,
+    /// Label of the role on the page
+    pub name: String,
+}
+
+impl SSORole {
+    pub fn is_student(&self) -> bool {
+        self.name.contains("学生") || self.id.eq_ignore_ascii_case("student")
+    }
+}
+
+/// The page asking an account with several roles which one to log in as.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RoleSelection {
+    /// Absolute url the role is posted to
+    pub action: String,
+    /// Name of the input receiving the role
+    pub role_field: String,
+    pub fields: HashMap<String, String>,
+    pub roles: Vec<SSORole>,
+}
+
+impl RoleSelection {
+    #[inline(always)]
+    pub fn key() -> &'static str {
+        "sso-role-selection"
+    }
+
+    /// Key of the role id chosen by the user, picked on the next selection page.
+    #[inline(always)]
+    pub fn chosen_key() -> &'static str {
+        "sso-role"
+    }
+
+    /// The student role, or the first one for an account without it.
+    pub fn default_role(&self) -> Option<&SSORole> {
+        self.roles
+            .iter()
+            .find(|role| role.is_student())
+            .or(self.roles.first())
+    }
+}
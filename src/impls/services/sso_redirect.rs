@@ -10,7 +10,7 @@ use crate::internals::{
     fields::{ROOT_SSO_URL, ROOT_VPN_URL},
 };
 
-pub const STATIC_SERVER_MAP: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
+pub static STATIC_SERVER_MAP: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
     let mut map = HashMap::new();
 
     // jwcas
@@ -50,7 +50,7 @@ impl<C: Client> SSORedirect for C {
             SSOLoginConnectType::WEBVPN => &ROOT_VPN_URL,
             SSOLoginConnectType::COMMON => &ROOT_SSO_URL,
         };
-        let cookies = self.cookies().lock().unwrap().headers(&from);
+        let cookies = self.cookies().lock().unwrap().headers(from);
         let mut headers = DEFAULT_HEADERS.clone();
         headers.insert(COOKIE, cookies.parse().unwrap());
         headers
@@ -7,7 +7,10 @@ use crate::{
         client::Client,
         typing::{other_error, TorErr},
     },
-    internals::fields::{DEFAULT_HEADERS, ROOT_SSO_LOGIN, ROOT_VPN},
+    internals::{
+        fields::{DEFAULT_HEADERS, ROOT_SSO_LOGIN, ROOT_VPN},
+        response::{read_text, MeasuredSend},
+    },
 };
 
 use super::webvpn_type::{
@@ -55,12 +58,11 @@ impl<C: Client> WebVPNService for C {
                 user_id.into()
             ))
             .headers(DEFAULT_HEADERS.clone())
-            .send()
+            .send_measured(self)
             .await
         {
-            if let Ok(json) = response.text().await {
-                return Ok(serde_json::from_str(json.as_str())?);
-            }
+            let json = read_text(response, self.options().max_response_size).await?;
+            return Ok(serde_json::from_str(json.as_str())?);
         }
         Err(other_error("Get User Info failed"))
     }
@@ -84,12 +86,11 @@ impl<C: Client> WebVPNService for C {
             .header("Origin", ROOT_VPN)
             .header("Content-Type", "application/json;charset=utf-8")
             .body(serde_json::to_string(&body).unwrap())
-            .send()
+            .send_measured(self)
             .await
         {
-            if let Ok(json) = response.text().await {
-                return Ok(serde_json::from_str(json.as_str())?);
-            }
+            let json = read_text(response, self.options().max_response_size).await?;
+            return Ok(serde_json::from_str(json.as_str())?);
         }
         Err(other_error("Get Tree Service failed"))
     }
@@ -111,12 +112,11 @@ impl<C: Client> WebVPNService for C {
             .header("Referer", format!("{}/enlink/", ROOT_VPN))
             .header("Origin", ROOT_VPN)
             .query(&param)
-            .send()
+            .send_measured(self)
             .await
         {
-            if let Ok(json) = response.text().await {
-                return Ok(serde_json::from_str(json.as_str())?);
-            }
+            let json = read_text(response, self.options().max_response_size).await?;
+            return Ok(serde_json::from_str(json.as_str())?);
         }
         Err(other_error("Get User Service failed"))
     }
@@ -137,19 +137,23 @@ impl<C: Client> WebVPNService for C {
             .headers(DEFAULT_HEADERS.clone())
             .header("Referer", format!("{}/enlink/", ROOT_VPN))
             .query(&param)
-            .send()
+            .send_measured(self)
             .await
         {
-            if let Ok(json) = response.text().await {
-                return Ok(serde_json::from_str(json.as_str())?);
-            }
+            let json = read_text(response, self.options().max_response_size).await?;
+            return Ok(serde_json::from_str(json.as_str())?);
         }
         Err(other_error("Get User Visit Service failed"))
     }
 
     /// Client Redirect Policy: [`reqwest::redirect::Policy::none()`]
     async fn webvpn_available(&self) -> bool {
-        if let Ok(response) = self.reqwest_client().get(ROOT_SSO_LOGIN).send().await {
+        if let Ok(response) = self
+            .reqwest_client()
+            .get(ROOT_SSO_LOGIN)
+            .send_measured(self)
+            .await
+        {
             return response.status() == StatusCode::FOUND;
         }
         false
@@ -166,12 +170,11 @@ impl<C: Client> WebVPNService for C {
                 ROOT_VPN,
                 user_id.into()
             ))
-            .send()
+            .send_measured(self)
             .await
         {
-            if let Ok(json) = response.text().await {
-                return Ok(serde_json::from_str(json.as_str())?);
-            }
+            let json = read_text(response, self.options().max_response_size).await?;
+            return Ok(serde_json::from_str(json.as_str())?);
         }
         Err(other_error("Get Proxy Service failed"))
     }
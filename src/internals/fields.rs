@@ -15,10 +15,10 @@ pub static DEFAULT_HEADERS: LazyLock<HeaderMap> = LazyLock::new(|| {
     );
     headers
 });
-pub const ROOT_SSO: &'static str = "http://sso.cczu.edu.cn";
-pub const ROOT_SSO_URL: LazyLock<Url> = LazyLock::new(|| Url::parse(ROOT_SSO).unwrap());
-pub const ROOT_SSO_LOGIN: &'static str = "http://sso.cczu.edu.cn/sso/login";
-pub const ROOT_VPN: &'static str = "https://zmvpn.cczu.edu.cn";
-pub const ROOT_VPN_URL: LazyLock<Url> = LazyLock::new(|| Url::parse(ROOT_VPN).unwrap());
-pub const ROOT_YWTB: &'static str = "http://ywtb.cczu.edu.cn";
-pub const WECHAT_APP_API: &'static str = "http://202.195.102.7:8180";
+pub const ROOT_SSO: &str = "http://sso.cczu.edu.cn";
+pub static ROOT_SSO_URL: LazyLock<Url> = LazyLock::new(|| Url::parse(ROOT_SSO).unwrap());
+pub const ROOT_SSO_LOGIN: &str = "http://sso.cczu.edu.cn/sso/login";
+pub const ROOT_VPN: &str = "https://zmvpn.cczu.edu.cn";
+pub static ROOT_VPN_URL: LazyLock<Url> = LazyLock::new(|| Url::parse(ROOT_VPN).unwrap());
+pub const ROOT_YWTB: &str = "http://ywtb.cczu.edu.cn";
+pub const WECHAT_APP_API: &str = "http://202.195.102.7:8180";
@@ -1,3 +1,5 @@
 pub mod cookies_io;
 pub mod fields;
 pub mod recursion;
+pub mod response;
+pub mod trace;
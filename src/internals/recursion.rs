@@ -1,12 +1,70 @@
+use std::{future::Future, sync::Mutex};
+
 use crate::{
     base::{
         client::Client,
         typing::{other_error, TorErr},
     },
-    internals::fields::DEFAULT_HEADERS,
+    impls::login::sso_type::RedirectHop,
+    internals::{fields::DEFAULT_HEADERS, response::MeasuredSend},
 };
 use async_recursion::async_recursion;
-use reqwest::{header::LOCATION, Response, StatusCode};
+use reqwest::{header::LOCATION, Response, StatusCode, Url};
+use tracing::debug;
+
+tokio::task_local! {
+    /// The redirects followed inside [`record_redirects`]
+    static REDIRECTS: Mutex<Vec<RedirectHop>>;
+}
+
+/// `future`, with the redirects [`recursion_redirect_handle`] and [`record_redirect`] see while
+/// it runs.
+pub async fn record_redirects<T>(future: impl Future<Output = T>) -> (T, Vec<RedirectHop>) {
+    REDIRECTS
+        .scope(Mutex::new(vec![]), async {
+            let output = future.await;
+            let hops = REDIRECTS.with(|hops| std::mem::take(&mut *hops.lock().unwrap()));
+            (output, hops)
+        })
+        .await
+}
+
+/// Keep `url` and its `status` in the redirects of [`record_redirects`], if any.
+pub fn record_redirect(url: &str, status: StatusCode) {
+    let _ = REDIRECTS.try_with(|hops| {
+        hops.lock().unwrap().push(RedirectHop {
+            url: redact_tickets(url),
+            status: status.as_u16(),
+        })
+    });
+}
+
+/// `url` with the value of the tickets of its query (`ticket=ST-...`, a `TGT-...`) replaced,
+/// whoever reads them could use them.
+pub fn redact_tickets(url: &str) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.split('?').next().unwrap_or_default().to_owned();
+    };
+    if parsed.query().is_none() {
+        return url.to_owned();
+    }
+    let pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .map(|(name, value)| {
+            let ticket = name.to_ascii_lowercase().contains("ticket")
+                || value.starts_with("ST-")
+                || value.starts_with("TGT-");
+            let value = if ticket {
+                "redacted".into()
+            } else {
+                value.into_owned()
+            };
+            (name.into_owned(), value)
+        })
+        .collect();
+    parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    parsed.to_string()
+}
 
 #[async_recursion]
 pub async fn recursion_redirect_handle(
@@ -17,9 +75,13 @@ pub async fn recursion_redirect_handle(
         .reqwest_client()
         .get(url)
         .headers(DEFAULT_HEADERS.clone())
-        .send()
+        .send_measured(&client)
         .await
     {
+        // Without the query, it may hold a service ticket
+        let path = url.split('?').next().unwrap_or_default();
+        debug!(url = path, status = %response.status(), "Redirect followed");
+        record_redirect(url, response.status());
         if response.status() == StatusCode::FOUND {
             return recursion_redirect_handle(
                 client,
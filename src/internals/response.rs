@@ -0,0 +1,441 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use bytes::{Bytes, BytesMut};
+use reqwest::{
+    header::{HeaderMap, CONTENT_TYPE, LOCATION, RETRY_AFTER},
+    RequestBuilder, Response, StatusCode, Url,
+};
+use serde::de::DeserializeOwned;
+#[cfg(any(test, feature = "record-fixtures"))]
+use std::sync::Arc;
+
+use crate::base::{
+    cache::{CachedResponse, ResponseCache},
+    client::Client,
+    metrics::RequestOutcome,
+    typing::{other_error, typed_error, CczuError, TorErr},
+};
+#[cfg(any(test, feature = "record-fixtures"))]
+use crate::utils::record::FixtureRecorder;
+
+/// Send the requests of a client, telling its [`crate::base::metrics::Metrics`].
+pub trait MeasuredSend {
+    /// [`RequestBuilder::send`], timed and reported to the metrics of `client` if it has some.
+    fn send_measured(
+        self,
+        client: &impl Client,
+    ) -> impl Future<Output = reqwest::Result<Response>> + Send;
+}
+
+impl MeasuredSend for RequestBuilder {
+    fn send_measured(
+        self,
+        client: &impl Client,
+    ) -> impl Future<Output = reqwest::Result<Response>> + Send {
+        let metrics = client.metrics();
+        #[cfg(any(test, feature = "record-fixtures"))]
+        let recorder = client
+            .fixture_recorder()
+            .inspect(|recorder| recorder.learn(client));
+        async move {
+            let result = match metrics {
+                None => self.send().await,
+                Some(metrics) => {
+                    let (http, request) = self.build_split();
+                    let request = request?;
+                    let endpoint = endpoint(request.url());
+                    let start = Instant::now();
+                    let result = http.execute(request).await;
+                    metrics.on_request(&endpoint, start.elapsed(), RequestOutcome::of(&result));
+                    result
+                }
+            };
+            // Picked up by `read_bytes`, which has no client
+            #[cfg(any(test, feature = "record-fixtures"))]
+            let result = result.map(|mut response| {
+                if let Some(recorder) = recorder {
+                    response.extensions_mut().insert(recorder);
+                }
+                response
+            });
+            result
+        }
+    }
+}
+
+/// Host and path, the query holds ids and tickets that would make every request its own.
+fn endpoint(url: &Url) -> String {
+    format!("{}{}", url.host_str().unwrap_or_default(), url.path())
+}
+
+/// `Retry-After` in seconds, its HTTP date form is not sent by the portals.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds = headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+/// Read the whole body, but stop as soon as it goes past `limit` bytes.
+///
+/// A `429` is [`CczuError::RateLimited`], whatever its body. Sent by a client recording
+/// fixtures, the body is written as one, see [`crate::utils::record`].
+pub async fn read_bytes(mut response: Response, limit: usize) -> TorErr<Bytes> {
+    if response.status() == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = retry_after(response.headers());
+        return Err(typed_error(CczuError::RateLimited { retry_after }));
+    }
+    let too_large = || typed_error(CczuError::ResponseTooLarge { limit });
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit as u64)
+    {
+        return Err(too_large());
+    }
+
+    #[cfg(any(test, feature = "record-fixtures"))]
+    let recording = response
+        .extensions()
+        .get::<Arc<FixtureRecorder>>()
+        .cloned()
+        .map(|recorder| (recorder, response.url().clone()));
+    let mut body = BytesMut::new();
+    while let Some(chunk) = response.chunk().await.map_err(other_error)? {
+        if body.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    #[cfg(any(test, feature = "record-fixtures"))]
+    if let Some((recorder, url)) = recording {
+        // A fixture not written is no reason to fail the request
+        let _ = recorder.record(&url, &body);
+    }
+    Ok(body.freeze())
+}
+
+/// Inputs only a login page has, never the data of an app
+const LOGIN_WALL_MARKERS: [&str; 2] = ["type=\"password\"", "type='password'"];
+
+/// A login page, told by its password input. `html` is the `Content-Type` saying so,
+/// portals answering their errors as `application/json` or `text/plain` are caught by the `<`.
+pub fn is_login_wall(html: bool, body: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(body).to_ascii_lowercase();
+    (html || text.trim_start().starts_with('<'))
+        && LOGIN_WALL_MARKERS
+            .iter()
+            .any(|marker| text.contains(marker))
+}
+
+/// What the firewall of the campus says on the page it serves to a blocked IP
+const BLOCK_PAGE_MARKERS: [&str; 4] =
+    ["访问过于频繁", "请求过于频繁", "访问频率过高", "ip已被封禁"];
+
+/// The page served instead of the data while the IP is blocked, with a `200` or a `403`.
+pub fn is_block_page(html: bool, body: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(body).to_lowercase();
+    (html || text.trim_start().starts_with('<'))
+        && BLOCK_PAGE_MARKERS
+            .iter()
+            .any(|marker| text.contains(marker))
+}
+
+fn is_html(response: &Response) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("html"))
+}
+
+/// [`read_bytes`] for the data of an app, which once the session is gone redirects to the
+/// login or serves its page with a `200`: that is [`CczuError::SessionExpired`], not a body.
+/// The page of a blocked IP is [`CczuError::RateLimited`].
+pub async fn read_app_bytes(response: Response, limit: usize) -> TorErr<Bytes> {
+    let to_login = response.status().is_redirection()
+        && response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .is_some_and(|location| location.to_ascii_lowercase().contains("login"));
+    if to_login {
+        return Err(typed_error(CczuError::SessionExpired));
+    }
+
+    let html = is_html(&response);
+    let retry_after = retry_after(response.headers());
+    let body = read_bytes(response, limit).await?;
+    if is_login_wall(html, &body) {
+        return Err(typed_error(CczuError::SessionExpired));
+    }
+    if is_block_page(html, &body) {
+        return Err(typed_error(CczuError::RateLimited { retry_after }));
+    }
+    Ok(body)
+}
+
+pub async fn read_text(response: Response, limit: usize) -> TorErr<String> {
+    let bytes = read_bytes(response, limit).await?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub async fn read_json<T: DeserializeOwned>(response: Response, limit: usize) -> TorErr<T> {
+    let bytes = read_bytes(response, limit).await?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+pub async fn read_cached_response(response: Response, limit: usize) -> TorErr<CachedResponse> {
+    let url = response.url().clone();
+    let status = response.status();
+    let version = response.version();
+    let headers = response.headers().clone();
+    let body = read_bytes(response, limit).await?;
+    Ok(CachedResponse {
+        url,
+        status,
+        version,
+        headers,
+        body,
+    })
+}
+
+/// Read the body but hand a [`Response`] back, for the pages we need to look into
+/// before knowing if the caller should get them.
+pub async fn buffer_response(response: Response, limit: usize) -> TorErr<(String, Response)> {
+    let buffered = read_cached_response(response, limit).await?;
+    Ok((
+        String::from_utf8_lossy(&buffered.body).into_owned(),
+        buffered.to_response()?,
+    ))
+}
+
+/// Send a read request through the client's [`ResponseCache`], if it has one.
+///
+/// Only for requests that don't change anything on the portal.
+pub async fn send_cached(client: &impl Client, request: RequestBuilder) -> TorErr<Response> {
+    let Some(cache) = client.cache() else {
+        return request.send_measured(client).await.map_err(other_error);
+    };
+
+    let (http, request) = request.build_split();
+    let request = request.map_err(other_error)?;
+    let key = ResponseCache::key(
+        request.method(),
+        request.url(),
+        request.body().and_then(|body| body.as_bytes()),
+    );
+    if let Some(cached) = cache.get(&key) {
+        return cached.to_response();
+    }
+
+    let response = RequestBuilder::from_parts(http, request)
+        .send_measured(client)
+        .await
+        .map_err(other_error)?;
+    if !response.status().is_success() {
+        return Ok(response);
+    }
+    let html = is_html(&response);
+    let buffered = read_cached_response(response, client.options().max_response_size).await?;
+    // Served again after the next login otherwise
+    if !is_login_wall(html, &buffered.body) {
+        cache.insert(key, buffered.clone());
+    }
+    buffered.to_response()
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use reqwest::Url;
+
+    use super::{is_login_wall, read_bytes, send_cached, MeasuredSend};
+    use crate::{
+        base::{
+            cache::ResponseCache,
+            client::Client,
+            metrics::{Metrics, RequestOutcome},
+            typing::{CczuError, TypedErrorExt},
+        },
+        impls::client::DefaultClient,
+        utils::testing::{TestResponse, TestServer},
+    };
+
+    /// Answer every connection with `size` bytes, with or without a `Content-Length`.
+    async fn serve(size: usize, content_length: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let head = if content_length {
+                    format!("HTTP/1.1 200 OK\r\nContent-Length: {size}\r\n\r\n")
+                } else {
+                    "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n".to_string()
+                };
+                let _ = stream.write_all(head.as_bytes()).await;
+                let _ = stream.write_all(&vec![b'a'; size]).await;
+            }
+        });
+        format!("http://{address}/")
+    }
+
+    #[tokio::test]
+    async fn oversized_body() {
+        for content_length in [true, false] {
+            let url = serve(64 * 1024, content_length).await;
+
+            let response = reqwest::get(&url).await.unwrap();
+            let error = read_bytes(response, 1024).await.unwrap_err();
+            assert_eq!(
+                error.typed(),
+                Some(&CczuError::ResponseTooLarge { limit: 1024 })
+            );
+
+            let response = reqwest::get(&url).await.unwrap();
+            assert_eq!(
+                read_bytes(response, 64 * 1024).await.unwrap().len(),
+                64 * 1024
+            );
+        }
+    }
+
+    /// Answer `ok` to every request and count them.
+    async fn counting() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                counter.fetch_add(1, Ordering::SeqCst);
+                let _ = stream
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                    )
+                    .await;
+            }
+        });
+        (format!("http://{address}"), count)
+    }
+
+    #[tokio::test]
+    async fn cached_reads() {
+        let (root, count) = counting().await;
+        let client = DefaultClient::default().with_cache(
+            ResponseCache::new(Duration::from_secs(60)).with_ttl("/live", Duration::ZERO),
+        );
+        let get = |path: &str| {
+            let request = client.reqwest_client().get(format!("{root}{path}"));
+            send_cached(&client, request)
+        };
+
+        for _ in 0..2 {
+            let response = get("/schedule").await.unwrap();
+            assert_eq!(response.text().await.unwrap(), "ok");
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        // Another body is another entry
+        let post = client
+            .reqwest_client()
+            .post(format!("{root}/schedule"))
+            .body("xq=1");
+        send_cached(&client, post).await.unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+
+        client
+            .cache()
+            .unwrap()
+            .invalidate(&Url::parse(&format!("{root}/schedule")).unwrap());
+        get("/schedule").await.unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+
+        get("/live").await.unwrap();
+        get("/live").await.unwrap();
+        assert_eq!(count.load(Ordering::SeqCst), 5);
+
+        // No cache unless asked for
+        let client = DefaultClient::default();
+        for _ in 0..2 {
+            let request = client.reqwest_client().get(format!("{root}/schedule"));
+            send_cached(&client, request).await.unwrap();
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 7);
+    }
+
+    #[test]
+    fn login_wall() {
+        let page = br#"<html><form><input type="password" name="pwd"></form></html>"#;
+        assert!(is_login_wall(false, page));
+        assert!(is_login_wall(true, b"\n<INPUT TYPE='PASSWORD'>"));
+        // A record that happens to quote the input, and a data page
+        assert!(!is_login_wall(false, br#"{"note":"type=\"password\""}"#));
+        assert!(!is_login_wall(true, b"<table><tr><td>92</td></tr></table>"));
+    }
+
+    #[derive(Debug, Default)]
+    struct Recorded(Arc<Mutex<Vec<(String, RequestOutcome)>>>);
+
+    impl Metrics for Recorded {
+        fn on_request(&self, endpoint: &str, _duration: Duration, outcome: RequestOutcome) {
+            self.0.lock().unwrap().push((endpoint.to_owned(), outcome));
+        }
+    }
+
+    #[tokio::test]
+    async fn metrics_per_request() {
+        let server = TestServer::start().await;
+        server
+            .route("GET", "/api/ok", TestResponse::json("{}"))
+            .route("GET", "/api/moved", TestResponse::redirect("/api/ok"))
+            .route("GET", "/api/down", TestResponse::new(503, "text/plain", ""));
+        let recorded = Recorded::default();
+        let seen = recorded.0.clone();
+        let client = DefaultClient::default().with_metrics(recorded);
+
+        for path in ["/api/ok?xh=1", "/api/moved", "/api/missing", "/api/down"] {
+            let request = client.reqwest_client().get(server.url(path));
+            request.send_measured(&client).await.unwrap();
+        }
+        let closed = client.reqwest_client().get("http://127.0.0.1:9/api/ok");
+        assert!(closed.send_measured(&client).await.is_err());
+
+        let seen = seen.lock().unwrap().clone();
+        let endpoint = |path: &str| format!("127.0.0.1{path}");
+        assert_eq!(
+            seen,
+            [
+                (endpoint("/api/ok"), RequestOutcome::Success),
+                (endpoint("/api/moved"), RequestOutcome::Redirect),
+                (endpoint("/api/missing"), RequestOutcome::ClientError),
+                (endpoint("/api/down"), RequestOutcome::ServerError),
+                (endpoint("/api/ok"), RequestOutcome::NetworkError),
+            ]
+        );
+        // The redirect is not followed, the client leaves it to the logins
+        assert_eq!(server.requests().len(), 4);
+    }
+}
@@ -0,0 +1,66 @@
+use std::{
+    future::Future,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use tracing::{debug, info_span, Instrument};
+
+use crate::base::typing::{OperationError, TorErr, TypedErrorExt};
+
+static NEXT_OPERATION: AtomicU64 = AtomicU64::new(1);
+
+/// Run `future` in a span with a new operation id, so its events can be told apart from the
+/// ones of another login running at the same time. The id is also put in the error.
+///
+/// Inside another operation, the error gets the id of the inner one: its log lines are in
+/// both spans.
+pub async fn traced<T>(name: &'static str, future: impl Future<Output = TorErr<T>>) -> TorErr<T> {
+    let operation = NEXT_OPERATION.fetch_add(1, Ordering::Relaxed);
+    let span = info_span!("operation", name, operation);
+    let result = future.instrument(span.clone()).await;
+    result.map_err(|error| {
+        if error.operation().is_some() {
+            return error;
+        }
+        debug!(parent: &span, %error, "Operation failed");
+        tokio::io::Error::new(
+            error.kind(),
+            OperationError {
+                operation,
+                source: error,
+            },
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::traced;
+    use crate::base::typing::{other_error, typed_error, CczuError, TorErr, TypedErrorExt};
+
+    #[tokio::test]
+    async fn distinct_operations() {
+        let fail =
+            |error: tokio::io::Error| traced("sso_login", async { TorErr::<()>::Err(error) });
+        let (first, second) = tokio::join!(
+            fail(typed_error(CczuError::Forbidden)),
+            fail(other_error("Login Failed"))
+        );
+        let (first, second) = (first.unwrap_err(), second.unwrap_err());
+
+        let (first_id, second_id) = (first.operation().unwrap(), second.operation().unwrap());
+        assert_ne!(first_id, second_id);
+        assert_eq!(first.typed(), Some(&CczuError::Forbidden));
+        assert_eq!(
+            second.to_string(),
+            format!("Login Failed (operation {second_id})")
+        );
+
+        // Nested, only wrapped once
+        let nested = traced("outer", fail(other_error("Login Failed")))
+            .await
+            .unwrap_err();
+        assert_eq!(nested.unwrap_operation().to_string(), "Login Failed");
+        assert!(!nested.to_string().contains(") (operation"));
+    }
+}
@@ -1,6 +1,6 @@
 pub mod base;
-pub mod impls;
 pub mod ffi;
+pub mod impls;
 #[cfg(feature = "internals")]
 pub mod internals;
 #[cfg(not(feature = "internals"))]
@@ -18,14 +18,25 @@ mod test {
             app::{AppVisitor, Application},
             client::Client,
         },
-        extension::calendar::{parse_week_matrix, CalendarParser},
+        extension::calendar::{parse_week_matrix, CalendarParser, TermCalendarParser},
         impls::{
-            apps::{sso::jwcas::JwcasApplication, wechat::jwqywx::JwqywxApplication},
+            apps::{
+                iccard::{
+                    iccard::ICCardApplication, iccard_constants::PRESET_DORMBUILDINGS,
+                    iccard_type::DormBuilding,
+                },
+                sso::jwcas::JwcasApplication,
+                wechat::jwqywx::JwqywxApplication,
+            },
             client::DefaultClient,
-            login::sso::SSOUniversalLogin,
-            services::webvpn::WebVPNService,
+            login::{
+                sso::SSOUniversalLogin, sso_status::SSOLoginStatus, sso_type::SSOLoginConnectType,
+                webvpn::WebVPNLogin,
+            },
+            services::{sso_redirect::SSORedirect, webvpn::WebVPNService},
         },
         internals::recursion::recursion_redirect_handle,
+        utils::{fanout::check_new_grades_for_all, status::services_health},
     };
     #[tokio::test]
     async fn test_webvpn() {
@@ -73,6 +84,61 @@ mod test {
         });
     }
 
+    fn assert_send<T: Send>(_: T) {}
+
+    /// Never polled: only built to check they can be spawned, see [`DefaultClient`].
+    #[tokio::test]
+    async fn futures_are_send() {
+        let client = DefaultClient::default();
+        assert_send(client.sso_universal_login());
+        assert_send(client.sso_service_login(""));
+        assert_send(client.submit_second_factor(""));
+        assert_send(client.available_roles());
+        assert_send(client.select_role(""));
+        assert_send(client.sso_login_available());
+        assert_send(client.sso_login_type());
+        assert_send(client.sso_redirect(""));
+        assert_send(client.webvpn_login());
+        assert_send(client.webvpn_available());
+        assert_send(client.visit::<JwqywxApplication<_>>());
+        assert_send(client.overview(chrono::NaiveDate::MIN));
+        assert_send(client.selftest());
+        assert_send(check_new_grades_for_all(Vec::<((), _, _)>::new(), 1));
+        assert_send(services_health());
+
+        let jwqywx = client.visit::<JwqywxApplication<_>>().await;
+        assert_send(jwqywx.login());
+        assert_send(jwqywx.get_grades());
+        assert_send(jwqywx.get_grades_detailed());
+        assert_send(jwqywx.grade_detail("", 0));
+        assert_send(jwqywx.grades_since(""));
+        assert_send(jwqywx.get_credits_and_rank());
+        assert_send(jwqywx.terms_with_data());
+        assert_send(jwqywx.current_term());
+        assert_send(jwqywx.get_classinfo_week_matrix());
+        assert_send(jwqywx.get_term_classinfo_week_matrix(String::new()));
+
+        // Visited as logged in on campus
+        client.properties().write().await.insert(
+            SSOLoginConnectType::key(),
+            SSOLoginConnectType::COMMON.into(),
+        );
+        let jwcas = client.visit::<JwcasApplication<_>>().await;
+        assert_send(jwcas.get_gradeinfo_vec());
+        assert_send(jwcas.get_classinfo_week_matrix());
+
+        let iccard = client.visit::<ICCardApplication<_, _>>().await;
+        assert_send(iccard.list_all_preset_buildings());
+        assert_send(iccard.query_electricity_bill(
+            PRESET_DORMBUILDINGS[0].clone(),
+            DormBuilding {
+                building: String::new(),
+                buildingid: String::new(),
+            },
+            "",
+        ));
+    }
+
     #[tokio::test]
     async fn calendar() {
         let client = DefaultClient::default();
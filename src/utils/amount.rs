@@ -0,0 +1,143 @@
+//! Amounts of money as the portals print them, `¥1,234.50`, `1234.5` or `12,50 元`, read
+//! into exact cents.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// An amount in cents (分) of yuan, negative for a debt. Never a float, so the balance shown
+/// is the one of the portal to the cent.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct Money(i64);
+
+/// Around the number, dropped before reading it
+const CURRENCY_MARKS: [&str; 6] = ["¥", "￥", "$", "元", "RMB", "CNY"];
+
+impl Money {
+    pub const ZERO: Self = Self(0);
+
+    pub fn from_cents(cents: i64) -> Self {
+        Self(cents)
+    }
+
+    pub fn cents(self) -> i64 {
+        self.0
+    }
+
+    /// `None` when `text` is not an amount, or has a fraction of a cent.
+    ///
+    /// The currency marks and spaces are dropped. With both `,` and `.`, the last one is the
+    /// decimal separator and the other groups the thousands. With one of them only, it is the
+    /// decimal separator unless it is repeated, or a single `,` followed by three digits
+    /// (`1,234` is a thousand).
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut text = text.to_owned();
+        for mark in CURRENCY_MARKS {
+            text = text.replace(mark, "");
+        }
+        text.retain(|c| !c.is_whitespace());
+
+        let (negative, number) = match text.strip_prefix('-') {
+            Some(number) => (true, number),
+            None => (false, text.strip_prefix('+').unwrap_or(&text)),
+        };
+        let decimal = match (number.rfind('.'), number.rfind(',')) {
+            (Some(dot), Some(comma)) => Some(dot.max(comma)),
+            (Some(dot), None) => (number.matches('.').count() == 1).then_some(dot),
+            (None, Some(comma)) => {
+                (number.matches(',').count() == 1 && number.len() - comma - 1 != 3).then_some(comma)
+            }
+            (None, None) => None,
+        };
+        let (whole, fraction) = match decimal {
+            Some(at) => (&number[..at], &number[at + 1..]),
+            None => (number, ""),
+        };
+
+        let groups: Vec<&str> = whole.split([',', '.']).collect();
+        let grouped = groups.len() > 1;
+        let well_grouped = groups.iter().enumerate().all(|(index, group)| match index {
+            0 => !grouped || (1..=3).contains(&group.len()),
+            _ => group.len() == 3,
+        });
+        let digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+        if !well_grouped || !groups.iter().all(|group| digits(group)) || !digits(fraction) {
+            return None;
+        }
+        let whole = groups.concat();
+        // `1,` or `.` are not amounts
+        if (decimal.is_some() && fraction.is_empty()) || (whole.is_empty() && fraction.is_empty()) {
+            return None;
+        }
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.len() > 2 {
+            return None;
+        }
+
+        let yuan: i64 = if whole.is_empty() {
+            0
+        } else {
+            whole.parse().ok()?
+        };
+        let cents: i64 = format!("{fraction:0<2}").parse().ok()?;
+        let total = yuan.checked_mul(100)?.checked_add(cents)?;
+        Some(Self(if negative { -total } else { total }))
+    }
+}
+
+impl Display for Money {
+    /// Like `-1234.50`, without a currency mark.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let cents = self.0.unsigned_abs();
+        write!(f, "{sign}{}.{:02}", cents / 100, cents % 100)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Money;
+
+    #[test]
+    fn exact_cents() {
+        let cents = |text| Money::parse(text).map(Money::cents);
+        assert_eq!(cents("¥1,234.50"), Some(123450));
+        assert_eq!(cents("1234.5"), Some(123450));
+        assert_eq!(cents("1234"), Some(123400));
+        assert_eq!(cents(" ￥ 8 元"), Some(800));
+        assert_eq!(cents("-3.20"), Some(-320));
+        assert_eq!(cents("1.234,50"), Some(123450));
+        assert_eq!(cents("12,5"), Some(1250));
+        assert_eq!(cents("1,234"), Some(123400));
+        assert_eq!(cents("1,234,567.89"), Some(123456789));
+        assert_eq!(cents("0.10"), Some(10));
+        assert_eq!(cents(".5"), Some(50));
+        assert_eq!(cents("12.500"), Some(1250));
+        assert_eq!(cents("0.29 RMB"), Some(29));
+
+        // A float would take these
+        assert_eq!(cents("0.005"), None);
+        assert_eq!(cents("1e3"), None);
+        // Not amounts, or not grouped by thousands
+        for text in [
+            "",
+            "¥",
+            "-",
+            "abc",
+            "1.2.3",
+            "12,34,56",
+            "1,2345.00",
+            "1..5",
+            "1,",
+            ".",
+        ] {
+            assert_eq!(cents(text), None, "{text}");
+        }
+        assert_eq!(cents("99999999999999999999"), None);
+
+        assert_eq!(Money::from_cents(-320).to_string(), "-3.20");
+        assert_eq!(Money::from_cents(123450).to_string(), "1234.50");
+    }
+}
@@ -0,0 +1,189 @@
+//! Everything the crate can fetch of a student in one document, for the graduates keeping
+//! their record once the account is closed.
+
+use serde::Serialize;
+
+use crate::{
+    base::{
+        app::AppVisitor,
+        client::{Campus, Client},
+        typing::{other_error, TorErr},
+        version::VERSION,
+    },
+    extension::{
+        calendar::{flatten_week_matrix, ScheduleEntry, TermCalendarParser},
+        grades::{GpaSummary, GradeAnalytics},
+    },
+    impls::{
+        apps::wechat::{
+            jwqywx::JwqywxApplication,
+            jwqywx_type::{CourseGrade, StudentPoint},
+        },
+        client::DefaultClient,
+    },
+};
+
+/// What [`DefaultClient::archive`] got, each part failing on its own like the
+/// [`Overview`](super::overview::Overview).
+#[derive(Debug)]
+pub struct Archive {
+    /// From the credits and rank of jwqywx
+    pub profile: TorErr<StudentPoint>,
+    /// The grades of every term
+    pub grades: TorErr<Vec<CourseGrade>>,
+    /// [`GradeAnalytics::summary`] of [`Self::grades`]
+    pub gpa: TorErr<GpaSummary>,
+    /// The schedule of the current term
+    pub schedule: TorErr<Vec<ScheduleEntry>>,
+}
+
+/// A part of the archive as written: `{"data": ...}`, or `{"error": "..."}` when it couldn't
+/// be had.
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Section<'a, T> {
+    Data(&'a T),
+    Error(String),
+}
+
+impl<'a, T> From<&'a TorErr<T>> for Section<'a, T> {
+    fn from(part: &'a TorErr<T>) -> Self {
+        match part {
+            Ok(data) => Section::Data(data),
+            Err(error) => Section::Error(error.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ArchiveDocument<'a> {
+    /// Of the crate, telling how to read the rest
+    version: &'a str,
+    profile: Section<'a, StudentPoint>,
+    grades: Section<'a, Vec<CourseGrade>>,
+    gpa: Section<'a, GpaSummary>,
+    schedule: Section<'a, Vec<ScheduleEntry>>,
+}
+
+impl Archive {
+    /// The archive as a JSON document, an object of `version`, `profile`, `grades`, `gpa` and
+    /// `schedule`, the parts `{"data": ...}` or `{"error": "..."}`.
+    pub fn to_json(&self) -> TorErr<Vec<u8>> {
+        let document = ArchiveDocument {
+            version: VERSION,
+            profile: (&self.profile).into(),
+            grades: (&self.grades).into(),
+            gpa: (&self.gpa).into(),
+            schedule: (&self.schedule).into(),
+        };
+        serde_json::to_vec_pretty(&document).map_err(other_error)
+    }
+}
+
+/// [`Archive`] from `app`, logged in already, the profile, the grades and the schedule
+/// fetched concurrently. The locations are of `campus`.
+pub async fn archive_of<C: Client>(app: &JwqywxApplication<C>, campus: Option<Campus>) -> Archive {
+    let schedule = async {
+        let term = app.current_term().await?.term;
+        flatten_week_matrix(app.get_term_classinfo_week_matrix(term).await?, campus)
+    };
+    let (profile, grades, schedule) =
+        tokio::join!(app.get_credits_and_rank(), app.get_grades(), schedule);
+    let profile = profile.and_then(|points| {
+        points
+            .message
+            .into_iter()
+            .next()
+            .ok_or(other_error("No student in the credits and rank"))
+    });
+    let grades = grades.map(|grades| grades.message);
+    let gpa = match &grades {
+        Ok(grades) => Ok(GradeAnalytics::new(grades.clone()).summary()),
+        Err(error) => Err(other_error(error.to_string())),
+    };
+    Archive {
+        profile,
+        grades,
+        gpa,
+        schedule,
+    }
+}
+
+impl DefaultClient {
+    /// Log in to jwqywx once and get the [`Archive`], nothing but errors in it when the login
+    /// fails.
+    pub async fn archive(&self) -> Archive {
+        let app = self.visit::<JwqywxApplication<_>>().await;
+        if let Err(error) = app.login().await {
+            let message = format!("Failed to login to Jwqywx: {error}");
+            return Archive {
+                profile: Err(other_error(message.clone())),
+                grades: Err(other_error(message.clone())),
+                gpa: Err(other_error(message.clone())),
+                schedule: Err(other_error(message)),
+            };
+        }
+        archive_of(&app, self.options().campus).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value;
+
+    use super::archive_of;
+    use crate::impls::client::DefaultClient;
+    use crate::utils::testing::{TestResponse, TestServer};
+
+    #[tokio::test]
+    async fn sections_of_the_archive() {
+        let server = TestServer::start().await;
+        server
+            .route(
+                "POST",
+                "/api/login",
+                TestResponse::json(include_str!("../impls/apps/wechat/fixtures/login.json")),
+            )
+            .route(
+                "POST",
+                "/api/cj_xh_xfjd",
+                TestResponse::json(include_str!(
+                    "../impls/apps/wechat/fixtures/cj_xh_xfjd.json"
+                )),
+            )
+            .route(
+                "POST",
+                "/api/cj_xh",
+                TestResponse::json(include_str!(
+                    "../impls/apps/wechat/fixtures/cj_xh_detail.json"
+                )),
+            )
+            .route(
+                "POST",
+                "/api/kb_xq_xh",
+                TestResponse::new(502, "text/html", "<html>Bad Gateway</html>"),
+            );
+        let app = server
+            .jwqywx(DefaultClient::account("2300000101", "hunter2"))
+            .await;
+        app.login().await.unwrap();
+
+        let archive = archive_of(&app, None).await;
+        let document: Value = serde_json::from_slice(&archive.to_json().unwrap()).unwrap();
+        let sections: Vec<&str> = document
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        for section in ["version", "profile", "grades", "gpa", "schedule"] {
+            assert!(sections.contains(&section), "{sections:?}");
+        }
+        assert_eq!(document["profile"]["data"]["xh"], "2500000101");
+        assert_eq!(document["gpa"]["data"]["credits"], 8.0);
+        assert!(!document["grades"]["data"].as_array().unwrap().is_empty());
+        // The schedule alone is missing
+        assert!(document["schedule"]["error"].is_string());
+        assert!(document["schedule"].get("data").is_none());
+    }
+}
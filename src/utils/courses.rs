@@ -0,0 +1,209 @@
+//! A card per course of the term: when it meets and the grade it got, the schedule and the
+//! grades joined by course.
+
+use serde::Serialize;
+
+use crate::{
+    base::{
+        app::AppVisitor,
+        client::{Campus, Client},
+        typing::{other_error, TorErr},
+    },
+    extension::{
+        calendar::{flatten_week_matrix, ScheduleEntry, TermCalendarParser},
+        instructor::{split_names, Instructor},
+    },
+    impls::{
+        apps::wechat::{jwqywx::JwqywxApplication, jwqywx_type::CourseGrade},
+        client::DefaultClient,
+    },
+};
+
+/// One course of the schedule with its grade, see [`course_overview`].
+#[derive(Debug, Clone, Serialize)]
+pub struct CourseOverview {
+    /// The name in the schedule
+    pub course: String,
+    /// The `kcdm` of the grade, `None` without one
+    pub course_id: Option<String>,
+    /// Every teacher of the meetings
+    pub teachers: Vec<String>,
+    pub meetings: Vec<ScheduleEntry>,
+    /// `None` until the course is graded
+    pub grade: Option<CourseGrade>,
+}
+
+/// The name of a course as the sources agree on it: without spaces, with the full-width
+/// letters, digits and brackets (`（双语）`, `Ａ１`) of some pages made half-width.
+fn course_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            c => c,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The courses of `schedule`, in the order they first meet, with their grade in `grades`.
+///
+/// The schedule has names and teachers but no course code, so a grade goes with the course of
+/// the same name (spaces and full-width characters aside). A course taken again has several:
+/// the one of a teacher of the course is taken, the one of the latest term otherwise. The
+/// teachers are the same by their ids when both sides have them, see [`Instructor::is`].
+/// Grades of courses not in the schedule are left out.
+pub fn course_overview(
+    schedule: Vec<ScheduleEntry>,
+    grades: &[CourseGrade],
+) -> Vec<CourseOverview> {
+    let mut courses: Vec<(String, Vec<Instructor>, CourseOverview)> = vec![];
+    for entry in schedule {
+        let key = course_key(&entry.course);
+        let index = match courses.iter().position(|(known, _, _)| *known == key) {
+            Some(index) => index,
+            None => {
+                courses.push((
+                    key,
+                    vec![],
+                    CourseOverview {
+                        course: entry.course.clone(),
+                        course_id: None,
+                        teachers: vec![],
+                        meetings: vec![],
+                        grade: None,
+                    },
+                ));
+                courses.len() - 1
+            }
+        };
+        let (_, instructors, course) = &mut courses[index];
+        for teacher in split_names(&entry.teacher) {
+            if !course.teachers.iter().any(|known| known == teacher) {
+                course.teachers.push(teacher.to_owned());
+            }
+        }
+        for instructor in entry.instructors() {
+            if !instructors.contains(&instructor) {
+                instructors.push(instructor);
+            }
+        }
+        course.meetings.push(entry);
+    }
+
+    for (key, instructors, course) in courses.iter_mut() {
+        let candidates = grades
+            .iter()
+            .filter(|grade| course_key(&grade.course_name) == *key);
+        let taught = |grade: &&CourseGrade| {
+            grade
+                .instructors()
+                .iter()
+                .any(|teacher| instructors.iter().any(|known| known.is(teacher)))
+        };
+        let grade = candidates
+            .clone()
+            .filter(taught)
+            .max_by_key(|grade| grade.term)
+            .or_else(|| candidates.max_by_key(|grade| grade.term));
+        course.course_id = grade.map(|grade| grade.course_id.clone());
+        course.grade = grade.cloned();
+    }
+    courses.into_iter().map(|(_, _, course)| course).collect()
+}
+
+/// The entries of `schedule` taught by `instructor`, among others or not.
+pub fn classes_with<'a>(
+    schedule: &'a [ScheduleEntry],
+    instructor: &Instructor,
+) -> Vec<&'a ScheduleEntry> {
+    schedule
+        .iter()
+        .filter(|entry| {
+            entry
+                .instructors()
+                .iter()
+                .any(|teacher| teacher.is(instructor))
+        })
+        .collect()
+}
+
+/// [`course_overview`] of the current term from `app`, logged in already, the schedule and
+/// the grades fetched concurrently. The locations are of `campus`.
+pub async fn course_overview_of<C: Client>(
+    app: &JwqywxApplication<C>,
+    campus: Option<Campus>,
+) -> TorErr<Vec<CourseOverview>> {
+    let term = app.current_term().await?.term;
+    let (matrix, grades) = tokio::join!(app.get_term_classinfo_week_matrix(term), app.get_grades());
+    let schedule = flatten_week_matrix(matrix?, campus)?;
+    Ok(course_overview(schedule, &grades?.message))
+}
+
+impl DefaultClient {
+    /// Log in to jwqywx once and get the [`course_overview`] of the current term.
+    pub async fn course_overview(&self) -> TorErr<Vec<CourseOverview>> {
+        let app = self.visit::<JwqywxApplication<_>>().await;
+        app.login()
+            .await
+            .map_err(|error| other_error(format!("Failed to login to Jwqywx: {error}")))?;
+        course_overview_of(&app, self.options().campus).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{classes_with, course_overview};
+    use crate::{
+        extension::{calendar::ScheduleEntry, instructor::Instructor},
+        impls::apps::wechat::jwqywx_type::{CourseGrade, Message},
+    };
+
+    #[test]
+    fn schedule_joined_with_grades() {
+        let schedule: Vec<ScheduleEntry> =
+            serde_json::from_str(include_str!("fixtures/course_schedule.json")).unwrap();
+        let grades: Message<CourseGrade> =
+            serde_json::from_str(include_str!("fixtures/course_grades.json")).unwrap();
+        let courses = course_overview(schedule, &grades.message);
+
+        let names: Vec<&str> = courses
+            .iter()
+            .map(|course| course.course.as_str())
+            .collect();
+        assert_eq!(names, ["数据结构（双语）", "线性代数", "大学物理B"]);
+
+        // Twice a week, by two teachers, graded as `数据结构(双语)`
+        assert_eq!(courses[0].meetings.len(), 2);
+        assert_eq!(courses[0].teachers, ["王五", "赵六"]);
+        assert_eq!(courses[0].course_id.as_deref(), Some("20400041"));
+        // Taken again with 李四, not the grade of the first time
+        let retake = courses[1].grade.as_ref().unwrap();
+        assert_eq!(retake.term, 4);
+        assert_eq!(retake.grade.to_string(), "75");
+        // Not graded yet
+        assert!(courses[2].grade.is_none());
+        assert!(courses[2].course_id.is_none());
+    }
+
+    #[test]
+    fn namesakes_told_apart_by_id() {
+        let schedule: Vec<ScheduleEntry> =
+            serde_json::from_str(include_str!("fixtures/course_schedule_ids.json")).unwrap();
+        let grades: Message<CourseGrade> =
+            serde_json::from_str(include_str!("fixtures/course_grades_ids.json")).unwrap();
+
+        // The 李四 of the schedule graded the term 2, another 李四 the term 4
+        let courses = course_overview(schedule.clone(), &grades.message);
+        let grade = courses[0].grade.as_ref().unwrap();
+        assert_eq!(grade.term, 2);
+        assert_eq!(grade.teacher_id.as_deref(), Some("T2002"));
+
+        let teacher = &grade.instructors()[0];
+        let classes = classes_with(&schedule, teacher);
+        assert_eq!(classes.len(), 1);
+        assert_eq!(classes[0].course, "线性代数");
+        // By the name alone, both classes are of the same 李四
+        assert_eq!(classes_with(&schedule, &Instructor::named("李四")).len(), 2);
+    }
+}
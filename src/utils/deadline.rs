@@ -0,0 +1,81 @@
+//! One limit for the whole of an operation of several requests, a login and the fetches after
+//! it, where the timeout of the client only bounds each request.
+
+use std::{future::Future, time::Duration};
+
+use tokio::time::timeout;
+
+use crate::base::typing::{typed_error, CczuError, TorErr};
+
+/// `operation`, or [`CczuError::DeadlineExceeded`] once `deadline` has passed since the call,
+/// whichever of its requests is running then.
+///
+/// The operation is dropped at the deadline with its requests in flight, and so are the waits
+/// it would have gone on with (the retry after a rate limit, the new login of an expired
+/// token): the deadline holds for everything it awaits, without being handed down to them.
+pub async fn with_deadline<T>(
+    deadline: Duration,
+    operation: impl Future<Output = TorErr<T>>,
+) -> TorErr<T> {
+    timeout(deadline, operation)
+        .await
+        .unwrap_or_else(|_| Err(typed_error(CczuError::DeadlineExceeded { deadline })))
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::with_deadline;
+    use crate::{
+        base::typing::{CczuError, TypedErrorExt},
+        impls::client::DefaultClient,
+        utils::testing::{Fault, FaultInjection, TestResponse, TestServer},
+    };
+
+    #[tokio::test]
+    async fn whole_operation_bounded() {
+        let server = TestServer::start().await;
+        server
+            .route(
+                "POST",
+                "/api/login",
+                TestResponse::json(include_str!("../impls/apps/wechat/fixtures/login.json")),
+            )
+            .route(
+                "POST",
+                "/api/cj_xh",
+                TestResponse::json(include_str!(
+                    "../impls/apps/wechat/fixtures/cj_xh_detail.json"
+                )),
+            )
+            .inject(
+                FaultInjection::new()
+                    .on("/api/login", Fault::Slow(Duration::from_secs(1)))
+                    .on("/api/cj_xh", Fault::Slow(Duration::from_secs(1))),
+            );
+        let app = server
+            .jwqywx(DefaultClient::account("2300000101", "hunter2"))
+            .await;
+        let grades = || async {
+            app.login().await?;
+            app.get_grades().await
+        };
+
+        // Each request in time, not both
+        let deadline = Duration::from_millis(1500);
+        let error = with_deadline(deadline, grades()).await.unwrap_err();
+        assert_eq!(
+            error.typed(),
+            Some(&CczuError::DeadlineExceeded { deadline })
+        );
+        // Cut during the grades, not asked again
+        assert_eq!(server.requests_to("/api/login").len(), 1);
+        assert_eq!(server.requests_to("/api/cj_xh").len(), 1);
+
+        let grades = with_deadline(Duration::from_secs(5), grades())
+            .await
+            .unwrap();
+        assert_eq!(grades.message.len(), 3);
+    }
+}
@@ -0,0 +1,124 @@
+//! Grade checks of many accounts at once, for a server notifying the students of their new
+//! grades without flooding the portal.
+
+use std::sync::Arc;
+
+use tokio::{sync::Semaphore, task::JoinSet};
+
+use crate::{
+    base::typing::{other_error, TorErr},
+    extension::grades::GradesSince,
+    impls::{apps::wechat::jwqywx::JwqywxApplication, client::DefaultClient},
+};
+
+/// Accounts checked at the same time by [`check_new_grades_for_all`] when unsure
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 4;
+
+async fn check_new_grades(
+    app: JwqywxApplication<DefaultClient>,
+    token: String,
+) -> TorErr<GradesSince> {
+    if !app.logged_in().await {
+        app.login().await?;
+    }
+    app.grades_since(&token).await
+}
+
+/// [`JwqywxApplication::grades_since`] of every account, a `(handle, app, token)` each,
+/// logging in first the apps not logged in yet.
+///
+/// At most `max_in_flight` accounts are checked at the same time however many there are, and
+/// each sends its requests one after the other: never more than `max_in_flight` requests are
+/// waiting on the portal. The results come back with their handle, in the order of
+/// `accounts`, every account failing on its own.
+///
+/// Must be called inside a tokio runtime.
+pub async fn check_new_grades_for_all<H>(
+    accounts: impl IntoIterator<Item = (H, JwqywxApplication<DefaultClient>, String)>,
+    max_in_flight: usize,
+) -> Vec<(H, TorErr<GradesSince>)> {
+    let permits = Arc::new(Semaphore::new(max_in_flight.max(1)));
+    let mut handles = vec![];
+    let mut checks = JoinSet::new();
+    for (index, (handle, app, token)) in accounts.into_iter().enumerate() {
+        handles.push(handle);
+        let permits = permits.clone();
+        checks.spawn(async move {
+            let result = match permits.acquire_owned().await {
+                Ok(_permit) => check_new_grades(app, token).await,
+                Err(error) => Err(other_error(error)),
+            };
+            (index, result)
+        });
+    }
+
+    let mut results: Vec<Option<TorErr<GradesSince>>> = handles.iter().map(|_| None).collect();
+    while let Some(joined) = checks.join_next().await {
+        if let Ok((index, result)) = joined {
+            results[index] = Some(result);
+        }
+    }
+    handles
+        .into_iter()
+        .zip(results)
+        .map(|(handle, result)| {
+            let result = result.unwrap_or_else(|| Err(other_error("Grade check panicked")));
+            (handle, result)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::check_new_grades_for_all;
+    use crate::{
+        impls::client::DefaultClient,
+        utils::testing::{Fault, FaultInjection, TestResponse, TestServer},
+    };
+
+    #[tokio::test]
+    async fn bounded_in_flight() {
+        let server = TestServer::start().await;
+        server
+            .route(
+                "POST",
+                "/api/login",
+                TestResponse::json(include_str!("../impls/apps/wechat/fixtures/login.json")),
+            )
+            .route(
+                "POST",
+                "/api/cj_xh",
+                TestResponse::json(include_str!(
+                    "../impls/apps/wechat/fixtures/cj_xh_detail.json"
+                )),
+            )
+            .inject(
+                FaultInjection::new()
+                    .on("/api/login", Fault::Slow(Duration::from_millis(20)))
+                    .on("/api/cj_xh", Fault::Slow(Duration::from_millis(20))),
+            );
+
+        let mut accounts = vec![];
+        for index in 0..10 {
+            let app = server
+                .jwqywx(DefaultClient::account(
+                    format!("23000001{index:02}"),
+                    "hunter2",
+                ))
+                .await;
+            accounts.push((index, app, String::new()));
+        }
+        let results = check_new_grades_for_all(accounts, 3).await;
+
+        assert_eq!(server.requests_to("/api/login").len(), 10);
+        assert_eq!(server.requests_to("/api/cj_xh").len(), 10);
+        assert!(server.max_in_flight() <= 3, "{}", server.max_in_flight());
+        assert!(server.max_in_flight() > 1);
+        for (expected, (index, result)) in results.into_iter().enumerate() {
+            assert_eq!(index, expected);
+            assert!(!result.unwrap().new_grades.is_empty());
+        }
+    }
+}
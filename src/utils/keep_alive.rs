@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use tokio::{
+    runtime::Handle,
+    task::AbortHandle,
+    time::{interval_at, Instant, MissedTickBehavior},
+};
+
+use crate::{
+    base::typing::{other_error, TorErr},
+    impls::{
+        client::DefaultClient,
+        login::{sso::SSOUniversalLogin, sso_status::SSOLoginStatus},
+    },
+};
+
+/// The task of [`DefaultClient::keep_alive`], held by the client: it stops with the last clone.
+#[derive(Debug)]
+pub(crate) struct KeepAliveTask {
+    task: AbortHandle,
+    interval: Duration,
+}
+
+impl Drop for KeepAliveTask {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Stops the task of [`DefaultClient::keep_alive`], without keeping the client alive.
+#[derive(Debug, Clone)]
+pub struct KeepAlive {
+    task: AbortHandle,
+}
+
+impl KeepAlive {
+    pub fn stop(&self) {
+        self.task.abort();
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+}
+
+impl DefaultClient {
+    /// Every `interval`, check that the SSO session is still alive and log in again if not,
+    /// so the next request of the user doesn't wait for a login. Apps logging in through SSO
+    /// then get their tokens without a password round trip.
+    ///
+    /// One task per client and its clones: while it runs, this hands out the running task
+    /// whatever the `interval`. It is stopped by [`KeepAlive::stop`],
+    /// [`Self::stop_keep_alive`] or dropping the client. A login stopped halfway resumes
+    /// on the next tick.
+    ///
+    /// Must be called inside a tokio runtime.
+    pub fn keep_alive(&self, interval: Duration) -> TorErr<KeepAlive> {
+        let mut slot = self
+            .keep_alive
+            .lock()
+            .map_err(|e| other_error(e.to_string()))?;
+        if let Some(running) = slot.as_ref().filter(|task| !task.task.is_finished()) {
+            return Ok(KeepAlive {
+                task: running.task.clone(),
+            });
+        }
+        if interval.is_zero() {
+            return Err(other_error("Keep alive interval can't be zero"));
+        }
+        let runtime = Handle::try_current().map_err(other_error)?;
+
+        // Without the slot, or the task would keep the client from being dropped
+        let client = self.detached();
+        let task = runtime.spawn(async move {
+            let mut ticks = interval_at(Instant::now() + interval, interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticks.tick().await;
+                if !client.sso_login_available().await {
+                    let _login = client.login.lock().await;
+                    // Tried again next tick, a failure here is not the user's request failing
+                    let _ = client.sso_universal_login().await;
+                }
+            }
+        });
+
+        let task = task.abort_handle();
+        *slot = Some(KeepAliveTask {
+            task: task.clone(),
+            interval,
+        });
+        Ok(KeepAlive { task })
+    }
+
+    pub fn stop_keep_alive(&self) {
+        if let Ok(mut slot) = self.keep_alive.lock() {
+            slot.take();
+        }
+    }
+
+    /// Interval of the running keep alive task.
+    pub fn keep_alive_interval(&self) -> Option<Duration> {
+        let slot = self.keep_alive.lock().ok()?;
+        slot.as_ref()
+            .filter(|task| !task.task.is_finished())
+            .map(|task| task.interval)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::impls::client::DefaultClient;
+
+    #[tokio::test]
+    async fn one_task_per_client() {
+        let client = DefaultClient::default();
+        let first = client.keep_alive(Duration::from_secs(3600)).unwrap();
+        let again = client.clone().keep_alive(Duration::from_secs(60)).unwrap();
+        assert_eq!(
+            client.keep_alive_interval(),
+            Some(Duration::from_secs(3600))
+        );
+        assert!(first.is_running() && again.is_running());
+
+        again.stop();
+        tokio::task::yield_now().await;
+        assert!(!first.is_running());
+        assert_eq!(client.keep_alive_interval(), None);
+
+        // Restarted, then gone with the client
+        let handle = client.keep_alive(Duration::from_secs(60)).unwrap();
+        assert!(handle.is_running());
+        drop(client);
+        tokio::task::yield_now().await;
+        assert!(!handle.is_running());
+    }
+
+    #[test]
+    fn needs_a_runtime() {
+        assert!(DefaultClient::default()
+            .keep_alive(Duration::from_secs(60))
+            .is_err());
+    }
+}
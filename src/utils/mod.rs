@@ -1 +1,22 @@
+pub mod amount;
+#[cfg(feature = "calendar")]
+pub mod archive;
+#[cfg(feature = "calendar")]
+pub mod courses;
+pub mod deadline;
+pub mod fanout;
+pub mod keep_alive;
+#[cfg(feature = "calendar")]
+pub mod overview;
+#[cfg(any(test, feature = "record-fixtures"))]
+pub mod record;
+#[cfg(feature = "calendar")]
+pub mod schedule_watch;
+#[cfg(feature = "calendar")]
+pub mod selftest;
+#[cfg(feature = "session-crypto")]
+pub mod session_crypto;
 pub mod status;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod text;
@@ -0,0 +1,145 @@
+//! The first screen after a login in one call: who the student is, their GPA, the week of
+//! the term and whether they are at risk.
+
+use chrono::{FixedOffset, NaiveDate, Utc};
+
+use crate::{
+    base::{
+        app::AppVisitor,
+        client::Client,
+        typing::{other_error, TorErr},
+    },
+    extension::{
+        calendar::{current_week, CurrentWeek},
+        grades::{AcademicWarning, GpaSummary, GradeAnalytics},
+    },
+    impls::{
+        apps::wechat::{jwqywx::JwqywxApplication, jwqywx_type::StudentPoint},
+        client::DefaultClient,
+    },
+};
+
+/// What [`DefaultClient::overview`] got, each part failing on its own: the profile is kept
+/// when the grades can't be had, and the other way around.
+#[derive(Debug)]
+pub struct Overview {
+    /// From the credits and rank of jwqywx
+    pub profile: TorErr<StudentPoint>,
+    /// [`GradeAnalytics::summary`] of the grades
+    pub gpa: TorErr<GpaSummary>,
+    pub current_week: TorErr<CurrentWeek>,
+    /// [`GradeAnalytics::academic_warning`] of the same grades as [`Self::gpa`]
+    pub academic_warning: TorErr<AcademicWarning>,
+}
+
+/// The day it is at CCZU, whatever the timezone of the device.
+fn beijing_today() -> NaiveDate {
+    let beijing = FixedOffset::east_opt(8 * 3600).unwrap();
+    Utc::now().with_timezone(&beijing).date_naive()
+}
+
+/// [`Overview`] from `app`, logged in already, with the profile and the grades fetched
+/// concurrently.
+pub async fn overview_of<C: Client>(
+    app: &JwqywxApplication<C>,
+    term_start: NaiveDate,
+    today: NaiveDate,
+) -> Overview {
+    let (profile, grades) = tokio::join!(app.get_credits_and_rank(), app.get_grades());
+    let profile = profile.and_then(|points| {
+        points
+            .message
+            .into_iter()
+            .next()
+            .ok_or(other_error("No student in the credits and rank"))
+    });
+    let (gpa, academic_warning) = match grades {
+        Ok(grades) => {
+            let analytics = GradeAnalytics::new(grades.message);
+            (Ok(analytics.summary()), Ok(analytics.academic_warning()))
+        }
+        Err(error) => (Err(other_error(error.to_string())), Err(error)),
+    };
+    Overview {
+        profile,
+        gpa,
+        current_week: current_week(Ok(term_start), None, today),
+        academic_warning,
+    }
+}
+
+impl DefaultClient {
+    /// Log in to jwqywx once and get the [`Overview`], the week counted from `term_start`
+    /// (the Monday of the first week) on the day it is in Beijing.
+    ///
+    /// Only the week is there when the login fails.
+    pub async fn overview(&self, term_start: NaiveDate) -> Overview {
+        let app = self.visit::<JwqywxApplication<_>>().await;
+        let today = beijing_today();
+        if let Err(error) = app.login().await {
+            let message = format!("Failed to login to Jwqywx: {error}");
+            return Overview {
+                profile: Err(other_error(message.clone())),
+                gpa: Err(other_error(message.clone())),
+                current_week: current_week(Ok(term_start), None, today),
+                academic_warning: Err(other_error(message)),
+            };
+        }
+        overview_of(&app, term_start, today).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::overview_of;
+    use crate::impls::client::DefaultClient;
+    use crate::utils::testing::{TestResponse, TestServer};
+
+    #[tokio::test]
+    async fn profile_without_grades() {
+        let server = TestServer::start().await;
+        server
+            .route(
+                "POST",
+                "/api/login",
+                TestResponse::json(include_str!("../impls/apps/wechat/fixtures/login.json")),
+            )
+            .route(
+                "POST",
+                "/api/cj_xh_xfjd",
+                TestResponse::json(include_str!(
+                    "../impls/apps/wechat/fixtures/cj_xh_xfjd.json"
+                )),
+            )
+            .route(
+                "POST",
+                "/api/cj_xh",
+                TestResponse::new(502, "text/html", "<html>Bad Gateway</html>"),
+            );
+        let app = server
+            .jwqywx(DefaultClient::account("2300000101", "hunter2"))
+            .await;
+        app.login().await.unwrap();
+
+        let date = |date| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        let overview = overview_of(&app, date("2025-09-01"), date("2025-10-15")).await;
+        let profile = overview.profile.unwrap();
+        assert_eq!(profile.student_id, "2500000101");
+        assert!(overview.gpa.is_err());
+        assert!(overview.academic_warning.is_err());
+        assert_eq!(overview.current_week.unwrap().week, 7);
+
+        server.route(
+            "POST",
+            "/api/cj_xh",
+            TestResponse::json(include_str!(
+                "../impls/apps/wechat/fixtures/cj_xh_detail.json"
+            )),
+        );
+        let overview = overview_of(&app, date("2025-09-01"), date("2025-10-15")).await;
+        assert!(overview.profile.is_ok());
+        assert_eq!(overview.gpa.unwrap().credits, 8.0);
+    }
+}
@@ -0,0 +1,425 @@
+//! Answers of a live session saved as fixtures, for the maintainers adding a parser test when
+//! a portal changes.
+//!
+//! With the `record-fixtures` feature, a client given [`DefaultClient::record_fixtures`] writes
+//! the body of every response it reads to the directory, one file per endpoint named by its
+//! host and path (`jwqywx.cczu.edu.cn_api_cj_xh.json`, then `..._2.json` for the next one).
+//! The headers are never written, the `Set-Cookie` of the answers neither.
+//!
+//! The bodies are [`scrub`]bed first: the ids, names, phones and photos of a student, the
+//! tokens and tickets, the account and the cookies of the client are replaced wherever they
+//! are. Read a fixture before committing it all the same, a portal may put a name where no
+//! one expects it.
+//!
+//! Log in and fetch as usual, the fixtures are written as the answers are read.
+//!
+//! Nothing of it is in a build without the feature.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+};
+
+use reqwest::Url;
+use serde_json::Value;
+
+use crate::{
+    base::{
+        client::Client,
+        typing::{other_error, TorErr},
+    },
+    impls::client::DefaultClient,
+};
+
+/// Keys of the portals holding who the student is: ids, names, phones, photos
+const PII_KEYS: [&str; 20] = [
+    "xh", "yhdm", "yhid", "userid", "gh", "sfzh", "zjhm", "ksh", "xm", "yhmc", "xsxm", "sjh",
+    "mobile", "phone", "email", "dzyx", "zp", "photo", "avatar", "txdz",
+];
+
+/// Parts of the keys of what logs in: `token`, `access_token`, `userpwd`...
+const SECRET_KEY_PARTS: [&str; 5] = ["token", "ticket", "pwd", "password", "smscode"];
+
+/// In the urls of the photos of the students, `.../xszp/2300000101.jpg`
+const PHOTO_MARKERS: [&str; 4] = ["photo", "avatar", "xszp", "zpdz"];
+
+/// Labels of the cells of a page followed by who the student is, `<td>姓名：</td><td>张三</td>`
+const PII_LABELS: [&str; 4] = ["姓名", "学号", "工号", "身份证号"];
+
+/// Shorter cookie values are settings, not sessions
+const MIN_COOKIE_SECRET_LEN: usize = 16;
+
+/// The photo left in place of the one of a student
+const PHOTO_PLACEHOLDER: &str = "https://example.invalid/photo.jpg";
+
+fn sensitive(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    PII_KEYS.contains(&key.as_str()) || SECRET_KEY_PARTS.iter().any(|part| key.contains(part))
+}
+
+/// What is left of `value`: as many zeros for an id, so a parser reading it as a number still
+/// does, a placeholder otherwise.
+fn placeholder(value: &str) -> String {
+    if value.chars().all(|c| c.is_ascii_digit()) {
+        "0".repeat(value.len())
+    } else if value.starts_with("http") || value.starts_with('/') {
+        PHOTO_PLACEHOLDER.into()
+    } else {
+        "redacted".into()
+    }
+}
+
+/// Kept to be scrubbed from the rest of the bodies too, unless it is too short to be told from
+/// anything else (a `1` of an id).
+fn remember(secrets: &mut Vec<String>, value: &str) {
+    let long_enough = match value.chars().all(|c| c.is_ascii_digit()) {
+        true => value.len() >= 4,
+        false => value.chars().count() >= 2,
+    };
+    if long_enough && !secrets.iter().any(|secret| secret == value) {
+        secrets.push(value.to_owned());
+    }
+}
+
+/// Every string and number of `value`, the value of a sensitive key.
+fn redact(value: &mut Value, secrets: &mut Vec<String>) {
+    match value {
+        Value::String(text) if !text.is_empty() => {
+            remember(secrets, text);
+            *text = placeholder(text);
+        }
+        Value::Number(number) => {
+            remember(secrets, &number.to_string());
+            *value = Value::from(0);
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| redact(value, secrets)),
+        Value::Object(map) => map.values_mut().for_each(|value| redact(value, secrets)),
+        _ => {}
+    }
+}
+
+fn scrub_value(value: &mut Value, secrets: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if sensitive(key) {
+                    redact(value, secrets);
+                } else {
+                    scrub_value(value, secrets);
+                }
+            }
+        }
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| scrub_value(value, secrets)),
+        _ => {}
+    }
+}
+
+/// The values following the [`PII_LABELS`] of a page, past the tags, colons and spaces between,
+/// kept in `secrets` to be replaced like the others.
+fn learn_labelled(page: &str, secrets: &mut Vec<String>) {
+    for label in PII_LABELS {
+        for (start, _) in page.match_indices(label) {
+            let mut rest = &page[start + label.len()..];
+            loop {
+                let trimmed = rest
+                    .trim_start_matches(|c: char| c.is_whitespace() || matches!(c, ':' | '：'))
+                    .trim_start_matches("&nbsp;");
+                let trimmed = match trimmed.strip_prefix('<') {
+                    Some(tag) => tag.split_once('>').map_or("", |(_, rest)| rest),
+                    None => trimmed,
+                };
+                if trimmed.len() == rest.len() {
+                    break;
+                }
+                rest = trimmed;
+            }
+            let end = rest
+                .find(|c: char| c == '<' || c == '&' || c.is_whitespace())
+                .unwrap_or(rest.len());
+            let value = &rest[..end];
+            // The next label of a header row, not a value
+            if !PII_LABELS.iter().any(|label| value.starts_with(label)) {
+                remember(secrets, value);
+            }
+        }
+    }
+}
+
+/// `text` with the runs of `is_run` characters starting with one of `prefixes`, and not in the
+/// middle of a word, replaced by what `replace` gives for them.
+fn replace_runs(
+    text: &str,
+    prefixes: &[&str],
+    is_run: impl Fn(char) -> bool,
+    replace: impl Fn(&str) -> Option<String>,
+) -> String {
+    let mut scrubbed = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut boundary = true;
+    while let Some(c) = rest.chars().next() {
+        if boundary && prefixes.iter().any(|prefix| rest.starts_with(prefix)) {
+            let end = rest.find(|c| !is_run(c)).unwrap_or(rest.len());
+            if let Some(replacement) = replace(&rest[..end]) {
+                scrubbed.push_str(&replacement);
+                rest = &rest[end..];
+                boundary = false;
+                continue;
+            }
+        }
+        scrubbed.push(c);
+        boundary = !c.is_ascii_alphanumeric();
+        rest = &rest[c.len_utf8()..];
+    }
+    scrubbed
+}
+
+fn token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')
+}
+
+fn url_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '"' | '\'' | '<' | '>' | '(' | ')')
+}
+
+/// `body` without what tells who the student is or logs them in, ready to be a fixture.
+///
+/// The values of the keys of the portals holding an id, a name, a phone or a photo (`xh`,
+/// `yhmc`, `sjh`, `zp`...) and of those with a `token`, `ticket` or `pwd` are replaced, by
+/// zeros for the ids, and so are the cells of a page following a `姓名` or a `学号` label. They
+/// are kept in `secrets`, with the `secrets` known already (the account...) replaced everywhere
+/// else in the body: a page printing `张三的成绩` loses the name too, and so do the bodies scrubbed next with the same `secrets`. JWTs (`eyJ...`), `ST-`
+/// and `TGT-` tickets and the urls of photos are replaced wherever they are, of a JSON body
+/// or of a page.
+///
+/// A JSON body comes out compact, its keys sorted.
+pub fn scrub(body: &str, secrets: &mut Vec<String>) -> String {
+    let mut text = match serde_json::from_str::<Value>(body) {
+        Ok(mut value) => {
+            scrub_value(&mut value, secrets);
+            value.to_string()
+        }
+        Err(_) => {
+            learn_labelled(body, secrets);
+            body.to_owned()
+        }
+    };
+
+    // The longest first, not the `0101` of a `2300000101`
+    secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+    for secret in secrets.iter() {
+        text = text.replace(secret.as_str(), &placeholder(secret));
+    }
+    let text = replace_runs(&text, &["eyJ"], token_char, |run| {
+        (run.matches('.').count() == 2).then(|| "redacted".into())
+    });
+    let text = replace_runs(&text, &["ST-", "TGT-"], token_char, |_| {
+        Some("redacted".into())
+    });
+    replace_runs(&text, &["http://", "https://"], url_char, |url| {
+        let url = url.to_lowercase();
+        PHOTO_MARKERS
+            .iter()
+            .any(|marker| url.contains(marker))
+            .then(|| PHOTO_PLACEHOLDER.into())
+    })
+}
+
+/// Writes the bodies of the responses of a client to a directory, see the [module](self).
+#[derive(Debug)]
+pub struct FixtureRecorder {
+    dir: PathBuf,
+    secrets: Mutex<Vec<String>>,
+    /// Fixtures written per file name, the next one of an endpoint is `_2`
+    recorded: Mutex<HashMap<String, usize>>,
+}
+
+impl FixtureRecorder {
+    /// Record to `dir`, created if missing.
+    pub fn new(dir: impl Into<PathBuf>) -> TorErr<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            secrets: Mutex::default(),
+            recorded: Mutex::default(),
+        })
+    }
+
+    /// Have the account of `client` and the values of its cookies scrubbed from the next
+    /// bodies, they may be in none of the keys [`scrub`] knows.
+    pub fn learn(&self, client: &impl Client) {
+        let account = client.account();
+        let mut secrets = self.secrets.lock().unwrap_or_else(PoisonError::into_inner);
+        remember(&mut secrets, &account.user);
+        remember(&mut secrets, &account.password);
+        let cookies = client.cookies();
+        let cookies = cookies.lock().unwrap_or_else(PoisonError::into_inner);
+        for cookie in cookies.iter_any() {
+            // Not the `true` or `zh_CN` of some cookies, replaced in every body
+            if cookie.value().len() >= MIN_COOKIE_SECRET_LEN {
+                remember(&mut secrets, cookie.value());
+            }
+        }
+    }
+
+    /// [`scrub`] `body`, read from `url`, and write it to the next file of its endpoint.
+    pub fn record(&self, url: &Url, body: &[u8]) -> TorErr<PathBuf> {
+        let body = String::from_utf8_lossy(body);
+        let mut secrets = self.secrets.lock().unwrap_or_else(PoisonError::into_inner);
+        let scrubbed = scrub(&body, &mut secrets);
+
+        // The query holds ids and tickets, the path sometimes an id too
+        let endpoint = format!("{}{}", url.host_str().unwrap_or_default(), url.path());
+        let endpoint = scrub(&endpoint, &mut secrets);
+        drop(secrets);
+        let stem: String = endpoint
+            .trim_end_matches('/')
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+                _ => '_',
+            })
+            .collect();
+        let extension = match body.trim_start().chars().next() {
+            Some('{' | '[') => "json",
+            Some('<') => "html",
+            _ => "txt",
+        };
+        let count = {
+            let mut recorded = self.recorded.lock().unwrap_or_else(PoisonError::into_inner);
+            let count = recorded.entry(format!("{stem}.{extension}")).or_default();
+            *count += 1;
+            *count
+        };
+        let name = match count {
+            1 => format!("{stem}.{extension}"),
+            count => format!("{stem}_{count}.{extension}"),
+        };
+        let path = self.dir.join(name);
+        fs::write(&path, scrubbed).map_err(other_error)?;
+        Ok(path)
+    }
+}
+
+impl DefaultClient {
+    /// Write every body this client and its clones read to `dir`, scrubbed, see
+    /// [`crate::utils::record`].
+    pub fn record_fixtures(mut self, dir: impl Into<PathBuf>) -> TorErr<Self> {
+        self.recorder = Some(std::sync::Arc::new(FixtureRecorder::new(dir)?));
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use reqwest::Url;
+
+    use super::{scrub, FixtureRecorder};
+    use crate::{
+        impls::client::DefaultClient,
+        utils::testing::{TestResponse, TestServer},
+    };
+
+    #[test]
+    fn personal_data_scrubbed() {
+        let mut secrets = vec!["hunter2".to_owned()];
+        let login = scrub(
+            include_str!("../impls/apps/wechat/fixtures/login.json"),
+            &mut secrets,
+        );
+        for personal in ["2300000101", "张三", "1001", "eyJhbGciOiJIUzI1NiJ9"] {
+            assert!(!login.contains(personal), "{personal} in {login}");
+        }
+        // Still a login answer
+        let answer: serde_json::Value = serde_json::from_str(&login).unwrap();
+        assert_eq!(answer["message"][0]["yhdm"], "0000000000");
+        assert_eq!(answer["message"][0]["xq"], "25-26-1");
+        assert_eq!(answer["status"], 1);
+
+        // What the login told is gone from a page too
+        let page = scrub(
+            r#"<p>张三（2300000101）的成绩</p><img src="http://jwqywx.cczu.edu.cn/xszp/2300000101.jpg">
+<a href="/cas/login?ticket=ST-1234-abcdEFGH-cas">hunter2</a>"#,
+            &mut secrets,
+        );
+        for personal in ["张三", "2300000101", "ST-1234", "hunter2", "xszp"] {
+            assert!(!page.contains(personal), "{personal} in {page}");
+        }
+        assert!(page.contains("的成绩"));
+    }
+
+    #[test]
+    fn names_of_a_page_scrubbed() {
+        let dir = std::env::temp_dir().join(format!("cczuni-pages-{}", std::process::id()));
+        let recorder = FixtureRecorder::new(&dir).unwrap();
+        let url = Url::parse("http://jwcas.cczu.edu.cn/web_jxrw/cx_kb_xsgrkb.aspx").unwrap();
+        let page = recorder
+            .record(
+                &url,
+                "<table><tr><td>姓名：</td><td>李四</td><td>学号：</td><td> 2300000102 </td></tr>\
+                 <tr><td>李四的课表</td></tr></table>"
+                    .as_bytes(),
+            )
+            .unwrap();
+        let page = fs::read_to_string(page).unwrap();
+        for personal in ["李四", "2300000102"] {
+            assert!(!page.contains(personal), "{personal} in {page}");
+        }
+        assert!(page.contains("姓名：") && page.contains("的课表"), "{page}");
+
+        // And from the next answers, which don't label it
+        let url = Url::parse("http://jwqywx.cczu.edu.cn/api/kb_xq_xh").unwrap();
+        let answer = recorder
+            .record(&url, r#"{"message":[{"jsmc":"李四"}]}"#.as_bytes())
+            .unwrap();
+        assert!(!fs::read_to_string(answer).unwrap().contains("李四"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn recorded_from_a_session() {
+        let server = TestServer::start().await;
+        server
+            .route(
+                "POST",
+                "/api/login",
+                TestResponse::json(include_str!("../impls/apps/wechat/fixtures/login.json")),
+            )
+            .route(
+                "POST",
+                "/api/cj_xh",
+                TestResponse::json(include_str!(
+                    "../impls/apps/wechat/fixtures/cj_xh_detail.json"
+                )),
+            );
+        let dir = std::env::temp_dir().join(format!("cczuni-fixtures-{}", std::process::id()));
+        let client = DefaultClient::account("2300000101", "hunter2")
+            .record_fixtures(&dir)
+            .unwrap();
+        let app = server.jwqywx(client).await;
+        app.login().await.unwrap();
+        app.get_grades().await.unwrap();
+        app.get_grades().await.unwrap();
+
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 3, "{names:?}");
+        assert!(names[0].ends_with("_api_cj_xh.json"), "{names:?}");
+        assert!(names[1].ends_with("_api_cj_xh_2.json"), "{names:?}");
+        assert!(names[2].ends_with("_api_login.json"), "{names:?}");
+        for name in names {
+            let fixture = fs::read_to_string(dir.join(name)).unwrap();
+            assert!(!fixture.contains("2300000101") && !fixture.contains("张三"));
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,230 @@
+//! A task fetching the schedule of the current term again and again, telling the changes
+//! (a class moved to another room...) as they show up.
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use tokio::{
+    runtime::Handle,
+    sync::OnceCell,
+    task::AbortHandle,
+    time::{interval_at, Instant, MissedTickBehavior},
+};
+
+use crate::{
+    base::{
+        app::AppVisitor,
+        client::{Campus, Client},
+        typing::{other_error, CczuError, TorErr, TypedErrorExt},
+    },
+    extension::calendar::{
+        diff_schedule, flatten_week_matrix, ScheduleDiff, ScheduleEntry, TermCalendarParser,
+    },
+    impls::{apps::wechat::jwqywx::JwqywxApplication, client::DefaultClient},
+};
+
+/// The task of [`watch_schedule`], stopped by [`Self::stop`] or when dropped.
+#[derive(Debug)]
+pub struct ScheduleWatch {
+    task: AbortHandle,
+}
+
+impl ScheduleWatch {
+    pub fn stop(&self) {
+        self.task.abort();
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+}
+
+impl Drop for ScheduleWatch {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Call `fetch` now, then every `interval`, and `on_change` with what changed from the last
+/// schedule fetched whenever something did. The first schedule is only kept to compare with.
+///
+/// A failed fetch is tried again on the next tick, compared with the last schedule fetched.
+/// The fetches never overlap: one slower than `interval` is followed right away by a single
+/// fetch for all the ticks it missed, then one every `interval` again. Stopped during a fetch,
+/// the task drops it without a call to `on_change`.
+///
+/// Must be called inside a tokio runtime.
+pub fn watch_schedule<F, Fut>(
+    mut fetch: F,
+    interval: Duration,
+    on_change: impl Fn(&ScheduleDiff) + Send + 'static,
+) -> TorErr<ScheduleWatch>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = TorErr<Vec<ScheduleEntry>>> + Send,
+{
+    if interval.is_zero() {
+        return Err(other_error("Schedule watch interval can't be zero"));
+    }
+    let runtime = Handle::try_current().map_err(other_error)?;
+
+    let task = runtime.spawn(async move {
+        let mut ticks = interval_at(Instant::now(), interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut last: Option<Vec<ScheduleEntry>> = None;
+        loop {
+            ticks.tick().await;
+            let Ok(schedule) = fetch().await else {
+                continue;
+            };
+            if let Some(last) = &last {
+                let diff = diff_schedule(last, &schedule);
+                if !diff.is_empty() {
+                    on_change(&diff);
+                }
+            }
+            last = Some(schedule);
+        }
+    });
+    Ok(ScheduleWatch {
+        task: task.abort_handle(),
+    })
+}
+
+/// The schedule of the current term from jwqywx, logging in again once the session is gone.
+async fn current_schedule(
+    app: &JwqywxApplication<DefaultClient>,
+    campus: Option<Campus>,
+) -> TorErr<Vec<ScheduleEntry>> {
+    if !app.logged_in().await {
+        app.login().await?;
+    }
+    let term = app.current_term().await?.term;
+    let matrix = match app.get_term_classinfo_week_matrix(term.clone()).await {
+        Err(error) if error.typed() == Some(&CczuError::SessionExpired) => {
+            app.login().await?;
+            app.get_term_classinfo_week_matrix(term).await?
+        }
+        matrix => matrix?,
+    };
+    flatten_week_matrix(matrix, campus)
+}
+
+impl DefaultClient {
+    /// [`watch_schedule`] of the current term from jwqywx, logged in to by the task.
+    ///
+    /// Must be called inside a tokio runtime.
+    pub fn watch_schedule(
+        &self,
+        interval: Duration,
+        on_change: impl Fn(&ScheduleDiff) + Send + 'static,
+    ) -> TorErr<ScheduleWatch> {
+        let client = self.detached();
+        let app = Arc::new(OnceCell::new());
+        watch_schedule(
+            move || {
+                let (client, app) = (client.clone(), app.clone());
+                async move {
+                    let app = app
+                        .get_or_init(|| client.visit::<JwqywxApplication<_>>())
+                        .await;
+                    current_schedule(app, client.options().campus).await
+                }
+            },
+            interval,
+            on_change,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use tokio::time::sleep;
+
+    use super::watch_schedule;
+    use crate::{
+        base::typing::other_error,
+        extension::calendar::{ScheduleChangeKind, ScheduleDiff, ScheduleEntry},
+    };
+
+    fn entry(location: &str) -> ScheduleEntry {
+        ScheduleEntry {
+            course: "数据结构".into(),
+            teacher: "李四".into(),
+            teacher_id: None,
+            location: location.into(),
+            campus: None,
+            weekday: 1,
+            start_period: 1,
+            end_period: 2,
+            start_time: None,
+            end_time: None,
+            weeks: vec![1, 2, 3],
+        }
+    }
+
+    #[tokio::test]
+    async fn room_change_told_once() {
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let counter = fetched.clone();
+        let fetch = move || {
+            let count = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match count {
+                    0 | 1 => Ok(vec![entry("W101")]),
+                    2 => Err(other_error("Request Failed")),
+                    _ => Ok(vec![entry("W203")]),
+                }
+            }
+        };
+        let changes: Arc<Mutex<Vec<ScheduleDiff>>> = Arc::default();
+        let seen = changes.clone();
+        let watch = watch_schedule(fetch, Duration::from_millis(10), move |diff| {
+            seen.lock().unwrap().push(diff.clone())
+        })
+        .unwrap();
+
+        while fetched.load(Ordering::SeqCst) < 8 {
+            sleep(Duration::from_millis(5)).await;
+        }
+        watch.stop();
+        let changes = changes.lock().unwrap().clone();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].changed[0].after.location, "W203");
+        assert_eq!(changes[0].changed[0].kinds, [ScheduleChangeKind::Location]);
+    }
+
+    #[tokio::test]
+    async fn slow_fetches_coalesce() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let (fetched, overlapped) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let (running, counter, overlaps) = (in_flight.clone(), fetched.clone(), overlapped.clone());
+        let fetch = move || {
+            let (running, counter, overlaps) = (running.clone(), counter.clone(), overlaps.clone());
+            async move {
+                if running.fetch_add(1, Ordering::SeqCst) > 0 {
+                    overlaps.fetch_add(1, Ordering::SeqCst);
+                }
+                sleep(Duration::from_millis(30)).await;
+                counter.fetch_add(1, Ordering::SeqCst);
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(vec![])
+            }
+        };
+        let watch = watch_schedule(fetch, Duration::from_millis(5), |_| {}).unwrap();
+
+        sleep(Duration::from_millis(100)).await;
+        drop(watch);
+        let fetched = fetched.load(Ordering::SeqCst);
+        // One every 30ms, not the 20 ticks of 5ms
+        assert!((1..=4).contains(&fetched), "{fetched}");
+        assert_eq!(overlapped.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,197 @@
+use std::{future::Future, io::ErrorKind, time::Instant};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use serde::Serialize;
+
+use crate::{
+    base::{
+        app::AppVisitor,
+        client::Client,
+        typing::{CczuError, TorErr, TypedErrorExt},
+    },
+    impls::{
+        apps::wechat::jwqywx::JwqywxApplication,
+        client::DefaultClient,
+        login::{sso::SSOUniversalLogin, sso_status::SSOLoginStatus},
+    },
+};
+
+/// One step of [`DefaultClient::selftest`].
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestStep {
+    /// `reachability`, `sso_login` or `fetch`
+    pub name: &'static str,
+    pub ok: bool,
+    pub elapsed_ms: u64,
+    /// What went wrong: `network`, `auth`, `second_factor`, `password_change`, `rate_limited`
+    /// or `parse`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<&'static str>,
+    /// Error message, without the password
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    /// The steps run, the last one is the failing one if any
+    pub steps: Vec<SelfTestStep>,
+    pub failed_step: Option<&'static str>,
+}
+
+/// Replace the password, as typed and as posted to SSO, with `***`.
+fn redact(message: String, password: &str) -> String {
+    if password.is_empty() {
+        return message;
+    }
+    message
+        .replace(password, "***")
+        .replace(&BASE64_STANDARD.encode(password), "***")
+}
+
+impl SelfTestReport {
+    /// Add `step`, `true` when it failed.
+    fn push(&mut self, step: SelfTestStep) -> bool {
+        if !step.ok {
+            self.failed_step = Some(step.name);
+        }
+        self.steps.push(step);
+        self.failed_step.is_some()
+    }
+}
+
+impl DefaultClient {
+    /// Reach SSO, login, then fetch the term list of jwqywx with its own login,
+    /// stopping at the first step failing. Nothing is written to the portals.
+    pub async fn selftest(&self) -> SelfTestReport {
+        let password = self.account().password;
+        let mut report = SelfTestReport {
+            steps: vec![],
+            failed_step: None,
+        };
+
+        let reachability = run_step("reachability", &password, |_| "network", async {
+            self.sso_login_type().await.map(|_| ())
+        });
+        if report.push(reachability.await) {
+            return report;
+        }
+
+        let login = run_step("sso_login", &password, login_failure, async {
+            self.sso_universal_login().await.map(|_| ())
+        });
+        if report.push(login.await) {
+            return report;
+        }
+
+        let fetch = run_step("fetch", &password, fetch_failure, async {
+            let app = self.visit::<JwqywxApplication<_>>().await;
+            app.login().await?;
+            app.terms().await.map(|_| ())
+        });
+        report.push(fetch.await);
+        report
+    }
+}
+
+/// The connection itself failed, whatever the step.
+fn is_network(error: &tokio::io::Error) -> bool {
+    error
+        .unwrap_operation()
+        .get_ref()
+        .is_some_and(|error| error.is::<reqwest::Error>())
+}
+
+fn login_failure(error: &tokio::io::Error) -> &'static str {
+    match error.typed() {
+        Some(CczuError::SecondFactorRequired { .. }) => "second_factor",
+        Some(CczuError::PasswordChangeRequired { .. }) => "password_change",
+        Some(CczuError::AccountNotActivated { .. }) => "not_activated",
+        Some(CczuError::RateLimited { .. }) => "rate_limited",
+        _ if is_network(error) => "network",
+        _ => "auth",
+    }
+}
+
+fn fetch_failure(error: &tokio::io::Error) -> &'static str {
+    match error.kind() {
+        _ if matches!(error.typed(), Some(CczuError::RateLimited { .. })) => "rate_limited",
+        _ if is_network(error) => "network",
+        ErrorKind::InvalidData | ErrorKind::UnexpectedEof => "parse",
+        _ => "auth",
+    }
+}
+
+async fn run_step(
+    name: &'static str,
+    password: &str,
+    kind: fn(&tokio::io::Error) -> &'static str,
+    future: impl Future<Output = TorErr<()>>,
+) -> SelfTestStep {
+    let start = Instant::now();
+    let result = future.await;
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+    match result {
+        Ok(()) => SelfTestStep {
+            name,
+            ok: true,
+            elapsed_ms,
+            kind: None,
+            error: None,
+        },
+        Err(error) => SelfTestStep {
+            name,
+            ok: false,
+            elapsed_ms,
+            kind: Some(kind(&error)),
+            error: Some(redact(error.to_string(), password)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use base64::{prelude::BASE64_STANDARD, Engine};
+
+    use super::{fetch_failure, login_failure, redact, run_step};
+    use crate::base::typing::{other_error, typed_error, CczuError};
+
+    #[test]
+    fn redacts_password() {
+        let posted = BASE64_STANDARD.encode("hunter2");
+        assert_eq!(
+            redact(format!("bad password hunter2 ({posted})"), "hunter2"),
+            "bad password *** (***)"
+        );
+        assert_eq!(redact("no password".into(), ""), "no password");
+    }
+
+    #[tokio::test]
+    async fn failing_step() {
+        let step = run_step("sso_login", "hunter2", login_failure, async {
+            Err(other_error("Login with hunter2 refused"))
+        })
+        .await;
+        assert!(!step.ok);
+        assert_eq!(step.kind, Some("auth"));
+        assert_eq!(step.error.as_deref(), Some("Login with *** refused"));
+
+        let step = run_step("sso_login", "hunter2", login_failure, async {
+            Err(typed_error(CczuError::SecondFactorRequired {
+                method: "sms".into(),
+                hint: String::new(),
+            }))
+        })
+        .await;
+        assert_eq!(step.kind, Some("second_factor"));
+
+        let step = run_step("fetch", "hunter2", fetch_failure, async {
+            Err(serde_json::from_str::<u8>("<html>").unwrap_err().into())
+        })
+        .await;
+        assert_eq!(step.kind, Some("parse"));
+
+        let step = run_step("fetch", "hunter2", fetch_failure, async { Ok(()) }).await;
+        assert!(step.ok && step.error.is_none());
+    }
+}
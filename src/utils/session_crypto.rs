@@ -0,0 +1,135 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+
+use crate::{
+    base::{
+        client::{Account, ClientOptions},
+        typing::{other_error, TorErr},
+    },
+    impls::client::{DefaultClient, SessionPolicy},
+};
+
+/// Length of the keys of [`DefaultClient::export_session_encrypted`]
+pub const SESSION_KEY_LEN: usize = 32;
+
+/// Binds the blob to this format, a blob of another use of the key doesn't open.
+const AAD: &[u8] = b"cczuni-session-v1";
+
+fn key(key: &[u8; SESSION_KEY_LEN]) -> LessSafeKey {
+    LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, key).unwrap())
+}
+
+fn seal(session: &str, secret: &[u8; SESSION_KEY_LEN]) -> TorErr<String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| other_error("No randomness for the nonce"))?;
+
+    let mut sealed = session.as_bytes().to_vec();
+    key(secret)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(AAD),
+            &mut sealed,
+        )
+        .map_err(|_| other_error("Session encryption failed"))?;
+    Ok(BASE64_STANDARD.encode([&nonce[..], &sealed].concat()))
+}
+
+fn open(blob: &str, secret: &[u8; SESSION_KEY_LEN]) -> TorErr<String> {
+    let damaged = || other_error("Wrong key or damaged session");
+    let mut bytes = BASE64_STANDARD.decode(blob.trim()).map_err(|_| damaged())?;
+    if bytes.len() < NONCE_LEN {
+        return Err(damaged());
+    }
+    let mut sealed = bytes.split_off(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(&bytes).map_err(|_| damaged())?;
+    let session = key(secret)
+        .open_in_place(nonce, Aad::from(AAD), &mut sealed)
+        .map_err(|_| damaged())?;
+    String::from_utf8(session.to_vec()).map_err(|_| damaged())
+}
+
+impl DefaultClient {
+    /// [`Self::export_session`] encrypted with ChaCha20-Poly1305, as base64.
+    ///
+    /// The blob is useless without `key`, and a blob changed on disk doesn't import.
+    /// Keep the key out of the file, in the keystore of the platform.
+    pub fn export_session_encrypted(
+        &self,
+        policy: &SessionPolicy,
+        key: &[u8; SESSION_KEY_LEN],
+    ) -> TorErr<String> {
+        seal(&self.export_session(policy)?, key)
+    }
+
+    /// A client from a blob of [`Self::export_session_encrypted`], failing on the wrong key.
+    pub fn import_session_encrypted(
+        account: Account,
+        options: ClientOptions,
+        blob: &str,
+        key: &[u8; SESSION_KEY_LEN],
+    ) -> TorErr<Self> {
+        Self::import_session_with_options(account, options, &open(blob, key)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use reqwest::Url;
+
+    use crate::{
+        base::client::{Account, Client, ClientOptions},
+        impls::client::{DefaultClient, SessionPolicy},
+    };
+
+    #[test]
+    fn wrong_key() {
+        let client = DefaultClient::default();
+        let url = Url::parse("http://sso.cczu.edu.cn/").unwrap();
+        client
+            .cookies()
+            .lock()
+            .unwrap()
+            .parse("JSESSIONID=F00D; Path=/", &url)
+            .unwrap();
+
+        let key = [7u8; 32];
+        let blob = client
+            .export_session_encrypted(&SessionPolicy::default(), &key)
+            .unwrap();
+        assert!(!blob.contains("F00D"));
+        let open = |blob: &str, key: &[u8; 32]| {
+            DefaultClient::import_session_encrypted(
+                Account::default(),
+                ClientOptions::default(),
+                blob,
+                key,
+            )
+        };
+
+        let rebuilt = open(&blob, &key).unwrap();
+        assert!(rebuilt
+            .cookies()
+            .lock()
+            .unwrap()
+            .contains("sso.cczu.edu.cn", "/", "JSESSIONID"));
+        assert!(open(&blob, &[8u8; 32]).is_err());
+
+        // Another nonce each time, and a flipped byte doesn't open
+        assert_ne!(
+            blob,
+            client
+                .export_session_encrypted(&SessionPolicy::default(), &key)
+                .unwrap()
+        );
+        let mut tampered = BASE64_STANDARD.decode(&blob).unwrap();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(open(&BASE64_STANDARD.encode(tampered), &key).is_err());
+        assert!(open("short", &key).is_err());
+    }
+}
@@ -1,8 +1,375 @@
-use crate::internals::fields::{DEFAULT_HEADERS, ROOT_SSO_LOGIN, WECHAT_APP_API};
+use crate::{
+    base::typing::TorErr,
+    internals::fields::{DEFAULT_HEADERS, ROOT_SSO_LOGIN, WECHAT_APP_API},
+    utils::text::element_text,
+};
 use const_format::formatcp;
-use reqwest::{Method, StatusCode, Url};
-use std::{collections::HashMap, time::Duration};
-use tokio::task::JoinSet;
+use reqwest::{header::LOCATION, Method, StatusCode, Url};
+use scraper::{Html, Selector};
+use serde::{Serialize, Serializer};
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::{sync::watch, task::JoinSet, time::timeout};
+
+/// A service the status probes check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum ServiceId {
+    #[serde(rename = "sso")]
+    Sso,
+    #[serde(rename = "wechat")]
+    WeChat,
+    #[serde(rename = "webvpn")]
+    WebVpn,
+}
+
+impl ServiceId {
+    pub const ALL: [ServiceId; 3] = [ServiceId::Sso, ServiceId::WeChat, ServiceId::WebVpn];
+
+    /// The key of the service in the maps of [`services_probe`] and the others
+    pub const fn key(self) -> &'static str {
+        match self {
+            ServiceId::Sso => "SSO",
+            ServiceId::WeChat => "WeChat",
+            ServiceId::WebVpn => "WebVPN",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|id| id.key() == key)
+    }
+
+    /// The name the students know the service by
+    pub fn display_name(self) -> &'static str {
+        match self {
+            ServiceId::Sso => "统一身份认证",
+            ServiceId::WeChat => "微信教务",
+            ServiceId::WebVpn => "WebVPN",
+        }
+    }
+
+    /// The page probed
+    pub const fn url(self) -> &'static str {
+        match self {
+            ServiceId::Sso => ROOT_SSO_LOGIN,
+            ServiceId::WeChat => formatcp!("{}/api/login", WECHAT_APP_API),
+            ServiceId::WebVpn => "https://zmvpn.cczu.edu.cn/enlink/sso/login",
+        }
+    }
+}
+
+const SERVICES: [(&str, &str); 3] = [
+    (ServiceId::Sso.key(), ServiceId::Sso.url()),
+    (ServiceId::WeChat.key(), ServiceId::WeChat.url()),
+    (ServiceId::WebVpn.key(), ServiceId::WebVpn.url()),
+];
+
+/// How long a probe waits for each service by default.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Words of the maintenance pages, which answer 200 like the service itself.
+pub const MAINTENANCE_MARKERS: [&str; 4] = ["系统维护", "维护中", "升级维护", "maintenance"];
+
+/// `Some` with the notice of the page when `html` is a maintenance page, the notice is empty
+/// when only the title tells.
+pub fn maintenance_message(html: &str) -> Option<String> {
+    let marked = |text: &str| {
+        let text = text.to_lowercase();
+        MAINTENANCE_MARKERS
+            .iter()
+            .any(|marker| text.contains(marker))
+    };
+    let dom = Html::parse_document(html);
+    let title = Selector::parse("title, h1, h2, h3").unwrap();
+    let notice = Selector::parse("body p").unwrap();
+    let titled = dom
+        .select(&title)
+        .map(element_text)
+        .any(|title| marked(&title));
+    let paragraphs: Vec<String> = dom.select(&notice).map(element_text).collect();
+    match paragraphs.iter().find(|paragraph| marked(paragraph)) {
+        Some(paragraph) => Some(paragraph.clone()),
+        None if titled => Some(paragraphs.into_iter().next().unwrap_or_default()),
+        None => None,
+    }
+}
+
+/// In the urls and pages of the captive portals of the campus network and of the others,
+/// `http://10.1.1.1/eportal/index.jsp?wlanuserip=...`
+pub const CAPTIVE_MARKERS: [&str; 8] = [
+    "eportal",
+    "ac_portal",
+    "portal.do",
+    "wlanuserip",
+    "wlanacname",
+    "上网认证",
+    "认证上网",
+    "网络认证",
+];
+
+/// The domain of the services, a redirect out of it is not theirs
+const CAMPUS_DOMAIN: &str = "cczu.edu.cn";
+
+/// Whether the answer to the probe of `probed` is the one of a captive portal, the network
+/// of the device wanting a login before letting anything through.
+///
+/// Its `location` goes to another host, an IP or one out of [`CAMPUS_DOMAIN`], or has one of
+/// the [`CAPTIVE_MARKERS`]. Its `page` has one of them too, the portal answered with a `200`
+/// in place of the service.
+pub fn is_captive_portal(probed: &str, location: Option<&str>, page: Option<&str>) -> bool {
+    let marked = |text: &str| {
+        let text = text.to_lowercase();
+        CAPTIVE_MARKERS.iter().any(|marker| text.contains(marker))
+    };
+    if page.is_some_and(marked) {
+        return true;
+    }
+    let Some(location) = location else {
+        return false;
+    };
+    let Ok(probed) = Url::parse(probed) else {
+        return marked(location);
+    };
+    let Ok(target) = probed.join(location) else {
+        return marked(location);
+    };
+    let (to, from) = (target.host_str(), probed.host_str());
+    let leaves = to.is_some_and(|to| {
+        let ip = to.trim_matches(['[', ']']).parse::<IpAddr>().is_ok();
+        Some(to) != from && (ip || !to.ends_with(CAMPUS_DOMAIN))
+    });
+    leaves || marked(location)
+}
+
+/// Stops the status sweeps it is handed to, see [`services_probe_with`].
+///
+/// Clones share the same state, a sweep started after [`Self::cancel`] stops at once.
+#[derive(Debug, Clone)]
+pub struct CancelToken {
+    cancelled: Arc<watch::Sender<bool>>,
+}
+
+impl Default for CancelToken {
+    fn default() -> Self {
+        Self {
+            cancelled: Arc::new(watch::Sender::new(false)),
+        }
+    }
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.cancelled.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancelled.borrow()
+    }
+
+    async fn cancelled(&self) {
+        let mut cancelled = self.cancelled.subscribe();
+        // The sender lives in `self`, this can't fail
+        let _ = cancelled.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+/// What a status probe tells about a service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceHealth {
+    /// Answered 2xx, a 3xx that isn't a login, or 405 (the probe uses `OPTIONS`)
+    Up,
+    /// Answered, but with 404, 429, 500 or another 4xx
+    Degraded,
+    /// Up, but sends us to the login: 401 or a 3xx to a login page
+    AuthRedirect,
+    /// 502, 503, 504 or the connection failed
+    Down,
+    /// 408 or no answer in time
+    Timeout,
+    /// Answered with the page of a planned outage, see [`maintenance_message`]
+    Maintenance,
+    /// The network answered in place of the service, see [`is_captive_portal`]: the device has
+    /// to log in to the network first, the service may well be up
+    CaptivePortal,
+    Unknown(u16),
+}
+
+impl ServiceHealth {
+    pub fn from_status(status: StatusCode, location: Option<&str>) -> Self {
+        match status.as_u16() {
+            200..=299 | 405 => ServiceHealth::Up,
+            300..=399 => {
+                if location.is_some_and(|location| location.contains("login")) {
+                    ServiceHealth::AuthRedirect
+                } else {
+                    ServiceHealth::Up
+                }
+            }
+            401 => ServiceHealth::AuthRedirect,
+            408 => ServiceHealth::Timeout,
+            502..=504 => ServiceHealth::Down,
+            400..=500 => ServiceHealth::Degraded,
+            code => ServiceHealth::Unknown(code),
+        }
+    }
+
+    pub fn from_error(error: &reqwest::Error) -> Self {
+        if error.is_timeout() {
+            ServiceHealth::Timeout
+        } else {
+            ServiceHealth::Down
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ServiceHealth::Up => "up",
+            ServiceHealth::Degraded => "degraded",
+            ServiceHealth::AuthRedirect => "auth_redirect",
+            ServiceHealth::Down => "down",
+            ServiceHealth::Timeout => "timeout",
+            ServiceHealth::Maintenance => "maintenance",
+            ServiceHealth::CaptivePortal => "captive_portal",
+            ServiceHealth::Unknown(_) => "unknown",
+        }
+    }
+}
+
+impl Serialize for ServiceHealth {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.label())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceProbe {
+    pub health: ServiceHealth,
+    /// `None` when the service didn't answer
+    pub code: Option<u16>,
+    /// Time until the answer came, `None` when the service didn't answer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Only filled by [`services_status_with_latency`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_latency_ms: Option<f64>,
+    /// What the maintenance page says, for [`ServiceHealth::Maintenance`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// A [`ServiceProbe`] with what the service is, for the apps showing it as it comes.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceStatus {
+    pub id: ServiceId,
+    pub display_name: &'static str,
+    pub url: &'static str,
+    pub health: ServiceHealth,
+    /// Unix seconds of the probe
+    pub checked_at: u64,
+    /// `None` when the service didn't answer
+    pub latency_ms: Option<u64>,
+}
+
+/// `probes`, a [`ServiceStatus`] each in the order of [`ServiceId::ALL`], checked at
+/// `checked_at` (Unix seconds). The probes of services not in the catalog are left out.
+pub fn service_statuses(
+    probes: &HashMap<&'static str, ServiceProbe>,
+    checked_at: u64,
+) -> Vec<ServiceStatus> {
+    ServiceId::ALL
+        .into_iter()
+        .filter_map(|id| {
+            let probe = probes.get(id.key())?;
+            Some(ServiceStatus {
+                id,
+                display_name: id.display_name(),
+                url: id.url(),
+                health: probe.health,
+                checked_at,
+                latency_ms: probe.latency_ms,
+            })
+        })
+        .collect()
+}
+
+/// [`service_statuses`] by their id.
+pub fn service_status_map(statuses: Vec<ServiceStatus>) -> HashMap<ServiceId, ServiceStatus> {
+    statuses
+        .into_iter()
+        .map(|status| (status.id, status))
+        .collect()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// [`services_probe`] as [`ServiceStatus`]es.
+pub async fn services_status() -> Vec<ServiceStatus> {
+    service_statuses(&services_probe().await, unix_now())
+}
+
+/// [`services_status`] with the limits of [`services_probe_with`], the answers also fed to
+/// `tracker`.
+pub async fn services_status_until(
+    tracker: &LatencyTracker,
+    timeout: Duration,
+    cancel: &CancelToken,
+) -> TorErr<Vec<ServiceStatus>> {
+    let probes = services_status_with_latency_until(tracker, timeout, cancel).await?;
+    Ok(service_statuses(&probes, unix_now()))
+}
+
+/// Exponential moving average of the latency of each service, kept between probes.
+///
+/// Each new sample moves the average by `alpha` of the gap, `alpha` in `(0, 1]`.
+#[derive(Debug)]
+pub struct LatencyTracker {
+    alpha: f64,
+    averages: Mutex<HashMap<&'static str, f64>>,
+}
+
+impl Default for LatencyTracker {
+    fn default() -> Self {
+        Self::with_window(5)
+    }
+}
+
+impl LatencyTracker {
+    pub fn new(alpha: f64) -> Self {
+        Self {
+            alpha: alpha.clamp(f64::EPSILON, 1.0),
+            averages: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Roughly the average of the last `window` samples, `alpha = 2 / (window + 1)`.
+    pub fn with_window(window: usize) -> Self {
+        Self::new(2.0 / (window.max(1) as f64 + 1.0))
+    }
+
+    /// Add a sample and get the new average.
+    pub fn record(&self, service: &'static str, latency: Duration) -> f64 {
+        let sample = latency.as_secs_f64() * 1000.0;
+        let mut averages = self.averages.lock().unwrap();
+        let average = averages
+            .entry(service)
+            .and_modify(|average| *average += self.alpha * (sample - *average))
+            .or_insert(sample);
+        *average
+    }
+
+    pub fn average(&self, service: &str) -> Option<f64> {
+        self.averages.lock().unwrap().get(service).copied()
+    }
+}
 
 pub async fn url_status_code(url: Url) -> StatusCode {
     reqwest::Client::new()
@@ -16,16 +383,10 @@ pub async fn url_status_code(url: Url) -> StatusCode {
 pub async fn services_status_code() -> HashMap<&'static str, StatusCode> {
     let mut status_map = HashMap::new();
     let client = reqwest::Client::new();
-    // Example URLs for different services
-    let sites = [
-        ("SSO", ROOT_SSO_LOGIN),
-        ("WeChat", formatcp!("{}/api/login", WECHAT_APP_API)),
-        ("WebVPN", "https://zmvpn.cczu.edu.cn/enlink/sso/login"),
-    ];
 
     let mut tasks = JoinSet::new();
 
-    for (name, url) in sites.into_iter() {
+    for (name, url) in SERVICES.into_iter() {
         let client = client.clone();
         tasks.spawn(async move {
             (
@@ -33,7 +394,7 @@ pub async fn services_status_code() -> HashMap<&'static str, StatusCode> {
                 client
                     .request(Method::OPTIONS, url)
                     .headers(DEFAULT_HEADERS.clone())
-                    .timeout(Duration::from_secs(3))
+                    .timeout(PROBE_TIMEOUT)
                     //  .headers(DEFAULT_HEADERS.clone())
                     .send()
                     .await
@@ -54,7 +415,378 @@ pub async fn services_status_code() -> HashMap<&'static str, StatusCode> {
     status_map
 }
 
+/// Like [`services_status_code`], but tells what the answer means.
+pub async fn services_probe() -> HashMap<&'static str, ServiceProbe> {
+    probe(&SERVICES, PROBE_TIMEOUT).await
+}
+
+/// [`services_probe`] waiting `timeout` for each service, a service slower than that is
+/// [`ServiceHealth::Timeout`].
+///
+/// Fails with [`ErrorKind::Interrupted`] once `cancel` is cancelled, the probes still running
+/// are dropped.
+pub async fn services_probe_with(
+    timeout: Duration,
+    cancel: &CancelToken,
+) -> TorErr<HashMap<&'static str, ServiceProbe>> {
+    tokio::select! {
+        probes = probe(&SERVICES, timeout) => Ok(probes),
+        _ = cancel.cancelled() => Err(tokio::io::Error::new(
+            ErrorKind::Interrupted,
+            "Status sweep cancelled",
+        )),
+    }
+}
+
+async fn probe(
+    services: &[(&'static str, &str)],
+    limit: Duration,
+) -> HashMap<&'static str, ServiceProbe> {
+    // Keep the redirects, a redirect to the login page is an answer
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    let mut tasks = JoinSet::new();
+
+    for &(name, url) in services {
+        let request = client
+            .request(Method::OPTIONS, url)
+            .headers(DEFAULT_HEADERS.clone());
+        let url = url.to_owned();
+        tasks.spawn(async move {
+            let start = Instant::now();
+            let answer = timeout(limit, async {
+                let response = request.send().await?;
+                let status = response.status();
+                let location = response
+                    .headers()
+                    .get(LOCATION)
+                    .and_then(|location| location.to_str().ok())
+                    .map(String::from);
+                let latency = start.elapsed();
+                // Only a page answering like the service can be its maintenance page, or the
+                // one of a captive portal
+                let page = match ServiceHealth::from_status(status, location.as_deref()) {
+                    ServiceHealth::Up => Some(response.text().await?),
+                    _ => None,
+                };
+                let captive = is_captive_portal(&url, location.as_deref(), page.as_deref());
+                let notice = page.as_deref().and_then(maintenance_message);
+                Ok::<_, reqwest::Error>((status, location, latency, captive, notice))
+            })
+            .await;
+            let probe = match answer {
+                Ok(Ok((status, location, latency, captive, notice))) => ServiceProbe {
+                    health: match notice {
+                        _ if captive => ServiceHealth::CaptivePortal,
+                        Some(_) => ServiceHealth::Maintenance,
+                        None => ServiceHealth::from_status(status, location.as_deref()),
+                    },
+                    code: Some(status.as_u16()),
+                    latency_ms: Some(latency.as_millis() as u64),
+                    avg_latency_ms: None,
+                    message: notice.filter(|_| !captive),
+                },
+                Ok(Err(error)) => ServiceProbe {
+                    health: ServiceHealth::from_error(&error),
+                    code: None,
+                    latency_ms: None,
+                    avg_latency_ms: None,
+                    message: None,
+                },
+                Err(_) => ServiceProbe {
+                    health: ServiceHealth::Timeout,
+                    code: None,
+                    latency_ms: None,
+                    avg_latency_ms: None,
+                    message: None,
+                },
+            };
+            (name, probe)
+        });
+    }
+
+    tasks.join_all().await.into_iter().collect()
+}
+
+/// [`services_probe`], with the answers also fed to `tracker`.
+///
+/// Services that didn't answer keep their previous average.
+pub async fn services_status_with_latency(
+    tracker: &LatencyTracker,
+) -> HashMap<&'static str, ServiceProbe> {
+    track(tracker, services_probe().await)
+}
+
+/// [`services_status_with_latency`] with the limits of [`services_probe_with`].
+pub async fn services_status_with_latency_until(
+    tracker: &LatencyTracker,
+    timeout: Duration,
+    cancel: &CancelToken,
+) -> TorErr<HashMap<&'static str, ServiceProbe>> {
+    Ok(track(tracker, services_probe_with(timeout, cancel).await?))
+}
+
+fn track(
+    tracker: &LatencyTracker,
+    mut probes: HashMap<&'static str, ServiceProbe>,
+) -> HashMap<&'static str, ServiceProbe> {
+    for (name, probe) in probes.iter_mut() {
+        probe.avg_latency_ms = match probe.latency_ms {
+            Some(latency) => Some(tracker.record(name, Duration::from_millis(latency))),
+            None => tracker.average(name),
+        };
+    }
+    probes
+}
+
+pub async fn services_health() -> HashMap<&'static str, ServiceHealth> {
+    services_probe()
+        .await
+        .into_iter()
+        .map(|(name, probe)| (name, probe.health))
+        .collect()
+}
+
 #[tokio::test]
 async fn test() {
     println!("{:?}", services_status_code().await)
 }
+
+#[test]
+fn health_rules() {
+    let health = |code: u16, location: Option<&str>| {
+        ServiceHealth::from_status(StatusCode::from_u16(code).unwrap(), location)
+    };
+
+    assert_eq!(health(200, None), ServiceHealth::Up);
+    assert_eq!(health(405, None), ServiceHealth::Up);
+    assert_eq!(
+        health(302, Some("http://sso.cczu.edu.cn/sso/login?service=x")),
+        ServiceHealth::AuthRedirect
+    );
+    assert_eq!(health(302, Some("/index.html")), ServiceHealth::Up);
+    assert_eq!(health(401, None), ServiceHealth::AuthRedirect);
+    assert_eq!(health(404, None), ServiceHealth::Degraded);
+    assert_eq!(health(429, None), ServiceHealth::Degraded);
+    assert_eq!(health(500, None), ServiceHealth::Degraded);
+    assert_eq!(health(503, None), ServiceHealth::Down);
+    assert_eq!(health(408, None), ServiceHealth::Timeout);
+    assert_eq!(health(101, None), ServiceHealth::Unknown(101));
+    assert_eq!(health(599, None), ServiceHealth::Unknown(599));
+
+    assert_eq!(
+        serde_json::to_string(&ServiceProbe {
+            health: ServiceHealth::AuthRedirect,
+            code: Some(302),
+            latency_ms: None,
+            avg_latency_ms: None,
+            message: None,
+        })
+        .unwrap(),
+        r#"{"health":"auth_redirect","code":302}"#
+    );
+}
+
+#[test]
+fn status_shape() {
+    let probes = HashMap::from([
+        (
+            "WeChat",
+            ServiceProbe {
+                health: ServiceHealth::Up,
+                code: Some(200),
+                latency_ms: Some(120),
+                avg_latency_ms: None,
+                message: None,
+            },
+        ),
+        (
+            "SSO",
+            ServiceProbe {
+                health: ServiceHealth::Timeout,
+                code: None,
+                latency_ms: None,
+                avg_latency_ms: None,
+                message: None,
+            },
+        ),
+        (
+            "Elsewhere",
+            ServiceProbe {
+                health: ServiceHealth::Down,
+                code: None,
+                latency_ms: None,
+                avg_latency_ms: None,
+                message: None,
+            },
+        ),
+    ]);
+    let statuses = service_statuses(&probes, 1760400000);
+    assert_eq!(
+        serde_json::to_value(&statuses).unwrap(),
+        serde_json::json!([
+            {
+                "id": "sso",
+                "display_name": "统一身份认证",
+                "url": ROOT_SSO_LOGIN,
+                "health": "timeout",
+                "checked_at": 1760400000,
+                "latency_ms": null
+            },
+            {
+                "id": "wechat",
+                "display_name": "微信教务",
+                "url": ServiceId::WeChat.url(),
+                "health": "up",
+                "checked_at": 1760400000,
+                "latency_ms": 120
+            }
+        ])
+    );
+    assert_eq!(ServiceId::from_key("WebVPN"), Some(ServiceId::WebVpn));
+    let map = service_status_map(statuses);
+    assert_eq!(map[&ServiceId::WeChat].latency_ms, Some(120));
+    assert!(!map.contains_key(&ServiceId::WebVpn));
+}
+
+#[test]
+fn latency_average() {
+    let tracker = LatencyTracker::new(0.5);
+    assert_eq!(tracker.average("SSO"), None);
+    assert_eq!(tracker.record("SSO", Duration::from_millis(100)), 100.0);
+    assert_eq!(tracker.record("SSO", Duration::from_millis(300)), 200.0);
+    assert_eq!(tracker.record("SSO", Duration::from_millis(200)), 200.0);
+    assert_eq!(tracker.average("WeChat"), None);
+
+    let tracker = LatencyTracker::with_window(3);
+    tracker.record("SSO", Duration::from_millis(100));
+    assert_eq!(tracker.record("SSO", Duration::from_millis(300)), 200.0);
+
+    assert_eq!(
+        serde_json::to_string(&ServiceProbe {
+            health: ServiceHealth::Up,
+            code: Some(200),
+            latency_ms: Some(120),
+            avg_latency_ms: Some(80.5),
+            message: None,
+        })
+        .unwrap(),
+        r#"{"health":"up","code":200,"latency_ms":120,"avg_latency_ms":80.5}"#
+    );
+}
+
+#[tokio::test]
+async fn slow_service() {
+    // Accepts, then never answers
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut streams = vec![];
+        while let Ok((stream, _)) = listener.accept().await {
+            streams.push(stream);
+        }
+    });
+
+    let start = Instant::now();
+    let probes = probe(&[("Slow", &url)], Duration::from_millis(200)).await;
+    assert_eq!(probes["Slow"].health, ServiceHealth::Timeout);
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    let cancel = CancelToken::default();
+    cancel.clone().cancel();
+    let start = Instant::now();
+    let error = services_probe_with(Duration::from_secs(60), &cancel)
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Interrupted);
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn maintenance_page() {
+    let message = maintenance_message(include_str!("fixtures/maintenance.html")).unwrap();
+    assert!(message.starts_with("为提升服务质量"));
+    assert!(message.contains("10月18日 22:00"));
+
+    assert_eq!(
+        maintenance_message(include_str!("../impls/login/fixtures/sso_login.html")),
+        None
+    );
+    assert_eq!(
+        maintenance_message("<title>System maintenance</title>").as_deref(),
+        Some("")
+    );
+}
+
+#[tokio::test]
+async fn maintenance_probe() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 4096];
+        let _ = stream.read(&mut buf).await;
+        let body = include_str!("fixtures/maintenance.html");
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        );
+        let _ = stream.write_all(head.as_bytes()).await;
+        let _ = stream.write_all(body.as_bytes()).await;
+    });
+
+    let probes = probe(&[("SSO", &url)], Duration::from_secs(5)).await;
+    assert_eq!(probes["SSO"].health, ServiceHealth::Maintenance);
+    assert_eq!(probes["SSO"].code, Some(200));
+    assert!(probes["SSO"]
+        .message
+        .as_ref()
+        .unwrap()
+        .contains("系统升级维护"));
+}
+
+#[tokio::test]
+async fn captive_portal() {
+    use crate::utils::testing::{TestResponse, TestServer};
+
+    // The network answers for every host with its login page
+    let server = TestServer::start().await;
+    // What the probes send
+    server.route(
+        "OPTIONS",
+        "/",
+        TestResponse::html(include_str!("fixtures/captive_portal.html")),
+    );
+    let url = server.url("/");
+    let probes = probe(&[("WeChat", &url)], Duration::from_secs(5)).await;
+    assert_eq!(probes["WeChat"].health, ServiceHealth::CaptivePortal);
+    assert_eq!(probes["WeChat"].message, None);
+    assert_eq!(
+        serde_json::to_value(probes["WeChat"].health).unwrap(),
+        "captive_portal"
+    );
+
+    // Sent away from the campus, or to an IP
+    let wechat = ServiceId::WeChat.url();
+    let captive = |location| is_captive_portal(wechat, Some(location), None);
+    assert!(captive("http://10.1.1.1/index.jsp"));
+    assert!(captive("https://wifi.example.com/login"));
+    assert!(captive("/eportal/index.jsp?wlanuserip=a1b2c3d4"));
+    // The login of the service itself, or a page of its own
+    assert!(!captive(
+        "http://sso.cczu.edu.cn/sso/login?service=http://jwqywx.cczu.edu.cn/"
+    ));
+    assert!(!captive("/index.html"));
+    assert!(!is_captive_portal(wechat, None, None));
+    for page in [
+        include_str!("fixtures/maintenance.html"),
+        include_str!("../impls/login/fixtures/sso_login.html"),
+    ] {
+        assert!(!is_captive_portal(ROOT_SSO_LOGIN, None, Some(page)));
+    }
+}
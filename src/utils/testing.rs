@@ -0,0 +1,541 @@
+//! A local HTTP server serving fixtures and keeping the requests it got, to test the parsing
+//! of the apps or a wrapper around them without the portals.
+//!
+//! Built for the tests of this crate, and with the `testing` feature for the crates using it.
+//! See [`TestServer::jwqywx`] and [`TestServer::jwcas`] for apps pointed at the server, and
+//! [`TestServer::inject`] to have it fail on purpose.
+//!
+//! Nothing of it is in a build without the feature, a release build can't fail this way.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::AbortHandle,
+    time::sleep,
+};
+
+use crate::{
+    base::{app::Application, client::Client},
+    impls::{
+        apps::{sso::jwcas::JwcasApplication, wechat::jwqywx::JwqywxApplication},
+        login::sso_type::SSOLoginConnectType,
+    },
+};
+
+/// What a route answers.
+#[derive(Debug, Clone)]
+pub struct TestResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl TestResponse {
+    pub fn new(status: u16, content_type: &str, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".into(), content_type.into())],
+            body: body.into(),
+        }
+    }
+
+    pub fn json(body: impl Into<String>) -> Self {
+        Self::new(200, "application/json", body)
+    }
+
+    pub fn html(body: impl Into<String>) -> Self {
+        Self::new(200, "text/html; charset=utf-8", body)
+    }
+
+    /// A `302` to `location`.
+    pub fn redirect(location: impl Into<String>) -> Self {
+        Self {
+            status: 302,
+            headers: vec![("Location".into(), location.into())],
+            body: String::new(),
+        }
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// A request the server got.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// With the query
+    pub path: String,
+    /// Names in lower case
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+}
+
+type Routes = HashMap<(String, String), Sequence>;
+
+/// The answers of a route, one per request, the last one again once past the end.
+#[derive(Debug)]
+struct Sequence {
+    responses: Vec<TestResponse>,
+    answered: usize,
+}
+
+impl Sequence {
+    fn next(&mut self) -> TestResponse {
+        let response = &self.responses[self.answered.min(self.responses.len() - 1)];
+        self.answered += 1;
+        response.clone()
+    }
+}
+
+/// A failure of [`FaultInjection`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// No answer, the connection is held open until the client gives up: a timeout with
+    /// [`crate::base::client::ClientOptions::timeout`] set
+    Timeout,
+    /// `503 Service Unavailable`, what the portals answer when overloaded
+    Unavailable,
+    /// The head announces the whole body, the connection closes after half of it
+    Truncated,
+    /// `200` with a login page, what an app answers once the session is gone
+    LoginWall,
+    /// `429 Too Many Requests` with a `Retry-After` of this many seconds
+    RateLimited(u64),
+    /// The usual answer, after this long: requests held at the same time, see
+    /// [`TestServer::max_in_flight`]
+    Slow(Duration),
+}
+
+/// Which requests of a [`TestServer`] fail and how, instead of the answer of their route.
+///
+/// The first fault given for a request wins, the requests without one are answered as usual.
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjection {
+    /// By the number of the request, from 1
+    nth: Vec<(usize, Fault)>,
+    /// By the path, query left out
+    paths: Vec<(String, Fault)>,
+}
+
+impl FaultInjection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `fault` for the `n`th request the server gets, counting from 1 on every path.
+    pub fn nth(mut self, n: usize, fault: Fault) -> Self {
+        self.nth.push((n, fault));
+        self
+    }
+
+    /// `fault` for every request on `path`, like `/api/login` for a login that always fails.
+    pub fn on(mut self, path: &str, fault: Fault) -> Self {
+        self.paths.push((path.into(), fault));
+        self
+    }
+
+    fn fault(&self, n: usize, path: &str) -> Option<Fault> {
+        let path = path.split('?').next().unwrap_or_default();
+        let nth = self.nth.iter().find(|(nth, _)| *nth == n);
+        let on = self.paths.iter().find(|(on, _)| on == path);
+        nth.map(|(_, fault)| *fault).or(on.map(|(_, fault)| *fault))
+    }
+}
+
+/// Served for [`Fault::LoginWall`]
+const LOGIN_WALL: &str = "<html><body><form action=\"/login\" method=\"post\">\
+    <input type=\"text\" name=\"username\"><input type=\"password\" name=\"password\">\
+    </form></body></html>";
+
+/// Answers the routes given to [`Self::route`], `404` to the others. Stops when dropped.
+pub struct TestServer {
+    root: String,
+    routes: Arc<Mutex<Routes>>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    faults: Arc<Mutex<FaultInjection>>,
+    in_flight: Arc<InFlight>,
+    task: AbortHandle,
+}
+
+/// Requests read and not answered yet
+#[derive(Debug, Default)]
+struct InFlight {
+    now: AtomicUsize,
+    max: AtomicUsize,
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl TestServer {
+    /// Listen on a free port of `127.0.0.1`, inside a tokio runtime.
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("No free port");
+        let root = format!("http://{}", listener.local_addr().unwrap());
+        let routes = Arc::new(Mutex::new(Routes::new()));
+        let requests = Arc::new(Mutex::new(vec![]));
+        let faults = Arc::new(Mutex::new(FaultInjection::default()));
+        let in_flight = Arc::new(InFlight::default());
+
+        let (served, seen, failing, answering) = (
+            routes.clone(),
+            requests.clone(),
+            faults.clone(),
+            in_flight.clone(),
+        );
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (routes, requests, faults) = (served.clone(), seen.clone(), failing.clone());
+                let in_flight = answering.clone();
+                tokio::spawn(async move {
+                    let now = in_flight.now.fetch_add(1, Ordering::SeqCst) + 1;
+                    in_flight.max.fetch_max(now, Ordering::SeqCst);
+                    answer(stream, routes, requests, faults).await;
+                    in_flight.now.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        Self {
+            root,
+            routes,
+            requests,
+            faults,
+            in_flight,
+            task: task.abort_handle(),
+        }
+    }
+
+    /// Like `http://127.0.0.1:41234`, without a trailing `/`.
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.root)
+    }
+
+    /// Answer `method` on `path` with `response`, replacing what it answered before.
+    ///
+    /// A `path` without a query also takes the requests with one.
+    pub fn route(&self, method: &str, path: &str, response: TestResponse) -> &Self {
+        self.sequence(method, path, vec![response])
+    }
+
+    /// Answer the n-th request of `method` on `path` with the n-th of `responses`, the last
+    /// one once past the end, like [`Self::route`] otherwise.
+    pub fn sequence(&self, method: &str, path: &str, responses: Vec<TestResponse>) -> &Self {
+        assert!(!responses.is_empty(), "A sequence answers at least once");
+        let sequence = Sequence {
+            responses,
+            answered: 0,
+        };
+        self.routes
+            .lock()
+            .unwrap()
+            .insert((method.to_ascii_uppercase(), path.into()), sequence);
+        self
+    }
+
+    /// Fail the requests picked by `faults` from now on, replacing the faults given before.
+    ///
+    /// The failed requests are kept by [`Self::requests`] too, and counted by
+    /// [`FaultInjection::nth`].
+    pub fn inject(&self, faults: FaultInjection) -> &Self {
+        *self.faults.lock().unwrap() = faults;
+        self
+    }
+
+    /// Every request so far, in the order they came.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Most connections being answered at the same time so far, one per request: the
+    /// client opens a connection for each.
+    pub fn max_in_flight(&self) -> usize {
+        self.in_flight.max.load(Ordering::SeqCst)
+    }
+
+    /// The requests on `path`, query left out.
+    pub fn requests_to(&self, path: &str) -> Vec<RecordedRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|request| request.path.split('?').next() == Some(path))
+            .collect()
+    }
+
+    /// [`JwqywxApplication`] of `client` sending its requests here.
+    pub async fn jwqywx<C: Client>(&self, client: C) -> JwqywxApplication<C> {
+        let mut app = JwqywxApplication::from_client(client).await;
+        app.root = self.root.clone();
+        app
+    }
+
+    /// [`JwcasApplication`] of `client` sending its requests here, its JSON API too.
+    ///
+    /// A `client` that never logged in is taken as logged in on campus.
+    pub async fn jwcas<C: Client + Clone + Send>(&self, client: C) -> JwcasApplication<C> {
+        client
+            .properties()
+            .write()
+            .await
+            .entry(SSOLoginConnectType::key())
+            .or_insert(SSOLoginConnectType::COMMON.into());
+        let mut app = JwcasApplication::from_client(client).await;
+        app.root = self.root.clone();
+        app.api.root = self.root.clone();
+        app
+    }
+}
+
+/// Read the head, then the body announced by its `Content-Length`.
+async fn read_request(stream: &mut TcpStream) -> Option<RecordedRequest> {
+    let mut request = vec![];
+    let mut buf = [0u8; 4096];
+    let (head_end, length) = loop {
+        let read = stream.read(&mut buf).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        request.extend_from_slice(&buf[..read]);
+        let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n") else {
+            continue;
+        };
+        let head = String::from_utf8_lossy(&request[..end]).to_ascii_lowercase();
+        let length = head
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length:"))
+            .map_or(0, |length| length.trim().parse().unwrap_or(0));
+        break (end + 4, length);
+    };
+    while request.len() < head_end + length {
+        let read = stream.read(&mut buf).await.ok()?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+
+    let head = String::from_utf8_lossy(&request[..head_end - 4]).into_owned();
+    let mut lines = head.lines();
+    let mut start = lines.next()?.split(' ');
+    let (method, path) = (start.next()?.to_string(), start.next()?.to_string());
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    Some(RecordedRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&request[head_end..]).into_owned(),
+    })
+}
+
+async fn answer(
+    mut stream: TcpStream,
+    routes: Arc<Mutex<Routes>>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    faults: Arc<Mutex<FaultInjection>>,
+) {
+    let Some(request) = read_request(&mut stream).await else {
+        return;
+    };
+    let mut response = {
+        let mut routes = routes.lock().unwrap();
+        let without_query = request.path.split('?').next().unwrap_or_default();
+        let key = match routes.contains_key(&(request.method.clone(), request.path.clone())) {
+            true => (request.method.clone(), request.path.clone()),
+            false => (request.method.clone(), without_query.to_string()),
+        };
+        routes
+            .get_mut(&key)
+            .map(Sequence::next)
+            .unwrap_or(TestResponse::new(404, "text/plain", "Not Found"))
+    };
+    let fault = {
+        let mut requests = requests.lock().unwrap();
+        let fault = faults
+            .lock()
+            .unwrap()
+            .fault(requests.len() + 1, &request.path);
+        requests.push(request);
+        fault
+    };
+    match fault {
+        Some(Fault::Timeout) => {
+            let mut buf = [0u8; 1024];
+            while matches!(stream.read(&mut buf).await, Ok(read) if read > 0) {}
+            return;
+        }
+        Some(Fault::Unavailable) => {
+            response = TestResponse::new(503, "text/plain", "Service Unavailable");
+        }
+        Some(Fault::LoginWall) => response = TestResponse::html(LOGIN_WALL),
+        Some(Fault::RateLimited(seconds)) => {
+            response = TestResponse::new(429, "text/plain", "Too Many Requests")
+                .header("Retry-After", seconds.to_string());
+        }
+        Some(Fault::Slow(delay)) => sleep(delay).await,
+        Some(Fault::Truncated) | None => {}
+    }
+
+    let mut head = format!("HTTP/1.1 {} Test\r\n", response.status);
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        response.body.len()
+    ));
+    let _ = stream.write_all(head.as_bytes()).await;
+    let body = response.body.as_bytes();
+    let sent = match fault {
+        Some(Fault::Truncated) => &body[..body.len() / 2],
+        _ => body,
+    };
+    let _ = stream.write_all(sent).await;
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{Fault, FaultInjection, TestResponse, TestServer};
+    use crate::base::client::{Account, ClientOptions};
+    use crate::base::typing::{CczuError, TypedErrorExt};
+    use crate::impls::client::DefaultClient;
+
+    async fn grades_server() -> TestServer {
+        let server = TestServer::start().await;
+        server
+            .route(
+                "POST",
+                "/api/login",
+                TestResponse::json(include_str!(
+                    "../impls/apps/wechat/fixtures/login_bearer.json"
+                )),
+            )
+            .route(
+                "POST",
+                "/api/cj_xh",
+                TestResponse::json(include_str!(
+                    "../impls/apps/wechat/fixtures/cj_xh_detail.json"
+                )),
+            );
+        server
+    }
+
+    #[tokio::test]
+    async fn login_then_grades() {
+        let server = grades_server().await;
+        let app = server
+            .jwqywx(DefaultClient::account("2300000101", "hunter2"))
+            .await;
+        app.login().await.unwrap();
+        let grades = app.get_grades().await.unwrap().message;
+        assert_eq!(grades.len(), 3);
+
+        let login = &server.requests_to("/api/login")[0];
+        assert!(login.body.contains(r#""userid":"2300000101""#));
+        let fetch = &server.requests_to("/api/cj_xh")[0];
+        assert_eq!(
+            fetch.header("Authorization"),
+            Some("Bearer eyJhbGciOiJIUzI1NiJ9.e30.c2lnbmF0dXJl")
+        );
+        assert_eq!(fetch.body, r#"{"xh":"1001"}"#);
+
+        // Anything else is a 404
+        assert!(app.terms().await.is_err());
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn timeout_then_success() {
+        let server = grades_server().await;
+        server.inject(FaultInjection::new().nth(2, Fault::Timeout));
+        let client = DefaultClient::with_options(
+            Account::new("2300000101", "hunter2"),
+            ClientOptions {
+                timeout: Some(Duration::from_millis(200)),
+                ..Default::default()
+            },
+        );
+        let app = server.jwqywx(client).await;
+        app.login().await.unwrap();
+
+        assert!(app.get_grades().await.is_err());
+        assert_eq!(app.get_grades().await.unwrap().message.len(), 3);
+        assert_eq!(server.requests_to("/api/cj_xh").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn faults() {
+        let server = grades_server().await;
+        server.inject(FaultInjection::new().on("/api/login", Fault::Unavailable));
+        let app = server
+            .jwqywx(DefaultClient::account("2300000101", "hunter2"))
+            .await;
+        assert!(app.login().await.is_err());
+
+        // The failed login counts
+        server.inject(FaultInjection::new().nth(3, Fault::LoginWall));
+        app.login().await.unwrap();
+        let error = app.get_grades().await.unwrap_err();
+        assert_eq!(error.typed(), Some(&CczuError::SessionExpired));
+
+        server.inject(FaultInjection::new().on("/api/cj_xh", Fault::Truncated));
+        assert!(app.get_grades().await.is_err());
+
+        server.inject(FaultInjection::new());
+        assert!(app.get_grades().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn sequence() {
+        let server = grades_server().await;
+        server.sequence(
+            "POST",
+            "/api/cj_xh",
+            vec![
+                TestResponse::json(r#"{"status":1,"message":[]}"#),
+                TestResponse::json(include_str!(
+                    "../impls/apps/wechat/fixtures/cj_xh_detail.json"
+                )),
+            ],
+        );
+        let app = server
+            .jwqywx(DefaultClient::account("2300000101", "hunter2"))
+            .await;
+        app.login().await.unwrap();
+        assert!(app.get_grades().await.unwrap().message.is_empty());
+        // The last one stays
+        assert_eq!(app.get_grades().await.unwrap().message.len(), 3);
+        assert_eq!(app.get_grades().await.unwrap().message.len(), 3);
+    }
+}
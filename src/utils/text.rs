@@ -0,0 +1,158 @@
+//! Plain text of the HTML the portals put in their notices and remarks, for summaries and
+//! notifications.
+
+use scraper::{node::Node, ElementRef, Html};
+
+/// Never shown to the user
+const HIDDEN: [&str; 5] = ["script", "style", "head", "noscript", "template"];
+
+/// Start on a line of their own
+const BLOCKS: [&str; 24] = [
+    "address",
+    "article",
+    "blockquote",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tr",
+    "ul",
+];
+
+#[derive(Default)]
+struct Flattened {
+    text: String,
+    /// Lists open around the current node, with the number of the next item of the ordered ones
+    lists: Vec<Option<usize>>,
+}
+
+impl Flattened {
+    fn line_break(&mut self) {
+        self.text.truncate(self.text.trim_end_matches(' ').len());
+        if !self.text.is_empty() && !self.text.ends_with('\n') {
+            self.text.push('\n');
+        }
+    }
+
+    fn space(&mut self) {
+        if !self.text.is_empty() && !self.text.ends_with([' ', '\n']) {
+            self.text.push(' ');
+        }
+    }
+
+    fn words(&mut self, text: &str) {
+        if text.starts_with(char::is_whitespace) {
+            self.space();
+        }
+        for (index, word) in text.split_whitespace().enumerate() {
+            if index > 0 {
+                self.text.push(' ');
+            }
+            self.text.push_str(word);
+        }
+        if text.ends_with(char::is_whitespace) {
+            self.space();
+        }
+    }
+
+    fn item(&mut self) {
+        self.line_break();
+        let depth = self.lists.len().saturating_sub(1);
+        self.text.push_str(&"  ".repeat(depth));
+        match self.lists.last_mut() {
+            Some(Some(number)) => {
+                self.text.push_str(&format!("{number}. "));
+                *number += 1;
+            }
+            _ => self.text.push_str("- "),
+        }
+    }
+
+    fn element(&mut self, element: ElementRef) {
+        let name = element.value().name();
+        if HIDDEN.contains(&name) {
+            return;
+        }
+        match name {
+            "br" => return self.line_break(),
+            "li" => self.item(),
+            "td" | "th" => self.space(),
+            name if BLOCKS.contains(&name) => self.line_break(),
+            _ => {}
+        }
+        match name {
+            "ul" => self.lists.push(None),
+            "ol" => self.lists.push(Some(1)),
+            _ => {}
+        }
+
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => self.words(text),
+                Node::Element(_) => self.element(ElementRef::wrap(child).unwrap()),
+                _ => {}
+            }
+        }
+
+        if matches!(name, "ul" | "ol") {
+            self.lists.pop();
+        }
+        if name == "li" || BLOCKS.contains(&name) {
+            self.line_break();
+        }
+    }
+}
+
+/// The text of `element` as it reads on the page: a line per paragraph, list item or `<br>`,
+/// items marked `- ` or numbered and indented by their nesting, spaces collapsed, scripts and
+/// styles left out. Entities are already decoded by the parser.
+pub fn element_text(element: ElementRef) -> String {
+    let mut flattened = Flattened::default();
+    flattened.element(element);
+    flattened.text.trim_end().to_string()
+}
+
+/// [`element_text`] of a fragment or a whole page.
+pub fn html_to_text(html: &str) -> String {
+    element_text(Html::parse_fragment(html).root_element())
+}
+
+#[cfg(test)]
+mod test {
+    use super::html_to_text;
+
+    #[test]
+    fn nested_lists() {
+        assert_eq!(
+            html_to_text(include_str!("fixtures/notice.html")),
+            "关于 2025-2026 学年第一学期补考安排的通知\n\
+             各位同学：\n\
+             补考将于 开学第一周 进行，请注意：\n\
+             1. 携带学生证 & 身份证\n\
+             2. 考试科目：\n\
+             \x20 - 高等数学A1\n\
+             \x20 - 大学英语 \"读写\"\n\
+             3. 不得使用计算器 <除特别说明外>\n\
+             教务处\n\
+             2025年8月25日"
+        );
+
+        assert_eq!(html_to_text("a<br><br>b &nbsp; c"), "a\nb c");
+        assert_eq!(html_to_text("   "), "");
+    }
+}